@@ -0,0 +1,94 @@
+use actix_web::{HttpRequest, ResponseError, Result};
+use actix_web::middleware::{Middleware, Started};
+use log::warn;
+
+use crate::constants::ADMIN;
+use crate::error::{ConfCountError, ConfCountResult};
+use crate::session::current_user;
+use crate::state::AppState;
+use crate::storage::User;
+
+static MISSING_ROLE: &str = "You are not allowed to access this resource";
+
+/// Checks that the signed in user holds a role
+///
+/// # Arguments
+///
+/// * `request` - An HTTP Request
+///
+/// * `role` - User type required by the resource
+///
+/// # Return
+///
+/// Returns the signed in user, an unauthorized error without a session or a forbidden error
+/// when the user holds another role
+pub fn authorize(request: &HttpRequest<AppState>, role: &str) -> ConfCountResult<User> {
+    let user: User = current_user(request).map_err(|error| {
+        warn!("Denied {} {} to a client without a session", request.method(), request.path());
+        error
+    })?;
+    if user.user_type == role {
+        Ok(user)
+    } else {
+        warn!("Denied {} {} to {} of type {:?}, {:?} is required",
+              request.method(), request.path(), user.email, user.user_type, role);
+        Err(ConfCountError::Forbidden(MISSING_ROLE.to_string()))
+    }
+}
+
+/// Answers the requests of the users lacking the role of a resource before its handlers run
+pub struct RequireRole(pub &'static str);
+
+impl RequireRole {
+    /// Guard of the resources reserved to the administrators
+    pub fn admin() -> Self {
+        RequireRole(ADMIN)
+    }
+}
+
+impl Middleware<AppState> for RequireRole {
+    fn start(&self, request: &HttpRequest<AppState>) -> Result<Started> {
+        Ok(match authorize(request, self.0) {
+            Ok(_) => Started::Done,
+            Err(error) => Started::Response(error.error_response()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use actix_web::http::StatusCode;
+    use actix_web::middleware::{Middleware, Started};
+
+    use crate::authorization::RequireRole;
+    use crate::constants::{ADMIN_EMAIL, TEST_EMAIL_ID};
+    use crate::session::test::sign_in;
+    use crate::state::test::test_request;
+
+    fn denial_status(started: Started) -> Option<StatusCode> {
+        match started {
+            Started::Response(response) => Some(response.status()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_require_admin_success() {
+        let started: Started =
+            RequireRole::admin().start(&sign_in(test_request(), ADMIN_EMAIL)).unwrap();
+        assert_eq!(denial_status(started), None);
+    }
+
+    #[test]
+    fn test_require_admin_forbidden() {
+        let started: Started =
+            RequireRole::admin().start(&sign_in(test_request(), TEST_EMAIL_ID)).unwrap();
+        assert_eq!(denial_status(started), Some(StatusCode::FORBIDDEN));
+    }
+
+    #[test]
+    fn test_require_admin_unauthorized() {
+        let started: Started = RequireRole::admin().start(&test_request()).unwrap();
+        assert_eq!(denial_status(started), Some(StatusCode::UNAUTHORIZED));
+    }
+}
@@ -0,0 +1,154 @@
+use std::process::{Command, Output};
+
+use log::error;
+
+use crate::capture::{CaptureDevice, CaptureError, CaptureResult};
+use crate::config::CameraConfig;
+
+static RASPISTILL: &str = "raspistill";
+static LIBCAMERA_STILL: &str = "libcamera-still";
+
+/// Camera driven by a command line program writing a still image to a file
+#[derive(Debug, PartialEq)]
+pub struct CommandDevice {
+    program: String,
+    arguments: Vec<String>,
+    output_path: String,
+}
+
+impl CommandDevice {
+    /// Camera of the legacy Raspberry Pi camera stack
+    pub fn raspistill(camera_config: &CameraConfig) -> CommandDevice {
+        CommandDevice {
+            program: RASPISTILL.to_string(),
+            arguments: vec![
+                "-t".to_string(),
+                camera_config.timeout.to_string(),
+                "-q".to_string(),
+                camera_config.quality.to_string(),
+                "-co".to_string(),
+                camera_config.contrast.to_string(),
+                "-sh".to_string(),
+                camera_config.sharpness.to_string(),
+                "-br".to_string(),
+                camera_config.brightness.to_string(),
+                "-o".to_string(),
+                camera_config.clicked_image_path.clone(),
+            ],
+            output_path: camera_config.clicked_image_path.clone(),
+        }
+    }
+
+    /// Camera of the libcamera stack shipped with current Raspberry Pi OS images.
+    /// Contrast and sharpness are scaled around 1 and brightness around 0, as libcamera expects
+    pub fn libcamera_still(camera_config: &CameraConfig) -> CommandDevice {
+        CommandDevice {
+            program: LIBCAMERA_STILL.to_string(),
+            arguments: vec![
+                "-n".to_string(),
+                "-t".to_string(),
+                camera_config.timeout.to_string(),
+                "-q".to_string(),
+                camera_config.quality.to_string(),
+                "--contrast".to_string(),
+                (1.0 + f64::from(camera_config.contrast) / 100.0).to_string(),
+                "--sharpness".to_string(),
+                (1.0 + f64::from(camera_config.sharpness) / 100.0).to_string(),
+                "--brightness".to_string(),
+                (f64::from(camera_config.brightness - 50) / 50.0).to_string(),
+                "-o".to_string(),
+                camera_config.clicked_image_path.clone(),
+            ],
+            output_path: camera_config.clicked_image_path.clone(),
+        }
+    }
+
+    /// Camera driven by the command line of the configuration, split on whitespace, whose
+    /// placeholders are replaced by the camera settings
+    pub fn from_template(camera_config: &CameraConfig) -> CaptureResult<CommandDevice> {
+        let mut words = camera_config.command.split_whitespace().map(|word| word
+            .replace("{output}", camera_config.clicked_image_path.as_str())
+            .replace("{timeout}", camera_config.timeout.to_string().as_str())
+            .replace("{quality}", camera_config.quality.to_string().as_str())
+            .replace("{contrast}", camera_config.contrast.to_string().as_str())
+            .replace("{sharpness}", camera_config.sharpness.to_string().as_str())
+            .replace("{brightness}", camera_config.brightness.to_string().as_str()));
+        match words.next() {
+            Some(program) => Ok(CommandDevice {
+                program,
+                arguments: words.collect(),
+                output_path: camera_config.clicked_image_path.clone(),
+            }),
+            None => Err(CaptureError("The camera command is empty".to_string())),
+        }
+    }
+}
+
+impl CaptureDevice for CommandDevice {
+    fn capture(&self) -> CaptureResult<String> {
+        let output: Output = Command::new(self.program.as_str())
+            .args(&self.arguments)
+            .output()
+            .map_err(|error| CaptureError(format!("Unable to run {}: {}", self.program, error)))?;
+        if output.status.success() {
+            Ok(self.output_path.clone())
+        } else {
+            error!("{}", String::from_utf8_lossy(&output.stderr));
+            Err(CaptureError(format!("{} failed with {}", self.program, output.status)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::capture::CaptureDevice;
+    use crate::capture::command::CommandDevice;
+    use crate::config::CameraConfig;
+    use crate::config::test::test_config;
+
+    fn template(command: &str) -> CameraConfig {
+        let mut camera_config: CameraConfig = test_config().camera;
+        camera_config.command = command.to_string();
+        camera_config
+    }
+
+    #[test]
+    fn test_raspistill_arguments_success() {
+        let device: CommandDevice = CommandDevice::raspistill(&test_config().camera);
+        assert_eq!(device.arguments, vec!["-t", "3000", "-q", "100", "-co", "50", "-sh", "30",
+                                          "-br", "60", "-o", "tests/resources/test.jpg"]);
+    }
+
+    #[test]
+    fn test_libcamera_still_arguments_success() {
+        let device: CommandDevice = CommandDevice::libcamera_still(&test_config().camera);
+        assert_eq!(device.arguments, vec!["-n", "-t", "3000", "-q", "100", "--contrast", "1.5",
+                                          "--sharpness", "1.3", "--brightness", "0.2", "-o",
+                                          "tests/resources/test.jpg"]);
+    }
+
+    #[test]
+    fn test_from_template_success() {
+        let device: CommandDevice =
+            CommandDevice::from_template(&template("fswebcam -S {timeout} {output}")).unwrap();
+        assert_eq!(device.program, "fswebcam");
+        assert_eq!(device.arguments, vec!["-S", "3000", "tests/resources/test.jpg"]);
+        assert!(CommandDevice::from_template(&template(" ")).is_err());
+    }
+
+    #[test]
+    fn test_capture_success() {
+        let device: CommandDevice = CommandDevice::from_template(&template("true {output}"))
+            .unwrap();
+        assert_eq!(device.capture().unwrap(), "tests/resources/test.jpg");
+    }
+
+    #[test]
+    fn test_capture_failure() {
+        let device: CommandDevice = CommandDevice::from_template(&template("false {output}"))
+            .unwrap();
+        assert!(device.capture().is_err());
+        assert!(CommandDevice::from_template(&template("conf-count-missing-camera {output}"))
+            .unwrap().capture().is_err());
+    }
+}
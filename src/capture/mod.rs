@@ -0,0 +1,110 @@
+use std::fmt;
+use std::fs;
+use std::sync::Arc;
+
+use crypto::digest::Digest;
+use crypto::sha2::Sha256;
+use uuid::Uuid;
+
+use crate::capture::command::CommandDevice;
+use crate::capture::replay::ReplayDevice;
+use crate::config::{CameraConfig, CameraDevice};
+use crate::session::unix_time;
+
+pub mod command;
+
+pub mod replay;
+
+/// Failure of the camera
+#[derive(Debug, PartialEq)]
+pub struct CaptureError(pub String);
+
+impl fmt::Display for CaptureError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "Capture error: {}", self.0)
+    }
+}
+
+impl std::error::Error for CaptureError {}
+
+pub type CaptureResult<T> = Result<T, CaptureError>;
+
+/// Source of the images of the audience
+pub trait CaptureDevice: Send + Sync {
+    /// Captures an image and returns the path it can be read from
+    fn capture(&self) -> CaptureResult<String>;
+}
+
+/// Image of the audience, identified so that the attendance it marked can be audited
+#[derive(Clone, Debug, PartialEq)]
+pub struct Capture {
+    pub id: String,
+    pub image_path: String,
+    /// UNIX time in seconds of the capture
+    pub captured_at: i64,
+}
+
+impl Capture {
+    /// Identifies an image captured now
+    pub fn new(image_path: String) -> Capture {
+        Capture {
+            id: Uuid::new_v4().to_simple().to_string(),
+            image_path,
+            captured_at: unix_time() as i64,
+        }
+    }
+
+    /// Identifies an image captured now by its content, so that running the same image again
+    /// replaces what it showed instead of counting as another capture
+    ///
+    /// # Arguments
+    ///
+    /// * `image_path` - Path the captured image can be read from
+    ///
+    /// # Return
+    ///
+    /// Returns the capture, or the failure of reading the image
+    pub fn from_image(image_path: String) -> CaptureResult<Capture> {
+        let image: Vec<u8> = fs::read(&image_path).map_err(|read_error| {
+            CaptureError(format!("Unable to read {}: {}", image_path, read_error))
+        })?;
+        let mut hasher: Sha256 = Sha256::new();
+        hasher.input(&image);
+        Ok(Capture { id: hasher.result_str(), image_path, captured_at: unix_time() as i64 })
+    }
+}
+
+/// Creates the capture device selected in the configuration
+///
+/// # Arguments
+///
+/// * `camera_config` - Camera settings of the application
+///
+/// # Return
+///
+/// Returns the device shared by all the handlers, or the error of setting it up
+pub fn create_capture_device(camera_config: &CameraConfig)
+                             -> CaptureResult<Arc<dyn CaptureDevice>> {
+    Ok(match camera_config.device {
+        CameraDevice::Raspistill => Arc::new(CommandDevice::raspistill(camera_config)),
+        CameraDevice::LibcameraStill => Arc::new(CommandDevice::libcamera_still(camera_config)),
+        CameraDevice::Command => Arc::new(CommandDevice::from_template(camera_config)?),
+        CameraDevice::Replay =>
+            Arc::new(ReplayDevice::from_directory(&camera_config.replay_directory)?),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::capture::Capture;
+
+    #[test]
+    fn test_from_image_identifies_content() {
+        let capture: Capture = Capture::from_image("tests/resources/test.jpg".to_string()).unwrap();
+        assert_eq!(Capture::from_image("tests/resources/test.jpg".to_string()).unwrap().id,
+                   capture.id);
+        assert_ne!(Capture::from_image("tests/resources/test2.jpg".to_string()).unwrap().id,
+                   capture.id);
+        assert!(Capture::from_image("tests/resources/missing.jpg".to_string()).is_err());
+    }
+}
@@ -0,0 +1,71 @@
+use std::ffi::OsStr;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::capture::{CaptureDevice, CaptureError, CaptureResult};
+
+const IMAGE_EXTENSIONS: [&str; 3] = ["jpg", "jpeg", "png"];
+
+/// Device serving the images of a directory in the order of their names, starting over after
+/// the last one, so that captures can be replayed without a camera
+pub struct ReplayDevice {
+    images: Vec<PathBuf>,
+    next: Mutex<usize>,
+}
+
+impl ReplayDevice {
+    /// Creates a device replaying the images of a directory
+    ///
+    /// # Arguments
+    ///
+    /// * `directory` - Directory holding the JPEG and PNG images to replay
+    ///
+    /// # Return
+    ///
+    /// Returns the replay device, or an error when the directory holds no image
+    pub fn from_directory(directory: &str) -> CaptureResult<ReplayDevice> {
+        let mut images: Vec<PathBuf> = fs::read_dir(directory)
+            .map_err(|error| CaptureError(format!("Unable to read {}: {}", directory, error)))?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().and_then(OsStr::to_str)
+                .map_or(false, |extension| IMAGE_EXTENSIONS.contains(&extension)))
+            .collect();
+        if images.is_empty() {
+            return Err(CaptureError(format!("No image to replay in {}", directory)));
+        }
+        images.sort();
+        Ok(ReplayDevice { images, next: Mutex::new(0) })
+    }
+}
+
+impl CaptureDevice for ReplayDevice {
+    fn capture(&self) -> CaptureResult<String> {
+        let mut next = self.next.lock()
+            .map_err(|_| CaptureError("Replay device lock poisoned".to_string()))?;
+        let image: &PathBuf = &self.images[*next % self.images.len()];
+        *next += 1;
+        Ok(image.to_string_lossy().into_owned())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::capture::CaptureDevice;
+    use crate::capture::replay::ReplayDevice;
+
+    #[test]
+    fn test_capture_in_order_success() {
+        let device: ReplayDevice = ReplayDevice::from_directory("tests/resources").unwrap();
+        assert_eq!(device.capture().unwrap(), "tests/resources/test.jpg");
+        assert_eq!(device.capture().unwrap(), "tests/resources/test1.jpg");
+        assert_eq!(device.capture().unwrap(), "tests/resources/test2.jpg");
+        assert_eq!(device.capture().unwrap(), "tests/resources/test.jpg");
+    }
+
+    #[test]
+    fn test_from_directory_failure() {
+        assert!(ReplayDevice::from_directory("tests/missing_directory").is_err());
+        assert!(ReplayDevice::from_directory("src/capture").is_err());
+    }
+}
@@ -0,0 +1,1354 @@
+use std::{env, fmt, fs, io};
+use std::net::SocketAddr;
+use std::path::Path;
+use std::str::FromStr;
+
+use log::info;
+use rusoto_core::Region;
+
+use crate::utils::check_email_format;
+
+static CONFIG_PATH_VARIABLE: &str = "CONF_COUNT_CONFIG";
+static DEFAULT_CONFIG_PATH: &str = "conf_count.toml";
+
+/// Environment variables that override a value of the configuration file.
+/// The legacy names exported by older deployments are still honoured.
+static SOCKET_VARIABLES: [&str; 1] = ["CONF_COUNT_SOCKET"];
+static BLOCKING_THREADS_VARIABLES: [&str; 1] = ["CONF_COUNT_BLOCKING_THREADS"];
+static PUBLIC_URL_VARIABLES: [&str; 1] = ["CONF_COUNT_PUBLIC_URL"];
+static STORAGE_BACKEND_VARIABLES: [&str; 1] = ["CONF_COUNT_STORAGE_BACKEND"];
+static SQLITE_PATH_VARIABLES: [&str; 1] = ["CONF_COUNT_SQLITE_PATH"];
+static MONGO_HOST_VARIABLES: [&str; 2] = ["CONF_COUNT_MONGO_HOST", "Host"];
+static MONGO_PORT_VARIABLES: [&str; 1] = ["CONF_COUNT_MONGO_PORT"];
+static MONGO_DATABASE_VARIABLES: [&str; 1] = ["CONF_COUNT_MONGO_DATABASE"];
+static MONGO_POOL_SIZE_VARIABLES: [&str; 1] = ["CONF_COUNT_MONGO_POOL_SIZE"];
+static MONGO_CONNECT_TIMEOUT_VARIABLES: [&str; 1] = ["CONF_COUNT_MONGO_CONNECT_TIMEOUT_MS"];
+static MONGO_CHECKOUT_TIMEOUT_VARIABLES: [&str; 1] = ["CONF_COUNT_MONGO_CHECKOUT_TIMEOUT_MS"];
+static REGION_VARIABLES: [&str; 2] = ["CONF_COUNT_AWS_REGION", "Region"];
+static BUCKET_VARIABLES: [&str; 2] = ["CONF_COUNT_AWS_BUCKET", "Clicked_Image_Bucket"];
+static S3_ENDPOINT_VARIABLES: [&str; 1] = ["CONF_COUNT_S3_ENDPOINT"];
+static PHOTO_BACKEND_VARIABLES: [&str; 1] = ["CONF_COUNT_PHOTO_BACKEND"];
+static PHOTO_DIRECTORY_VARIABLES: [&str; 1] = ["CONF_COUNT_PHOTO_DIRECTORY"];
+static PHOTO_MAX_UPLOAD_VARIABLES: [&str; 1] = ["CONF_COUNT_PHOTO_MAX_UPLOAD_BYTES"];
+static PHOTO_MIN_DIMENSION_VARIABLES: [&str; 1] = ["CONF_COUNT_PHOTO_MIN_DIMENSION"];
+static PHOTO_MAX_PIXELS_VARIABLES: [&str; 1] = ["CONF_COUNT_PHOTO_MAX_PIXELS"];
+static PHOTO_MAX_DIMENSION_VARIABLES: [&str; 1] = ["CONF_COUNT_PHOTO_MAX_DIMENSION"];
+static PHOTO_JPEG_QUALITY_VARIABLES: [&str; 1] = ["CONF_COUNT_PHOTO_JPEG_QUALITY"];
+static PHOTO_MAX_STORED_VARIABLES: [&str; 1] = ["CONF_COUNT_PHOTO_MAX_STORED_BYTES"];
+static CLICKED_IMAGE_PATH_VARIABLES: [&str; 2] = ["CONF_COUNT_CLICKED_IMAGE_PATH",
+    "Clicked_Image_Path"];
+static CAMERA_DEVICE_VARIABLES: [&str; 1] = ["CONF_COUNT_CAMERA_DEVICE"];
+static CAMERA_COMMAND_VARIABLES: [&str; 1] = ["CONF_COUNT_CAMERA_COMMAND"];
+static REPLAY_DIRECTORY_VARIABLES: [&str; 1] = ["CONF_COUNT_REPLAY_DIRECTORY"];
+static SENDER_VARIABLES: [&str; 1] = ["CONF_COUNT_MAIL_SENDER"];
+static MAIL_TRANSPORT_VARIABLES: [&str; 1] = ["CONF_COUNT_MAIL_TRANSPORT"];
+static SMTP_HOST_VARIABLES: [&str; 1] = ["CONF_COUNT_SMTP_HOST"];
+static SMTP_PORT_VARIABLES: [&str; 1] = ["CONF_COUNT_SMTP_PORT"];
+static SMTP_STARTTLS_VARIABLES: [&str; 1] = ["CONF_COUNT_SMTP_STARTTLS"];
+static SMTP_USERNAME_VARIABLES: [&str; 1] = ["CONF_COUNT_SMTP_USERNAME"];
+static SMTP_PASSWORD_VARIABLES: [&str; 1] = ["CONF_COUNT_SMTP_PASSWORD"];
+static MAIL_SPOOL_VARIABLES: [&str; 1] = ["CONF_COUNT_MAIL_SPOOL"];
+static SIMILARITY_THRESHOLD_VARIABLES: [&str; 1] = ["CONF_COUNT_SIMILARITY_THRESHOLD"];
+static MATCHER_VARIABLES: [&str; 1] = ["CONF_COUNT_FACE_MATCHER"];
+static FACE_FIXTURES_VARIABLES: [&str; 1] = ["CONF_COUNT_FACE_FIXTURES"];
+static FACE_COLLECTION_VARIABLES: [&str; 1] = ["CONF_COUNT_FACE_COLLECTION"];
+static REVIEW_BAND_VARIABLES: [&str; 1] = ["CONF_COUNT_REVIEW_BAND"];
+static SESSION_SECRET_VARIABLES: [&str; 1] = ["CONF_COUNT_SESSION_SECRET"];
+static SESSION_TTL_VARIABLES: [&str; 1] = ["CONF_COUNT_SESSION_TTL_MINUTES"];
+static SESSION_SECURE_COOKIE_VARIABLES: [&str; 1] = ["CONF_COUNT_SESSION_SECURE_COOKIE"];
+static RESET_TOKEN_TTL_VARIABLES: [&str; 1] = ["CONF_COUNT_RESET_TOKEN_TTL_MINUTES"];
+static VERIFICATION_TTL_VARIABLES: [&str; 1] = ["CONF_COUNT_VERIFICATION_TTL_HOURS"];
+static ACCOUNT_FREE_FAILURES_VARIABLES: [&str; 1] = ["CONF_COUNT_LOGIN_ACCOUNT_FREE_FAILURES"];
+static ACCOUNT_LOCKOUT_FAILURES_VARIABLES: [&str; 1] =
+    ["CONF_COUNT_LOGIN_ACCOUNT_LOCKOUT_FAILURES"];
+static CLIENT_FREE_FAILURES_VARIABLES: [&str; 1] = ["CONF_COUNT_LOGIN_CLIENT_FREE_FAILURES"];
+static CLIENT_LOCKOUT_FAILURES_VARIABLES: [&str; 1] = ["CONF_COUNT_LOGIN_CLIENT_LOCKOUT_FAILURES"];
+static BASE_DELAY_VARIABLES: [&str; 1] = ["CONF_COUNT_LOGIN_BASE_DELAY_SECONDS"];
+static MAX_DELAY_VARIABLES: [&str; 1] = ["CONF_COUNT_LOGIN_MAX_DELAY_SECONDS"];
+static LOCKOUT_VARIABLES: [&str; 1] = ["CONF_COUNT_LOGIN_LOCKOUT_MINUTES"];
+static FAILURE_RESET_VARIABLES: [&str; 1] = ["CONF_COUNT_LOGIN_RESET_AFTER_MINUTES"];
+static FACE_CONFIDENCE_VARIABLES: [&str; 1] = ["CONF_COUNT_ENROLLMENT_MIN_FACE_CONFIDENCE"];
+static MIN_BRIGHTNESS_VARIABLES: [&str; 1] = ["CONF_COUNT_ENROLLMENT_MIN_BRIGHTNESS"];
+static MAX_BRIGHTNESS_VARIABLES: [&str; 1] = ["CONF_COUNT_ENROLLMENT_MAX_BRIGHTNESS"];
+static MIN_SHARPNESS_VARIABLES: [&str; 1] = ["CONF_COUNT_ENROLLMENT_MIN_SHARPNESS"];
+
+/// Shortest secret accepted to sign the sessions
+static MIN_SESSION_SECRET_LENGTH: usize = 32;
+
+/// Settings of the whole application, loaded once at startup
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct AppConfig {
+    pub server: ServerConfig,
+    pub storage: StorageConfig,
+    pub mongo: MongoConfig,
+    pub aws: AwsConfig,
+    pub photos: PhotoConfig,
+    pub camera: CameraConfig,
+    pub mail: MailConfig,
+    pub matching: MatchingConfig,
+    pub session: SessionConfig,
+    pub password_reset: PasswordResetConfig,
+    pub verification: VerificationConfig,
+    pub login_throttle: LoginThrottleConfig,
+    pub enrollment: EnrollmentConfig,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ServerConfig {
+    pub socket: String,
+    /// Threads running the blocking calls to the database, the camera and the AWS services
+    pub blocking_threads: usize,
+    /// Address the users reach the application at, used in the links of the mails
+    pub public_url: String,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            socket: "127.0.0.1:8088".to_string(),
+            blocking_threads: 4,
+            public_url: "http://127.0.0.1:8088".to_string(),
+        }
+    }
+}
+
+/// Backends able to persist users, conferences and attendance
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    Mongo,
+    Memory,
+    Sqlite,
+}
+
+impl FromStr for StorageBackend {
+    type Err = ();
+
+    fn from_str(backend: &str) -> Result<Self, Self::Err> {
+        match backend {
+            "mongo" => Ok(StorageBackend::Mongo),
+            "memory" => Ok(StorageBackend::Memory),
+            "sqlite" => Ok(StorageBackend::Sqlite),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct StorageConfig {
+    pub backend: StorageBackend,
+    /// Database file used by the SQLite backend, created on first start
+    pub sqlite_path: String,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        StorageConfig {
+            backend: StorageBackend::Mongo,
+            sqlite_path: "conf_count.db".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct MongoConfig {
+    pub host: String,
+    pub port: u16,
+    pub database: String,
+    /// Most operations checked out against MongoDB at the same time, the driver keeping its own
+    /// connections
+    pub pool_size: usize,
+    /// Milliseconds to wait for a reachable server
+    pub connect_timeout_ms: u64,
+    /// Milliseconds an operation waits to be checked out once the limit is reached
+    pub checkout_timeout_ms: u64,
+}
+
+impl Default for MongoConfig {
+    fn default() -> Self {
+        MongoConfig {
+            host: String::new(),
+            port: 27017,
+            database: "Conf_Count".to_string(),
+            pool_size: 5,
+            connect_timeout_ms: 5000,
+            checkout_timeout_ms: 5000,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct AwsConfig {
+    pub region: String,
+    pub bucket: String,
+    /// Endpoint of an S3 compatible service such as MinIO, empty for Amazon S3
+    pub endpoint: String,
+}
+
+/// Stores able to keep the reference photos of the users
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum PhotoBackend {
+    S3,
+    Local,
+}
+
+impl FromStr for PhotoBackend {
+    type Err = ();
+
+    fn from_str(backend: &str) -> Result<Self, Self::Err> {
+        match backend {
+            "s3" => Ok(PhotoBackend::S3),
+            "local" => Ok(PhotoBackend::Local),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct PhotoConfig {
+    pub backend: PhotoBackend,
+    /// Directory the `local` backend keeps one file per user in, created on first start
+    pub directory: String,
+    /// Largest photo accepted at signup, before any processing
+    pub max_upload_bytes: u64,
+    /// Shortest side of the accepted photos, the face matching needs at least 80 pixels
+    pub min_dimension: u32,
+    /// Most pixels of the accepted photos, checked before decoding them
+    pub max_pixels: u64,
+    /// Longest side of the stored photos, the larger ones are scaled down
+    pub max_dimension: u32,
+    /// Quality of the stored JPEG, lowered down to 50 while the photo exceeds `max_stored_bytes`
+    pub jpeg_quality: u8,
+    /// Largest stored photo, at most the 5 MB the face matching accepts
+    pub max_stored_bytes: u64,
+}
+
+impl Default for PhotoConfig {
+    fn default() -> Self {
+        PhotoConfig {
+            backend: PhotoBackend::S3,
+            directory: "photos".to_string(),
+            max_upload_bytes: 10 * 1024 * 1024,
+            min_dimension: 80,
+            max_pixels: 40_000_000,
+            max_dimension: 1920,
+            jpeg_quality: 90,
+            max_stored_bytes: 5 * 1024 * 1024,
+        }
+    }
+}
+
+impl PhotoConfig {
+    fn problems(&self) -> Vec<String> {
+        let mut problems: Vec<String> = Vec::new();
+        if self.max_upload_bytes == 0 || self.max_stored_bytes == 0 {
+            problems.push("photos.max_upload_bytes and photos.max_stored_bytes must be greater \
+                           than 0".to_string());
+        }
+        if self.min_dimension == 0 || self.max_dimension < self.min_dimension {
+            problems.push("photos.min_dimension must be greater than 0 and at most \
+                           photos.max_dimension".to_string());
+        }
+        if self.max_pixels < u64::from(self.min_dimension) * u64::from(self.min_dimension) {
+            problems.push("photos.max_pixels must allow a photo of photos.min_dimension \
+                           squared".to_string());
+        }
+        if !(50..=100).contains(&self.jpeg_quality) {
+            problems.push("photos.jpeg_quality must be between 50 and 100".to_string());
+        }
+        problems
+    }
+}
+
+/// Devices able to capture an image of the audience
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum CameraDevice {
+    Raspistill,
+    LibcameraStill,
+    Command,
+    Replay,
+}
+
+impl FromStr for CameraDevice {
+    type Err = ();
+
+    fn from_str(device: &str) -> Result<Self, Self::Err> {
+        match device {
+            "raspistill" => Ok(CameraDevice::Raspistill),
+            "libcamera-still" => Ok(CameraDevice::LibcameraStill),
+            "command" => Ok(CameraDevice::Command),
+            "replay" => Ok(CameraDevice::Replay),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Camera settings, expressed on the scales of raspistill
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct CameraConfig {
+    pub clicked_image_path: String,
+    pub device: CameraDevice,
+    /// Milliseconds the camera runs before capturing
+    pub timeout: u32,
+    /// JPEG quality, 0 to 100
+    pub quality: u8,
+    /// -100 to 100
+    pub contrast: i32,
+    /// -100 to 100
+    pub sharpness: i32,
+    /// 0 to 100
+    pub brightness: i32,
+    /// Command line of the `command` device, with `{output}`, `{timeout}`, `{quality}`,
+    /// `{contrast}`, `{sharpness}` and `{brightness}` placeholders
+    pub command: String,
+    /// Directory whose images the `replay` device serves in order
+    pub replay_directory: String,
+}
+
+impl Default for CameraConfig {
+    fn default() -> Self {
+        CameraConfig {
+            clicked_image_path: String::new(),
+            device: CameraDevice::Raspistill,
+            timeout: 3000,
+            quality: 100,
+            contrast: 50,
+            sharpness: 30,
+            brightness: 60,
+            command: String::new(),
+            replay_directory: String::new(),
+        }
+    }
+}
+
+impl CameraConfig {
+    fn problems(&self) -> Vec<String> {
+        let mut problems: Vec<String> = Vec::new();
+        if self.quality > 100 {
+            problems.push("camera.quality must be between 0 and 100".to_string());
+        }
+        if !(-100..=100).contains(&self.contrast) {
+            problems.push("camera.contrast must be between -100 and 100".to_string());
+        }
+        if !(-100..=100).contains(&self.sharpness) {
+            problems.push("camera.sharpness must be between -100 and 100".to_string());
+        }
+        if !(0..=100).contains(&self.brightness) {
+            problems.push("camera.brightness must be between 0 and 100".to_string());
+        }
+        if self.device == CameraDevice::Command && !self.command.contains("{output}") {
+            problems.push("camera.command must write the image to {output}".to_string());
+        }
+        if self.device == CameraDevice::Replay && self.replay_directory.trim().is_empty() {
+            problems.push("camera.replay_directory is required by the replay device".to_string());
+        }
+        problems
+    }
+}
+
+/// Ways of delivering the mails of the application
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum MailTransport {
+    Smtp,
+    Spool,
+    Memory,
+}
+
+impl FromStr for MailTransport {
+    type Err = ();
+
+    fn from_str(transport: &str) -> Result<Self, Self::Err> {
+        match transport {
+            "smtp" => Ok(MailTransport::Smtp),
+            "spool" => Ok(MailTransport::Spool),
+            "memory" => Ok(MailTransport::Memory),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct MailConfig {
+    pub sender: String,
+    pub transport: MailTransport,
+    pub smtp_host: String,
+    pub smtp_port: u16,
+    /// Upgrades the SMTP connection with STARTTLS, refusing to send when the server can not
+    pub starttls: bool,
+    pub username: String,
+    pub password: String,
+    /// Directory the `spool` transport writes every mail to
+    pub spool_directory: String,
+}
+
+impl Default for MailConfig {
+    fn default() -> Self {
+        MailConfig {
+            sender: "ayush@knoldus.com".to_string(),
+            transport: MailTransport::Smtp,
+            smtp_host: "localhost".to_string(),
+            smtp_port: 25,
+            starttls: false,
+            username: String::new(),
+            password: String::new(),
+            spool_directory: String::new(),
+        }
+    }
+}
+
+impl MailConfig {
+    fn problems(&self) -> Vec<String> {
+        let mut problems: Vec<String> = Vec::new();
+        if !check_email_format(self.sender.as_str()) {
+            problems.push(format!("mail.sender {:?} is not a valid email", self.sender));
+        }
+        match self.transport {
+            MailTransport::Smtp => {
+                if self.smtp_host.trim().is_empty() {
+                    problems.push("mail.smtp_host must not be empty".to_string());
+                }
+                if self.smtp_port == 0 {
+                    problems.push("mail.smtp_port must be greater than 0".to_string());
+                }
+                if self.username.is_empty() != self.password.is_empty() {
+                    problems.push("mail.username and mail.password go together".to_string());
+                }
+            }
+            MailTransport::Spool if self.spool_directory.trim().is_empty() => {
+                problems.push("mail.spool_directory is required by the spool transport"
+                    .to_string());
+            }
+            _ => {}
+        }
+        problems
+    }
+}
+
+/// Services able to tell how similar two faces are
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum MatcherBackend {
+    Rekognition,
+    Fixtures,
+}
+
+impl FromStr for MatcherBackend {
+    type Err = ();
+
+    fn from_str(backend: &str) -> Result<Self, Self::Err> {
+        match backend {
+            "rekognition" => Ok(MatcherBackend::Rekognition),
+            "fixtures" => Ok(MatcherBackend::Fixtures),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct MatchingConfig {
+    pub similarity_threshold: f32,
+    pub matcher: MatcherBackend,
+    /// Similarities served by the fixtures matcher
+    pub fixtures_path: String,
+    /// Rekognition collection indexing the reference faces, created when missing
+    pub collection_id: String,
+    /// Similarity points under the threshold of a conference within which a match waits for an
+    /// admin to review it instead of marking the user absent, 0 to disable the reviews
+    pub review_band: f32,
+}
+
+impl Default for MatchingConfig {
+    fn default() -> Self {
+        MatchingConfig {
+            similarity_threshold: 75.0,
+            matcher: MatcherBackend::Rekognition,
+            fixtures_path: String::new(),
+            collection_id: "conf-count-faces".to_string(),
+            review_band: 10.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct SessionConfig {
+    /// Key signing the session tokens, every token is invalidated when it changes
+    pub secret: String,
+    pub ttl_minutes: u64,
+    /// Sends the session cookie over HTTPS only
+    pub secure_cookie: bool,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        SessionConfig {
+            secret: String::new(),
+            ttl_minutes: 720,
+            secure_cookie: false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct PasswordResetConfig {
+    /// How long the link mailed by `/forgot` can be used
+    pub token_ttl_minutes: i64,
+}
+
+impl Default for PasswordResetConfig {
+    fn default() -> Self {
+        PasswordResetConfig {
+            token_ttl_minutes: 30,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct VerificationConfig {
+    /// How long a new account waits for its email address to be verified before it is deleted
+    pub pending_ttl_hours: i64,
+}
+
+impl Default for VerificationConfig {
+    fn default() -> Self {
+        VerificationConfig {
+            pending_ttl_hours: 48,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct LoginThrottleConfig {
+    /// Failed logins of an account answered without any delay
+    pub account_free_failures: u32,
+    /// Failed logins locking an account out
+    pub account_lockout_failures: u32,
+    /// Failed logins of a client IP answered without any delay, across all the accounts
+    pub client_free_failures: u32,
+    /// Failed logins locking a client IP out
+    pub client_lockout_failures: u32,
+    /// Delay after the first failure past the free ones, doubled by every further failure
+    pub base_delay_seconds: u64,
+    pub max_delay_seconds: u64,
+    pub lockout_minutes: u64,
+    /// The failures are forgotten after this long without any new one
+    pub reset_after_minutes: u64,
+}
+
+impl Default for LoginThrottleConfig {
+    fn default() -> Self {
+        LoginThrottleConfig {
+            account_free_failures: 3,
+            account_lockout_failures: 10,
+            client_free_failures: 20,
+            client_lockout_failures: 100,
+            base_delay_seconds: 1,
+            max_delay_seconds: 300,
+            lockout_minutes: 15,
+            reset_after_minutes: 60,
+        }
+    }
+}
+
+impl LoginThrottleConfig {
+    fn problems(&self) -> Vec<String> {
+        let mut problems: Vec<String> = Vec::new();
+        if self.account_lockout_failures <= self.account_free_failures {
+            problems.push("login_throttle.account_lockout_failures must be greater than \
+                           login_throttle.account_free_failures".to_string());
+        }
+        if self.client_lockout_failures <= self.client_free_failures {
+            problems.push("login_throttle.client_lockout_failures must be greater than \
+                           login_throttle.client_free_failures".to_string());
+        }
+        if self.base_delay_seconds == 0 || self.max_delay_seconds < self.base_delay_seconds {
+            problems.push("login_throttle.base_delay_seconds must be greater than 0 and at most \
+                           login_throttle.max_delay_seconds".to_string());
+        }
+        if self.lockout_minutes == 0 || self.reset_after_minutes == 0 {
+            problems.push("login_throttle.lockout_minutes and login_throttle.reset_after_minutes \
+                           must be greater than 0".to_string());
+        }
+        problems
+    }
+}
+
+/// Quality required of the face of the photo uploaded at signup, on the 0 to 100 scales of the
+/// face matching backend
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct EnrollmentConfig {
+    /// Confidence below which a detected face is considered unclear
+    pub min_face_confidence: f32,
+    pub min_brightness: f32,
+    pub max_brightness: f32,
+    pub min_sharpness: f32,
+}
+
+impl Default for EnrollmentConfig {
+    fn default() -> Self {
+        EnrollmentConfig {
+            min_face_confidence: 90.0,
+            min_brightness: 20.0,
+            max_brightness: 95.0,
+            min_sharpness: 10.0,
+        }
+    }
+}
+
+impl EnrollmentConfig {
+    fn problems(&self) -> Vec<String> {
+        let mut problems: Vec<String> = Vec::new();
+        let measures: [f32; 4] = [self.min_face_confidence, self.min_brightness,
+            self.max_brightness, self.min_sharpness];
+        if measures.iter().any(|measure| !(0.0..=100.0).contains(measure)) {
+            problems.push("enrollment thresholds must be between 0 and 100".to_string());
+        }
+        if self.max_brightness <= self.min_brightness {
+            problems.push("enrollment.max_brightness must be greater than \
+                           enrollment.min_brightness".to_string());
+        }
+        problems
+    }
+}
+
+/// Reasons for which the configuration could not be loaded
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(String, io::Error),
+    Parse(String, toml::de::Error),
+    Override(&'static str, String),
+    Invalid(Vec<String>),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Io(path, error) =>
+                write!(formatter, "Unable to read config file {}: {}", path, error),
+            ConfigError::Parse(path, error) =>
+                write!(formatter, "Unable to parse config file {}: {}", path, error),
+            ConfigError::Override(variable, value) =>
+                write!(formatter, "Environment variable {} has an invalid value {:?}",
+                       variable, value),
+            ConfigError::Invalid(problems) =>
+                write!(formatter, "Invalid configuration: {}", problems.join("; ")),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl AppConfig {
+    /// Loads the configuration file named by `CONF_COUNT_CONFIG` (or `conf_count.toml` in the
+    /// working directory when present), applies the environment overrides and validates the
+    /// result
+    ///
+    /// # Return
+    ///
+    /// Returns the validated configuration or the reason it is unusable
+    pub fn load() -> Result<AppConfig, ConfigError> {
+        let mut config: AppConfig = match env::var(CONFIG_PATH_VARIABLE) {
+            Ok(path) => AppConfig::from_file(path.as_str())?,
+            Err(_) if Path::new(DEFAULT_CONFIG_PATH).exists() =>
+                AppConfig::from_file(DEFAULT_CONFIG_PATH)?,
+            Err(_) => {
+                info!("No config file found, using defaults and environment variables");
+                AppConfig::default()
+            }
+        };
+        config.apply_overrides(|variable| env::var(variable).ok())?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Parses a configuration file without applying overrides
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the TOML file
+    ///
+    /// # Return
+    ///
+    /// Returns the parsed configuration
+    pub fn from_file(path: &str) -> Result<AppConfig, ConfigError> {
+        let contents: String = fs::read_to_string(path)
+            .map_err(|error| ConfigError::Io(path.to_string(), error))?;
+        toml::from_str(contents.as_str())
+            .map_err(|error| ConfigError::Parse(path.to_string(), error))
+    }
+
+    /// Replaces configured values with the ones found in the environment
+    ///
+    /// # Arguments
+    ///
+    /// * `lookup` - Returns the value of an environment variable, if set
+    ///
+    /// # Return
+    ///
+    /// Returns an error when an override cannot be parsed
+    pub fn apply_overrides<F>(&mut self, lookup: F) -> Result<(), ConfigError>
+        where F: Fn(&str) -> Option<String> {
+        let find = |variables: &[&'static str]| variables.iter()
+            .find_map(|variable| lookup(variable).map(|value| (*variable, value)));
+
+        if let Some((_, socket)) = find(&SOCKET_VARIABLES) {
+            self.server.socket = socket;
+        }
+        if let Some((variable, threads)) = find(&BLOCKING_THREADS_VARIABLES) {
+            self.server.blocking_threads = parse_override(variable, threads)?;
+        }
+        if let Some((_, public_url)) = find(&PUBLIC_URL_VARIABLES) {
+            self.server.public_url = public_url;
+        }
+        if let Some((variable, backend)) = find(&STORAGE_BACKEND_VARIABLES) {
+            self.storage.backend = parse_override(variable, backend)?;
+        }
+        if let Some((_, path)) = find(&SQLITE_PATH_VARIABLES) {
+            self.storage.sqlite_path = path;
+        }
+        if let Some((_, host)) = find(&MONGO_HOST_VARIABLES) {
+            self.mongo.host = host;
+        }
+        if let Some((variable, port)) = find(&MONGO_PORT_VARIABLES) {
+            self.mongo.port = parse_override(variable, port)?;
+        }
+        if let Some((_, database)) = find(&MONGO_DATABASE_VARIABLES) {
+            self.mongo.database = database;
+        }
+        if let Some((variable, pool_size)) = find(&MONGO_POOL_SIZE_VARIABLES) {
+            self.mongo.pool_size = parse_override(variable, pool_size)?;
+        }
+        if let Some((variable, timeout)) = find(&MONGO_CONNECT_TIMEOUT_VARIABLES) {
+            self.mongo.connect_timeout_ms = parse_override(variable, timeout)?;
+        }
+        if let Some((variable, timeout)) = find(&MONGO_CHECKOUT_TIMEOUT_VARIABLES) {
+            self.mongo.checkout_timeout_ms = parse_override(variable, timeout)?;
+        }
+        if let Some((_, region)) = find(&REGION_VARIABLES) {
+            self.aws.region = region;
+        }
+        if let Some((_, bucket)) = find(&BUCKET_VARIABLES) {
+            self.aws.bucket = bucket;
+        }
+        if let Some((_, endpoint)) = find(&S3_ENDPOINT_VARIABLES) {
+            self.aws.endpoint = endpoint;
+        }
+        if let Some((variable, backend)) = find(&PHOTO_BACKEND_VARIABLES) {
+            self.photos.backend = parse_override(variable, backend)?;
+        }
+        if let Some((_, directory)) = find(&PHOTO_DIRECTORY_VARIABLES) {
+            self.photos.directory = directory;
+        }
+        if let Some((variable, bytes)) = find(&PHOTO_MAX_UPLOAD_VARIABLES) {
+            self.photos.max_upload_bytes = parse_override(variable, bytes)?;
+        }
+        if let Some((variable, dimension)) = find(&PHOTO_MIN_DIMENSION_VARIABLES) {
+            self.photos.min_dimension = parse_override(variable, dimension)?;
+        }
+        if let Some((variable, pixels)) = find(&PHOTO_MAX_PIXELS_VARIABLES) {
+            self.photos.max_pixels = parse_override(variable, pixels)?;
+        }
+        if let Some((variable, dimension)) = find(&PHOTO_MAX_DIMENSION_VARIABLES) {
+            self.photos.max_dimension = parse_override(variable, dimension)?;
+        }
+        if let Some((variable, quality)) = find(&PHOTO_JPEG_QUALITY_VARIABLES) {
+            self.photos.jpeg_quality = parse_override(variable, quality)?;
+        }
+        if let Some((variable, bytes)) = find(&PHOTO_MAX_STORED_VARIABLES) {
+            self.photos.max_stored_bytes = parse_override(variable, bytes)?;
+        }
+        if let Some((_, path)) = find(&CLICKED_IMAGE_PATH_VARIABLES) {
+            self.camera.clicked_image_path = path;
+        }
+        if let Some((variable, device)) = find(&CAMERA_DEVICE_VARIABLES) {
+            self.camera.device = parse_override(variable, device)?;
+        }
+        if let Some((_, command)) = find(&CAMERA_COMMAND_VARIABLES) {
+            self.camera.command = command;
+        }
+        if let Some((_, directory)) = find(&REPLAY_DIRECTORY_VARIABLES) {
+            self.camera.replay_directory = directory;
+        }
+        if let Some((_, sender)) = find(&SENDER_VARIABLES) {
+            self.mail.sender = sender;
+        }
+        if let Some((variable, transport)) = find(&MAIL_TRANSPORT_VARIABLES) {
+            self.mail.transport = parse_override(variable, transport)?;
+        }
+        if let Some((_, host)) = find(&SMTP_HOST_VARIABLES) {
+            self.mail.smtp_host = host;
+        }
+        if let Some((variable, port)) = find(&SMTP_PORT_VARIABLES) {
+            self.mail.smtp_port = parse_override(variable, port)?;
+        }
+        if let Some((variable, starttls)) = find(&SMTP_STARTTLS_VARIABLES) {
+            self.mail.starttls = parse_override(variable, starttls)?;
+        }
+        if let Some((_, username)) = find(&SMTP_USERNAME_VARIABLES) {
+            self.mail.username = username;
+        }
+        if let Some((_, password)) = find(&SMTP_PASSWORD_VARIABLES) {
+            self.mail.password = password;
+        }
+        if let Some((_, directory)) = find(&MAIL_SPOOL_VARIABLES) {
+            self.mail.spool_directory = directory;
+        }
+        if let Some((variable, threshold)) = find(&SIMILARITY_THRESHOLD_VARIABLES) {
+            self.matching.similarity_threshold = parse_override(variable, threshold)?;
+        }
+        if let Some((variable, matcher)) = find(&MATCHER_VARIABLES) {
+            self.matching.matcher = parse_override(variable, matcher)?;
+        }
+        if let Some((_, path)) = find(&FACE_FIXTURES_VARIABLES) {
+            self.matching.fixtures_path = path;
+        }
+        if let Some((_, collection)) = find(&FACE_COLLECTION_VARIABLES) {
+            self.matching.collection_id = collection;
+        }
+        if let Some((variable, band)) = find(&REVIEW_BAND_VARIABLES) {
+            self.matching.review_band = parse_override(variable, band)?;
+        }
+        if let Some((_, secret)) = find(&SESSION_SECRET_VARIABLES) {
+            self.session.secret = secret;
+        }
+        if let Some((variable, ttl)) = find(&SESSION_TTL_VARIABLES) {
+            self.session.ttl_minutes = parse_override(variable, ttl)?;
+        }
+        if let Some((variable, secure)) = find(&SESSION_SECURE_COOKIE_VARIABLES) {
+            self.session.secure_cookie = parse_override(variable, secure)?;
+        }
+        if let Some((variable, ttl)) = find(&RESET_TOKEN_TTL_VARIABLES) {
+            self.password_reset.token_ttl_minutes = parse_override(variable, ttl)?;
+        }
+        if let Some((variable, ttl)) = find(&VERIFICATION_TTL_VARIABLES) {
+            self.verification.pending_ttl_hours = parse_override(variable, ttl)?;
+        }
+        if let Some((variable, failures)) = find(&ACCOUNT_FREE_FAILURES_VARIABLES) {
+            self.login_throttle.account_free_failures = parse_override(variable, failures)?;
+        }
+        if let Some((variable, failures)) = find(&ACCOUNT_LOCKOUT_FAILURES_VARIABLES) {
+            self.login_throttle.account_lockout_failures = parse_override(variable, failures)?;
+        }
+        if let Some((variable, failures)) = find(&CLIENT_FREE_FAILURES_VARIABLES) {
+            self.login_throttle.client_free_failures = parse_override(variable, failures)?;
+        }
+        if let Some((variable, failures)) = find(&CLIENT_LOCKOUT_FAILURES_VARIABLES) {
+            self.login_throttle.client_lockout_failures = parse_override(variable, failures)?;
+        }
+        if let Some((variable, delay)) = find(&BASE_DELAY_VARIABLES) {
+            self.login_throttle.base_delay_seconds = parse_override(variable, delay)?;
+        }
+        if let Some((variable, delay)) = find(&MAX_DELAY_VARIABLES) {
+            self.login_throttle.max_delay_seconds = parse_override(variable, delay)?;
+        }
+        if let Some((variable, lockout)) = find(&LOCKOUT_VARIABLES) {
+            self.login_throttle.lockout_minutes = parse_override(variable, lockout)?;
+        }
+        if let Some((variable, reset)) = find(&FAILURE_RESET_VARIABLES) {
+            self.login_throttle.reset_after_minutes = parse_override(variable, reset)?;
+        }
+        if let Some((variable, confidence)) = find(&FACE_CONFIDENCE_VARIABLES) {
+            self.enrollment.min_face_confidence = parse_override(variable, confidence)?;
+        }
+        if let Some((variable, brightness)) = find(&MIN_BRIGHTNESS_VARIABLES) {
+            self.enrollment.min_brightness = parse_override(variable, brightness)?;
+        }
+        if let Some((variable, brightness)) = find(&MAX_BRIGHTNESS_VARIABLES) {
+            self.enrollment.max_brightness = parse_override(variable, brightness)?;
+        }
+        if let Some((variable, sharpness)) = find(&MIN_SHARPNESS_VARIABLES) {
+            self.enrollment.min_sharpness = parse_override(variable, sharpness)?;
+        }
+        Ok(())
+    }
+
+    /// Checks that every setting is usable
+    ///
+    /// # Return
+    ///
+    /// Returns all the problems found in the configuration
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut problems: Vec<String> = Vec::new();
+        if SocketAddr::from_str(self.server.socket.as_str()).is_err() {
+            problems.push(format!("server.socket {:?} is not a valid socket address",
+                                  self.server.socket));
+        }
+        if self.server.blocking_threads == 0 {
+            problems.push("server.blocking_threads must be greater than 0".to_string());
+        }
+        if !self.server.public_url.starts_with("http://")
+            && !self.server.public_url.starts_with("https://") {
+            problems.push(format!("server.public_url {:?} is not an http(s) URL",
+                                  self.server.public_url));
+        }
+        if self.storage.backend == StorageBackend::Mongo {
+            if self.mongo.host.trim().is_empty() {
+                problems.push("mongo.host is required (or export Host)".to_string());
+            }
+            if self.mongo.port == 0 {
+                problems.push("mongo.port must be greater than 0".to_string());
+            }
+            if self.mongo.database.trim().is_empty() {
+                problems.push("mongo.database must not be empty".to_string());
+            }
+            if self.mongo.pool_size == 0 {
+                problems.push("mongo.pool_size must be greater than 0".to_string());
+            }
+            if self.mongo.connect_timeout_ms == 0 || self.mongo.checkout_timeout_ms == 0 {
+                problems.push("mongo timeouts must be greater than 0".to_string());
+            }
+        }
+        if self.storage.backend == StorageBackend::Sqlite
+            && self.storage.sqlite_path.trim().is_empty() {
+            problems.push("storage.sqlite_path must not be empty".to_string());
+        }
+        let amazon_s3: bool =
+            self.photos.backend == PhotoBackend::S3 && self.aws.endpoint.trim().is_empty();
+        if (amazon_s3 || self.matching.matcher == MatcherBackend::Rekognition)
+            && Region::from_str(self.aws.region.as_str()).is_err() {
+            problems.push(format!("aws.region {:?} is not a known AWS region (or export Region)",
+                                  self.aws.region));
+        }
+        match self.photos.backend {
+            PhotoBackend::S3 if self.aws.bucket.trim().is_empty() => problems.push(
+                "aws.bucket is required (or export Clicked_Image_Bucket)".to_string()),
+            PhotoBackend::Local if self.photos.directory.trim().is_empty() => problems.push(
+                "photos.directory is required by the local backend".to_string()),
+            _ => {}
+        }
+        problems.extend(self.photos.problems());
+        if self.camera.clicked_image_path.trim().is_empty() {
+            problems.push(
+                "camera.clicked_image_path is required (or export Clicked_Image_Path)"
+                    .to_string());
+        }
+        problems.extend(self.camera.problems());
+        problems.extend(self.mail.problems());
+        if !(0.0..=100.0).contains(&self.matching.similarity_threshold) {
+            problems.push("matching.similarity_threshold must be between 0 and 100".to_string());
+        }
+        if !(0.0..=100.0).contains(&self.matching.review_band) {
+            problems.push("matching.review_band must be between 0 and 100".to_string());
+        }
+        if self.matching.matcher == MatcherBackend::Fixtures
+            && self.matching.fixtures_path.trim().is_empty() {
+            problems.push("matching.fixtures_path is required by the fixtures matcher".to_string());
+        }
+        if self.matching.matcher == MatcherBackend::Rekognition
+            && !valid_collection_id(self.matching.collection_id.as_str()) {
+            problems.push(format!("matching.collection_id {:?} must be made of letters, digits, \
+                                   '_', '.' and '-'", self.matching.collection_id));
+        }
+        if self.session.secret.len() < MIN_SESSION_SECRET_LENGTH {
+            problems.push(format!("session.secret must be at least {} characters long",
+                                  MIN_SESSION_SECRET_LENGTH));
+        }
+        if self.session.ttl_minutes == 0 {
+            problems.push("session.ttl_minutes must be greater than 0".to_string());
+        }
+        if self.password_reset.token_ttl_minutes <= 0 {
+            problems.push("password_reset.token_ttl_minutes must be greater than 0".to_string());
+        }
+        if self.verification.pending_ttl_hours <= 0 {
+            problems.push("verification.pending_ttl_hours must be greater than 0".to_string());
+        }
+        problems.extend(self.login_throttle.problems());
+        problems.extend(self.enrollment.problems());
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(problems))
+        }
+    }
+}
+
+/// Checks that an id is accepted by Rekognition as the id of a collection
+fn valid_collection_id(collection_id: &str) -> bool {
+    !collection_id.is_empty() && collection_id.len() <= 255 && collection_id.chars()
+        .all(|character| character.is_ascii_alphanumeric() || "_.-".contains(character))
+}
+
+/// Parses the value of an environment override
+///
+/// # Arguments
+///
+/// * `variable` - Name of the environment variable
+///
+/// * `value` - Value of the environment variable
+///
+/// # Return
+///
+/// Returns the parsed value
+fn parse_override<T: FromStr>(variable: &'static str, value: String) -> Result<T, ConfigError> {
+    value.trim().parse().map_err(|_| ConfigError::Override(variable, value))
+}
+
+#[cfg(test)]
+pub mod test {
+    use std::collections::HashMap;
+
+    use crate::config::{AppConfig, CameraDevice, ConfigError, MailTransport, MatcherBackend,
+                        PhotoBackend, SessionConfig, StorageBackend};
+
+    static TEST_CONFIG: &str = r#"
+        [server]
+        socket = "0.0.0.0:9000"
+
+        [storage]
+        backend = "memory"
+
+        [mongo]
+        host = "localhost"
+
+        [aws]
+        region = "ap-south-1"
+        bucket = "labelsfacedetect"
+
+        [photos]
+        backend = "local"
+        directory = "target/test_photos"
+
+        [camera]
+        clicked_image_path = "tests/resources/test.jpg"
+        device = "replay"
+        replay_directory = "tests/resources"
+
+        [mail]
+        transport = "memory"
+
+        [matching]
+        matcher = "fixtures"
+        fixtures_path = "tests/resources/face_fixtures.toml"
+
+        [session]
+        secret = "test-secret-signing-the-conf-count-sessions"
+    "#;
+
+    /// Configuration shared by the tests of the crate
+    pub fn test_config() -> AppConfig {
+        toml::from_str(TEST_CONFIG).unwrap()
+    }
+
+    #[test]
+    fn test_parse_config_success() {
+        let config: AppConfig = test_config();
+        assert_eq!(config.server.socket, "0.0.0.0:9000");
+        assert_eq!(config.mongo.port, 27017);
+        assert_eq!(config.server.blocking_threads, 4);
+        assert_eq!(config.matching.similarity_threshold, 75.0);
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_from_file_failure() {
+        assert!(matches!(AppConfig::from_file("tests/resources/missing.toml"),
+                         Err(ConfigError::Io(_, _))))
+    }
+
+    #[test]
+    fn test_apply_overrides_success() {
+        let mut variables: HashMap<&str, &str> = HashMap::new();
+        variables.insert("Host", "10.0.0.5");
+        variables.insert("CONF_COUNT_MONGO_PORT", "27018");
+        variables.insert("CONF_COUNT_SIMILARITY_THRESHOLD", "82.5");
+        variables.insert("CONF_COUNT_STORAGE_BACKEND", "mongo");
+        let mut config: AppConfig = test_config();
+        config.apply_overrides(|variable| variables.get(variable).map(|value| value.to_string()))
+            .unwrap();
+        assert_eq!(config.mongo.host, "10.0.0.5");
+        assert_eq!(config.mongo.port, 27018);
+        assert_eq!(config.matching.similarity_threshold, 82.5);
+        assert_eq!(config.storage.backend, StorageBackend::Mongo);
+    }
+
+    #[test]
+    fn test_sqlite_backend_success() {
+        let mut config: AppConfig = test_config();
+        config.apply_overrides(|variable| match variable {
+            "CONF_COUNT_STORAGE_BACKEND" => Some("sqlite".to_string()),
+            _ => None,
+        }).unwrap();
+        assert_eq!(config.storage.backend, StorageBackend::Sqlite);
+        assert_eq!(config.storage.sqlite_path, "conf_count.db");
+        config.storage.sqlite_path = String::new();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_camera_device_success() {
+        let mut config: AppConfig = test_config();
+        config.apply_overrides(|variable| match variable {
+            "CONF_COUNT_CAMERA_DEVICE" => Some("libcamera-still".to_string()),
+            _ => None,
+        }).unwrap();
+        assert_eq!(config.camera.device, CameraDevice::LibcameraStill);
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_camera_command_failure() {
+        let mut config: AppConfig = test_config();
+        config.camera.device = CameraDevice::Command;
+        config.camera.command = "fswebcam --no-banner".to_string();
+        config.camera.brightness = 120;
+        match config.validate() {
+            Err(ConfigError::Invalid(problems)) => assert_eq!(problems.len(), 2),
+            _ => panic!("configuration should be invalid"),
+        }
+    }
+
+    #[test]
+    fn test_mail_transport_success() {
+        let mut variables: HashMap<&str, &str> = HashMap::new();
+        variables.insert("CONF_COUNT_MAIL_TRANSPORT", "smtp");
+        variables.insert("CONF_COUNT_SMTP_HOST", "smtp.example.com");
+        variables.insert("CONF_COUNT_SMTP_PORT", "587");
+        variables.insert("CONF_COUNT_SMTP_STARTTLS", "true");
+        variables.insert("CONF_COUNT_SMTP_USERNAME", "conf_count");
+        variables.insert("CONF_COUNT_SMTP_PASSWORD", "secret");
+        let mut config: AppConfig = test_config();
+        config.apply_overrides(|variable| variables.get(variable).map(|value| value.to_string()))
+            .unwrap();
+        assert_eq!(config.mail.transport, MailTransport::Smtp);
+        assert_eq!(config.mail.smtp_port, 587);
+        assert!(config.mail.starttls);
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_mail_transport_failure() {
+        let mut config: AppConfig = test_config();
+        config.mail.transport = MailTransport::Smtp;
+        config.mail.username = "conf_count".to_string();
+        assert!(config.validate().is_err());
+        config.mail.transport = MailTransport::Spool;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_fixtures_matcher_requires_path() {
+        let mut config: AppConfig = test_config();
+        assert_eq!(config.matching.matcher, MatcherBackend::Fixtures);
+        config.matching.fixtures_path = String::new();
+        assert!(config.validate().is_err());
+        config.matching.matcher = MatcherBackend::Rekognition;
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_face_collection_override_success() {
+        let mut config: AppConfig = test_config();
+        assert_eq!(config.matching.collection_id, "conf-count-faces");
+        config.apply_overrides(|variable| match variable {
+            "CONF_COUNT_FACE_COLLECTION" => Some("conference faces".to_string()),
+            "CONF_COUNT_FACE_MATCHER" => Some("rekognition".to_string()),
+            _ => None,
+        }).unwrap();
+        assert_eq!(config.matching.collection_id, "conference faces");
+        assert!(config.validate().is_err());
+        config.matching.collection_id = "conference_faces-2".to_string();
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_photo_backend_success() {
+        let mut variables: HashMap<&str, &str> = HashMap::new();
+        variables.insert("CONF_COUNT_PHOTO_BACKEND", "s3");
+        variables.insert("CONF_COUNT_S3_ENDPOINT", "http://minio.local:9000");
+        variables.insert("CONF_COUNT_AWS_REGION", "");
+        let mut config: AppConfig = test_config();
+        config.apply_overrides(|variable| variables.get(variable).map(|value| value.to_string()))
+            .unwrap();
+        assert_eq!(config.photos.backend, PhotoBackend::S3);
+        assert_eq!(config.aws.endpoint, "http://minio.local:9000");
+        assert!(config.validate().is_ok());
+        config.aws.endpoint = String::new();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_photo_backend_failure() {
+        let mut config: AppConfig = test_config();
+        config.photos.directory = String::new();
+        assert!(config.validate().is_err());
+        config.photos.backend = PhotoBackend::S3;
+        config.aws.bucket = String::new();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_photo_limits_overrides_success() {
+        let mut variables: HashMap<&str, &str> = HashMap::new();
+        variables.insert("CONF_COUNT_PHOTO_MAX_DIMENSION", "1024");
+        variables.insert("CONF_COUNT_PHOTO_JPEG_QUALITY", "80");
+        let mut config: AppConfig = test_config();
+        config.apply_overrides(|variable| variables.get(variable).map(|value| value.to_string()))
+            .unwrap();
+        assert_eq!(config.photos.max_dimension, 1024);
+        assert_eq!(config.photos.jpeg_quality, 80);
+        assert!(config.validate().is_ok());
+        config.photos.min_dimension = 2048;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_apply_overrides_failure() {
+        let mut config: AppConfig = test_config();
+        assert!(config.apply_overrides(|variable| match variable {
+            "CONF_COUNT_MONGO_PORT" => Some("not a port".to_string()),
+            _ => None,
+        }).is_err())
+    }
+
+    #[test]
+    fn test_mongo_pool_overrides_success() {
+        let mut variables: HashMap<&str, &str> = HashMap::new();
+        variables.insert("CONF_COUNT_MONGO_POOL_SIZE", "2");
+        variables.insert("CONF_COUNT_MONGO_CHECKOUT_TIMEOUT_MS", "250");
+        let mut config: AppConfig = test_config();
+        config.apply_overrides(|variable| variables.get(variable).map(|value| value.to_string()))
+            .unwrap();
+        assert_eq!(config.mongo.pool_size, 2);
+        assert_eq!(config.mongo.connect_timeout_ms, 5000);
+        assert_eq!(config.mongo.checkout_timeout_ms, 250);
+    }
+
+    #[test]
+    fn test_mongo_pool_size_failure() {
+        let mut config: AppConfig = test_config();
+        config.storage.backend = StorageBackend::Mongo;
+        assert!(config.validate().is_ok());
+        config.mongo.pool_size = 0;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_validate_mongo_host_not_required() {
+        let mut config: AppConfig = test_config();
+        config.mongo.host = String::new();
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_review_band_override_success() {
+        let mut config: AppConfig = test_config();
+        assert_eq!(config.matching.review_band, 10.0);
+        config.apply_overrides(|variable| match variable {
+            "CONF_COUNT_REVIEW_BAND" => Some("-5".to_string()),
+            _ => None,
+        }).unwrap();
+        assert_eq!(config.matching.review_band, -5.0);
+        assert!(config.validate().is_err());
+        config.matching.review_band = 0.0;
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_failure() {
+        let mut config: AppConfig = test_config();
+        config.storage.backend = StorageBackend::Mongo;
+        config.mongo.host = String::new();
+        config.matching.similarity_threshold = 120.0;
+        match config.validate() {
+            Err(ConfigError::Invalid(problems)) => assert_eq!(problems.len(), 2),
+            _ => panic!("configuration should be invalid"),
+        }
+    }
+
+    #[test]
+    fn test_session_overrides_success() {
+        let mut variables: HashMap<&str, &str> = HashMap::new();
+        variables.insert("CONF_COUNT_SESSION_SECRET", "another-secret-of-at-least-32-chars");
+        variables.insert("CONF_COUNT_SESSION_TTL_MINUTES", "30");
+        variables.insert("CONF_COUNT_SESSION_SECURE_COOKIE", "true");
+        let mut config: AppConfig = test_config();
+        config.apply_overrides(|variable| variables.get(variable).map(|value| value.to_string()))
+            .unwrap();
+        assert_eq!(config.session.ttl_minutes, 30);
+        assert!(config.session.secure_cookie);
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_session_secret_failure() {
+        let mut config: AppConfig = test_config();
+        config.session.secret = "too short".to_string();
+        assert!(config.validate().is_err());
+        config.session = SessionConfig::default();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_password_reset_overrides_success() {
+        let mut variables: HashMap<&str, &str> = HashMap::new();
+        variables.insert("CONF_COUNT_PUBLIC_URL", "https://conf.example.com");
+        variables.insert("CONF_COUNT_RESET_TOKEN_TTL_MINUTES", "15");
+        let mut config: AppConfig = test_config();
+        config.apply_overrides(|variable| variables.get(variable).map(|value| value.to_string()))
+            .unwrap();
+        assert_eq!(config.server.public_url, "https://conf.example.com");
+        assert_eq!(config.password_reset.token_ttl_minutes, 15);
+        assert!(config.validate().is_ok());
+        config.server.public_url = "conf.example.com".to_string();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_verification_overrides_success() {
+        let mut variables: HashMap<&str, &str> = HashMap::new();
+        variables.insert("CONF_COUNT_VERIFICATION_TTL_HOURS", "24");
+        let mut config: AppConfig = test_config();
+        assert_eq!(config.verification.pending_ttl_hours, 48);
+        config.apply_overrides(|variable| variables.get(variable).map(|value| value.to_string()))
+            .unwrap();
+        assert_eq!(config.verification.pending_ttl_hours, 24);
+        config.verification.pending_ttl_hours = 0;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_login_throttle_overrides_success() {
+        let mut variables: HashMap<&str, &str> = HashMap::new();
+        variables.insert("CONF_COUNT_LOGIN_ACCOUNT_LOCKOUT_FAILURES", "6");
+        variables.insert("CONF_COUNT_LOGIN_MAX_DELAY_SECONDS", "60");
+        let mut config: AppConfig = test_config();
+        config.apply_overrides(|variable| variables.get(variable).map(|value| value.to_string()))
+            .unwrap();
+        assert_eq!(config.login_throttle.account_lockout_failures, 6);
+        assert_eq!(config.login_throttle.max_delay_seconds, 60);
+        assert!(config.validate().is_ok());
+        config.login_throttle.account_lockout_failures = 3;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_enrollment_overrides_success() {
+        let mut variables: HashMap<&str, &str> = HashMap::new();
+        variables.insert("CONF_COUNT_ENROLLMENT_MIN_BRIGHTNESS", "30");
+        let mut config: AppConfig = test_config();
+        config.apply_overrides(|variable| variables.get(variable).map(|value| value.to_string()))
+            .unwrap();
+        assert_eq!(config.enrollment.min_brightness, 30.0);
+        assert!(config.validate().is_ok());
+        config.enrollment.max_brightness = 25.0;
+        assert!(config.validate().is_err());
+        config.enrollment.max_brightness = 120.0;
+        assert!(config.validate().is_err());
+    }
+}
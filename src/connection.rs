@@ -1,20 +1,13 @@
-use std::env;
-
 use log::error;
-use mongodb::{Client, ThreadedClient};
+use mongodb::{Client, ClientOptions, ThreadedClient};
 use mongodb::coll::Collection;
 use mongodb::db::ThreadedDatabase;
 use mongodb::Document;
 use rusoto_rekognition::RekognitionClient;
-use s3::{bucket::Bucket, credentials::Credentials};
-
-use crate::constants::DB_SUCCESS_MESSAGE;
+use s3::{bucket::Bucket, credentials::Credentials, region::Region};
 
-static DB_FAILURE_MESSAGE: &str = "Unable to insert data";
-static HOST: &str = "Host";
-static PORT: u16 = 27017;
-static BUCKET_NAME: &str = "Clicked_Image_Bucket";
-static REGION: &str = "Region";
+use crate::config::{AwsConfig, MongoConfig};
+use crate::constants::{DB_FAILURE_MESSAGE, DB_SUCCESS_MESSAGE};
 
 /// This function inserts the data into database
 ///
@@ -50,38 +43,50 @@ pub fn connect_database_collection(client: Client, database: &str, collection: &
     client.db(database).collection(collection)
 }
 
-/// Establishes connection with MongoDB
+/// Establishes connection with MongoDB, meant to be done once and shared
+///
+/// # Arguments
+///
+/// * `mongo_config` - Host, port and connect timeout of MongoDB
 ///
 /// # Return
 ///
 /// Returns client of MongoDB
-pub fn create_db_connection() -> Client {
-    Client::connect(env::var(
-        HOST).expect("Host not exported").as_str(), PORT).expect(
-        "Database not ready")
+pub fn create_db_connection(mongo_config: &MongoConfig) -> mongodb::Result<Client> {
+    let mut options: ClientOptions = ClientOptions::new();
+    options.server_selection_timeout_ms = mongo_config.connect_timeout_ms as i64;
+    Client::connect_with_options(mongo_config.host.as_str(), mongo_config.port, options)
 }
 
 /// The function create_rekognition_connection establishes connection with RekognitionClient
 ///
+/// # Arguments
+///
+/// * `aws_config` - AWS region, already validated at startup
+///
 /// # Return
 ///
 ///  This function returns client of RekognitionClient
-pub fn create_rekognition_connection() -> RekognitionClient {
-    RekognitionClient::new(env::var(REGION)
-        .expect("Region not exported").parse().unwrap())
+pub fn create_rekognition_connection(aws_config: &AwsConfig) -> RekognitionClient {
+    RekognitionClient::new(aws_config.region.parse().expect("Region validated at startup"))
 }
 
 /// The function create_bucket_connection establishes connection with S3 bucket and returns the bucket instance
 ///
+/// # Arguments
+///
+/// * `aws_config` - AWS region, bucket name and endpoint of an S3 compatible service, if any
+///
 /// # Return
 ///
 ///  This function returns instance of S3 Bucket
-pub fn create_bucket_connection() -> Bucket {
-    Bucket::new(
-        env::var(BUCKET_NAME).expect("Bucket Name not exported").as_str(),
-        env::var(REGION).expect("Region not exported").parse().unwrap(),
-        Credentials::default(),
-    )
+pub fn create_bucket_connection(aws_config: &AwsConfig) -> Bucket {
+    let region: Region = if aws_config.endpoint.trim().is_empty() {
+        aws_config.region.parse().expect("Region validated at startup")
+    } else {
+        Region::Custom(aws_config.endpoint.trim().to_string())
+    };
+    Bucket::new(aws_config.bucket.as_str(), region, Credentials::default())
 }
 
 #[cfg(test)]
@@ -94,6 +99,8 @@ mod test {
     use s3::credentials::Credentials;
     use s3::region::Region;
 
+    use crate::config::AwsConfig;
+    use crate::config::test::test_config;
     use crate::connection::{connect_database_collection, create_db_connection};
     use crate::connection::{create_bucket_connection, create_rekognition_connection};
 
@@ -106,7 +113,7 @@ mod test {
 
     #[test]
     fn test_create_db_connection() {
-        assert_eq!(0, create_db_connection().get_req_id())
+        assert_eq!(0, create_db_connection(&test_config().mongo).unwrap().get_req_id())
     }
 
     #[test]
@@ -119,7 +126,7 @@ mod test {
 
     #[test]
     fn test_create_rekognition_connection_success() {
-        let client: RekognitionClient = create_rekognition_connection();
+        let client: RekognitionClient = create_rekognition_connection(&test_config().aws);
         let request: ListCollectionsRequest = ListCollectionsRequest::default();
         assert!(client.list_collections(request).sync().is_ok())
     }
@@ -128,13 +135,23 @@ mod test {
     fn test_create_bucket_connection_success() {
         let credentials: Credentials = Credentials::default();
         let bucket: Bucket = Bucket::new(TEST_BUCKET_NAME, REGION, credentials);
-        assert_eq!(bucket, create_bucket_connection());
+        assert_eq!(bucket, create_bucket_connection(&test_config().aws));
+    }
+
+    #[test]
+    fn test_create_bucket_connection_custom_endpoint() {
+        let mut aws_config: AwsConfig = test_config().aws;
+        aws_config.endpoint = "http://minio.local:9000".to_string();
+        let bucket: Bucket = Bucket::new(TEST_BUCKET_NAME,
+                                         Region::Custom(aws_config.endpoint.clone()),
+                                         Credentials::default());
+        assert_eq!(bucket, create_bucket_connection(&aws_config));
     }
 
     #[test]
     fn test_create_bucket_connection_failure() {
         let credentials: Credentials = Credentials::default();
         let bucket: Bucket = Bucket::new("invalid_bucket", REGION, credentials);
-        assert_ne!(bucket, create_bucket_connection())
+        assert_ne!(bucket, create_bucket_connection(&test_config().aws))
     }
 }
\ No newline at end of file
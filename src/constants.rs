@@ -1,5 +1,3 @@
-pub static DB_NAME: &str = "Conf_Count";
-
 pub static CONFERENCE_DETAILS: &str = "conference_details";
 
 pub static JSON: &str = "application/json";
@@ -29,6 +27,8 @@ pub static USER_ID: &str = "user_id";
 pub static CONFERENCE: &str = "conference";
 
 pub static RESPONSE: &str = "response";
+pub static TOKEN: &str = "token";
+pub static CSRF_TOKEN: &str = "csrf_token";
 
 pub static COMPLETED: &str = "completed";
 
@@ -46,10 +46,6 @@ pub static TEST_EMAIL_ID: &str = "test@knoldus.in";
 
 pub static TEST_NAME: &str = "Test";
 
-pub static UPDATION_FAIL: &str = "Unable to update data";
-
-pub static DELETION_FAIL: &str = "Unable to delete data";
-
 pub static DELETION_SUCCESS: &str = "Successfully deleted";
 
 pub static UPDATION_SUCCESS: &str = "Successfully updated";
@@ -72,24 +68,12 @@ pub static SUCCESS: &str = "Successfully Updated";
 
 pub static PRESENT: &str = "Already Present";
 
-pub static FAILURE: &str = "Unable to update data";
-
 pub static COLLECTION_EMPTY: &str = "The Data for this Conference has not been recorded";
 
 pub const PROCESS_COMPLETE: &str = "The process has been completed successfully";
 
-pub const SIMILAR: &str = "Similar Images";
-
-pub const DIFFERENT: &str = "Different Images";
-
-pub const SIMILARITY_THRESHOLD: f32 = 75.0;
-
 pub const ALREADY_REGISTERED: &str = "Already registered";
 
-pub const S3_UPLOAD_SUCCESS: &str = "Image uploaded successfully on S3 bucket";
-
-pub static USER_INFO: &str = "user_data";
-
 pub static WRONG_EMAIL_FORMAT: &str = "Wrong Email format";
 
 pub static WRONG_PASSWORD_FORMAT: &str = "Wrong password format. It must be between 5 to 20 characters";
@@ -102,6 +86,13 @@ pub static WRONG_CONFERENCE_ID_FORMAT: &str = "Conference Id should be numeric w
 
 pub static EMPTY_ADDRESS: &str = "Address1, address2 and address3 are compulsory";
 
+pub static WRONG_SIMILARITY_THRESHOLD: &str =
+    "Similarity threshold should be a number from 0 to 100, or empty for the default one";
+
+pub static WRONG_PRESENCE_RULE: &str =
+    "Presence rule should be any_capture, seen_in_captures with at most as many sightings as \
+     captures, or seen_within_minutes with a number of minutes";
+
 pub static CONFERENCE_ID: &str = "conf_id";
 
 pub static NO_CONFERENCE: &str = "No such conference exist";
@@ -112,5 +103,7 @@ pub static TEST_CONFERENCE_ID: &str = "123456789";
 
 pub static DB_SUCCESS_MESSAGE: &str = "Successfully Inserted";
 
-pub static UPLOAD: &str = "upload.jpg";
+pub static DB_FAILURE_MESSAGE: &str = "Unable to insert data";
+
+pub static STORAGE_FAILURE: &str = "Unable to access the storage";
 
@@ -1,288 +1,968 @@
-use std::env;
+use std::collections::{HashMap, HashSet};
+use std::fs;
 
-use mongodb::{bson, doc};
-use mongodb::Bson;
-use mongodb::coll::Collection;
-use mongodb::cursor::Cursor;
-use mongodb::Document;
-use rusoto_rekognition::RekognitionClient;
+use log::{error, info};
+use uuid::Uuid;
 
-use crate::constants::{CONFERENCE_ID, DIFFERENT, EMAIL, FAILURE, PRESENT, SET, SIMILAR, SUCCESS,
-                       USER_ID};
+use crate::capture::Capture;
+use crate::constants::{COMPLETED, NO_CONFERENCE, PRESENT, SUCCESS};
 use crate::email_service::send_email;
-use crate::image_comparison::compare_images;
-use crate::request_generator::create_compare_faces_request;
+use crate::error::{ConfCountError, ConfCountResult};
+use crate::face_matching::FaceBox;
+use crate::mail::Mailer;
+use crate::photo_processing::{crop_face, STORED_CONTENT_TYPE};
+use crate::photo_store::PhotoStore;
+use crate::state::AppState;
+use crate::storage::{AttendanceMatch, Conference, Observation, PresenceRule, Review, Storage};
 
-static TARGET_IMAGE_PATH: &str = "Clicked_Image_Path";
 static DEFAULT_MAIL_STATUS: &str = "Email not sent";
-static INDEFINITE_RESULT: &str = "Indefinite Result";
-static SENDER: &str = "ayush@knoldus.com";
-static STATUS: &str = "status";
 static ABSENT_STATUS: &str = "absent";
 static PRESENT_STATUS: &str = "present";
+static REVIEW_QUEUED: &str = "Match queued for review";
+static REVIEW_PENDING: &str = "A better match is already pending review";
+static SEEN: &str = "Seen, but not yet present under the presence rule of the conference";
+static NOT_SEEN: &str = "Not seen, absence notices are sent once the conference is completed";
+static REVIEW_APPROVED: &str = "Review approved, the match counts as a sighting of the user";
+static REVIEW_REJECTED: &str = "Review rejected, the user is left absent";
+static NO_REVIEW: &str = "No such review is pending";
+static REVIEW_FACE_PREFIX: &str = "review-";
 
-/// Update the records for the absent participants
+/// Rules deciding the attendance of the users of a conference, along with the captures of the
+/// conference they are applied to
+#[derive(Clone, Debug, PartialEq)]
+pub struct AttendanceRules {
+    /// Similarity a match needs for the user to count as seen in a capture
+    pub similarity_threshold: f32,
+    pub presence_rule: PresenceRule,
+    /// Ids and UNIX times of the captures of the conference, in the order they were taken
+    pub captures: Vec<(String, i64)>,
+}
+
+impl AttendanceRules {
+    /// Adds a capture to those of the conference, unless it is being run again
+    pub fn add_capture(&mut self, capture: &Capture) {
+        if self.captured_at(capture.id.as_str()).is_none() {
+            self.captures.push((capture.id.clone(), capture.captured_at));
+            sort_captures(&mut self.captures);
+        }
+    }
+
+    /// Time a capture of the conference was first taken at
+    fn captured_at(&self, capture_id: &str) -> Option<i64> {
+        self.captures.iter()
+            .find(|(id, _)| id == capture_id)
+            .map(|(_, captured_at)| *captured_at)
+    }
+
+    /// Decides whether the observations of a user meet the presence rule of the conference
+    ///
+    /// # Arguments
+    ///
+    /// * `observations` - What the captures of the conference showed of the user
+    ///
+    /// # Return
+    ///
+    /// Returns whether the user is present. A capture counts as a sighting when the user was
+    /// matched from the similarity threshold, or when an admin approved the match.
+    pub fn is_present(&self, observations: &[Observation]) -> bool {
+        let sightings: HashSet<&str> = observations.iter()
+            .filter(|observation| observation.approved
+                || observation.attendance_match.similarity >= self.similarity_threshold)
+            .map(|observation| observation.attendance_match.capture_id.as_str())
+            .collect();
+        let seen = |capture: &&(String, i64)| sightings.contains(capture.0.as_str());
+        match self.presence_rule {
+            PresenceRule::AnyCapture => !sightings.is_empty(),
+            PresenceRule::SeenInCaptures { seen: times, captures } =>
+                self.captures.iter().take(captures as usize).filter(seen).count()
+                    >= times as usize,
+            PresenceRule::SeenWithinMinutes { minutes } => match self.captures.first() {
+                Some((_, first)) => self.captures.iter()
+                    .take_while(|(_, captured_at)| captured_at - first <= i64::from(minutes) * 60)
+                    .any(|capture| seen(&capture)),
+                None => false,
+            },
+        }
+    }
+}
+
+/// Orders captures by the time they were taken, then by id for those taken at the same second
+fn sort_captures(captures: &mut [(String, i64)]) {
+    captures.sort_by(|(first_id, first_at), (second_id, second_at)| {
+        (first_at, first_id).cmp(&(second_at, second_id))
+    });
+}
+
+/// Loads the rules deciding the attendance of the users of a conference
 ///
 /// # Arguments
 ///
-/// * `conference_collection` - Instance of the Database collection
+/// * `state` - State of the application, holding the storage and the configured threshold
 ///
-/// * `data` - Data in the bson format(Alias for OrderedDocument)
+/// * `conference_id` - Id of the conference
+///
+/// # Return
+///
+/// Returns the rules of the conference, falling back to the configured threshold and to
+/// presence from any capture, or the failure of the storage
+pub fn attendance_rules(state: &AppState, conference_id: &str)
+                        -> ConfCountResult<AttendanceRules> {
+    let conference: Option<Conference> = state.storage.conference(conference_id)?;
+    let mut first_taken: HashMap<String, i64> = HashMap::new();
+    for observation in state.storage.observations_for_conference(conference_id)? {
+        let observed: AttendanceMatch = observation.attendance_match;
+        let captured_at: &mut i64 =
+            first_taken.entry(observed.capture_id).or_insert(observed.captured_at);
+        *captured_at = (*captured_at).min(observed.captured_at);
+    }
+    let mut captures: Vec<(String, i64)> = first_taken.into_iter().collect();
+    sort_captures(&mut captures);
+    Ok(AttendanceRules {
+        similarity_threshold: conference.as_ref()
+            .and_then(|conference| conference.similarity_threshold)
+            .unwrap_or(state.config.matching.similarity_threshold),
+        presence_rule: conference.map(|conference| conference.presence_rule).unwrap_or_default(),
+        captures,
+    })
+}
+
+/// Records what a capture showed of a user, keeping the approval of its review when the
+/// capture is run again
+///
+/// # Arguments
+///
+/// * `storage` - Storage holding the observations
+///
+/// * `conference_id` - Id of the conference
 ///
 /// * `user_id` - Id of the user
 ///
+/// * `attendance_match` - Match of the user in the capture
+///
+/// * `approved` - Whether an admin approved the match
+///
 /// # Return
 ///
-/// Returns the success or failure message of update operation
-fn update_record(
-    conference_collection: &Collection,
-    data: Document,
+/// Returns the failure of the storage, if any
+fn observe(
+    storage: &dyn Storage,
+    conference_id: &str,
     user_id: &str,
-) -> &'static str {
-    let record = doc! {USER_ID: user_id};
-    match conference_collection.update_one(record, data, None) {
-        Ok(doc) => {
-            match doc.modified_count {
-                0 => PRESENT,
-                _ => SUCCESS
-            }
-        }
-        Err(_) => FAILURE
-    }
+    attendance_match: &AttendanceMatch,
+    approved: bool,
+) -> ConfCountResult<()> {
+    let approved: bool = approved || storage.observations(user_id, conference_id)?.iter()
+        .any(|observation| observation.approved
+            && observation.attendance_match.capture_id == attendance_match.capture_id);
+    storage.save_observation(&Observation {
+        user_id: user_id.to_string(),
+        conference_id: conference_id.to_string(),
+        attendance_match: attendance_match.clone(),
+        approved,
+    })?;
+    Ok(())
 }
 
-/// Update the records for the present participants
+/// Derives the attendance of a user from all the captures of the conference
 ///
 /// # Arguments
 ///
-/// * `conference_collection` - Instance of the Database collection
+/// * `storage` - Storage holding the registrations and the observations
+///
+/// * `rules` - Rules deciding the attendance at the conference
+///
+/// * `conference_id` - Id of the conference
 ///
 /// * `user_id` - Id of the user
 ///
 /// # Return
 ///
-/// Returns the success or failure message of update db service
-fn update_present_status(
-    conference_collection: &Collection,
+/// Returns whether the user is present and whether their status changed, or the failure of
+/// the storage
+fn derive_status(
+    storage: &dyn Storage,
+    rules: &AttendanceRules,
+    conference_id: &str,
     user_id: &str,
-) -> &'static str {
-    let status_present = doc! {STATUS: PRESENT_STATUS};
-    let data = doc! {SET => status_present};
-    update_record(&conference_collection, data, &user_id)
+) -> ConfCountResult<(bool, bool)> {
+    let present: bool = rules.is_present(&storage.observations(user_id, conference_id)?);
+    let status: &str = if present { PRESENT_STATUS } else { ABSENT_STATUS };
+    Ok((present, storage.set_attendance_status(user_id, conference_id, status)?))
 }
 
-/// Sends the E-mail to the absent participants
+/// Sends the E-mail to an absent participant, unless they were already sent one
 ///
 /// # Arguments
 ///
-/// * `conference_collection` - Instance of the Database collection
+/// * `storage` - Storage holding the registrations
+///
+/// * `mailer` - Mailer delivering the notice
+///
+/// * `conference_id` - Id of the conference
 ///
 /// * `user_id` - Id of the user
 ///
+/// * `sender` - E-mail address the notice is sent from
+///
 /// # Return
 ///
-/// Returns the success or failure message of E-mail service
+/// Returns the success message of E-mail service, or the failure of the storage or the mailer
 fn update_absent_status(
-    conference_collection: &Collection,
+    storage: &dyn Storage,
+    mailer: &dyn Mailer,
+    conference_id: &str,
     user_id: &str,
-) -> &'static str {
-    let status_absent = doc! {STATUS: ABSENT_STATUS, USER_ID: user_id};
-    let mut mail_status: &str = DEFAULT_MAIL_STATUS;
-    let absentee_emails: Cursor = conference_collection.find(
-        Some(status_absent), None)
-        .expect("Database connection disrupted");
-    for email_list in absentee_emails {
-        if let Ok(email_list) = email_list {
-            if let Some(&Bson::String(ref email)) =
-            email_list.get(EMAIL) {
-                mail_status = send_email(SENDER, email);
-            }
+    sender: &str,
+) -> ConfCountResult<&'static str> {
+    match storage.registration(user_id, conference_id)? {
+        Some(ref registration) if registration.status == ABSENT_STATUS
+            && !registration.notified => {
+            let sent: &'static str = send_email(mailer, sender, registration.email.as_str())?;
+            storage.set_notified(user_id, conference_id)?;
+            Ok(sent)
+        }
+        _ => Ok(DEFAULT_MAIL_STATUS),
+    }
+}
+
+/// Records the match of a capture on the registration, unless an earlier capture matched the
+/// user at least as well
+///
+/// # Arguments
+///
+/// * `storage` - Storage holding the registrations
+///
+/// * `conference_id` - Id of the conference
+///
+/// * `user_id` - Id of the user
+///
+/// * `attendance_match` - Match of the user in the capture
+///
+/// # Return
+///
+/// Returns the failure of the storage, if any
+fn record_match(
+    storage: &dyn Storage,
+    conference_id: &str,
+    user_id: &str,
+    attendance_match: &AttendanceMatch,
+) -> ConfCountResult<()> {
+    let recorded: Option<AttendanceMatch> = storage.registration(user_id, conference_id)?
+        .and_then(|registration| registration.attendance_match);
+    if recorded.map_or(true, |recorded| attendance_match.similarity > recorded.similarity) {
+        storage.set_attendance_match(user_id, conference_id, attendance_match)?;
+    }
+    Ok(())
+}
+
+/// Crops the matched face out of the capture into the photo store, for the review of the match
+///
+/// # Arguments
+///
+/// * `photos` - Store keeping the cropped face
+///
+/// * `review_id` - Id of the review
+///
+/// * `capture` - Capture the face was matched in
+///
+/// * `face_box` - Position of the face in the capture
+///
+/// # Return
+///
+/// Returns the key of the cropped face, none when it could not be cropped or stored
+fn store_face_crop(
+    photos: &dyn PhotoStore,
+    review_id: &str,
+    capture: &Capture,
+    face_box: &FaceBox,
+) -> Option<String> {
+    let key: String = format!("{}{}", REVIEW_FACE_PREFIX, review_id);
+    let stored: Result<(), String> = fs::read(capture.image_path.as_str())
+        .map_err(|read_error| read_error.to_string())
+        .and_then(|bytes| crop_face(&bytes, face_box).map_err(|crop_error| crop_error.to_string()))
+        .and_then(|face| photos.put(key.as_str(), &face, STORED_CONTENT_TYPE)
+            .map_err(|store_error| store_error.to_string()));
+    match stored {
+        Ok(()) => Some(key),
+        Err(failure) => {
+            error!("Unable to keep the face of review {} from {} - {}", review_id,
+                   capture.image_path, failure);
+            None
         }
     }
-    mail_status
+}
+
+/// Removes a review along with its cropped face
+///
+/// # Arguments
+///
+/// * `storage` - Storage holding the reviews
+///
+/// * `photos` - Store holding the cropped face
+///
+/// * `review` - Review to remove
+///
+/// # Return
+///
+/// Returns whether the review was still pending, or the failure of the storage
+pub fn discard_review(
+    storage: &dyn Storage,
+    photos: &dyn PhotoStore,
+    review: &Review,
+) -> ConfCountResult<bool> {
+    if let Some(ref face_key) = review.face_key {
+        if let Err(store_error) = photos.delete(face_key.as_str()) {
+            error!("Unable to delete the face of review {} - {}", review.id, store_error);
+        }
+    }
+    Ok(storage.delete_review(review.id.as_str())?)
+}
+
+/// Queues a match under the similarity threshold for an admin to review, in place of the
+/// pending review of the user in the same capture when it is a better match, so that each
+/// capture can be approved into its own observation
+///
+/// # Arguments
+///
+/// * `state` - State of the application, holding the storage and the photo store
+///
+/// * `conference_id` - Id of the conference
+///
+/// * `user_id` - Id of the user
+///
+/// * `attendance_match` - Match of the user in the capture
+///
+/// * `capture` - Capture the user was matched in
+///
+/// # Return
+///
+/// Returns the outcome for the user, or the failure of the storage
+fn queue_review(
+    state: &AppState,
+    conference_id: &str,
+    user_id: &str,
+    attendance_match: &AttendanceMatch,
+    capture: &Capture,
+) -> ConfCountResult<&'static str> {
+    let pending: Vec<Review> = state.storage.reviews_for_user(user_id)?.into_iter()
+        .filter(|review| review.conference_id == conference_id
+            && review.attendance_match.capture_id == attendance_match.capture_id)
+        .collect();
+    if pending.iter()
+        .any(|review| review.attendance_match.similarity >= attendance_match.similarity) {
+        return Ok(REVIEW_PENDING);
+    }
+    let review_id: String = Uuid::new_v4().to_simple().to_string();
+    let face_key: Option<String> = attendance_match.face_box.as_ref().and_then(|face_box| {
+        store_face_crop(&*state.photo_store, review_id.as_str(), capture, face_box)
+    });
+    state.storage.insert_review(&Review {
+        id: review_id,
+        user_id: user_id.to_string(),
+        conference_id: conference_id.to_string(),
+        attendance_match: attendance_match.clone(),
+        face_key,
+        reference_key: user_id.to_string(),
+    })?;
+    for review in &pending {
+        discard_review(&*state.storage, &*state.photo_store, review)?;
+    }
+    Ok(REVIEW_QUEUED)
+}
+
+/// Takes a pending review out of the queue to apply the decision of an admin
+fn take_review(state: &AppState, review_id: &str) -> ConfCountResult<Review> {
+    let review: Review = state.storage.review(review_id)?
+        .ok_or_else(|| ConfCountError::NotFound(NO_REVIEW.to_string()))?;
+    if discard_review(&*state.storage, &*state.photo_store, &review)? {
+        Ok(review)
+    } else {
+        Err(ConfCountError::NotFound(NO_REVIEW.to_string()))
+    }
+}
+
+/// Approves a match pending review, counting it as a sighting of the user
+///
+/// # Arguments
+///
+/// * `state` - State of the application, holding the storage and the photo store
+///
+/// * `review_id` - Id of the review
+///
+/// # Return
+///
+/// Returns the confirmation of the approval, or the failure of one of the services
+pub fn approve_review(state: &AppState, review_id: &str) -> ConfCountResult<&'static str> {
+    let review: Review = take_review(state, review_id)?;
+    let (conference_id, user_id): (&str, &str) =
+        (review.conference_id.as_str(), review.user_id.as_str());
+    observe(&*state.storage, conference_id, user_id, &review.attendance_match, true)?;
+    derive_status(&*state.storage, &attendance_rules(state, conference_id)?, conference_id,
+                  user_id)?;
+    info!("Admin approved review {} of user {} for conference {}", review.id, review.user_id,
+          review.conference_id);
+    Ok(REVIEW_APPROVED)
+}
+
+/// Rejects a match pending review, leaving the user absent as if they were not recognised
+///
+/// # Arguments
+///
+/// * `state` - State of the application, holding the storage and the photo store
+///
+/// * `review_id` - Id of the review
+///
+/// # Return
+///
+/// Returns the confirmation of the rejection, or the failure of one of the services
+pub fn reject_review(state: &AppState, review_id: &str) -> ConfCountResult<&'static str> {
+    let review: Review = take_review(state, review_id)?;
+    info!("Admin rejected review {} of user {} for conference {}", review.id, review.user_id,
+          review.conference_id);
+    Ok(REVIEW_REJECTED)
 }
 
 /// Update the records for the absent and present participants
 ///
 /// # Arguments
 ///
-/// * `rekognition_client` - Instance of the client of RekognitionClient
+/// * `state` - State of the application, holding the storage, face matcher and mailer
 ///
-/// * `conference_collection` - Instance of the Database collection
+/// * `conference_id` - Id of the conference
 ///
 /// * `user_id` - Id of the user
 ///
+/// * `attendance_match` - Match of the user in the captured image, of similarity 0 when they
+/// were not recognised
+///
+/// * `rules` - Rules deciding the attendance at the conference, including the capture
+///
+/// * `capture` - Capture the user was searched in
+///
 /// # Return
 ///
-/// Returns the success or failure message of match operation
+/// Returns the success message of match operation, or the failure of one of the services.
+/// The match is recorded as an observation and the status of the user derived again from all
+/// the captures of the conference, so that running a capture again replaces what it showed.
+/// A user matched within `matching.review_band` under the threshold has the match queued for
+/// review. The absent users are notified once the conference is completed, when the presence
+/// rule can no longer be met.
 pub fn update_db(
-    rekognition_client: &RekognitionClient,
-    conference_collection: &Collection,
+    state: &AppState,
+    conference_id: &str,
     user_id: String,
-) -> &'static str {
-    match compare_images(&rekognition_client,
-                         create_compare_faces_request(
-                             &user_id, env::var(TARGET_IMAGE_PATH)
-                                 .expect("Target Image path not specified").as_str())) {
-        Ok(response) => {
-            match response
-                {
-                    SIMILAR => {
-                        update_present_status(conference_collection, user_id.as_str())
-                    }
-                    DIFFERENT => {
-                        update_absent_status(conference_collection, user_id.as_str())
-                    }
-                    _ => INDEFINITE_RESULT
-                }
-        }
-        Err(aws_error) => aws_error
+    attendance_match: &AttendanceMatch,
+    rules: &AttendanceRules,
+    capture: &Capture,
+) -> ConfCountResult<&'static str> {
+    let storage: &dyn Storage = &*state.storage;
+    if storage.registration(user_id.as_str(), conference_id)?.is_none() {
+        return Ok(DEFAULT_MAIL_STATUS);
+    }
+    let observed: AttendanceMatch = AttendanceMatch {
+        captured_at: rules.captured_at(attendance_match.capture_id.as_str())
+            .unwrap_or(attendance_match.captured_at),
+        ..attendance_match.clone()
+    };
+    record_match(storage, conference_id, user_id.as_str(), &observed)?;
+    observe(storage, conference_id, user_id.as_str(), &observed, false)?;
+    match derive_status(storage, rules, conference_id, user_id.as_str())? {
+        (true, true) => Ok(SUCCESS),
+        (true, false) => Ok(PRESENT),
+        _ if observed.similarity >= rules.similarity_threshold => Ok(SEEN),
+        _ if observed.similarity > 0.0 && observed.similarity
+            >= rules.similarity_threshold - state.config.matching.review_band =>
+            queue_review(state, conference_id, user_id.as_str(), &observed, capture),
+        _ => Ok(NOT_SEEN),
     }
 }
 
-/// Fetch user Ids of the registered user for a particular conference
+/// Marks a conference completed and sends the notice of their absence to the users still absent,
+/// at most once per user
 ///
 /// # Arguments
 ///
-/// * `conference_collection` - Instance of the Database collection
+/// * `state` - State of the application, holding the storage and the mailer
+///
+/// * `conference_id` - Id of the conference
 ///
 /// # Return
 ///
-/// Returns the Ids of the user registered for a conference
-pub fn fetch_user_ids(conference_collection: &Collection, conference_id: &str) -> Vec<String> {
-    let mut user_ids: Vec<String> = Vec::new();
-    let filter = doc!(CONFERENCE_ID: conference_id);
-    if let Ok(user_data) = conference_collection.find(Some(filter), None) {
-        let mut user_document: Vec<bson::Document> = Vec::new();
-        for conf_user in user_data {
-            user_document.push(conf_user.expect("Unable to push data into vector"))
+/// Returns the number of notices sent, or the failure of the storage. The users whose notice
+/// could not be delivered are notified when the conference is completed again.
+pub fn complete_conference(state: &AppState, conference_id: &str) -> ConfCountResult<String> {
+    let storage: &dyn Storage = &*state.storage;
+    if !storage.set_conference_status(conference_id, COMPLETED)? {
+        return Err(ConfCountError::NotFound(NO_CONFERENCE.to_string()));
+    }
+    let mut notified: usize = 0;
+    for user_id in fetch_user_ids(storage, conference_id)? {
+        match update_absent_status(storage, &*state.mailer, conference_id, user_id.as_str(),
+                                   state.config.mail.sender.as_str()) {
+            Ok(sent) if sent == DEFAULT_MAIL_STATUS => {}
+            Ok(_) => notified += 1,
+            Err(ConfCountError::Mail(mail_error)) =>
+                error!("Unable to notify the absence of {} - {}", user_id, mail_error),
+            Err(update_error) => return Err(update_error),
         }
-        for user_id in user_document {
-            user_ids.push(user_id.get_str("user_id")
-                .expect("No such key exist")
-                .to_string());
+    }
+    info!("Conference {} completed, {} absent users notified", conference_id, notified);
+    Ok(format!("Conference completed, {} absence notices sent", notified))
+}
+
+/// Derives again the attendance of all the users of a conference, after its rules changed
+///
+/// # Arguments
+///
+/// * `state` - State of the application, holding the storage and the configured threshold
+///
+/// * `conference_id` - Id of the conference
+///
+/// # Return
+///
+/// Returns the number of users whose status changed, or the failure of the storage
+pub fn recompute_attendance(state: &AppState, conference_id: &str) -> ConfCountResult<usize> {
+    let rules: AttendanceRules = attendance_rules(state, conference_id)?;
+    let mut changed: usize = 0;
+    for user_id in fetch_user_ids(&*state.storage, conference_id)? {
+        if derive_status(&*state.storage, &rules, conference_id, user_id.as_str())?.1 {
+            changed += 1;
         }
     }
-    user_ids
+    Ok(changed)
+}
+
+/// Fetch user Ids of the registered user for a particular conference
+///
+/// # Arguments
+///
+/// * `storage` - Storage holding the registrations
+///
+/// * `conference_id` - Id of the conference
+///
+/// # Return
+///
+/// Returns the Ids of the user registered for a conference
+pub fn fetch_user_ids(storage: &dyn Storage, conference_id: &str)
+                      -> ConfCountResult<Vec<String>> {
+    Ok(storage.registrations_for_conference(conference_id)?
+        .into_iter()
+        .map(|registration| registration.user_id)
+        .collect())
 }
 
 
 #[cfg(test)]
 mod test {
-    use mongodb::{bson, doc};
-    use mongodb::Client;
-    use mongodb::coll::Collection;
-    use mongodb::db::ThreadedDatabase;
-    use mongodb::ThreadedClient;
-    use rusoto_rekognition::RekognitionClient;
-
-    use crate::connection::create_rekognition_connection;
-    use crate::constants::{FAILURE, PRESENT};
-    use crate::db_operations::{DEFAULT_MAIL_STATUS, PRESENT_STATUS, STATUS, update_absent_status, update_db, update_present_status, update_record};
-
-    static AWS_SERVER_ERROR: &str = "Image key not found in s3 bucket";
-    static TEST_HOST: &str = "localhost";
-    const TEST_PORT: u16 = 27017;
-    const INVALID_TEST_PORT: u16 = 20000;
-    static TEST_DB_NAME: &str = "Conf_Count";
-    static TEST_COLLECTION_NAME: &str = "emp_test";
-    static MATCHED_IMAGE_KEY: &str = "1122334455";
+    use std::sync::Arc;
+
+    use crate::capture::Capture;
+    use crate::constants::{COMPLETED, NO_CONFERENCE, PRESENT, SUCCESS};
+    use crate::db_operations::{ABSENT_STATUS, approve_review, attendance_rules, AttendanceRules,
+                               complete_conference, DEFAULT_MAIL_STATUS, fetch_user_ids,
+                               NO_REVIEW, NOT_SEEN, PRESENT_STATUS, recompute_attendance,
+                               record_match, reject_review, REVIEW_APPROVED, REVIEW_PENDING,
+                               REVIEW_QUEUED, REVIEW_REJECTED, SEEN, update_absent_status,
+                               update_db};
+    use crate::error::ConfCountError;
+    use crate::mail::memory::MemoryOutbox;
+    use crate::state::AppState;
+    use crate::state::test::test_state;
+    use crate::storage::{AttendanceMatch, AttendanceRepository, Conference,
+                         ConferenceRepository, ConferenceUpdate, Observation, PresenceRule,
+                         Review, ReviewRepository};
+    use crate::storage::memory::MemoryStorage;
+    use crate::storage::memory::test::{TEST_CONFERENCE_ID, TEST_USER_ID, test_attendance_match,
+                                       test_observation, test_storage};
+
     static UNMATCHED_IMAGE_KEY: &str = "knol-2000";
-    static INVALID_IMAGE_KEY: &str = "IMAGE_KEY";
     static EMAIL_SENT: &str = "Email Sent";
-    static EMAIL_NOT_SENT: &str = "Email not sent";
-    static ABSENT_REGISTRATION_ID: &str = "2008";
-    static PRESENT_REGISTRATION_ID: &str = "2006";
+    static TEST_SENDER: &str = "ayush@knoldus.com";
+    static CAPTURED_IMAGE: &str = "tests/resources/test.jpg";
+    const MATCHED_SIMILARITY: f32 = 98.5;
+    const UNMATCHED_SIMILARITY: f32 = 12.0;
+    const SIMILARITY_THRESHOLD: f32 = 75.0;
+    /// Threshold putting the matched similarity within the default review band
+    const REVIEWED_THRESHOLD: f32 = 99.5;
+
+    fn test_capture() -> Capture {
+        Capture::new(CAPTURED_IMAGE.to_string())
+    }
+
+    /// Rules presenting from any capture the users matched from a threshold
+    fn rules(similarity_threshold: f32) -> AttendanceRules {
+        AttendanceRules {
+            similarity_threshold,
+            presence_rule: PresenceRule::AnyCapture,
+            captures: vec![("capture".to_string(), 1000)],
+        }
+    }
+
+    /// Match of the test user in a capture taken at a given time
+    fn match_at(capture_id: &str, captured_at: i64, similarity: f32) -> AttendanceMatch {
+        AttendanceMatch {
+            capture_id: capture_id.to_string(),
+            captured_at,
+            ..test_attendance_match(similarity)
+        }
+    }
+
+    fn set_presence_rule(state: &AppState, presence_rule: PresenceRule) {
+        let conference: Conference =
+            state.storage.conference(TEST_CONFERENCE_ID).unwrap().unwrap();
+        set_rules(state, conference.similarity_threshold, presence_rule);
+    }
+
+    fn set_rules(state: &AppState, similarity_threshold: Option<f32>, presence_rule: PresenceRule) {
+        let conference: Conference =
+            state.storage.conference(TEST_CONFERENCE_ID).unwrap().unwrap();
+        state.storage.update_conference(TEST_CONFERENCE_ID, &ConferenceUpdate {
+            conference_date: conference.conference_date,
+            conference_address1: conference.conference_address1,
+            conference_address2: conference.conference_address2,
+            conference_address3: conference.conference_address3,
+            similarity_threshold,
+            presence_rule,
+        }).unwrap();
+    }
+
+    fn status(state: &AppState) -> String {
+        state.storage.registration(TEST_USER_ID, TEST_CONFERENCE_ID).unwrap().unwrap().status
+    }
+
+    /// The single review pending for the test user
+    fn pending_review(state: &AppState) -> Review {
+        let reviews: Vec<Review> = state.storage.reviews_for_user(TEST_USER_ID).unwrap();
+        assert_eq!(reviews.len(), 1);
+        reviews[0].clone()
+    }
 
     #[test]
-    fn test_update_record_success() {
-        let client: Client = Client::connect(TEST_HOST, TEST_PORT).unwrap();
-        let employees_collection: Collection =
-            client.db(TEST_DB_NAME).collection(TEST_COLLECTION_NAME);
-        let data = doc! {"$set" => {STATUS => PRESENT_STATUS }};
-        assert_eq!(PRESENT, update_record(&employees_collection, data,
-                                          MATCHED_IMAGE_KEY));
+    fn test_update_db_success()
+    {
+        let state: AppState = test_state();
+        assert_eq!(Ok(SUCCESS), update_db(
+            &state, TEST_CONFERENCE_ID, TEST_USER_ID.to_string(),
+            &test_attendance_match(MATCHED_SIMILARITY), &rules(SIMILARITY_THRESHOLD),
+            &test_capture()));
+        assert_eq!(Ok(PRESENT), update_db(
+            &state, TEST_CONFERENCE_ID, TEST_USER_ID.to_string(),
+            &test_attendance_match(MATCHED_SIMILARITY), &rules(SIMILARITY_THRESHOLD),
+            &test_capture()));
     }
 
     #[test]
-    fn test_update_record_failure() {
-        let client: Client = Client::connect(TEST_HOST, INVALID_TEST_PORT).unwrap();
-        let employees_collection: Collection =
-            client.db(TEST_DB_NAME).collection(TEST_COLLECTION_NAME);
-        let data = doc! {"$set" => {STATUS => PRESENT_STATUS}};
-        assert_eq!(FAILURE, update_record(&employees_collection, data,
-                                          MATCHED_IMAGE_KEY));
+    fn test_update_db_failure()
+    {
+        assert_eq!(Ok(DEFAULT_MAIL_STATUS), update_db(
+            &test_state(), TEST_CONFERENCE_ID, UNMATCHED_IMAGE_KEY.to_string(),
+            &test_attendance_match(UNMATCHED_SIMILARITY), &rules(SIMILARITY_THRESHOLD),
+            &test_capture()));
     }
 
     #[test]
-    fn test_update_db_error()
+    fn test_update_db_absent_notice_success()
     {
-        let rekognition_client: RekognitionClient = create_rekognition_connection();
-        let client: Client = Client::connect(TEST_HOST, TEST_PORT).unwrap();
-        let employees_collection: Collection =
-            client.db(TEST_DB_NAME).collection(TEST_COLLECTION_NAME);
-        assert_eq!(AWS_SERVER_ERROR, update_db(
-            &rekognition_client, &employees_collection,
-            INVALID_IMAGE_KEY.to_string()));
+        let outbox: Arc<MemoryOutbox> = Arc::new(MemoryOutbox::default());
+        let state: AppState = AppState { mailer: outbox.clone(), ..test_state() };
+        assert_eq!(Ok(NOT_SEEN), update_db(
+            &state, TEST_CONFERENCE_ID, TEST_USER_ID.to_string(),
+            &test_attendance_match(UNMATCHED_SIMILARITY), &rules(SIMILARITY_THRESHOLD),
+            &test_capture()));
+        assert!(outbox.mails().is_empty());
+        assert_eq!(complete_conference(&state, TEST_CONFERENCE_ID),
+                   Ok("Conference completed, 1 absence notices sent".to_string()));
+        assert_eq!(outbox.mails().len(), 1);
+        assert_eq!(state.storage.conference(TEST_CONFERENCE_ID).unwrap().unwrap().status,
+                   COMPLETED);
     }
 
     #[test]
-    fn test_update_db_success()
+    fn test_absence_notified_once()
     {
-        let rekognition_client: RekognitionClient = create_rekognition_connection();
-        let client: Client = Client::connect(TEST_HOST, TEST_PORT).unwrap();
-        let employees_collection: Collection =
-            client.db(TEST_DB_NAME).collection(TEST_COLLECTION_NAME);
-        assert_eq!(DEFAULT_MAIL_STATUS, update_db(
-            &rekognition_client, &employees_collection,
-            MATCHED_IMAGE_KEY.to_string()));
+        let outbox: Arc<MemoryOutbox> = Arc::new(MemoryOutbox::default());
+        let state: AppState = AppState { mailer: outbox.clone(), ..test_state() };
+        set_presence_rule(&state, PresenceRule::SeenInCaptures { seen: 2, captures: 2 });
+        for (capture_id, captured_at) in &[("first", 1000), ("second", 2000)] {
+            let mut rules: AttendanceRules = attendance_rules(&state, TEST_CONFERENCE_ID).unwrap();
+            rules.add_capture(&Capture {
+                id: capture_id.to_string(),
+                image_path: CAPTURED_IMAGE.to_string(),
+                captured_at: *captured_at,
+            });
+            assert_eq!(update_db(&state, TEST_CONFERENCE_ID, TEST_USER_ID.to_string(),
+                                 &match_at(capture_id, *captured_at, UNMATCHED_SIMILARITY),
+                                 &rules, &test_capture()), Ok(NOT_SEEN));
+        }
+        assert!(outbox.mails().is_empty());
+        complete_conference(&state, TEST_CONFERENCE_ID).unwrap();
+        assert_eq!(complete_conference(&state, TEST_CONFERENCE_ID),
+                   Ok("Conference completed, 0 absence notices sent".to_string()));
+        assert_eq!(outbox.mails().len(), 1);
+        assert_eq!(outbox.mails()[0].to, "test@knoldus.in");
+        assert_eq!(complete_conference(&state, "55443311"),
+                   Err(ConfCountError::NotFound(NO_CONFERENCE.to_string())));
     }
 
     #[test]
-    fn test_update_db_failure()
+    fn test_update_db_queues_review()
     {
-        let rekognition_client: RekognitionClient = create_rekognition_connection();
-        let client: Client = Client::connect(TEST_HOST, TEST_PORT).unwrap();
-        let employees_collection: Collection =
-            client.db(TEST_DB_NAME).collection(TEST_COLLECTION_NAME);
-        assert_eq!(AWS_SERVER_ERROR, update_db(
-            &rekognition_client, &employees_collection,
-            UNMATCHED_IMAGE_KEY.to_string()));
+        let outbox: Arc<MemoryOutbox> = Arc::new(MemoryOutbox::default());
+        let state: AppState = AppState { mailer: outbox.clone(), ..test_state() };
+        let update = |similarity: f32| update_db(
+            &state, TEST_CONFERENCE_ID, TEST_USER_ID.to_string(),
+            &test_attendance_match(similarity), &rules(REVIEWED_THRESHOLD), &test_capture());
+        assert_eq!(update(MATCHED_SIMILARITY), Ok(REVIEW_QUEUED));
+        let first: Review = pending_review(&state);
+        let face_key: String = first.face_key.clone().unwrap();
+        assert!(state.photo_store.get(face_key.as_str()).unwrap().is_some());
+        assert_eq!(first.reference_key, TEST_USER_ID);
+        assert_eq!(update(95.0), Ok(REVIEW_PENDING));
+        assert_eq!(update(98.9), Ok(REVIEW_QUEUED));
+        assert_eq!(pending_review(&state).attendance_match.similarity, 98.9);
+        assert_eq!(state.photo_store.get(face_key.as_str()).unwrap(), None);
+        assert_eq!(update(UNMATCHED_SIMILARITY), Ok(NOT_SEEN));
+        assert!(outbox.mails().is_empty());
+        assert_eq!(update(99.6), Ok(SUCCESS));
+        assert_eq!(update_db(&state, TEST_CONFERENCE_ID, TEST_USER_ID.to_string(),
+                             &match_at("later", 2000, 99.0), &rules(REVIEWED_THRESHOLD),
+                             &test_capture()), Ok(PRESENT));
     }
 
     #[test]
-    fn test_update_present_status_success() {
-        let client: Client = Client::connect(TEST_HOST, TEST_PORT).unwrap();
-        let employees_collection: Collection =
-            client.db(TEST_DB_NAME).collection(TEST_COLLECTION_NAME);
-        assert_eq!(PRESENT, update_present_status(
-            &employees_collection, ABSENT_REGISTRATION_ID))
+    fn test_reviews_per_capture()
+    {
+        let state: AppState = test_state();
+        set_rules(&state, Some(REVIEWED_THRESHOLD),
+                  PresenceRule::SeenInCaptures { seen: 2, captures: 2 });
+        let mut rules: AttendanceRules = attendance_rules(&state, TEST_CONFERENCE_ID).unwrap();
+        for (capture_id, captured_at) in &[("first", 1000), ("second", 2000)] {
+            rules.add_capture(&Capture {
+                id: capture_id.to_string(),
+                image_path: CAPTURED_IMAGE.to_string(),
+                captured_at: *captured_at,
+            });
+            assert_eq!(update_db(&state, TEST_CONFERENCE_ID, TEST_USER_ID.to_string(),
+                                 &match_at(capture_id, *captured_at, MATCHED_SIMILARITY), &rules,
+                                 &test_capture()), Ok(REVIEW_QUEUED));
+        }
+        let reviews: Vec<Review> = state.storage.reviews_for_user(TEST_USER_ID).unwrap();
+        assert_eq!(reviews.len(), 2);
+        approve_review(&state, reviews[0].id.as_str()).unwrap();
+        assert_eq!(status(&state), ABSENT_STATUS);
+        approve_review(&state, reviews[1].id.as_str()).unwrap();
+        assert_eq!(status(&state), PRESENT_STATUS);
+        assert_eq!(state.storage.observations(TEST_USER_ID, TEST_CONFERENCE_ID).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_update_db_presence_rule()
+    {
+        let outbox: Arc<MemoryOutbox> = Arc::new(MemoryOutbox::default());
+        let state: AppState = AppState { mailer: outbox.clone(), ..test_state() };
+        set_presence_rule(&state, PresenceRule::SeenInCaptures { seen: 2, captures: 2 });
+        let update = |capture_id: &str, captured_at: i64, similarity: f32| {
+            let mut rules: AttendanceRules = attendance_rules(&state, TEST_CONFERENCE_ID).unwrap();
+            rules.add_capture(&Capture {
+                id: capture_id.to_string(),
+                image_path: CAPTURED_IMAGE.to_string(),
+                captured_at,
+            });
+            update_db(&state, TEST_CONFERENCE_ID, TEST_USER_ID.to_string(),
+                      &match_at(capture_id, captured_at, similarity), &rules, &test_capture())
+        };
+        assert_eq!(update("first", 1000, MATCHED_SIMILARITY), Ok(SEEN));
+        assert_eq!(status(&state), ABSENT_STATUS);
+        assert_eq!(update("second", 2000, MATCHED_SIMILARITY), Ok(SUCCESS));
+        assert_eq!(update("second", 3000, MATCHED_SIMILARITY), Ok(PRESENT));
+        assert_eq!(state.storage.observations(TEST_USER_ID, TEST_CONFERENCE_ID).unwrap().len(), 2);
+        assert!(state.storage.observations(TEST_USER_ID, TEST_CONFERENCE_ID).unwrap().iter()
+            .all(|observation| observation.attendance_match.captured_at < 3000));
+        assert_eq!(update("first", 1000, UNMATCHED_SIMILARITY), Ok(NOT_SEEN));
+        assert_eq!(status(&state), ABSENT_STATUS);
+        assert!(outbox.mails().is_empty());
     }
 
     #[test]
-    fn test_update_present_status_failure() {
-        let client: Client = Client::connect(TEST_HOST, INVALID_TEST_PORT).unwrap();
-        let employees_collection: Collection =
-            client.db(TEST_DB_NAME).collection(TEST_COLLECTION_NAME);
-        assert_eq!(FAILURE, update_present_status(
-            &employees_collection, ABSENT_REGISTRATION_ID))
+    fn test_update_db_seen_within_minutes()
+    {
+        let state: AppState = test_state();
+        set_presence_rule(&state, PresenceRule::SeenWithinMinutes { minutes: 10 });
+        let update = |capture_id: &str, captured_at: i64, similarity: f32| {
+            let mut rules: AttendanceRules = attendance_rules(&state, TEST_CONFERENCE_ID).unwrap();
+            rules.add_capture(&Capture {
+                id: capture_id.to_string(),
+                image_path: CAPTURED_IMAGE.to_string(),
+                captured_at,
+            });
+            update_db(&state, TEST_CONFERENCE_ID, TEST_USER_ID.to_string(),
+                      &match_at(capture_id, captured_at, similarity), &rules, &test_capture())
+        };
+        assert_eq!(update("opening", 1000, UNMATCHED_SIMILARITY), Ok(NOT_SEEN));
+        assert_eq!(update("late", 1000 + 11 * 60, MATCHED_SIMILARITY), Ok(SEEN));
+        assert_eq!(status(&state), ABSENT_STATUS);
+        assert_eq!(update("early", 1000 + 9 * 60, MATCHED_SIMILARITY), Ok(SUCCESS));
+        assert_eq!(status(&state), PRESENT_STATUS);
+    }
+
+    #[test]
+    fn test_is_present_rules()
+    {
+        let mut attendance: AttendanceRules = rules(SIMILARITY_THRESHOLD);
+        attendance.captures = vec![("a".to_string(), 1000), ("b".to_string(), 1600),
+                                   ("c".to_string(), 2500), ("d".to_string(), 4000)];
+        let seen_in = |capture_ids: &[&str]| -> Vec<Observation> {
+            capture_ids.iter()
+                .map(|capture_id| test_observation(capture_id, 1000, MATCHED_SIMILARITY))
+                .chain(vec![test_observation("b", 1600, UNMATCHED_SIMILARITY)])
+                .collect()
+        };
+        assert!(!attendance.is_present(&seen_in(&[])));
+        assert!(attendance.is_present(&seen_in(&["d"])));
+        attendance.presence_rule = PresenceRule::SeenInCaptures { seen: 2, captures: 3 };
+        assert!(!attendance.is_present(&seen_in(&["a", "d"])));
+        assert!(attendance.is_present(&seen_in(&["a", "c"])));
+        attendance.presence_rule = PresenceRule::SeenWithinMinutes { minutes: 10 };
+        assert!(!attendance.is_present(&seen_in(&["c", "d"])));
+        let mut approved: Vec<Observation> = seen_in(&[]);
+        approved[0].approved = true;
+        assert!(attendance.is_present(&approved));
+    }
+
+    #[test]
+    fn test_recompute_attendance_success()
+    {
+        let state: AppState = test_state();
+        update_db(&state, TEST_CONFERENCE_ID, TEST_USER_ID.to_string(),
+                  &test_attendance_match(MATCHED_SIMILARITY), &rules(SIMILARITY_THRESHOLD),
+                  &test_capture()).unwrap();
+        assert_eq!(status(&state), PRESENT_STATUS);
+        set_presence_rule(&state, PresenceRule::SeenInCaptures { seen: 2, captures: 3 });
+        assert_eq!(recompute_attendance(&state, TEST_CONFERENCE_ID), Ok(1));
+        assert_eq!(status(&state), ABSENT_STATUS);
+        set_presence_rule(&state, PresenceRule::AnyCapture);
+        assert_eq!(recompute_attendance(&state, TEST_CONFERENCE_ID), Ok(1));
+        assert_eq!(recompute_attendance(&state, TEST_CONFERENCE_ID), Ok(0));
+        assert_eq!(status(&state), PRESENT_STATUS);
+    }
+
+    #[test]
+    fn test_approve_review_success()
+    {
+        let state: AppState = test_state();
+        update_db(&state, TEST_CONFERENCE_ID, TEST_USER_ID.to_string(),
+                  &test_attendance_match(MATCHED_SIMILARITY), &rules(REVIEWED_THRESHOLD),
+                  &test_capture())
+            .unwrap();
+        let review: Review = pending_review(&state);
+        assert_eq!(approve_review(&state, review.id.as_str()), Ok(REVIEW_APPROVED));
+        assert_eq!(status(&state), PRESENT_STATUS);
+        assert!(state.storage.observations(TEST_USER_ID, TEST_CONFERENCE_ID).unwrap()[0].approved);
+        assert!(state.storage.reviews_for_conference(TEST_CONFERENCE_ID).unwrap().is_empty());
+        assert_eq!(state.photo_store.get(review.face_key.unwrap().as_str()).unwrap(), None);
+        assert_eq!(approve_review(&state, review.id.as_str()),
+                   Err(ConfCountError::NotFound(NO_REVIEW.to_string())));
+    }
+
+    #[test]
+    fn test_reject_review_success()
+    {
+        let outbox: Arc<MemoryOutbox> = Arc::new(MemoryOutbox::default());
+        let state: AppState = AppState { mailer: outbox.clone(), ..test_state() };
+        update_db(&state, TEST_CONFERENCE_ID, TEST_USER_ID.to_string(),
+                  &test_attendance_match(MATCHED_SIMILARITY), &rules(REVIEWED_THRESHOLD),
+                  &test_capture())
+            .unwrap();
+        assert!(outbox.mails().is_empty());
+        let review: Review = pending_review(&state);
+        assert_eq!(reject_review(&state, review.id.as_str()), Ok(REVIEW_REJECTED));
+        assert!(outbox.mails().is_empty());
+        assert_eq!(status(&state), ABSENT_STATUS);
+        assert_eq!(reject_review(&state, review.id.as_str()),
+                   Err(ConfCountError::NotFound(NO_REVIEW.to_string())));
+    }
+
+    #[test]
+    fn test_attendance_rules_per_conference() {
+        let mut state: AppState = test_state();
+        Arc::get_mut(&mut state.config).unwrap().matching.similarity_threshold = 80.0;
+        let threshold = |conference_id: &str|
+            attendance_rules(&state, conference_id).unwrap().similarity_threshold;
+        assert_eq!(threshold(TEST_CONFERENCE_ID), 80.0);
+        assert_eq!(threshold("test_conference"), 80.0);
+        let mut conference: Conference =
+            state.storage.conference(TEST_CONFERENCE_ID).unwrap().unwrap();
+        conference.id = "6655443322".to_string();
+        conference.similarity_threshold = Some(60.0);
+        conference.presence_rule = PresenceRule::SeenWithinMinutes { minutes: 15 };
+        state.storage.insert_conference(&conference).unwrap();
+        assert_eq!(threshold("6655443322"), 60.0);
+        assert_eq!(attendance_rules(&state, "6655443322").unwrap().presence_rule,
+                   PresenceRule::SeenWithinMinutes { minutes: 15 });
+    }
+
+    #[test]
+    fn test_attendance_rules_orders_captures() {
+        let state: AppState = test_state();
+        for (capture_id, captured_at) in &[("late", 3000), ("early", 1000), ("again", 2000)] {
+            state.storage.save_observation(&test_observation(capture_id, *captured_at, 0.0))
+                .unwrap();
+        }
+        let mut rules: AttendanceRules = attendance_rules(&state, TEST_CONFERENCE_ID).unwrap();
+        rules.add_capture(&Capture {
+            id: "early".to_string(),
+            image_path: CAPTURED_IMAGE.to_string(),
+            captured_at: 4000,
+        });
+        rules.add_capture(&Capture {
+            id: "new".to_string(),
+            image_path: CAPTURED_IMAGE.to_string(),
+            captured_at: 2500,
+        });
+        assert_eq!(rules.captures, vec![("early".to_string(), 1000), ("again".to_string(), 2000),
+                                        ("new".to_string(), 2500), ("late".to_string(), 3000)]);
+    }
+
+    #[test]
+    fn test_record_match_keeps_best() {
+        let storage: MemoryStorage = test_storage();
+        let matched_at = |similarity: f32, captured_at: i64| AttendanceMatch {
+            captured_at,
+            ..test_attendance_match(similarity)
+        };
+        let recorded = || storage.registration(TEST_USER_ID, TEST_CONFERENCE_ID).unwrap().unwrap()
+            .attendance_match;
+        record_match(&storage, TEST_CONFERENCE_ID, TEST_USER_ID, &matched_at(0.0, 1)).unwrap();
+        assert_eq!(recorded(), Some(matched_at(0.0, 1)));
+        record_match(&storage, TEST_CONFERENCE_ID, TEST_USER_ID, &matched_at(90.0, 2)).unwrap();
+        record_match(&storage, TEST_CONFERENCE_ID, TEST_USER_ID, &matched_at(40.0, 3)).unwrap();
+        record_match(&storage, TEST_CONFERENCE_ID, TEST_USER_ID, &matched_at(90.0, 4)).unwrap();
+        assert_eq!(recorded(), Some(matched_at(90.0, 2)));
     }
 
     #[test]
     fn test_update_absent_status_success() {
-        let client: Client = Client::connect(TEST_HOST, TEST_PORT).unwrap();
-        let employees_collection: Collection =
-            client.db(TEST_DB_NAME).collection(TEST_COLLECTION_NAME);
-        assert_eq!(EMAIL_SENT, update_absent_status(
-            &employees_collection, PRESENT_REGISTRATION_ID))
+        let storage: MemoryStorage = test_storage();
+        let outbox: MemoryOutbox = MemoryOutbox::default();
+        assert_eq!(Ok(EMAIL_SENT), update_absent_status(
+            &storage, &outbox, TEST_CONFERENCE_ID, TEST_USER_ID, TEST_SENDER));
+        assert_eq!(Ok(DEFAULT_MAIL_STATUS), update_absent_status(
+            &storage, &outbox, TEST_CONFERENCE_ID, TEST_USER_ID, TEST_SENDER));
+        assert_eq!(outbox.mails().len(), 1);
+        assert_eq!(outbox.mails()[0].to, "test@knoldus.in");
     }
 
     #[test]
     fn test_update_absent_status_failure() {
-        let client: Client = Client::connect(TEST_HOST, TEST_PORT).unwrap();
-        let employees_collection: Collection =
-            client.db(TEST_DB_NAME).collection(TEST_COLLECTION_NAME);
-        assert_eq!(EMAIL_NOT_SENT, update_absent_status(
-            &employees_collection, UNMATCHED_IMAGE_KEY))
+        assert_eq!(Ok(DEFAULT_MAIL_STATUS), update_absent_status(
+            &test_storage(), &MemoryOutbox::default(), TEST_CONFERENCE_ID, UNMATCHED_IMAGE_KEY,
+            TEST_SENDER))
+    }
+
+    #[test]
+    fn test_fetch_user_ids_success() {
+        assert_eq!(fetch_user_ids(&test_storage(), TEST_CONFERENCE_ID).unwrap(),
+                   vec![TEST_USER_ID]);
+        assert!(fetch_user_ids(&test_storage(), "test_conference").unwrap().is_empty());
     }
 }
@@ -1,61 +1,139 @@
-use lettre::{SmtpClient, Transport};
-use lettre_email::Email;
-use log::error;
+use crate::error::ConfCountResult;
+use crate::mail::{Mail, Mailer};
 
 static SUBJECT: &str = "Conference Mail";
 static BODY: &str = "You were absent";
-static SUCCESS: &str = "Email Sent";
-static FAILURE: &str = "Invalid Email";
+static RESET_SUBJECT: &str = "Reset your password";
+static VERIFICATION_SUBJECT: &str = "Verify your email address";
+pub static SUCCESS: &str = "Email Sent";
 
 /// The function send_email sends E-mail to the absent participants
 ///
 /// # Arguments
 ///
+/// * `mailer` - Mailer delivering the E-mail
+///
 /// * `sender` - This is the sender's E-mail
 ///
 /// * `receiver` - This is the receiver's E-mail
 ///
 /// # Return
 ///
-///  This function returns Success or Failure message for the E-mail
-pub fn send_email(sender: &str, receiver: &str) -> &'static str {
-    match Email::builder()
-        .to(receiver)
-        .from(sender)
-        .subject(SUBJECT)
-        .body(BODY)
-        .build() {
-        Ok(email) => {
-            let mailer: SmtpClient = SmtpClient::new_unencrypted_localhost()
-                .expect("Something's not right with SMTP Client");
-            mailer.transport().send(email.into())
-                .expect("Something's not right with E-mail service");
-            SUCCESS
-        }
-        Err(error) => {
-            error!("{}", error);
-            FAILURE
-        }
-    }
+///  This function returns Success message for the E-mail, or the failure of delivering it
+pub fn send_email(mailer: &dyn Mailer, sender: &str, receiver: &str)
+                  -> ConfCountResult<&'static str> {
+    let mail: Mail = Mail {
+        from: sender.to_string(),
+        to: receiver.to_string(),
+        subject: SUBJECT.to_string(),
+        body: BODY.to_string(),
+    };
+    mailer.send(&mail)?;
+    Ok(SUCCESS)
+}
+
+/// Sends the link resetting the password of a user
+///
+/// # Arguments
+///
+/// * `mailer` - Mailer delivering the E-mail
+///
+/// * `sender` - This is the sender's E-mail
+///
+/// * `receiver` - E-mail of the user who forgot the password
+///
+/// * `link` - Link to the reset form, carrying the reset token
+///
+/// * `ttl_minutes` - How long the link can be used
+///
+/// # Return
+///
+/// Returns Success message for the E-mail, or the failure of delivering it
+pub fn send_reset_link(mailer: &dyn Mailer, sender: &str, receiver: &str, link: &str,
+                       ttl_minutes: i64) -> ConfCountResult<&'static str> {
+    let mail: Mail = Mail {
+        from: sender.to_string(),
+        to: receiver.to_string(),
+        subject: RESET_SUBJECT.to_string(),
+        body: format!("Follow this link within {} minutes to choose a new password:\n\n{}\n\n\
+                       The link can be used once. If you did not ask for it, ignore this mail.",
+                      ttl_minutes, link),
+    };
+    mailer.send(&mail)?;
+    Ok(SUCCESS)
+}
+
+/// Sends the link verifying the email address of a new user
+///
+/// # Arguments
+///
+/// * `mailer` - Mailer delivering the E-mail
+///
+/// * `sender` - This is the sender's E-mail
+///
+/// * `receiver` - E-mail given at signup
+///
+/// * `link` - Verification link, carrying the signed token
+///
+/// * `ttl_hours` - How long the account waits for the verification
+///
+/// # Return
+///
+/// Returns Success message for the E-mail, or the failure of delivering it
+pub fn send_verification_link(mailer: &dyn Mailer, sender: &str, receiver: &str, link: &str,
+                              ttl_hours: i64) -> ConfCountResult<&'static str> {
+    let mail: Mail = Mail {
+        from: sender.to_string(),
+        to: receiver.to_string(),
+        subject: VERIFICATION_SUBJECT.to_string(),
+        body: format!("Follow this link within {} hours to verify your email address and \
+                       activate your account:\n\n{}\n\n\
+                       If you did not sign up, ignore this mail and the account will be deleted.",
+                      ttl_hours, link),
+    };
+    mailer.send(&mail)?;
+    Ok(SUCCESS)
 }
 
 #[cfg(test)]
 mod test {
-    use crate::email_service::{FAILURE, send_email, SUCCESS};
+    use crate::email_service::{send_email, send_reset_link, send_verification_link, SUCCESS};
+    use crate::error::ConfCountError;
+    use crate::mail::memory::MemoryOutbox;
 
     static SENDER: &str = "alok.jha@knoldus.in";
-    static INVALID_SENDER: &str = "pankaj@gmail.com";
+    static INVALID_SENDER: &str = "pankaj";
     static RECEIVER: &str = "pankaj.chaudhary@knoldus.in";
 
     #[test]
     fn test_send_email_success() {
+        let outbox: MemoryOutbox = MemoryOutbox::default();
         assert_eq!(send_email(
-            SENDER, RECEIVER), SUCCESS);
+            &outbox, SENDER, RECEIVER), Ok(SUCCESS));
+        assert_eq!(outbox.mails()[0].to, RECEIVER);
     }
 
     #[test]
     fn test_send_email_failure() {
-        assert_eq!(send_email(
-            INVALID_SENDER, RECEIVER), FAILURE);
+        let outbox: MemoryOutbox = MemoryOutbox::default();
+        assert!(matches!(send_email(&outbox, INVALID_SENDER, RECEIVER),
+                         Err(ConfCountError::Mail(_))));
+        assert!(outbox.mails().is_empty());
+    }
+
+    #[test]
+    fn test_send_reset_link_success() {
+        let outbox: MemoryOutbox = MemoryOutbox::default();
+        let link: &str = "http://127.0.0.1:8088/reset-password?token=abc";
+        assert_eq!(send_reset_link(&outbox, SENDER, RECEIVER, link, 30), Ok(SUCCESS));
+        assert!(outbox.mails()[0].body.contains(link));
+    }
+
+    #[test]
+    fn test_send_verification_link_success() {
+        let outbox: MemoryOutbox = MemoryOutbox::default();
+        let link: &str = "http://127.0.0.1:8088/verify-email?token=abc";
+        assert_eq!(send_verification_link(&outbox, SENDER, RECEIVER, link, 48), Ok(SUCCESS));
+        assert!(outbox.mails()[0].body.contains(link));
     }
 }
@@ -0,0 +1,406 @@
+use std::fmt;
+
+use actix_web::{Error, http::header, http::StatusCode, HttpRequest, HttpResponse, ResponseError};
+use actix_web::error::UrlencodedError;
+use futures::Future;
+use log::{error, info};
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::capture::CaptureError;
+use crate::constants::{RESPONSE, STORAGE_FAILURE};
+use crate::face_matching::FaceMatchError;
+use crate::mail::MailError;
+use crate::photo_store::PhotoStoreError;
+use crate::storage::StorageError;
+
+static FACE_MATCHING_FAILURE: &str = "Unable to match the faces";
+static MAIL_FAILURE: &str = "Unable to send the mail";
+static CAMERA_FAILURE: &str = "No Camera Detected";
+static INTERNAL_FAILURE: &str = "Something went wrong, please try again";
+static INVALID_FIELDS: &str = "Some fields are invalid";
+static UNKNOWN_ROUTE: &str = "No such route exists";
+static CODE: &str = "code";
+static MESSAGE: &str = "message";
+static FIELDS: &str = "fields";
+
+/// Reason a single field of a request was rejected
+#[derive(Debug, PartialEq, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: &str, message: &str) -> Self {
+        FieldError {
+            field: field.to_string(),
+            message: message.to_string(),
+        }
+    }
+}
+
+/// Failures of the operations of the application.
+///
+/// Client errors carry the message shown to the user, server errors the cause which is only
+/// logged.
+#[derive(Debug, PartialEq)]
+pub enum ConfCountError {
+    /// The input was rejected
+    Validation(String),
+    /// Named fields of the input were rejected
+    InvalidFields(Vec<FieldError>),
+    /// The credentials are missing or wrong
+    Unauthorized(String),
+    /// The signed in user lacks the role the resource requires
+    Forbidden(String),
+    /// The requested record does not exist
+    NotFound(String),
+    /// The record already exists or is already in the requested state
+    Conflict(String),
+    /// The client has to wait for the given number of seconds before trying again
+    TooManyRequests(String, u64),
+    /// The storage of records or photos failed
+    Storage(String),
+    /// The face matching service failed
+    FaceMatching(String),
+    /// The mail could not be delivered
+    Mail(String),
+    /// The camera failed to capture an image
+    Camera(String),
+    /// The application itself failed
+    Internal(String),
+}
+
+pub type ConfCountResult<T> = Result<T, ConfCountError>;
+
+impl ConfCountError {
+    /// Rejects a single field of the input
+    pub fn invalid_field(field: &str, message: &str) -> Self {
+        ConfCountError::InvalidFields(vec![FieldError::new(field, message)])
+    }
+
+    /// Stable code identifying the kind of failure for the clients
+    pub fn code(&self) -> &'static str {
+        match self {
+            ConfCountError::Validation(_) => "VALIDATION_FAILED",
+            ConfCountError::InvalidFields(_) => "INVALID_FIELDS",
+            ConfCountError::Unauthorized(_) => "UNAUTHORIZED",
+            ConfCountError::Forbidden(_) => "FORBIDDEN",
+            ConfCountError::NotFound(_) => "NOT_FOUND",
+            ConfCountError::Conflict(_) => "CONFLICT",
+            ConfCountError::TooManyRequests(_, _) => "TOO_MANY_REQUESTS",
+            ConfCountError::Storage(_) => "STORAGE_FAILURE",
+            ConfCountError::FaceMatching(_) => "FACE_MATCHING_FAILURE",
+            ConfCountError::Mail(_) => "MAIL_FAILURE",
+            ConfCountError::Camera(_) => "CAMERA_FAILURE",
+            ConfCountError::Internal(_) => "INTERNAL_FAILURE",
+        }
+    }
+
+    /// HTTP status answered for the failure
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ConfCountError::Validation(_) | ConfCountError::InvalidFields(_) =>
+                StatusCode::BAD_REQUEST,
+            ConfCountError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            ConfCountError::Forbidden(_) => StatusCode::FORBIDDEN,
+            ConfCountError::NotFound(_) => StatusCode::NOT_FOUND,
+            ConfCountError::Conflict(_) => StatusCode::CONFLICT,
+            ConfCountError::TooManyRequests(_, _) => StatusCode::TOO_MANY_REQUESTS,
+            ConfCountError::Storage(_) | ConfCountError::Camera(_) | ConfCountError::Internal(_) =>
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ConfCountError::FaceMatching(_) | ConfCountError::Mail(_) => StatusCode::BAD_GATEWAY,
+        }
+    }
+
+    /// Message safe to show to the user
+    pub fn public_message(&self) -> &str {
+        match self {
+            ConfCountError::Validation(message)
+            | ConfCountError::Unauthorized(message)
+            | ConfCountError::Forbidden(message)
+            | ConfCountError::NotFound(message)
+            | ConfCountError::Conflict(message)
+            | ConfCountError::TooManyRequests(message, _) => message.as_str(),
+            ConfCountError::InvalidFields(fields) => match fields.as_slice() {
+                [field] => field.message.as_str(),
+                _ => INVALID_FIELDS,
+            },
+            ConfCountError::Storage(_) => STORAGE_FAILURE,
+            ConfCountError::FaceMatching(_) => FACE_MATCHING_FAILURE,
+            ConfCountError::Mail(_) => MAIL_FAILURE,
+            ConfCountError::Camera(_) => CAMERA_FAILURE,
+            ConfCountError::Internal(_) => INTERNAL_FAILURE,
+        }
+    }
+}
+
+impl fmt::Display for ConfCountError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfCountError::Validation(message)
+            | ConfCountError::Unauthorized(message)
+            | ConfCountError::Forbidden(message)
+            | ConfCountError::NotFound(message)
+            | ConfCountError::Conflict(message)
+            | ConfCountError::TooManyRequests(message, _)
+            | ConfCountError::Storage(message)
+            | ConfCountError::FaceMatching(message)
+            | ConfCountError::Mail(message)
+            | ConfCountError::Camera(message)
+            | ConfCountError::Internal(message) => write!(formatter, "{}", message),
+            ConfCountError::InvalidFields(fields) => {
+                let reasons: Vec<String> = fields.iter()
+                    .map(|field| format!("{}: {}", field.field, field.message))
+                    .collect();
+                write!(formatter, "{}", reasons.join(", "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfCountError {}
+
+impl From<StorageError> for ConfCountError {
+    fn from(error: StorageError) -> Self {
+        ConfCountError::Storage(error.to_string())
+    }
+}
+
+impl From<PhotoStoreError> for ConfCountError {
+    fn from(error: PhotoStoreError) -> Self {
+        ConfCountError::Storage(error.to_string())
+    }
+}
+
+impl From<FaceMatchError> for ConfCountError {
+    fn from(error: FaceMatchError) -> Self {
+        ConfCountError::FaceMatching(error.to_string())
+    }
+}
+
+impl From<MailError> for ConfCountError {
+    fn from(error: MailError) -> Self {
+        ConfCountError::Mail(error.to_string())
+    }
+}
+
+impl From<CaptureError> for ConfCountError {
+    fn from(error: CaptureError) -> Self {
+        ConfCountError::Camera(error.to_string())
+    }
+}
+
+/// The single mapping of the errors of the application to HTTP responses
+impl ResponseError for ConfCountError {
+    fn error_response(&self) -> HttpResponse {
+        let status: StatusCode = self.status();
+        if status.is_server_error() {
+            error!("{}", self);
+        } else {
+            info!("{}", self);
+        }
+        let mut body: Value = json!({CODE: self.code(), MESSAGE: self.public_message()});
+        if let ConfCountError::InvalidFields(fields) = self {
+            body[FIELDS] = json!(fields);
+        }
+        let mut response = HttpResponse::build(status);
+        if let ConfCountError::TooManyRequests(_, retry_after) = self {
+            response.header(header::RETRY_AFTER, retry_after.to_string());
+        }
+        response.json(body)
+    }
+}
+
+/// Collects the fields of a request failing their checks
+#[derive(Default)]
+pub struct Validator {
+    errors: Vec<FieldError>,
+}
+
+impl Validator {
+    pub fn new() -> Self {
+        Validator::default()
+    }
+
+    /// Records the field as invalid when its check does not hold
+    ///
+    /// # Arguments
+    ///
+    /// * `valid` - Result of the check
+    ///
+    /// * `field` - Name of the checked field
+    ///
+    /// * `message` - Message shown to the user when the check fails
+    pub fn check(mut self, valid: bool, field: &str, message: &str) -> Self {
+        if !valid {
+            self.errors.push(FieldError::new(field, message));
+        }
+        self
+    }
+
+    /// Fails with every recorded field, if any
+    pub fn finish(self) -> ConfCountResult<()> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfCountError::InvalidFields(self.errors))
+        }
+    }
+}
+
+/// Fails with a validation error when a check does not hold
+///
+/// # Arguments
+///
+/// * `valid` - Result of the check
+///
+/// * `message` - Message shown to the user when the check fails
+///
+/// # Return
+///
+/// Returns the validation error of a failed check
+pub fn ensure(valid: bool, message: &str) -> ConfCountResult<()> {
+    if valid {
+        Ok(())
+    } else {
+        Err(ConfCountError::Validation(message.to_string()))
+    }
+}
+
+/// Builds the response of an operation
+///
+/// # Arguments
+///
+/// * `outcome` - Value answered on success, or the failure of the operation
+///
+/// # Return
+///
+/// Returns the value as the JSON response, or the response mapped from the error
+pub fn respond<T: Serialize>(outcome: ConfCountResult<T>) -> HttpResponse {
+    match outcome {
+        Ok(value) => HttpResponse::Ok().json(json!({RESPONSE: value})),
+        Err(error) => error.error_response(),
+    }
+}
+
+/// Builds the response of an operation once it completes
+///
+/// # Arguments
+///
+/// * `outcome` - Future value answered on success, or the failure of the operation
+///
+/// # Return
+///
+/// Returns the future JSON response
+pub fn respond_async<T, F>(outcome: F) -> Box<dyn Future<Item=HttpResponse, Error=Error>>
+    where T: Serialize + 'static,
+          F: Future<Item=T, Error=ConfCountError> + 'static {
+    Box::new(outcome.then(|outcome| Ok::<HttpResponse, Error>(respond(outcome))))
+}
+
+/// Answers the requests matching none of the routes
+///
+/// # Arguments
+///
+/// * `_request` - An HTTP request
+///
+/// # Return
+///
+/// Returns the not found response
+pub fn handle_unknown_route<S>(_request: &HttpRequest<S>) -> HttpResponse {
+    ConfCountError::NotFound(UNKNOWN_ROUTE.to_string()).error_response()
+}
+
+/// Rejects a form which could not be deserialized
+///
+/// # Arguments
+///
+/// * `form_error` - Failure of the form extractor
+///
+/// * `_request` - An HTTP request
+///
+/// # Return
+///
+/// Returns the validation error answered for the form
+pub fn reject_form<S>(form_error: UrlencodedError, _request: &HttpRequest<S>) -> Error {
+    ConfCountError::Validation(form_error.to_string()).into()
+}
+
+#[cfg(test)]
+mod test {
+    use actix_web::{HttpResponse, ResponseError};
+    use actix_web::http::{header, StatusCode};
+
+    use crate::constants::STORAGE_FAILURE;
+    use crate::error::{ConfCountError, ConfCountResult, ensure, FieldError, respond, Validator};
+    use crate::storage::StorageError;
+
+    #[test]
+    fn test_error_response_status() {
+        assert_eq!(ConfCountError::Validation("Wrong".to_string()).error_response().status(),
+                   StatusCode::BAD_REQUEST);
+        assert_eq!(ConfCountError::NotFound("Missing".to_string()).error_response().status(),
+                   StatusCode::NOT_FOUND);
+        assert_eq!(ConfCountError::Conflict("Twice".to_string()).error_response().status(),
+                   StatusCode::CONFLICT);
+        assert_eq!(ConfCountError::Forbidden("Admins only".to_string()).error_response().status(),
+                   StatusCode::FORBIDDEN);
+        assert_eq!(ConfCountError::Mail("Refused".to_string()).error_response().status(),
+                   StatusCode::BAD_GATEWAY);
+    }
+
+    #[test]
+    fn test_too_many_requests_retry_after() {
+        let response: HttpResponse =
+            ConfCountError::TooManyRequests("Wait".to_string(), 8).error_response();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers().get(header::RETRY_AFTER).unwrap(), "8");
+    }
+
+    #[test]
+    fn test_error_code() {
+        assert_eq!(ConfCountError::Conflict("Twice".to_string()).code(), "CONFLICT");
+        assert_eq!(ConfCountError::invalid_field("email", "Wrong").code(), "INVALID_FIELDS");
+    }
+
+    #[test]
+    fn test_invalid_field_message() {
+        let error: ConfCountError = ConfCountError::invalid_field("email", "Wrong Email format");
+        assert_eq!(error.public_message(), "Wrong Email format");
+        assert_eq!(error.error_response().status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_validator_collects_fields() {
+        assert_eq!(Validator::new()
+                       .check(false, "name", "Wrong name")
+                       .check(true, "email", "Wrong email")
+                       .check(false, "user_id", "Wrong id")
+                       .finish(),
+                   Err(ConfCountError::InvalidFields(vec![
+                       FieldError::new("name", "Wrong name"),
+                       FieldError::new("user_id", "Wrong id")])));
+        assert_eq!(Validator::new().check(true, "name", "Wrong name").finish(), Ok(()));
+    }
+
+    #[test]
+    fn test_storage_error_hides_cause() {
+        let error: ConfCountError = StorageError("connection refused".to_string()).into();
+        assert_eq!(error.public_message(), STORAGE_FAILURE);
+        assert_eq!(error.error_response().status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[test]
+    fn test_ensure_success() {
+        assert_eq!(ensure(true, "Wrong"), Ok(()));
+        assert_eq!(ensure(false, "Wrong"), Err(ConfCountError::Validation("Wrong".to_string())));
+    }
+
+    #[test]
+    fn test_respond_success() {
+        let outcome: ConfCountResult<&str> = Ok("Successfully updated");
+        assert_eq!(respond(outcome).status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,115 @@
+use actix::{Actor, Addr, Handler, Message, SyncArbiter, SyncContext};
+use futures::future;
+use futures::Future;
+use futures::sync::oneshot;
+
+use crate::error::{ConfCountError, ConfCountResult};
+
+static WORKER_LOST: &str = "The blocking worker stopped before finishing the work";
+
+/// Blocking work handed to the pool
+struct Job(Box<dyn FnOnce() + Send>);
+
+impl Message for Job {
+    type Result = ();
+}
+
+/// Worker of the pool, running one job at a time on its own thread
+pub struct BlockingWorker;
+
+impl Actor for BlockingWorker {
+    type Context = SyncContext<Self>;
+}
+
+impl Handler<Job> for BlockingWorker {
+    type Result = ();
+
+    fn handle(&mut self, job: Job, _context: &mut SyncContext<Self>) {
+        (job.0)()
+    }
+}
+
+/// Runs the blocking calls to the storage, the photo store, the camera, the face matcher and
+/// the mailer away from the event loop of the server
+#[derive(Clone)]
+pub enum Executor {
+    /// Pool of threads of a `SyncArbiter`
+    Pool(Addr<BlockingWorker>),
+    /// The calling thread, for tests and tools running without an actix system
+    Inline,
+}
+
+impl Executor {
+    /// Starts the pool of blocking workers in the current actix system
+    ///
+    /// # Arguments
+    ///
+    /// * `threads` - Number of worker threads
+    ///
+    /// # Return
+    ///
+    /// Returns the executor handing work to the pool
+    pub fn start(threads: usize) -> Executor {
+        Executor::Pool(SyncArbiter::start(threads, || BlockingWorker))
+    }
+
+    /// Runs blocking work
+    ///
+    /// # Arguments
+    ///
+    /// * `work` - Work to run, owning everything it needs
+    ///
+    /// # Return
+    ///
+    /// Returns the future outcome of the work
+    pub fn run<T, F>(&self, work: F) -> Box<dyn Future<Item=T, Error=ConfCountError>>
+        where F: FnOnce() -> ConfCountResult<T> + Send + 'static,
+              T: Send + 'static {
+        match self {
+            Executor::Inline => Box::new(future::result(work())),
+            Executor::Pool(address) => {
+                let (sender, receiver) = oneshot::channel();
+                address.do_send(Job(Box::new(move || {
+                    let _ = sender.send(work());
+                })));
+                Box::new(receiver
+                    .map_err(|_| ConfCountError::Internal(WORKER_LOST.to_string()))
+                    .and_then(future::result))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::thread;
+
+    use actix::System;
+    use futures::Future;
+
+    use crate::error::ConfCountError;
+    use crate::executor::Executor;
+
+    #[test]
+    fn test_pool_runs_off_calling_thread() {
+        let mut system = System::new("test_executor");
+        let caller: thread::ThreadId = thread::current().id();
+        let worker: thread::ThreadId = system
+            .block_on(Executor::start(2).run(|| Ok(thread::current().id())))
+            .unwrap();
+        assert_ne!(caller, worker);
+    }
+
+    #[test]
+    fn test_pool_forwards_failure() {
+        let mut system = System::new("test_executor");
+        let outcome: Result<(), ConfCountError> = system.block_on(Executor::start(1)
+            .run(|| Err(ConfCountError::NotFound("Missing".to_string()))));
+        assert_eq!(outcome, Err(ConfCountError::NotFound("Missing".to_string())));
+    }
+
+    #[test]
+    fn test_inline_success() {
+        assert_eq!(Executor::Inline.run(|| Ok(42)).wait(), Ok(42));
+    }
+}
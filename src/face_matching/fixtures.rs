@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::Path;
+
+use image::GrayImage;
+
+use crate::face_matching::{DetectedFace, FaceMatch, FaceMatcher, FaceMatchError, FaceMatchResult};
+
+/// Confidence of the single face the fixtures matcher finds in every photo
+const FIXTURE_FACE_CONFIDENCE: f32 = 99.0;
+/// Scales the mean difference between neighbouring pixels to a 0 to 100 sharpness
+const SHARPNESS_SCALE: f64 = 10.0;
+
+/// Similarity of the indexed reference face of a user with the captured images
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct FaceFixture {
+    pub user_id: String,
+    /// File name of the captured image the entry applies to, every capture when missing
+    pub image: Option<String>,
+    pub similarity: f32,
+}
+
+#[derive(Deserialize)]
+struct Fixtures {
+    #[serde(default)]
+    faces: Vec<FaceFixture>,
+}
+
+/// Deterministic matcher answering from a fixtures file, so that attendance can be taken
+/// without any network access. The fixtures stand for the index of the reference faces, which
+/// is left untouched by indexing or removing a face. It finds a single face in every photo,
+/// whose brightness and sharpness are measured from the pixels of the whole photo.
+pub struct FixtureMatcher {
+    faces: Vec<FaceFixture>,
+}
+
+impl FixtureMatcher {
+    /// Creates a matcher answering from the given fixtures
+    pub fn new(faces: Vec<FaceFixture>) -> FixtureMatcher {
+        FixtureMatcher { faces }
+    }
+
+    /// Loads the fixtures from a TOML file holding a `[[faces]]` table per entry
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the fixtures file
+    ///
+    /// # Return
+    ///
+    /// Returns the fixtures matcher
+    pub fn from_file(path: &str) -> FaceMatchResult<FixtureMatcher> {
+        let content: String = fs::read_to_string(path)
+            .map_err(|error| FaceMatchError(format!("Unable to read {}: {}", path, error)))?;
+        let fixtures: Fixtures = toml::from_str(content.as_str())
+            .map_err(|error| FaceMatchError(format!("Unable to parse {}: {}", path, error)))?;
+        Ok(FixtureMatcher::new(fixtures.faces))
+    }
+}
+
+impl FaceMatcher for FixtureMatcher {
+    fn index_face(&self, _user_id: &str, _photo: &[u8]) -> FaceMatchResult<()> {
+        Ok(())
+    }
+
+    fn remove_faces(&self, _user_ids: &[&str]) -> FaceMatchResult<()> {
+        Ok(())
+    }
+
+    fn search_faces(&self, captured_image_path: &str)
+                    -> FaceMatchResult<HashMap<String, FaceMatch>> {
+        fs::metadata(captured_image_path).map_err(|error| {
+            FaceMatchError(format!("Unable to read {}: {}", captured_image_path, error))
+        })?;
+        let image: Option<&str> = Path::new(captured_image_path).file_name()
+            .and_then(OsStr::to_str);
+        let mut matches: HashMap<String, FaceMatch> = HashMap::new();
+        for face in &self.faces {
+            if face.image.is_none() || face.image.as_ref().map(String::as_str) == image {
+                matches.entry(face.user_id.clone())
+                    .or_insert(FaceMatch { similarity: face.similarity, face_box: None });
+            }
+        }
+        Ok(matches)
+    }
+
+    fn detect_faces(&self, photo: &[u8]) -> FaceMatchResult<Vec<DetectedFace>> {
+        let luma: GrayImage = image::load_from_memory(photo)
+            .map_err(|error| FaceMatchError(format!("Unable to decode the photo: {}", error)))?
+            .to_luma();
+        let pixels: f64 = f64::from(luma.width()) * f64::from(luma.height());
+        let total: f64 = luma.pixels().map(|pixel| f64::from(pixel[0])).sum();
+        let contrast: f64 = luma.enumerate_pixels()
+            .filter(|(x, _, _)| x + 1 < luma.width())
+            .map(|(x, y, pixel)| {
+                (f64::from(luma.get_pixel(x + 1, y)[0]) - f64::from(pixel[0])).abs()
+            })
+            .sum();
+        Ok(vec![DetectedFace {
+            confidence: FIXTURE_FACE_CONFIDENCE,
+            brightness: (total / pixels.max(1.0) / 255.0 * 100.0) as f32,
+            sharpness: (contrast / pixels.max(1.0) * SHARPNESS_SCALE).min(100.0) as f32,
+        }])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::fs;
+
+    use image::{DynamicImage, GrayImage, ImageOutputFormat, Luma};
+
+    use crate::config::test::test_config;
+    use crate::face_matching::{DetectedFace, FaceMatch, FaceMatcher};
+    use crate::face_matching::fixtures::{FaceFixture, FixtureMatcher};
+
+    fn fixture(user_id: &str, image: Option<&str>, similarity: f32) -> FaceFixture {
+        FaceFixture {
+            user_id: user_id.to_string(),
+            image: image.map(str::to_string),
+            similarity,
+        }
+    }
+
+    #[test]
+    fn test_from_file_success() {
+        let matcher: FixtureMatcher =
+            FixtureMatcher::from_file(&test_config().matching.fixtures_path).unwrap();
+        assert!(matcher.search_faces("tests/resources/test.jpg").unwrap()["1111111111"].similarity
+            > 75.0);
+    }
+
+    #[test]
+    fn test_from_file_failure() {
+        assert!(FixtureMatcher::from_file("tests/resources/test.txt").is_err());
+        assert!(FixtureMatcher::from_file("tests/resources/missing.toml").is_err());
+    }
+
+    #[test]
+    fn test_search_faces_by_image_success() {
+        let matcher: FixtureMatcher = FixtureMatcher::new(vec![
+            fixture("1111111111", Some("test.jpg"), 91.0),
+            fixture("1111111111", Some("test1.jpg"), 12.5),
+            fixture("1122334455", None, 80.0),
+        ]);
+        let matches: HashMap<String, FaceMatch> =
+            matcher.search_faces("tests/resources/test.jpg").unwrap();
+        assert_eq!(matches.len(), 2);
+        assert_eq!(matches["1111111111"], FaceMatch { similarity: 91.0, face_box: None });
+        assert_eq!(matcher.search_faces("tests/resources/test1.jpg").unwrap()["1111111111"]
+                       .similarity, 12.5);
+        assert!(!matcher.search_faces("tests/resources/test2.jpg").unwrap()
+            .contains_key("1111111111"));
+    }
+
+    #[test]
+    fn test_search_faces_missing_capture_failure() {
+        let matcher: FixtureMatcher = FixtureMatcher::new(vec![fixture("1111111111", None, 91.0)]);
+        assert!(matcher.search_faces("tests/resources/missing.jpg").is_err());
+    }
+
+    fn grey_photo(value: u8) -> Vec<u8> {
+        let mut bytes: Vec<u8> = Vec::new();
+        DynamicImage::ImageLuma8(GrayImage::from_pixel(100, 100, Luma([value])))
+            .write_to(&mut bytes, ImageOutputFormat::PNG)
+            .unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_detect_faces_measures_photo() {
+        let matcher: FixtureMatcher = FixtureMatcher::new(Vec::new());
+        let faces: Vec<DetectedFace> =
+            matcher.detect_faces(&fs::read("tests/resources/test.jpg").unwrap()).unwrap();
+        assert_eq!(faces.len(), 1);
+        let dark: Vec<DetectedFace> = matcher.detect_faces(&grey_photo(0)).unwrap();
+        assert_eq!((dark[0].brightness, dark[0].sharpness), (0.0, 0.0));
+        assert_eq!(matcher.detect_faces(&grey_photo(255)).unwrap()[0].brightness, 100.0);
+        assert!(matcher.detect_faces(b"not a photo").is_err());
+    }
+}
@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use tokio::runtime::TaskExecutor;
+
+use crate::config::{AppConfig, MatcherBackend};
+use crate::connection::create_rekognition_connection;
+use crate::face_matching::fixtures::FixtureMatcher;
+use crate::face_matching::rekognition::RekognitionMatcher;
+
+pub mod fixtures;
+
+pub mod rekognition;
+
+pub mod quality;
+
+/// Failure of the face matching service
+#[derive(Debug, PartialEq)]
+pub struct FaceMatchError(pub String);
+
+impl fmt::Display for FaceMatchError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "Face matching error: {}", self.0)
+    }
+}
+
+impl std::error::Error for FaceMatchError {}
+
+pub type FaceMatchResult<T> = Result<T, FaceMatchError>;
+
+/// Face found in a photo, measured on scales from 0 to 100
+#[derive(Clone, Debug, PartialEq)]
+pub struct DetectedFace {
+    /// Confidence that the area is a face
+    pub confidence: f32,
+    pub brightness: f32,
+    pub sharpness: f32,
+}
+
+/// Position of a face in an image, in ratios of the width and the height of the image
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct FaceBox {
+    pub left: f32,
+    pub top: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+/// Face of a captured image most similar to the reference face of a user
+#[derive(Clone, Debug, PartialEq)]
+pub struct FaceMatch {
+    /// Similarity from 0 to 100
+    pub similarity: f32,
+    /// Position of the face in the capture, when the backend reports it
+    pub face_box: Option<FaceBox>,
+}
+
+/// Recognises the registered users in a captured image from the index of their reference faces
+pub trait FaceMatcher: Send + Sync {
+    /// Adds the reference face of a user to the index searched by the captures
+    fn index_face(&self, user_id: &str, photo: &[u8]) -> FaceMatchResult<()>;
+
+    /// Removes the reference faces of the users from the index, ignoring the users whose face was
+    /// not indexed
+    fn remove_faces(&self, user_ids: &[&str]) -> FaceMatchResult<()>;
+
+    /// Searches the index once for every face found in the captured image, and returns the most
+    /// similar face of each recognised user. The users resembling no face of the image are
+    /// missing.
+    fn search_faces(&self, captured_image_path: &str)
+                    -> FaceMatchResult<HashMap<String, FaceMatch>>;
+
+    /// Returns every face found in a photo, none when it shows no face
+    fn detect_faces(&self, photo: &[u8]) -> FaceMatchResult<Vec<DetectedFace>>;
+}
+
+/// Creates the face matcher selected in the configuration
+///
+/// # Arguments
+///
+/// * `config` - Configuration of the application
+///
+/// * `executor` - Executor of the runtime shared by the rusoto clients
+///
+/// # Return
+///
+/// Returns the matcher shared by all the handlers, or the error of loading it
+pub fn create_face_matcher(config: &AppConfig, executor: TaskExecutor)
+                           -> FaceMatchResult<Arc<dyn FaceMatcher>> {
+    Ok(match config.matching.matcher {
+        MatcherBackend::Rekognition => Arc::new(RekognitionMatcher::new(
+            create_rekognition_connection(&config.aws),
+            config.matching.collection_id.as_str(),
+            executor)?),
+        MatcherBackend::Fixtures =>
+            Arc::new(FixtureMatcher::from_file(&config.matching.fixtures_path)?),
+    })
+}
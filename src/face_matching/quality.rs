@@ -0,0 +1,117 @@
+use log::info;
+
+use crate::config::EnrollmentConfig;
+use crate::error::{ConfCountError, ConfCountResult};
+use crate::face_matching::{DetectedFace, FaceMatcher};
+use crate::signup_form::UPLOAD_IMAGE;
+
+pub static NO_FACE_FOUND: &str = "No face found in the photo, please upload a photo of your face";
+pub static MULTIPLE_FACES: &str =
+    "Multiple faces found in the photo, please upload a photo of your face alone";
+pub static FACE_UNCLEAR: &str = "The face in the photo is unclear, please upload a clearer photo";
+pub static TOO_DARK: &str = "The photo is too dark, please upload a brighter one";
+pub static TOO_BRIGHT: &str = "The photo is too bright, please upload a darker one";
+pub static TOO_BLURRY: &str = "The photo is too blurry, please upload a sharper one";
+
+/// Finds the reason a photo is unfit to be the reference face of a user
+///
+/// # Arguments
+///
+/// * `config` - Quality required of the face
+///
+/// * `faces` - Faces detected in the photo
+///
+/// # Return
+///
+/// Returns the feedback for the user, if the photo is unfit
+fn assess_faces(config: &EnrollmentConfig, faces: &[DetectedFace]) -> Option<&'static str> {
+    let clear: Vec<&DetectedFace> = faces.iter()
+        .filter(|face| face.confidence >= config.min_face_confidence)
+        .collect();
+    match clear.as_slice() {
+        [] if faces.is_empty() => Some(NO_FACE_FOUND),
+        [] => Some(FACE_UNCLEAR),
+        [face] if face.brightness < config.min_brightness => Some(TOO_DARK),
+        [face] if face.brightness > config.max_brightness => Some(TOO_BRIGHT),
+        [face] if face.sharpness < config.min_sharpness => Some(TOO_BLURRY),
+        [_] => None,
+        _ => Some(MULTIPLE_FACES),
+    }
+}
+
+/// Checks that a photo shows a single face of a quality the face matching can rely on
+///
+/// # Arguments
+///
+/// * `matcher` - Face matching backend detecting the faces
+///
+/// * `config` - Quality required of the face
+///
+/// * `photo` - Normalized photo uploaded at signup
+///
+/// # Return
+///
+/// Returns the specific feedback on the photo field when it is unfit, or the failure of the
+/// face matching backend
+pub fn check_reference_face(matcher: &dyn FaceMatcher, config: &EnrollmentConfig, photo: &[u8])
+                            -> ConfCountResult<()> {
+    let faces: Vec<DetectedFace> = matcher.detect_faces(photo)?;
+    match assess_faces(config, &faces) {
+        Some(feedback) => {
+            info!("Rejected a reference photo with faces {:?} - {}", faces, feedback);
+            Err(ConfCountError::invalid_field(UPLOAD_IMAGE, feedback))
+        }
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use crate::config::EnrollmentConfig;
+    use crate::error::ConfCountError;
+    use crate::face_matching::DetectedFace;
+    use crate::face_matching::fixtures::FixtureMatcher;
+    use crate::face_matching::quality::{assess_faces, check_reference_face, FACE_UNCLEAR,
+                                        MULTIPLE_FACES, NO_FACE_FOUND, TOO_BLURRY, TOO_BRIGHT,
+                                        TOO_DARK};
+    use crate::signup_form::UPLOAD_IMAGE;
+
+    fn face(confidence: f32, brightness: f32, sharpness: f32) -> DetectedFace {
+        DetectedFace { confidence, brightness, sharpness }
+    }
+
+    #[test]
+    fn test_assess_faces_single_clear_face() {
+        let config: EnrollmentConfig = EnrollmentConfig::default();
+        assert_eq!(assess_faces(&config, &[face(99.0, 60.0, 70.0)]), None);
+        assert_eq!(assess_faces(&config, &[face(99.0, 60.0, 70.0), face(40.0, 50.0, 5.0)]), None);
+    }
+
+    #[test]
+    fn test_assess_faces_feedback() {
+        let config: EnrollmentConfig = EnrollmentConfig::default();
+        assert_eq!(assess_faces(&config, &[]), Some(NO_FACE_FOUND));
+        assert_eq!(assess_faces(&config, &[face(99.0, 60.0, 70.0), face(95.0, 60.0, 70.0)]),
+                   Some(MULTIPLE_FACES));
+        assert_eq!(assess_faces(&config, &[face(50.0, 60.0, 70.0)]), Some(FACE_UNCLEAR));
+        assert_eq!(assess_faces(&config, &[face(99.0, 5.0, 70.0)]), Some(TOO_DARK));
+        assert_eq!(assess_faces(&config, &[face(99.0, 99.0, 70.0)]), Some(TOO_BRIGHT));
+        assert_eq!(assess_faces(&config, &[face(99.0, 60.0, 2.0)]), Some(TOO_BLURRY));
+    }
+
+    #[test]
+    fn test_check_reference_face() {
+        let matcher: FixtureMatcher = FixtureMatcher::new(Vec::new());
+        let photo: Vec<u8> = fs::read("tests/resources/test.jpg").unwrap();
+        assert_eq!(check_reference_face(&matcher, &EnrollmentConfig::default(), &photo), Ok(()));
+        let strict: EnrollmentConfig = EnrollmentConfig {
+            min_brightness: 99.0,
+            max_brightness: 100.0,
+            ..EnrollmentConfig::default()
+        };
+        assert_eq!(check_reference_face(&matcher, &strict, &photo),
+                   Err(ConfCountError::invalid_field(UPLOAD_IMAGE, TOO_DARK)));
+    }
+}
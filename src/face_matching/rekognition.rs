@@ -0,0 +1,296 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+
+use image::{DynamicImage, GenericImageView, ImageOutputFormat};
+use log::info;
+use rusoto_rekognition::{BoundingBox, Face, FaceDetail, ImageQuality, RekognitionClient};
+use tokio::runtime::TaskExecutor;
+
+use crate::face_matching::{DetectedFace, FaceBox, FaceMatch, FaceMatcher, FaceMatchError,
+                           FaceMatchResult};
+use crate::image_comparison::{create_collection, delete_faces, detect_faces, index_face,
+                              list_faces, search_faces};
+use crate::photo_processing::decode_upright;
+use crate::request_generator::{create_delete_faces_request, create_detect_faces_request,
+                               create_index_faces_request, create_list_faces_request,
+                               create_search_faces_request};
+
+/// Lowest similarity Rekognition is asked to report, so that the application applies its own
+/// threshold to every detected face
+const REPORTED_SIMILARITY: f32 = 0.0;
+/// Share of the size of a face added on each of its sides when cropping it from a capture
+const FACE_MARGIN: f32 = 0.25;
+/// Shortest side of the cropped faces, Rekognition does not search smaller ones
+const MIN_SEARCHED_FACE: u32 = 40;
+const CAPTURE_JPEG_QUALITY: u8 = 90;
+/// Most faces Rekognition removes in a single request
+const DELETED_FACES: usize = 4096;
+
+/// Matcher recognising the users in the captured image from a collection of Amazon Rekognition
+/// indexing their reference faces under their user ids
+pub struct RekognitionMatcher {
+    client: RekognitionClient,
+    collection_id: String,
+    executor: TaskExecutor,
+}
+
+impl RekognitionMatcher {
+    /// Creates a matcher searching a collection, creating the collection when missing
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - Client of Amazon Rekognition API
+    ///
+    /// * `collection_id` - Id of the collection of the reference faces
+    ///
+    /// * `executor` - Executor of the runtime shared by the rusoto clients
+    ///
+    /// # Return
+    ///
+    /// Returns the Rekognition matcher, or the failure of creating its collection
+    pub fn new(client: RekognitionClient, collection_id: &str, executor: TaskExecutor)
+               -> FaceMatchResult<RekognitionMatcher> {
+        create_collection(&client, collection_id, &executor)
+            .map_err(|aws_error| FaceMatchError(aws_error.to_string()))?;
+        Ok(RekognitionMatcher { client, collection_id: collection_id.to_string(), executor })
+    }
+
+    /// Returns the ids Rekognition gave to the indexed faces of the users, listing the collection
+    /// once whatever the number of users
+    fn face_ids(&self, user_ids: &[&str]) -> FaceMatchResult<Vec<String>> {
+        let users: HashSet<&str> = user_ids.iter().cloned().collect();
+        let mut face_ids: Vec<String> = Vec::new();
+        let mut next_token: Option<String> = None;
+        loop {
+            let (faces, token) = list_faces(
+                &self.client,
+                create_list_faces_request(self.collection_id.as_str(), next_token),
+                &self.executor)
+                .map_err(|aws_error| FaceMatchError(aws_error.to_string()))?;
+            face_ids.extend(faces_of_users(faces, &users));
+            match token {
+                Some(_) => next_token = token,
+                None => return Ok(face_ids),
+            }
+        }
+    }
+}
+
+impl FaceMatcher for RekognitionMatcher {
+    fn index_face(&self, user_id: &str, photo: &[u8]) -> FaceMatchResult<()> {
+        index_face(&self.client,
+                   create_index_faces_request(self.collection_id.as_str(), user_id, photo.to_vec()),
+                   &self.executor)
+            .map_err(|aws_error| FaceMatchError(aws_error.to_string()))
+    }
+
+    fn remove_faces(&self, user_ids: &[&str]) -> FaceMatchResult<()> {
+        if user_ids.is_empty() {
+            return Ok(());
+        }
+        for face_ids in self.face_ids(user_ids)?.chunks(DELETED_FACES) {
+            delete_faces(&self.client,
+                         create_delete_faces_request(self.collection_id.as_str(),
+                                                     face_ids.to_vec()),
+                         &self.executor)
+                .map_err(|aws_error| FaceMatchError(aws_error.to_string()))?;
+        }
+        Ok(())
+    }
+
+    fn search_faces(&self, captured_image_path: &str)
+                    -> FaceMatchResult<HashMap<String, FaceMatch>> {
+        let capture: Vec<u8> = fs::read(captured_image_path).map_err(|read_error| {
+            FaceMatchError(format!("Unable to read {}: {}", captured_image_path, read_error))
+        })?;
+        let mut upright: DynamicImage = decode_upright(&capture).map_err(|decode_error| {
+            FaceMatchError(format!("Unable to decode {}: {}", captured_image_path, decode_error))
+        })?;
+        let faces: Vec<FaceDetail> = detect_faces(
+            &self.client, create_detect_faces_request(encode_jpeg(&upright)?), &self.executor)
+            .map_err(|aws_error| FaceMatchError(aws_error.to_string()))?;
+        let mut matches: HashMap<String, FaceMatch> = HashMap::new();
+        for bounding_box in faces.iter().filter_map(|face| face.bounding_box.as_ref()) {
+            let (left, top, width, height) = match face_region(&upright, bounding_box) {
+                Some(region) => region,
+                None => {
+                    info!("Skipped a face too small to be searched - {:?}", bounding_box);
+                    continue;
+                }
+            };
+            let face: Vec<u8> = encode_jpeg(&upright.crop(left, top, width, height))?;
+            let best_match: Option<(String, f32)> = search_faces(
+                &self.client,
+                create_search_faces_request(self.collection_id.as_str(), face,
+                                            REPORTED_SIMILARITY),
+                &self.executor)
+                .map_err(|aws_error| FaceMatchError(aws_error.to_string()))?;
+            if let Some((user_id, similarity)) = best_match {
+                let face_match = FaceMatch { similarity, face_box: face_box(bounding_box) };
+                match matches.get(&user_id) {
+                    Some(best) if best.similarity >= similarity => {}
+                    _ => {
+                        matches.insert(user_id, face_match);
+                    }
+                }
+            }
+        }
+        Ok(matches)
+    }
+
+    fn detect_faces(&self, photo: &[u8]) -> FaceMatchResult<Vec<DetectedFace>> {
+        detect_faces(&self.client, create_detect_faces_request(photo.to_vec()), &self.executor)
+            .map(|faces| faces.iter().map(detected_face).collect())
+            .map_err(|aws_error| FaceMatchError(aws_error.to_string()))
+    }
+}
+
+/// Keeps the ids of the listed faces indexed under the id of one of the users
+fn faces_of_users(faces: Vec<Face>, users: &HashSet<&str>) -> Vec<String> {
+    faces.into_iter()
+        .filter(|face| face.external_image_id.as_ref()
+            .map_or(false, |user_id| users.contains(user_id.as_str())))
+        .filter_map(|face| face.face_id)
+        .collect()
+}
+
+/// Encodes an image as the JPEG sent to Rekognition
+fn encode_jpeg(image: &DynamicImage) -> FaceMatchResult<Vec<u8>> {
+    let mut bytes: Vec<u8> = Vec::new();
+    DynamicImage::ImageRgb8(image.to_rgb())
+        .write_to(&mut bytes, ImageOutputFormat::JPEG(CAPTURE_JPEG_QUALITY))
+        .map_err(|encode_error| {
+            FaceMatchError(format!("Unable to encode an image: {}", encode_error))
+        })?;
+    Ok(bytes)
+}
+
+/// Finds the area of the image to crop around a detected face
+///
+/// # Arguments
+///
+/// * `image` - Image the face was detected in
+///
+/// * `bounding_box` - Position of the face, in ratios of the size of the image
+///
+/// # Return
+///
+/// Returns the left, top, width and height of the area in pixels, none when the face is too
+/// small to be searched
+fn face_region(image: &DynamicImage, bounding_box: &BoundingBox) -> Option<(u32, u32, u32, u32)> {
+    let (image_width, image_height): (f32, f32) = (image.width() as f32, image.height() as f32);
+    let width: f32 = bounding_box.width? * image_width;
+    let height: f32 = bounding_box.height? * image_height;
+    let face_left: f32 = bounding_box.left? * image_width;
+    let face_top: f32 = bounding_box.top? * image_height;
+    let left: f32 = (face_left - width * FACE_MARGIN).max(0.0);
+    let top: f32 = (face_top - height * FACE_MARGIN).max(0.0);
+    let right: f32 = (face_left + width * (1.0 + FACE_MARGIN)).min(image_width);
+    let bottom: f32 = (face_top + height * (1.0 + FACE_MARGIN)).min(image_height);
+    if right - left < MIN_SEARCHED_FACE as f32 || bottom - top < MIN_SEARCHED_FACE as f32 {
+        return None;
+    }
+    Some((left as u32, top as u32, (right - left) as u32, (bottom - top) as u32))
+}
+
+/// Position of a face detected by Rekognition, none when not fully reported
+fn face_box(bounding_box: &BoundingBox) -> Option<FaceBox> {
+    Some(FaceBox {
+        left: bounding_box.left?,
+        top: bounding_box.top?,
+        width: bounding_box.width?,
+        height: bounding_box.height?,
+    })
+}
+
+/// Measures of a face detected by Rekognition, 0 when not reported
+fn detected_face(face: &FaceDetail) -> DetectedFace {
+    let quality: Option<&ImageQuality> = face.quality.as_ref();
+    DetectedFace {
+        confidence: face.confidence.unwrap_or(0.0),
+        brightness: quality.and_then(|quality| quality.brightness).unwrap_or(0.0),
+        sharpness: quality.and_then(|quality| quality.sharpness).unwrap_or(0.0),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::{HashMap, HashSet};
+    use std::fs;
+
+    use image::{DynamicImage, RgbImage};
+    use rusoto_rekognition::{BoundingBox, Face};
+    use tokio::runtime::Runtime;
+
+    use crate::config::test::test_config;
+    use crate::connection::create_rekognition_connection;
+    use crate::face_matching::{FaceMatch, FaceMatcher};
+    use crate::face_matching::rekognition::{face_region, faces_of_users, RekognitionMatcher};
+
+    static KEY: &str = "1122334455";
+    static TEST_COLLECTION: &str = "conf-count-test-faces";
+    static REFERENCE_IMAGE_FILE: &str = "tests/resources/test.jpg";
+    static SIMILAR_TEST_IMAGE_FILE: &str = "tests/resources/test2.jpg";
+
+    fn matcher(runtime: &Runtime) -> RekognitionMatcher {
+        RekognitionMatcher::new(create_rekognition_connection(&test_config().aws),
+                                TEST_COLLECTION, runtime.executor()).unwrap()
+    }
+
+    fn bounding_box(left: f32, top: f32, width: f32, height: f32) -> BoundingBox {
+        BoundingBox { left: Some(left), top: Some(top), width: Some(width), height: Some(height) }
+    }
+
+    #[test]
+    fn test_face_region_success() {
+        let image: DynamicImage = DynamicImage::ImageRgb8(RgbImage::new(400, 200));
+        assert_eq!(face_region(&image, &bounding_box(0.25, 0.25, 0.25, 0.5)),
+                   Some((75, 25, 150, 150)));
+        assert_eq!(face_region(&image, &bounding_box(-0.1, 0.8, 0.25, 0.5)),
+                   Some((0, 135, 85, 65)));
+    }
+
+    #[test]
+    fn test_face_region_too_small() {
+        let image: DynamicImage = DynamicImage::ImageRgb8(RgbImage::new(400, 200));
+        assert_eq!(face_region(&image, &bounding_box(0.5, 0.5, 0.05, 0.1)), None);
+        assert_eq!(face_region(&image, &BoundingBox::default()), None);
+    }
+
+    fn face(face_id: &str, user_id: Option<&str>) -> Face {
+        Face {
+            face_id: Some(face_id.to_string()),
+            external_image_id: user_id.map(str::to_string),
+            ..Face::default()
+        }
+    }
+
+    #[test]
+    fn test_faces_of_users_success() {
+        let users: HashSet<&str> = ["1111111111", "2222222222"].iter().cloned().collect();
+        let faces: Vec<Face> = vec![face("a", Some("1111111111")), face("b", Some("3333333333")),
+                                    face("c", None), face("d", Some("2222222222")),
+                                    face("e", Some("1111111111"))];
+        assert_eq!(faces_of_users(faces, &users), vec!["a", "d", "e"]);
+        assert!(faces_of_users(vec![face("a", Some("1111111111"))], &HashSet::new()).is_empty());
+    }
+
+    #[test]
+    fn test_rekognition_search_faces_success() {
+        let runtime: Runtime = Runtime::new().unwrap();
+        let matcher: RekognitionMatcher = matcher(&runtime);
+        matcher.remove_faces(&[KEY]).unwrap();
+        matcher.index_face(KEY, &fs::read(REFERENCE_IMAGE_FILE).unwrap()).unwrap();
+        let matches: HashMap<String, FaceMatch> =
+            matcher.search_faces(SIMILAR_TEST_IMAGE_FILE).unwrap();
+        assert!(matches[KEY].similarity >= test_config().matching.similarity_threshold);
+        assert!(matches[KEY].face_box.is_some());
+        matcher.remove_faces(&[KEY]).unwrap();
+    }
+
+    #[test]
+    fn test_rekognition_search_faces_failure() {
+        let runtime: Runtime = Runtime::new().unwrap();
+        assert!(matcher(&runtime).search_faces("tests/resources/test.txt").is_err())
+    }
+}
@@ -1,43 +1,202 @@
 use futures::future::Future;
 use futures::sync::oneshot::spawn;
 use log::error;
-use rusoto_rekognition::{CompareFacesRequest, Rekognition, RekognitionClient};
-use tokio::runtime::Runtime;
+use rusoto_rekognition::{CreateCollectionError, CreateCollectionRequest, DeleteFacesRequest,
+                         DetectFacesRequest, Face, FaceDetail, IndexFacesRequest,
+                         ListFacesRequest, Rekognition, RekognitionClient,
+                         SearchFacesByImageRequest};
+use tokio::runtime::TaskExecutor;
 
-use crate::constants::{DIFFERENT, SIMILAR};
+static DETECTION_ERROR: &str = "Unable to detect the faces of the image";
+static COLLECTION_ERROR: &str = "Unable to create the collection of the reference faces";
+static INDEXING_ERROR: &str = "Unable to index the reference face";
+static NO_FACE_INDEXED: &str = "No face could be indexed from the reference photo";
+static SEARCH_ERROR: &str = "Unable to search the collection of the reference faces";
+static LISTING_ERROR: &str = "Unable to list the reference faces";
+static REMOVAL_ERROR: &str = "Unable to remove the reference faces";
 
-static AWS_SERVER_ERROR: &str = "Image key not found in s3 bucket";
+/// Detects the faces of an image
+///
+/// # Arguments
+///
+/// * `rekognition_client` - This is the client of Amazon Rekognition API
+///
+/// * `detect_faces_request` - This is the instance of the DetectFacesRequest
+///
+/// * `executor` - Executor of the runtime shared by the rusoto clients
+///
+/// # Return
+///
+/// Returns the details of every face found, or the failure of the detection
+pub fn detect_faces(
+    rekognition_client: &RekognitionClient,
+    detect_faces_request: DetectFacesRequest,
+    executor: &TaskExecutor,
+) -> Result<Vec<FaceDetail>, &'static str> {
+    spawn(rekognition_client.detect_faces(detect_faces_request)
+              .map(|response| response.face_details.unwrap_or_default())
+              .map_err(|error| {
+                  error!("{}", error);
+                  DETECTION_ERROR
+              })
+          , executor)
+        .wait()
+}
 
-/// Compare two images and return their status(Similar or not)
+/// Creates the collection of the reference faces, unless it already exists
 ///
 /// # Arguments
 ///
 /// * `rekognition_client` - This is the client of Amazon Rekognition API
 ///
-/// * `compare_faces_request` - This is the instance of the CompareFacesRequest
+/// * `collection_id` - Id of the collection
+///
+/// * `executor` - Executor of the runtime shared by the rusoto clients
 ///
 /// # Return
 ///
-/// Returns the success or failure of image comparison
-pub fn compare_images(
+/// Returns the failure of the creation, if the collection is missing and could not be created
+pub fn create_collection(
     rekognition_client: &RekognitionClient,
-    compare_faces_request: CompareFacesRequest,
-) -> Result<&'static str, &'static str> {
-    spawn(rekognition_client.compare_faces(compare_faces_request)
-              .map(|response| {
-                  if !response.face_matches.expect("Empty payload").is_empty() {
-                      SIMILAR
-                  } else {
-                      DIFFERENT
+    collection_id: &str,
+    executor: &TaskExecutor,
+) -> Result<(), &'static str> {
+    let request = CreateCollectionRequest { collection_id: collection_id.to_string() };
+    spawn(rekognition_client.create_collection(request)
+              .then(|outcome| match outcome {
+                  Ok(_) | Err(CreateCollectionError::ResourceAlreadyExists(_)) => Ok(()),
+                  Err(error) => {
+                      error!("{}", error);
+                      Err(COLLECTION_ERROR)
                   }
               })
+          , executor)
+        .wait()
+}
+
+/// Indexes the face of a reference photo into a collection
+///
+/// # Arguments
+///
+/// * `rekognition_client` - This is the client of Amazon Rekognition API
+///
+/// * `index_faces_request` - This is the instance of the IndexFacesRequest
+///
+/// * `executor` - Executor of the runtime shared by the rusoto clients
+///
+/// # Return
+///
+/// Returns the failure of the indexing, or the absence of any indexed face
+pub fn index_face(
+    rekognition_client: &RekognitionClient,
+    index_faces_request: IndexFacesRequest,
+    executor: &TaskExecutor,
+) -> Result<(), &'static str> {
+    spawn(rekognition_client.index_faces(index_faces_request)
+              .map_err(|error| {
+                  error!("{}", error);
+                  INDEXING_ERROR
+              })
+              .and_then(|response| match response.face_records {
+                  Some(ref records) if !records.is_empty() => Ok(()),
+                  _ => Err(NO_FACE_INDEXED),
+              })
+          , executor)
+        .wait()
+}
+
+/// Searches a collection for the indexed face most similar to the face of an image
+///
+/// # Arguments
+///
+/// * `rekognition_client` - This is the client of Amazon Rekognition API
+///
+/// * `search_faces_request` - This is the instance of the SearchFacesByImageRequest
+///
+/// * `executor` - Executor of the runtime shared by the rusoto clients
+///
+/// # Return
+///
+/// Returns the id of the user of the most similar face with its similarity, none when no face
+/// is similar enough, or the failure of the search
+pub fn search_faces(
+    rekognition_client: &RekognitionClient,
+    search_faces_request: SearchFacesByImageRequest,
+    executor: &TaskExecutor,
+) -> Result<Option<(String, f32)>, &'static str> {
+    spawn(rekognition_client.search_faces_by_image(search_faces_request)
+              .map(|response| {
+                  response.face_matches.unwrap_or_default().into_iter()
+                      .filter_map(|face_match| {
+                          let user_id: String = face_match.face?.external_image_id?;
+                          Some((user_id, face_match.similarity.unwrap_or(0.0)))
+                      })
+                      .fold(None, |best: Option<(String, f32)>, candidate| match best {
+                          Some(best) if best.1 >= candidate.1 => Some(best),
+                          _ => Some(candidate),
+                      })
+              })
               .map_err(|error| {
                   error!("{}", error);
-                  AWS_SERVER_ERROR
+                  SEARCH_ERROR
               })
-          , &Runtime::new()
-            .expect("Tokio is not working")
-            .executor())
+          , executor)
+        .wait()
+}
+
+/// Lists a page of the faces of a collection
+///
+/// # Arguments
+///
+/// * `rekognition_client` - This is the client of Amazon Rekognition API
+///
+/// * `list_faces_request` - This is the instance of the ListFacesRequest
+///
+/// * `executor` - Executor of the runtime shared by the rusoto clients
+///
+/// # Return
+///
+/// Returns the faces of the page with the token of the next page, or the failure of the listing
+pub fn list_faces(
+    rekognition_client: &RekognitionClient,
+    list_faces_request: ListFacesRequest,
+    executor: &TaskExecutor,
+) -> Result<(Vec<Face>, Option<String>), &'static str> {
+    spawn(rekognition_client.list_faces(list_faces_request)
+              .map(|response| (response.faces.unwrap_or_default(), response.next_token))
+              .map_err(|error| {
+                  error!("{}", error);
+                  LISTING_ERROR
+              })
+          , executor)
+        .wait()
+}
+
+/// Removes faces from a collection
+///
+/// # Arguments
+///
+/// * `rekognition_client` - This is the client of Amazon Rekognition API
+///
+/// * `delete_faces_request` - This is the instance of the DeleteFacesRequest
+///
+/// * `executor` - Executor of the runtime shared by the rusoto clients
+///
+/// # Return
+///
+/// Returns the failure of the removal
+pub fn delete_faces(
+    rekognition_client: &RekognitionClient,
+    delete_faces_request: DeleteFacesRequest,
+    executor: &TaskExecutor,
+) -> Result<(), &'static str> {
+    spawn(rekognition_client.delete_faces(delete_faces_request)
+              .map(|_| ())
+              .map_err(|error| {
+                  error!("{}", error);
+                  REMOVAL_ERROR
+              })
+          , executor)
         .wait()
 }
 
@@ -46,89 +205,19 @@ mod test {
     use std::fs;
 
     use rusoto_core::Region;
-    use rusoto_rekognition::{CompareFacesRequest, Image, RekognitionClient, S3Object};
+    use rusoto_rekognition::RekognitionClient;
+    use tokio::runtime::Runtime;
 
-    use crate::constants::{DIFFERENT, SIMILAR};
-    use crate::image_comparison::compare_images;
+    use crate::image_comparison::detect_faces;
+    use crate::request_generator::create_detect_faces_request;
 
-    static BUCKET_NAME: &str = "labelsfacedetect";
-    static KEY: &str = "1122334455";
-    const SIMILARITY_THRESHOLD: f32 = 75.0;
     static TEST_IMAGE_FILE: &str = "tests/resources/test.jpg";
-    static DIFFERENT_TEST_IMAGE_FILE: &str = "tests/resources/test1.jpg";
-    static SIMILAR_TEST_IMAGE_FILE: &str = "tests/resources/test2.jpg";
-    static TEST_TEXT_FILE: &str = "tests/resources/test.txt";
-
-    fn get_utilities(
-        source_image: Image,
-        target_image: Image,
-    ) -> (RekognitionClient, CompareFacesRequest) {
-        (RekognitionClient::new(Region::ApSouth1),
-         CompareFacesRequest {
-             similarity_threshold: Some(SIMILARITY_THRESHOLD),
-             source_image,
-             target_image,
-         }
-        )
-    }
-
-    #[test]
-    fn test_compare_images_success() {
-        let source_image = Image {
-            bytes: None,
-            s3_object: Some(
-                S3Object {
-                    bucket: Some(BUCKET_NAME.to_string()),
-                    name: Some(KEY.to_string()),
-                    version: None,
-                }
-            ),
-        };
-        let target_image = Image {
-            bytes: Some(fs::read(SIMILAR_TEST_IMAGE_FILE).unwrap()),
-            s3_object: None,
-        };
-
-        let utilities: (RekognitionClient, CompareFacesRequest) =
-            get_utilities(source_image, target_image);
-        assert_eq!(SIMILAR, compare_images(
-            &utilities.0, utilities.1).unwrap())
-    }
-
-    #[test]
-    fn test_compare_images_failure() {
-        let source_image = Image {
-            bytes: None,
-            s3_object: Some(
-                S3Object {
-                    bucket: Some(BUCKET_NAME.to_string()),
-                    name: Some(KEY.to_string()),
-                    version: None,
-                }
-            ),
-        };
-        let target_image = Image {
-            bytes: Some(fs::read(DIFFERENT_TEST_IMAGE_FILE).unwrap()),
-            s3_object: None,
-        };
-        let utilities: (RekognitionClient, CompareFacesRequest) =
-            get_utilities(source_image, target_image);
-        assert_eq!(DIFFERENT, compare_images(
-            &utilities.0, utilities.1).unwrap())
-    }
 
     #[test]
-    fn test_compare_images_error() {
-        let source_image = Image {
-            bytes: Some(fs::read(TEST_IMAGE_FILE).unwrap()),
-            s3_object: None,
-        };
-        let target_image = Image {
-            bytes: Some(fs::read(TEST_TEXT_FILE).unwrap()),
-            s3_object: None,
-        };
-        let utilities: (RekognitionClient, CompareFacesRequest) =
-            get_utilities(source_image, target_image);
-        assert!(compare_images(&utilities.0, utilities.1).is_err())
+    fn test_detect_faces_success() {
+        let runtime: Runtime = Runtime::new().unwrap();
+        assert_eq!(detect_faces(&RekognitionClient::new(Region::ApSouth1),
+                                create_detect_faces_request(fs::read(TEST_IMAGE_FILE).unwrap()),
+                                &runtime.executor()).unwrap().len(), 1)
     }
 }
@@ -11,8 +11,11 @@ pub mod request_handlers {
     pub mod forgot_password_handler;
     pub mod reset_password_handler;
     pub mod logout_handler;
+    pub mod verification_handler;
 }
 
+pub mod config;
+
 pub mod connection;
 
 pub mod constants;
@@ -25,10 +28,36 @@ pub mod image_comparison;
 
 pub mod db_operations;
 
-pub mod user_data;
-
 pub mod email_service;
 
 pub mod response_service;
 
 pub mod user_data_upload_operations;
+
+pub mod signup_form;
+
+pub mod photo_processing;
+
+pub mod state;
+
+pub mod storage;
+
+pub mod face_matching;
+
+pub mod capture;
+
+pub mod mail;
+
+pub mod photo_store;
+
+pub mod error;
+
+pub mod executor;
+
+pub mod session;
+
+pub mod authorization;
+
+pub mod password;
+
+pub mod login_throttle;
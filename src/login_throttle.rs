@@ -0,0 +1,231 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+
+use log::{info, warn};
+
+use crate::config::LoginThrottleConfig;
+use crate::error::{ConfCountError, ConfCountResult};
+
+static TOO_MANY_FAILURES: &str = "Too many failed logins, please try again later";
+
+/// Failed logins recorded for an account or a client
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Failures {
+    count: u32,
+    /// UNIX time in seconds of the latest failure
+    last_failure: u64,
+    /// UNIX time in seconds until which the logins are refused
+    blocked_until: u64,
+}
+
+/// Counts the failed logins per account and per client IP, refusing the logins for an
+/// exponentially growing delay past the free failures and locking out for a while past the
+/// lockout threshold. The counters live in the memory of the process.
+pub struct LoginThrottle {
+    config: LoginThrottleConfig,
+    accounts: Mutex<HashMap<String, Failures>>,
+    clients: Mutex<HashMap<String, Failures>>,
+}
+
+/// Acquires the counters, which remain usable even if a thread panicked while holding them
+fn lock(failures: &Mutex<HashMap<String, Failures>>) -> MutexGuard<HashMap<String, Failures>> {
+    failures.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Key of the counters of an account, shared by every spelling of the email
+fn account_key(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+/// Seconds left before the key can try again
+fn blocked_for(failures: &HashMap<String, Failures>, key: &str, now: u64) -> u64 {
+    failures.get(key).map_or(0, |failures| failures.blocked_until.saturating_sub(now))
+}
+
+impl LoginThrottle {
+    pub fn new(config: LoginThrottleConfig) -> Self {
+        LoginThrottle {
+            config,
+            accounts: Mutex::new(HashMap::new()),
+            clients: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Refuses a login attempted while the account or the client is blocked
+    ///
+    /// # Arguments
+    ///
+    /// * `email` - Email the login is attempted for
+    ///
+    /// * `client` - IP of the client, if known
+    ///
+    /// * `now` - Current UNIX time in seconds
+    ///
+    /// # Return
+    ///
+    /// Returns a too many requests error carrying the seconds to wait, if blocked
+    pub fn check(&self, email: &str, client: Option<&str>, now: u64) -> ConfCountResult<()> {
+        let account_wait: u64 = blocked_for(&lock(&self.accounts), &account_key(email), now);
+        let client_wait: u64 =
+            client.map_or(0, |client| blocked_for(&lock(&self.clients), client, now));
+        let wait: u64 = account_wait.max(client_wait);
+        if wait > 0 {
+            info!("Refused a login for {} from {:?} for another {} seconds",
+                  email, client, wait);
+            Err(ConfCountError::TooManyRequests(TOO_MANY_FAILURES.to_string(), wait))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Records a failed login against the account and the client
+    ///
+    /// # Arguments
+    ///
+    /// * `email` - Email the login failed for
+    ///
+    /// * `client` - IP of the client, if known
+    ///
+    /// * `now` - Current UNIX time in seconds
+    pub fn record_failure(&self, email: &str, client: Option<&str>, now: u64) {
+        let key: String = account_key(email);
+        let account: Failures = self.register(&mut lock(&self.accounts), &key,
+                                              self.config.account_free_failures,
+                                              self.config.account_lockout_failures, now);
+        if account.count == self.config.account_lockout_failures {
+            warn!("Locked out account {} for {} minutes after {} failed logins, the last from \
+                   {:?}", key, self.config.lockout_minutes, account.count, client);
+        }
+        if let Some(client) = client {
+            let failures: Failures = self.register(&mut lock(&self.clients), client,
+                                                   self.config.client_free_failures,
+                                                   self.config.client_lockout_failures, now);
+            if failures.count == self.config.client_lockout_failures {
+                warn!("Locked out client {} for {} minutes after {} failed logins, the last for \
+                       {}", client, self.config.lockout_minutes, failures.count, key);
+            }
+        }
+    }
+
+    /// Forgets the failures of an account once its user logged in
+    pub fn record_success(&self, email: &str) {
+        lock(&self.accounts).remove(&account_key(email));
+    }
+
+    /// Lifts the lockout or the delay of an account
+    ///
+    /// # Argument
+    ///
+    /// * `email` - Email of the account
+    ///
+    /// # Return
+    ///
+    /// Returns whether failures were recorded for the account
+    pub fn unlock(&self, email: &str) -> bool {
+        lock(&self.accounts).remove(&account_key(email)).is_some()
+    }
+
+    /// Counts a failure of a key, forgetting the quiet ones, and blocks it when past the limits
+    fn register(&self, failures: &mut HashMap<String, Failures>, key: &str, free_failures: u32,
+                lockout_failures: u32, now: u64) -> Failures {
+        let reset_after: u64 = self.config.reset_after_minutes * 60;
+        failures.retain(|_, failures| failures.blocked_until > now
+            || now.saturating_sub(failures.last_failure) < reset_after);
+        let failures: &mut Failures = failures.entry(key.to_string()).or_default();
+        failures.count += 1;
+        failures.last_failure = now;
+        if failures.count >= lockout_failures {
+            failures.blocked_until = now + self.config.lockout_minutes * 60;
+        } else if failures.count > free_failures {
+            let doublings: u32 = (failures.count - free_failures - 1).min(63);
+            let delay: u64 = self.config.base_delay_seconds
+                .checked_mul(1u64 << doublings)
+                .unwrap_or(u64::max_value())
+                .min(self.config.max_delay_seconds);
+            failures.blocked_until = now + delay;
+        }
+        failures.clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::LoginThrottleConfig;
+    use crate::error::ConfCountError;
+    use crate::login_throttle::LoginThrottle;
+
+    static EMAIL: &str = "test@knoldus.in";
+    static CLIENT: &str = "10.0.0.7";
+
+    fn retry_after(throttle: &LoginThrottle, email: &str, client: Option<&str>, now: u64)
+                   -> u64 {
+        match throttle.check(email, client, now) {
+            Ok(()) => 0,
+            Err(ConfCountError::TooManyRequests(_, wait)) => wait,
+            Err(error) => panic!("unexpected error {}", error),
+        }
+    }
+
+    #[test]
+    fn test_backoff_doubles_past_free_failures() {
+        let throttle: LoginThrottle = LoginThrottle::new(LoginThrottleConfig::default());
+        for _ in 0..3 {
+            throttle.record_failure(EMAIL, None, 1000);
+        }
+        assert_eq!(retry_after(&throttle, EMAIL, None, 1000), 0);
+        throttle.record_failure(EMAIL, None, 1000);
+        assert_eq!(retry_after(&throttle, EMAIL, None, 1000), 1);
+        throttle.record_failure(EMAIL, None, 1001);
+        assert_eq!(retry_after(&throttle, EMAIL, None, 1001), 2);
+        throttle.record_failure(EMAIL, None, 1003);
+        assert_eq!(retry_after(&throttle, " Test@Knoldus.in ", None, 1003), 4);
+        assert_eq!(retry_after(&throttle, "other@knoldus.in", None, 1003), 0);
+    }
+
+    #[test]
+    fn test_lockout_and_unlock() {
+        let throttle: LoginThrottle = LoginThrottle::new(LoginThrottleConfig::default());
+        for _ in 0..10 {
+            throttle.record_failure(EMAIL, Some(CLIENT), 1000);
+        }
+        assert_eq!(retry_after(&throttle, EMAIL, None, 1000), 15 * 60);
+        assert!(throttle.unlock(EMAIL));
+        assert!(!throttle.unlock(EMAIL));
+        assert_eq!(retry_after(&throttle, EMAIL, None, 1000), 0);
+    }
+
+    #[test]
+    fn test_failures_reset_after_quiet_period() {
+        let throttle: LoginThrottle = LoginThrottle::new(LoginThrottleConfig::default());
+        for _ in 0..4 {
+            throttle.record_failure(EMAIL, None, 1000);
+        }
+        throttle.record_failure(EMAIL, None, 1000 + 60 * 60);
+        assert_eq!(retry_after(&throttle, EMAIL, None, 1000 + 60 * 60), 0);
+    }
+
+    #[test]
+    fn test_success_clears_account_failures() {
+        let throttle: LoginThrottle = LoginThrottle::new(LoginThrottleConfig::default());
+        for _ in 0..4 {
+            throttle.record_failure(EMAIL, None, 1000);
+        }
+        throttle.record_success(EMAIL);
+        assert_eq!(retry_after(&throttle, EMAIL, None, 1000), 0);
+    }
+
+    #[test]
+    fn test_client_failures_across_accounts() {
+        let throttle: LoginThrottle = LoginThrottle::new(LoginThrottleConfig {
+            client_free_failures: 2,
+            client_lockout_failures: 3,
+            ..LoginThrottleConfig::default()
+        });
+        for account in 0..3 {
+            throttle.record_failure(&format!("user{}@knoldus.in", account), Some(CLIENT), 1000);
+        }
+        assert_eq!(retry_after(&throttle, EMAIL, Some(CLIENT), 1000), 15 * 60);
+        assert_eq!(retry_after(&throttle, EMAIL, Some("10.0.0.8"), 1000), 0);
+        assert_eq!(retry_after(&throttle, EMAIL, None, 1000), 0);
+    }
+}
@@ -0,0 +1,45 @@
+use std::sync::Mutex;
+
+use crate::mail::{build_email, Mail, Mailer, MailError, MailResult};
+
+/// Mailer keeping the delivered mails in memory, for tests and demos
+#[derive(Default)]
+pub struct MemoryOutbox {
+    mails: Mutex<Vec<Mail>>,
+}
+
+impl MemoryOutbox {
+    /// Returns the mails delivered so far, oldest first
+    pub fn mails(&self) -> Vec<Mail> {
+        self.mails.lock().map(|mails| mails.clone()).unwrap_or_default()
+    }
+}
+
+impl Mailer for MemoryOutbox {
+    fn send(&self, mail: &Mail) -> MailResult<()> {
+        build_email(mail)?;
+        self.mails.lock()
+            .map_err(|_| MailError::Spool("Outbox lock poisoned".to_string()))?
+            .push(mail.clone());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mail::{Mail, Mailer};
+    use crate::mail::memory::MemoryOutbox;
+
+    #[test]
+    fn test_send_success() {
+        let outbox: MemoryOutbox = MemoryOutbox::default();
+        let mail: Mail = Mail {
+            from: "conference@knoldus.in".to_string(),
+            to: "test@knoldus.in".to_string(),
+            subject: "Conference Mail".to_string(),
+            body: "You were absent".to_string(),
+        };
+        outbox.send(&mail).unwrap();
+        assert_eq!(outbox.mails(), vec![mail]);
+    }
+}
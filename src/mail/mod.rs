@@ -0,0 +1,118 @@
+use std::fmt;
+use std::sync::Arc;
+
+use lettre_email::Email;
+
+use crate::config::{MailConfig, MailTransport};
+use crate::mail::memory::MemoryOutbox;
+use crate::mail::smtp::SmtpMailer;
+use crate::mail::spool::SpoolMailer;
+
+pub mod memory;
+
+pub mod smtp;
+
+pub mod spool;
+
+/// A plain text mail
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mail {
+    pub from: String,
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// Reasons for which a mail could not be delivered
+#[derive(Debug, PartialEq)]
+pub enum MailError {
+    /// The mail can not be built, most likely because of an invalid address
+    InvalidMail(String),
+    /// The mail server could not be reached or the connection failed
+    Connection(String),
+    /// The mail server refused the mail
+    Rejected(String),
+    /// The mail could not be written to the spool
+    Spool(String),
+}
+
+impl fmt::Display for MailError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MailError::InvalidMail(reason) => write!(formatter, "Invalid mail: {}", reason),
+            MailError::Connection(reason) =>
+                write!(formatter, "Unable to reach the mail server: {}", reason),
+            MailError::Rejected(reason) => write!(formatter, "Mail rejected: {}", reason),
+            MailError::Spool(reason) => write!(formatter, "Unable to spool the mail: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for MailError {}
+
+pub type MailResult<T> = Result<T, MailError>;
+
+/// Delivers the mails of the application
+pub trait Mailer: Send + Sync {
+    fn send(&self, mail: &Mail) -> MailResult<()>;
+}
+
+/// Builds the message of a mail
+///
+/// # Arguments
+///
+/// * `mail` - Mail to build
+///
+/// # Return
+///
+/// Returns the message, or an error when an address is invalid
+pub fn build_email(mail: &Mail) -> MailResult<Email> {
+    Email::builder()
+        .to(mail.to.as_str())
+        .from(mail.from.as_str())
+        .subject(mail.subject.as_str())
+        .body(mail.body.as_str())
+        .build()
+        .map_err(|error| MailError::InvalidMail(error.to_string()))
+}
+
+/// Creates the mailer selected in the configuration
+///
+/// # Arguments
+///
+/// * `mail_config` - Mail settings of the application
+///
+/// # Return
+///
+/// Returns the mailer shared by all the handlers, or the error of setting it up
+pub fn create_mailer(mail_config: &MailConfig) -> MailResult<Arc<dyn Mailer>> {
+    Ok(match mail_config.transport {
+        MailTransport::Smtp => Arc::new(SmtpMailer::new(mail_config)?),
+        MailTransport::Spool => Arc::new(SpoolMailer::new(&mail_config.spool_directory)?),
+        MailTransport::Memory => Arc::new(MemoryOutbox::default()),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mail::{build_email, Mail, MailError};
+
+    fn mail(to: &str) -> Mail {
+        Mail {
+            from: "conference@knoldus.in".to_string(),
+            to: to.to_string(),
+            subject: "Conference Mail".to_string(),
+            body: "You were absent".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_build_email_success() {
+        assert!(build_email(&mail("test@knoldus.in")).is_ok());
+    }
+
+    #[test]
+    fn test_build_email_failure() {
+        assert!(matches!(build_email(&mail("not-an-email")), Err(MailError::InvalidMail(_))));
+    }
+}
@@ -0,0 +1,90 @@
+use std::sync::Mutex;
+
+use lettre::{ClientSecurity, ClientTlsParameters, SmtpClient, SmtpTransport, Transport};
+use lettre::smtp::authentication::Credentials;
+use lettre::smtp::error::Error as SmtpError;
+use native_tls::TlsConnector;
+
+use crate::config::MailConfig;
+use crate::mail::{build_email, Mail, Mailer, MailError, MailResult};
+
+impl From<SmtpError> for MailError {
+    fn from(error: SmtpError) -> Self {
+        match error {
+            SmtpError::Transient(_) | SmtpError::Permanent(_) =>
+                MailError::Rejected(error.to_string()),
+            _ => MailError::Connection(error.to_string()),
+        }
+    }
+}
+
+/// Mailer delivering through an SMTP server
+pub struct SmtpMailer {
+    transport: Mutex<SmtpTransport>,
+}
+
+impl SmtpMailer {
+    /// Creates a mailer for the configured SMTP server
+    ///
+    /// # Arguments
+    ///
+    /// * `mail_config` - Host, port, STARTTLS and credentials of the server
+    ///
+    /// # Return
+    ///
+    /// Returns the SMTP mailer, or an error when the server address can not be resolved
+    pub fn new(mail_config: &MailConfig) -> MailResult<SmtpMailer> {
+        let host: &str = mail_config.smtp_host.as_str();
+        let security: ClientSecurity = if mail_config.starttls {
+            let connector: TlsConnector = TlsConnector::new()
+                .map_err(|error| MailError::Connection(error.to_string()))?;
+            ClientSecurity::Required(ClientTlsParameters::new(host.to_string(), connector))
+        } else {
+            ClientSecurity::None
+        };
+        let mut client: SmtpClient = SmtpClient::new((host, mail_config.smtp_port), security)?;
+        if !mail_config.username.is_empty() {
+            client = client.credentials(Credentials::new(mail_config.username.clone(),
+                                                         mail_config.password.clone()));
+        }
+        Ok(SmtpMailer { transport: Mutex::new(client.transport()) })
+    }
+}
+
+impl Mailer for SmtpMailer {
+    fn send(&self, mail: &Mail) -> MailResult<()> {
+        let email = build_email(mail)?;
+        self.transport.lock()
+            .map_err(|_| MailError::Connection("SMTP transport lock poisoned".to_string()))?
+            .send(email.into())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::MailConfig;
+    use crate::mail::{Mail, Mailer, MailError};
+    use crate::mail::smtp::SmtpMailer;
+
+    #[test]
+    fn test_new_failure() {
+        let mut mail_config: MailConfig = MailConfig::default();
+        mail_config.smtp_host = "unknown.invalid".to_string();
+        assert!(matches!(SmtpMailer::new(&mail_config), Err(MailError::Connection(_))));
+    }
+
+    #[test]
+    fn test_send_connection_failure() {
+        let mut mail_config: MailConfig = MailConfig::default();
+        mail_config.smtp_host = "127.0.0.1".to_string();
+        mail_config.smtp_port = 1;
+        let mailer: SmtpMailer = SmtpMailer::new(&mail_config).unwrap();
+        assert!(matches!(mailer.send(&Mail {
+            from: "conference@knoldus.in".to_string(),
+            to: "test@knoldus.in".to_string(),
+            subject: "Conference Mail".to_string(),
+            body: "You were absent".to_string(),
+        }), Err(MailError::Connection(_))));
+    }
+}
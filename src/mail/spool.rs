@@ -0,0 +1,65 @@
+use std::fs;
+use std::path::PathBuf;
+
+use lettre::{FileTransport, Transport};
+
+use crate::mail::{build_email, Mail, Mailer, MailError, MailResult};
+
+/// Mailer writing every mail to a file of a spool directory instead of sending it
+pub struct SpoolMailer {
+    directory: PathBuf,
+}
+
+impl SpoolMailer {
+    /// Creates a mailer spooling to a directory, created when missing
+    ///
+    /// # Arguments
+    ///
+    /// * `directory` - Directory receiving one file per mail
+    ///
+    /// # Return
+    ///
+    /// Returns the spool mailer
+    pub fn new(directory: &str) -> MailResult<SpoolMailer> {
+        fs::create_dir_all(directory)
+            .map_err(|error| MailError::Spool(format!("{}: {}", directory, error)))?;
+        Ok(SpoolMailer { directory: PathBuf::from(directory) })
+    }
+}
+
+impl Mailer for SpoolMailer {
+    fn send(&self, mail: &Mail) -> MailResult<()> {
+        let email = build_email(mail)?;
+        FileTransport::new(&self.directory)
+            .send(email.into())
+            .map_err(|error| MailError::Spool(error.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use crate::mail::{Mail, Mailer};
+    use crate::mail::spool::SpoolMailer;
+
+    static SPOOL_DIRECTORY: &str = "target/test_mail_spool";
+
+    #[test]
+    fn test_send_success() {
+        let _ = fs::remove_dir_all(SPOOL_DIRECTORY);
+        let mailer: SpoolMailer = SpoolMailer::new(SPOOL_DIRECTORY).unwrap();
+        mailer.send(&Mail {
+            from: "conference@knoldus.in".to_string(),
+            to: "test@knoldus.in".to_string(),
+            subject: "Conference Mail".to_string(),
+            body: "You were absent".to_string(),
+        }).unwrap();
+        assert_eq!(fs::read_dir(SPOOL_DIRECTORY).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_new_failure() {
+        assert!(SpoolMailer::new("tests/resources/test.txt/spool").is_err());
+    }
+}
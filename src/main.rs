@@ -1,12 +1,34 @@
 
-use actix_web::{App, middleware, server};
+use std::process;
+use std::sync::Arc;
+
+use actix::{System, SystemRunner};
+use actix_web::{App, FormConfig, middleware, server};
 use actix_web::http::Method;
+use log::error;
+use tokio::runtime::Runtime;
 
-use conf_count::request_handlers::admin_handler::{handle_admin_timeout,
+use conf_count::authorization::RequireRole;
+use conf_count::capture::{CaptureDevice, create_capture_device};
+use conf_count::config::AppConfig;
+use conf_count::error::{handle_unknown_route, reject_form};
+use conf_count::executor::Executor;
+use conf_count::face_matching::{create_face_matcher, FaceMatcher};
+use conf_count::mail::{create_mailer, Mailer};
+use conf_count::photo_store::{create_photo_store, PhotoStore};
+use conf_count::request_handlers::admin_handler::{handle_account_unlock,
+                                                       handle_admin_timeout,
+                                                       handle_attendance_audit,
                                                        handle_conference_addition,
+                                                       handle_conference_completion,
                                                        handle_conference_deletion,
                                                        handle_conference_filtration,
                                                        handle_conference_updation,
+                                                       handle_face_indexing,
+                                                       handle_pending_reviews,
+                                                       handle_photo_download,
+                                                       handle_review_approval,
+                                                       handle_review_rejection,
                                                        handle_user_deletion,
                                                        handle_user_filtration, handle_user_updation,
                                                        load_admin_dashboard};
@@ -16,13 +38,17 @@ use conf_count::request_handlers::conference_handler::{fetch_conference_details,
 use conf_count::request_handlers::forgot_password_handler::handle_forgot_password;
 use conf_count::request_handlers::login_handler::{handle_login, load_login_form};
 use conf_count::request_handlers::logout_handler::handle_logout;
-use conf_count::request_handlers::reset_password_handler::handle_reset_password;
+use conf_count::request_handlers::reset_password_handler::{handle_reset_password,
+                                                           load_reset_password_form};
 use conf_count::request_handlers::signup_handler::{handle_user_signup, load_registration_form};
 use conf_count::request_handlers::user_details_handler::{handle_conference_registration,
                                                               handle_user_details,
                                                               handle_user_details_updation};
+use conf_count::request_handlers::verification_handler::handle_email_verification;
+use conf_count::session::Authentication;
+use conf_count::state::AppState;
+use conf_count::storage::{create_storage, Storage};
 
-static SOCKET: &str = "127.0.0.1:8088";
 static LOGIN: &str = "/login";
 static USER_DETAILS: &str = "/user-details";
 static CONFERENCE_DETAILS: &str = "/conference-details";
@@ -36,20 +62,77 @@ static ADMIN_USER: &str = "/admin-user";
 static ADMIN_CONFERENCE: &str = "/admin-conference";
 static FILTER_CONFERENCE: &str = "/filter-conference";
 static FILTER_USER: &str = "/filter-user";
+static ADMIN_UNLOCK: &str = "/admin-unlock";
+static ADMIN_INDEX_FACES: &str = "/admin-index-faces";
+static ADMIN_ATTENDANCE: &str = "/admin-attendance";
+static ADMIN_COMPLETE_CONFERENCE: &str = "/admin-complete-conference";
+static ADMIN_REVIEWS: &str = "/admin-reviews";
+static ADMIN_REVIEW_APPROVE: &str = "/admin-review-approve";
+static ADMIN_REVIEW_REJECT: &str = "/admin-review-reject";
+static ADMIN_PHOTO: &str = "/admin-photo";
 static SESSION_TIMEOUT: &str = "/session-timeout";
 static SIGNUP: &str = "/signup";
+static VERIFY_EMAIL: &str = "/verify-email";
+
+/// Answers the forms which could not be deserialized with the JSON error body
+///
+/// # Arguments
+///
+/// * `config` - Configuration of the state and form extractors of a route
+#[cfg_attr(tarpaulin, skip)]
+fn reject_invalid_form(config: &mut ((), FormConfig<AppState>)) {
+    config.1.error_handler(reject_form);
+}
 
 /// This is a web application for Conference Records
 #[cfg_attr(tarpaulin, skip)]
 fn main() {
     env_logger::init();
+    let config: AppConfig = AppConfig::load().unwrap_or_else(|config_error| {
+        error!("{}", config_error);
+        process::exit(1)
+    });
+    let socket: String = config.server.socket.clone();
+    let system: SystemRunner = System::new("conf_count");
+    let rusoto_runtime: Runtime = Runtime::new().unwrap_or_else(|runtime_error| {
+        error!("Unable to start the runtime of the AWS clients - {}", runtime_error);
+        process::exit(1)
+    });
+    let storage: Arc<dyn Storage> = create_storage(&config).unwrap_or_else(|storage_error| {
+        error!("{}", storage_error);
+        process::exit(1)
+    });
+    let photo_store: Arc<dyn PhotoStore> = create_photo_store(&config)
+        .unwrap_or_else(|store_error| {
+            error!("{}", store_error);
+            process::exit(1)
+        });
+    let face_matcher: Arc<dyn FaceMatcher> =
+        create_face_matcher(&config, rusoto_runtime.executor())
+            .unwrap_or_else(|match_error| {
+                error!("{}", match_error);
+                process::exit(1)
+            });
+    let capture_device: Arc<dyn CaptureDevice> = create_capture_device(&config.camera)
+        .unwrap_or_else(|capture_error| {
+            error!("{}", capture_error);
+            process::exit(1)
+        });
+    let mailer: Arc<dyn Mailer> = create_mailer(&config.mail).unwrap_or_else(|mail_error| {
+        error!("{}", mail_error);
+        process::exit(1)
+    });
+    let executor: Executor = Executor::start(config.server.blocking_threads);
+    let state: AppState = AppState::new(config, storage, face_matcher, capture_device, mailer,
+                                        photo_store, executor);
     server::new(
-        || App::new()
+        move || App::with_state(state.clone())
             .middleware(middleware::Logger::default())
+            .middleware(Authentication)
             .resource(
                 LOGIN, |resource| {
                     resource.method(Method::GET).f(load_login_form);
-                    resource.method(Method::POST).with(handle_login);
+                    resource.method(Method::POST).with_config(handle_login, reject_invalid_form);
                 })
             .resource(
                 USER_DETAILS, |resource| {
@@ -75,7 +158,9 @@ fn main() {
                 })
             .resource(
                 RESET_PASSWORD, |response| {
-                    response.method(Method::POST).with(handle_reset_password);
+                    response.method(Method::GET).f(load_reset_password_form);
+                    response.method(Method::POST).with_config(handle_reset_password,
+                                                              reject_invalid_form);
                 })
             .resource(
                 TRIGGER_CAMERA, |resource| {
@@ -83,22 +168,72 @@ fn main() {
                 })
 
             .resource(ADMIN, |resource| {
+                resource.middleware(RequireRole::admin());
                 resource.method(Method::GET).f(load_admin_dashboard);
             })
             .resource(ADMIN_USER, |resource| {
-                resource.method(Method::DELETE).with(handle_user_deletion);
+                resource.middleware(RequireRole::admin());
+                resource.method(Method::DELETE).with_config(handle_user_deletion,
+                                                            reject_invalid_form);
                 resource.method(Method::PUT).f(handle_user_updation);
             })
             .resource(ADMIN_CONFERENCE, |resource| {
-                resource.method(Method::DELETE).with(handle_conference_deletion);
-                resource.method(Method::PUT).with(handle_conference_updation);
-                resource.method(Method::POST).with(handle_conference_addition);
+                resource.middleware(RequireRole::admin());
+                resource.method(Method::DELETE).with_config(handle_conference_deletion,
+                                                            reject_invalid_form);
+                resource.method(Method::PUT).with_config(handle_conference_updation,
+                                                         reject_invalid_form);
+                resource.method(Method::POST).with_config(handle_conference_addition,
+                                                          reject_invalid_form);
             })
             .resource(FILTER_CONFERENCE, |resources| {
-                resources.method(Method::POST).with(handle_conference_filtration);
+                resources.middleware(RequireRole::admin());
+                resources.method(Method::POST).with_config(handle_conference_filtration,
+                                                           reject_invalid_form);
             })
             .resource(FILTER_USER, |resources| {
-                resources.method(Method::POST).with(handle_user_filtration);
+                resources.middleware(RequireRole::admin());
+                resources.method(Method::POST).with_config(handle_user_filtration,
+                                                           reject_invalid_form);
+            })
+            .resource(ADMIN_UNLOCK, |resource| {
+                resource.middleware(RequireRole::admin());
+                resource.method(Method::POST).with_config(handle_account_unlock,
+                                                          reject_invalid_form);
+            })
+            .resource(ADMIN_INDEX_FACES, |resource| {
+                resource.middleware(RequireRole::admin());
+                resource.method(Method::POST).with_config(handle_face_indexing,
+                                                          reject_invalid_form);
+            })
+            .resource(ADMIN_ATTENDANCE, |resource| {
+                resource.middleware(RequireRole::admin());
+                resource.method(Method::POST).with_config(handle_attendance_audit,
+                                                          reject_invalid_form);
+            })
+            .resource(ADMIN_COMPLETE_CONFERENCE, |resource| {
+                resource.middleware(RequireRole::admin());
+                resource.method(Method::POST).with_config(handle_conference_completion,
+                                                          reject_invalid_form);
+            })
+            .resource(ADMIN_REVIEWS, |resource| {
+                resource.middleware(RequireRole::admin());
+                resource.method(Method::POST).with_config(handle_pending_reviews,
+                                                          reject_invalid_form);
+            })
+            .resource(ADMIN_REVIEW_APPROVE, |resource| {
+                resource.middleware(RequireRole::admin());
+                resource.method(Method::POST).with_config(handle_review_approval,
+                                                          reject_invalid_form);
+            })
+            .resource(ADMIN_REVIEW_REJECT, |resource| {
+                resource.middleware(RequireRole::admin());
+                resource.method(Method::POST).with_config(handle_review_rejection,
+                                                          reject_invalid_form);
+            })
+            .resource(ADMIN_PHOTO, |resource| {
+                resource.middleware(RequireRole::admin());
+                resource.method(Method::GET).f(handle_photo_download);
             })
             .resource(SESSION_TIMEOUT, |resources| {
                 resources.method(Method::GET).f(handle_admin_timeout);
@@ -107,7 +242,14 @@ fn main() {
                 resource.method(Method::GET).f(load_registration_form);
                 resource.method(Method::POST).f(handle_user_signup);
             })
+            .resource(VERIFY_EMAIL, |resource| {
+                resource.method(Method::GET).f(handle_email_verification);
+            })
+            .default_resource(|resource| {
+                resource.f(handle_unknown_route);
+            })
     )
-        .bind(SOCKET).unwrap()
-        .run();
+        .bind(socket).unwrap()
+        .start();
+    system.run();
 }
@@ -0,0 +1,114 @@
+use crypto::digest::Digest;
+use crypto::scrypt::{scrypt_check, scrypt_simple, ScryptParams};
+use crypto::sha2::Sha256;
+use crypto::util::fixed_time_eq;
+use log::error;
+use uuid::Uuid;
+
+use crate::error::{ConfCountError, ConfCountResult};
+
+/// Prefix of the hashes in the current format, `$rscrypt$<version>$<cost>$<salt>$<hash>$`,
+/// which carries the random salt and the cost next to the hash
+static CURRENT_FORMAT_PREFIX: &str = "$rscrypt$";
+/// Cost of the hashes: 2^14 iterations over blocks of 8 with no parallelism, about 16 MiB
+static SCRYPT_LOG_N: u8 = 14;
+static SCRYPT_R: u32 = 8;
+static SCRYPT_P: u32 = 1;
+
+/// Hashes a password with a fresh random salt
+///
+/// # Argument
+///
+/// * `password` - Password entered by the user
+///
+/// # Return
+///
+/// Returns the hash to store in the current format
+pub fn hash_password(password: &str) -> ConfCountResult<String> {
+    scrypt_simple(password, &ScryptParams::new(SCRYPT_LOG_N, SCRYPT_R, SCRYPT_P))
+        .map_err(|error| ConfCountError::Internal(format!("Unable to hash a password: {}", error)))
+}
+
+/// Checks a password against a stored hash, in the current format or a legacy unsalted SHA-256
+///
+/// # Arguments
+///
+/// * `password` - Password entered by the user
+///
+/// * `stored_hash` - Hash stored for the user
+///
+/// # Return
+///
+/// Returns whether the password matches
+pub fn verify_password(password: &str, stored_hash: &str) -> bool {
+    if needs_rehash(stored_hash) {
+        let mut legacy_hash: Sha256 = Sha256::new();
+        legacy_hash.input_str(password);
+        fixed_time_eq(legacy_hash.result_str().as_bytes(), stored_hash.as_bytes())
+    } else {
+        scrypt_check(password, stored_hash).unwrap_or_else(|error| {
+            error!("A stored password hash is malformed: {}", error);
+            false
+        })
+    }
+}
+
+/// Tells whether a stored hash predates the current format
+pub fn needs_rehash(stored_hash: &str) -> bool {
+    !stored_hash.starts_with(CURRENT_FORMAT_PREFIX)
+}
+
+/// Generates a random token of 244 bits for a link mailed to a user
+pub fn generate_token() -> String {
+    format!("{}{}", Uuid::new_v4().to_simple(), Uuid::new_v4().to_simple())
+}
+
+/// Hashes a token mailed to a user, so that the stored hash can not be used as the token
+pub fn hash_token(token: &str) -> String {
+    let mut token_hash: Sha256 = Sha256::new();
+    token_hash.input_str(token);
+    token_hash.result_str()
+}
+
+#[cfg(test)]
+mod test {
+    use crypto::digest::Digest;
+    use crypto::sha2::Sha256;
+
+    use crate::password::{generate_token, hash_password, hash_token, needs_rehash,
+                          verify_password};
+
+    #[test]
+    fn test_hash_password_success() {
+        let first_hash: String = hash_password("test123").unwrap();
+        let second_hash: String = hash_password("test123").unwrap();
+        assert_ne!(first_hash, second_hash);
+        assert!(!needs_rehash(first_hash.as_str()));
+        assert!(verify_password("test123", first_hash.as_str()));
+        assert!(!verify_password("test124", first_hash.as_str()));
+    }
+
+    #[test]
+    fn test_verify_legacy_password() {
+        let mut legacy_hash: Sha256 = Sha256::new();
+        legacy_hash.input_str("test123");
+        let legacy_hash: String = legacy_hash.result_str();
+        assert!(needs_rehash(legacy_hash.as_str()));
+        assert!(verify_password("test123", legacy_hash.as_str()));
+        assert!(!verify_password("test124", legacy_hash.as_str()));
+    }
+
+    #[test]
+    fn test_verify_malformed_hash() {
+        assert!(!verify_password("test123", "$rscrypt$0$broken$"));
+    }
+
+    #[test]
+    fn test_generate_token_success() {
+        let token: String = generate_token();
+        assert_eq!(token.len(), 64);
+        assert_ne!(token, generate_token());
+        assert_ne!(hash_token(token.as_str()), token);
+        assert_eq!(hash_token(token.as_str()), hash_token(token.as_str()));
+    }
+}
@@ -0,0 +1,367 @@
+use std::io::Cursor;
+
+use image::{DynamicImage, FilterType, GenericImageView, ImageDecoder, ImageFormat,
+            ImageOutputFormat, ImageResult};
+use image::jpeg::JPEGDecoder;
+use image::png::PNGDecoder;
+use log::info;
+
+use crate::config::PhotoConfig;
+use crate::error::{ConfCountError, ConfCountResult};
+use crate::face_matching::FaceBox;
+use crate::signup_form::{INVALID_IMAGE_FORMAT, PHOTO_TOO_LARGE, UPLOAD_IMAGE};
+
+pub static STORED_CONTENT_TYPE: &str = "image/jpeg";
+static UNREADABLE_PHOTO: &str = "The photo could not be read, please upload another one";
+static PHOTO_TOO_SMALL: &str = "The photo is too small, please upload a larger one";
+static PHOTO_TOO_MANY_PIXELS: &str = "The photo has too many pixels, please upload a smaller one";
+static PHOTO_TOO_DETAILED: &str = "The photo can not be stored, please upload a simpler one";
+static JPEG_SIGNATURE: [u8; 3] = [0xFF, 0xD8, 0xFF];
+static PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+static EXIF_HEADER: &[u8] = b"Exif\0\0";
+const EXIF_SEGMENT: u8 = 0xE1;
+const START_OF_SCAN: u8 = 0xDA;
+const ORIENTATION_TAG: u16 = 0x0112;
+/// Lowest quality a photo is re-encoded at to fit the stored size
+const MIN_JPEG_QUALITY: u8 = 50;
+const JPEG_QUALITY_STEP: u8 = 10;
+/// Share of the size of a face added on each of its sides when cropping it for a review
+const FACE_CROP_MARGIN: f32 = 0.25;
+const FACE_CROP_QUALITY: u8 = 90;
+
+/// Photo fit to be stored: a JPEG, upright, without metadata and within the configured limits
+#[derive(Debug)]
+pub struct NormalizedPhoto {
+    bytes: Vec<u8>,
+    width: u32,
+    height: u32,
+}
+
+impl NormalizedPhoto {
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+}
+
+fn invalid_photo(message: &str) -> ConfCountError {
+    ConfCountError::invalid_field(UPLOAD_IMAGE, message)
+}
+
+/// Recognises the accepted formats by their leading bytes
+fn sniff_format(upload: &[u8]) -> Option<ImageFormat> {
+    if upload.starts_with(&JPEG_SIGNATURE) {
+        Some(ImageFormat::JPEG)
+    } else if upload.starts_with(&PNG_SIGNATURE) {
+        Some(ImageFormat::PNG)
+    } else {
+        None
+    }
+}
+
+/// Dimensions declared by the header of the image, read without decoding its pixels
+fn header_dimensions(upload: &[u8], format: ImageFormat) -> ImageResult<(u64, u64)> {
+    if format == ImageFormat::PNG {
+        Ok(PNGDecoder::new(Cursor::new(upload))?.dimensions())
+    } else {
+        Ok(JPEGDecoder::new(Cursor::new(upload))?.dimensions())
+    }
+}
+
+/// Reads a big or little endian integer of a TIFF structure
+fn tiff_integer(tiff: &[u8], offset: usize, size: usize, big_endian: bool) -> Option<u32> {
+    let bytes: &[u8] = tiff.get(offset..offset.checked_add(size)?)?;
+    let fold = |value: u32, byte: &u8| value << 8 | u32::from(*byte);
+    if big_endian {
+        Some(bytes.iter().fold(0, fold))
+    } else {
+        Some(bytes.iter().rev().fold(0, fold))
+    }
+}
+
+/// Orientation tag of the first image directory of EXIF metadata
+fn tiff_orientation(tiff: &[u8]) -> Option<u16> {
+    let big_endian: bool = match tiff.get(0..2)? {
+        [b'M', b'M'] => true,
+        [b'I', b'I'] => false,
+        _ => return None,
+    };
+    let directory: usize = tiff_integer(tiff, 4, 4, big_endian)? as usize;
+    let entries: usize = tiff_integer(tiff, directory, 2, big_endian)? as usize;
+    (0..entries)
+        .map(|entry| directory + 2 + entry * 12)
+        .find(|entry| tiff_integer(tiff, *entry, 2, big_endian) == Some(u32::from(ORIENTATION_TAG)))
+        .and_then(|entry| tiff_integer(tiff, entry + 8, 2, big_endian))
+        .map(|orientation| orientation as u16)
+}
+
+/// Orientation of a JPEG from its EXIF segment, if any
+///
+/// # Arguments
+///
+/// * `jpeg` - Bytes of the JPEG
+///
+/// # Return
+///
+/// Returns the EXIF orientation, from 1 (upright) to 8
+fn exif_orientation(jpeg: &[u8]) -> Option<u16> {
+    // Past the two bytes of the start of image marker
+    let mut position: usize = 2;
+    while jpeg.get(position) == Some(&0xFF) {
+        let marker: u8 = *jpeg.get(position + 1)?;
+        if marker == START_OF_SCAN {
+            return None;
+        }
+        let length: usize = tiff_integer(jpeg, position + 2, 2, true)? as usize;
+        let segment: &[u8] = jpeg.get(position + 4..position + 2 + length.max(2))?;
+        if marker == EXIF_SEGMENT && segment.starts_with(EXIF_HEADER) {
+            return tiff_orientation(&segment[EXIF_HEADER.len()..]);
+        }
+        position += 2 + length;
+    }
+    None
+}
+
+/// Turns an image upright according to its EXIF orientation
+fn apply_orientation(image: DynamicImage, orientation: u16) -> DynamicImage {
+    match orientation {
+        2 => image.fliph(),
+        3 => image.rotate180(),
+        4 => image.flipv(),
+        5 => image.rotate90().fliph(),
+        6 => image.rotate90(),
+        7 => image.rotate270().fliph(),
+        8 => image.rotate270(),
+        _ => image,
+    }
+}
+
+/// Decodes an image and turns it upright according to its EXIF orientation, as a camera may
+/// store its captures sideways
+///
+/// # Arguments
+///
+/// * `bytes` - Bytes of a JPEG or PNG image
+///
+/// # Return
+///
+/// Returns the upright image, or the failure of decoding it
+pub fn decode_upright(bytes: &[u8]) -> ImageResult<DynamicImage> {
+    let decoded: DynamicImage = image::load_from_memory(bytes)?;
+    Ok(match sniff_format(bytes) {
+        Some(ImageFormat::JPEG) => apply_orientation(decoded, exif_orientation(bytes).unwrap_or(1)),
+        _ => decoded,
+    })
+}
+
+/// Crops a face out of a capture, with a margin around it, for an admin to compare with the
+/// reference photo of the user
+///
+/// # Arguments
+///
+/// * `capture` - Bytes of the capture
+///
+/// * `face_box` - Position of the face in the upright capture, in ratios of its size
+///
+/// # Return
+///
+/// Returns the face as a JPEG, or the failure of decoding the capture or of encoding the face
+pub fn crop_face(capture: &[u8], face_box: &FaceBox) -> ImageResult<Vec<u8>> {
+    let mut upright: DynamicImage = decode_upright(capture)?;
+    let (width, height): (f32, f32) = (upright.width() as f32, upright.height() as f32);
+    let left: f32 = ((face_box.left - face_box.width * FACE_CROP_MARGIN) * width).max(0.0);
+    let top: f32 = ((face_box.top - face_box.height * FACE_CROP_MARGIN) * height).max(0.0);
+    let right: f32 =
+        ((face_box.left + face_box.width * (1.0 + FACE_CROP_MARGIN)) * width).min(width);
+    let bottom: f32 =
+        ((face_box.top + face_box.height * (1.0 + FACE_CROP_MARGIN)) * height).min(height);
+    let face: DynamicImage = upright.crop(left as u32, top as u32, (right - left).max(1.0) as u32,
+                                          (bottom - top).max(1.0) as u32);
+    let mut bytes: Vec<u8> = Vec::new();
+    DynamicImage::ImageRgb8(face.to_rgb())
+        .write_to(&mut bytes, ImageOutputFormat::JPEG(FACE_CROP_QUALITY))?;
+    Ok(bytes)
+}
+
+/// Encodes the image as a JPEG, lowering its quality until it fits the stored size
+fn encode_bounded(image: &DynamicImage, limits: &PhotoConfig) -> ConfCountResult<NormalizedPhoto> {
+    let rgb: DynamicImage = DynamicImage::ImageRgb8(image.to_rgb());
+    let mut quality: u8 = limits.jpeg_quality;
+    loop {
+        let mut bytes: Vec<u8> = Vec::new();
+        rgb.write_to(&mut bytes, ImageOutputFormat::JPEG(quality)).map_err(|encode_error| {
+            ConfCountError::Internal(format!("Unable to encode a photo: {}", encode_error))
+        })?;
+        if bytes.len() as u64 <= limits.max_stored_bytes {
+            return Ok(NormalizedPhoto { bytes, width: rgb.width(), height: rgb.height() });
+        }
+        if quality <= MIN_JPEG_QUALITY {
+            info!("Rejected a photo still taking {} bytes at quality {}", bytes.len(), quality);
+            return Err(invalid_photo(PHOTO_TOO_DETAILED));
+        }
+        quality = quality.saturating_sub(JPEG_QUALITY_STEP).max(MIN_JPEG_QUALITY);
+    }
+}
+
+/// Validates an uploaded photo by its content and normalizes it for the storage and the face
+/// matching
+///
+/// # Arguments
+///
+/// * `limits` - Limits of the accepted and of the stored photos
+///
+/// * `upload` - Bytes of the photo as uploaded
+///
+/// # Return
+///
+/// Returns the normalized photo, or the reason the photo was rejected
+pub fn normalize_photo(limits: &PhotoConfig, upload: &[u8]) -> ConfCountResult<NormalizedPhoto> {
+    if upload.len() as u64 > limits.max_upload_bytes {
+        return Err(invalid_photo(PHOTO_TOO_LARGE));
+    }
+    let format: ImageFormat =
+        sniff_format(upload).ok_or_else(|| invalid_photo(INVALID_IMAGE_FORMAT))?;
+    let (width, height): (u64, u64) = header_dimensions(upload, format).map_err(|read_error| {
+        info!("Rejected a photo with an unreadable header - {}", read_error);
+        invalid_photo(UNREADABLE_PHOTO)
+    })?;
+    if width.min(height) < u64::from(limits.min_dimension) {
+        return Err(invalid_photo(PHOTO_TOO_SMALL));
+    }
+    if width.saturating_mul(height) > limits.max_pixels {
+        return Err(invalid_photo(PHOTO_TOO_MANY_PIXELS));
+    }
+    let decoded: DynamicImage =
+        image::load_from_memory_with_format(upload, format).map_err(|decode_error| {
+            info!("Rejected a photo which could not be decoded - {}", decode_error);
+            invalid_photo(UNREADABLE_PHOTO)
+        })?;
+    let upright: DynamicImage = if format == ImageFormat::JPEG {
+        apply_orientation(decoded, exif_orientation(upload).unwrap_or(1))
+    } else {
+        decoded
+    };
+    let bounded: DynamicImage = if upright.width().max(upright.height()) > limits.max_dimension {
+        upright.resize(limits.max_dimension, limits.max_dimension, FilterType::Triangle)
+    } else {
+        upright
+    };
+    encode_bounded(&bounded, limits)
+}
+
+#[cfg(test)]
+pub mod test {
+    use std::fs;
+
+    use image::{DynamicImage, GenericImageView, ImageOutputFormat, Rgb, RgbImage};
+
+    use crate::config::PhotoConfig;
+    use crate::error::ConfCountError;
+    use crate::face_matching::FaceBox;
+    use crate::photo_processing::{crop_face, decode_upright, exif_orientation,
+                                  INVALID_IMAGE_FORMAT, normalize_photo,
+                                  NormalizedPhoto, PHOTO_TOO_LARGE, PHOTO_TOO_SMALL,
+                                  UNREADABLE_PHOTO, UPLOAD_IMAGE};
+
+    static TEST_IMAGE_FILE: &str = "tests/resources/test.jpg";
+
+    /// Photo of the given size, red on its left half and blue on its right one
+    pub fn test_photo(width: u32, height: u32, format: ImageOutputFormat) -> Vec<u8> {
+        let image: RgbImage = RgbImage::from_fn(width, height, |x, _| {
+            if x < width / 2 { Rgb([255, 0, 0]) } else { Rgb([0, 0, 255]) }
+        });
+        let mut bytes: Vec<u8> = Vec::new();
+        DynamicImage::ImageRgb8(image).write_to(&mut bytes, format).unwrap();
+        bytes
+    }
+
+    /// Inserts an EXIF segment holding only the orientation right after the start of the JPEG
+    fn with_orientation(jpeg: &[u8], orientation: u8) -> Vec<u8> {
+        let mut tagged: Vec<u8> = jpeg[..2].to_vec();
+        tagged.extend_from_slice(&[0xFF, 0xE1, 0x00, 0x22]);
+        tagged.extend_from_slice(b"Exif\0\0MM\0\x2a\0\0\0\x08\0\x01");
+        tagged.extend_from_slice(&[0x01, 0x12, 0, 3, 0, 0, 0, 1, 0, orientation, 0, 0]);
+        tagged.extend_from_slice(&[0, 0, 0, 0]);
+        tagged.extend_from_slice(&jpeg[2..]);
+        tagged
+    }
+
+    fn rejection(upload: &[u8]) -> ConfCountError {
+        normalize_photo(&PhotoConfig::default(), upload).unwrap_err()
+    }
+
+    #[test]
+    fn test_normalize_photo_jpeg_success() {
+        let upload: Vec<u8> = fs::read(TEST_IMAGE_FILE).unwrap();
+        assert_eq!(exif_orientation(&upload), Some(1));
+        let photo: NormalizedPhoto = normalize_photo(&PhotoConfig::default(), &upload).unwrap();
+        assert_eq!(photo.dimensions(), (460, 350));
+        assert!(photo.bytes().starts_with(&[0xFF, 0xD8, 0xFF]));
+        assert_eq!(exif_orientation(photo.bytes()), None);
+    }
+
+    #[test]
+    fn test_normalize_photo_png_converted() {
+        let photo: NormalizedPhoto = normalize_photo(
+            &PhotoConfig::default(), &test_photo(200, 100, ImageOutputFormat::PNG)).unwrap();
+        assert_eq!(photo.dimensions(), (200, 100));
+        assert!(photo.bytes().starts_with(&[0xFF, 0xD8, 0xFF]));
+    }
+
+    #[test]
+    fn test_normalize_photo_orientation_applied() {
+        let jpeg: Vec<u8> = with_orientation(&test_photo(200, 100, ImageOutputFormat::JPEG(90)), 6);
+        assert_eq!(exif_orientation(&jpeg), Some(6));
+        let photo: NormalizedPhoto = normalize_photo(&PhotoConfig::default(), &jpeg).unwrap();
+        assert_eq!(photo.dimensions(), (100, 200));
+        let upright: DynamicImage = image::load_from_memory(photo.bytes()).unwrap();
+        assert!(upright.get_pixel(50, 10)[0] > 200);
+        assert!(upright.get_pixel(50, 190)[2] > 200);
+    }
+
+    #[test]
+    fn test_decode_upright_success() {
+        let jpeg: Vec<u8> = with_orientation(&test_photo(200, 100, ImageOutputFormat::JPEG(90)), 8);
+        assert_eq!(decode_upright(&jpeg).unwrap().dimensions(), (100, 200));
+        assert_eq!(decode_upright(&test_photo(200, 100, ImageOutputFormat::PNG)).unwrap()
+                       .dimensions(), (200, 100));
+        assert!(decode_upright(b"not an image").is_err());
+    }
+
+    #[test]
+    fn test_crop_face_success() {
+        let png: Vec<u8> = test_photo(200, 100, ImageOutputFormat::PNG);
+        let face: FaceBox = FaceBox { left: 0.25, top: 0.25, width: 0.5, height: 0.5 };
+        let cropped: Vec<u8> = crop_face(&png, &face).unwrap();
+        assert_eq!(image::load_from_memory(&cropped).unwrap().dimensions(), (150, 75));
+        let edge: FaceBox = FaceBox { left: 0.75, top: -0.25, width: 0.5, height: 0.5 };
+        assert_eq!(image::load_from_memory(&crop_face(&png, &edge).unwrap()).unwrap()
+                       .dimensions(), (75, 37));
+        assert!(crop_face(b"not an image", &face).is_err());
+    }
+
+    #[test]
+    fn test_normalize_photo_scaled_down() {
+        let limits: PhotoConfig = PhotoConfig { max_dimension: 150, ..PhotoConfig::default() };
+        let photo: NormalizedPhoto =
+            normalize_photo(&limits, &test_photo(300, 200, ImageOutputFormat::PNG)).unwrap();
+        assert_eq!(photo.dimensions(), (150, 100));
+    }
+
+    #[test]
+    fn test_normalize_photo_rejected() {
+        let invalid = |message: &str| ConfCountError::invalid_field(UPLOAD_IMAGE, message);
+        assert_eq!(rejection(b"GIF89a not a photo"), invalid(INVALID_IMAGE_FORMAT));
+        assert_eq!(rejection(&fs::read("tests/resources/test.txt").unwrap()),
+                   invalid(INVALID_IMAGE_FORMAT));
+        let jpeg: Vec<u8> = test_photo(200, 100, ImageOutputFormat::JPEG(90));
+        assert_eq!(rejection(&jpeg[..20]), invalid(UNREADABLE_PHOTO));
+        assert_eq!(rejection(&test_photo(60, 200, ImageOutputFormat::PNG)),
+                   invalid(PHOTO_TOO_SMALL));
+        let limits: PhotoConfig = PhotoConfig { max_upload_bytes: 100, ..PhotoConfig::default() };
+        assert_eq!(normalize_photo(&limits, &jpeg).unwrap_err(), invalid(PHOTO_TOO_LARGE));
+    }
+}
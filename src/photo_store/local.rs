@@ -0,0 +1,108 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+use crate::photo_store::{PhotoStore, PhotoStoreError, PhotoStoreResult};
+
+/// Photo store keeping one file per user in a local directory
+pub struct LocalPhotoStore {
+    directory: PathBuf,
+}
+
+impl From<std::io::Error> for PhotoStoreError {
+    fn from(error: std::io::Error) -> Self {
+        PhotoStoreError(error.to_string())
+    }
+}
+
+impl LocalPhotoStore {
+    /// Creates a store in a directory, creating the directory when missing
+    ///
+    /// # Arguments
+    ///
+    /// * `directory` - Directory holding the photos
+    ///
+    /// # Return
+    ///
+    /// Returns the local photo store
+    pub fn new(directory: &str) -> PhotoStoreResult<LocalPhotoStore> {
+        fs::create_dir_all(directory)?;
+        Ok(LocalPhotoStore { directory: PathBuf::from(directory) })
+    }
+
+    /// Path of the file holding a photo, refusing keys that would leave the directory
+    fn path(&self, key: &str) -> PhotoStoreResult<PathBuf> {
+        if key.is_empty() || key.starts_with('.') || key.contains(|c| c == '/' || c == '\\') {
+            Err(PhotoStoreError(format!("Invalid photo key {:?}", key)))
+        } else {
+            Ok(self.directory.join(key))
+        }
+    }
+}
+
+impl PhotoStore for LocalPhotoStore {
+    fn put(&self, key: &str, photo: &[u8], _content_type: &str) -> PhotoStoreResult<()> {
+        Ok(fs::write(self.path(key)?, photo)?)
+    }
+
+    fn get(&self, key: &str) -> PhotoStoreResult<Option<Vec<u8>>> {
+        match fs::read(self.path(key)?) {
+            Ok(photo) => Ok(Some(photo)),
+            Err(ref error) if error.kind() == ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    fn delete(&self, key: &str) -> PhotoStoreResult<()> {
+        match fs::remove_file(self.path(key)?) {
+            Err(ref error) if error.kind() != ErrorKind::NotFound => Err(
+                PhotoStoreError(error.to_string())),
+            _ => Ok(()),
+        }
+    }
+
+    fn list(&self) -> PhotoStoreResult<Vec<String>> {
+        let mut keys: Vec<String> = Vec::new();
+        for entry in fs::read_dir(&self.directory)? {
+            let entry = entry?;
+            if entry.file_type()?.is_file() {
+                keys.extend(entry.file_name().to_str().map(str::to_string));
+            }
+        }
+        keys.sort();
+        Ok(keys)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use crate::photo_store::local::LocalPhotoStore;
+    use crate::photo_store::PhotoStore;
+
+    static TEST_IMAGE_FILE: &str = "tests/resources/test.jpg";
+
+    #[test]
+    fn test_local_photo_store_success() {
+        let directory: &str = "target/test_local_photo_store";
+        let _ = fs::remove_dir_all(directory);
+        let store: LocalPhotoStore = LocalPhotoStore::new(directory).unwrap();
+        let photo: Vec<u8> = fs::read(TEST_IMAGE_FILE).unwrap();
+        store.put("1122334455", &photo, "jpg").unwrap();
+        store.put("1111111111", &photo, "jpg").unwrap();
+        assert_eq!(store.get("1122334455").unwrap(), Some(photo));
+        assert_eq!(store.list().unwrap(), vec!["1111111111", "1122334455"]);
+        store.delete("1122334455").unwrap();
+        store.delete("1122334455").unwrap();
+        assert_eq!(store.get("1122334455").unwrap(), None);
+        assert_eq!(store.list().unwrap(), vec!["1111111111"]);
+    }
+
+    #[test]
+    fn test_local_photo_store_invalid_key() {
+        let store: LocalPhotoStore = LocalPhotoStore::new("target/test_photos").unwrap();
+        assert!(store.put("../escape", b"photo", "jpg").is_err());
+        assert!(store.get("").is_err());
+    }
+}
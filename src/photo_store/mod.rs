@@ -0,0 +1,56 @@
+use std::fmt;
+use std::sync::Arc;
+
+use crate::config::{AppConfig, PhotoBackend};
+use crate::connection::create_bucket_connection;
+use crate::photo_store::local::LocalPhotoStore;
+use crate::photo_store::s3::S3PhotoStore;
+
+pub mod local;
+
+pub mod s3;
+
+/// Failure of the photo store
+#[derive(Debug, PartialEq)]
+pub struct PhotoStoreError(pub String);
+
+impl fmt::Display for PhotoStoreError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "Photo store error: {}", self.0)
+    }
+}
+
+impl std::error::Error for PhotoStoreError {}
+
+pub type PhotoStoreResult<T> = Result<T, PhotoStoreError>;
+
+/// Keeps the reference photos of the users, keyed by user id
+pub trait PhotoStore: Send + Sync {
+    /// Stores a photo, replacing the one already stored under the key
+    fn put(&self, key: &str, photo: &[u8], content_type: &str) -> PhotoStoreResult<()>;
+
+    /// Returns the photo stored under the key, `None` when there is none
+    fn get(&self, key: &str) -> PhotoStoreResult<Option<Vec<u8>>>;
+
+    /// Removes the photo stored under the key, if any
+    fn delete(&self, key: &str) -> PhotoStoreResult<()>;
+
+    /// Returns the keys of every stored photo
+    fn list(&self) -> PhotoStoreResult<Vec<String>>;
+}
+
+/// Creates the photo store selected in the configuration
+///
+/// # Arguments
+///
+/// * `config` - Configuration of the application
+///
+/// # Return
+///
+/// Returns the photo store shared by all the handlers, or the error of setting it up
+pub fn create_photo_store(config: &AppConfig) -> PhotoStoreResult<Arc<dyn PhotoStore>> {
+    Ok(match config.photos.backend {
+        PhotoBackend::S3 => Arc::new(S3PhotoStore::new(create_bucket_connection(&config.aws))),
+        PhotoBackend::Local => Arc::new(LocalPhotoStore::new(&config.photos.directory)?),
+    })
+}
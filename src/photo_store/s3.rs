@@ -0,0 +1,109 @@
+use std::fmt::Display;
+use std::thread::spawn;
+
+use s3::bucket::Bucket;
+
+use crate::photo_store::{PhotoStore, PhotoStoreError, PhotoStoreResult};
+
+const OK: u32 = 200;
+const NO_CONTENT: u32 = 204;
+const NOT_FOUND: u32 = 404;
+
+/// Photo store backed by an S3 bucket, on Amazon S3 or any compatible service such as MinIO
+pub struct S3PhotoStore {
+    bucket: Bucket,
+}
+
+impl S3PhotoStore {
+    /// Creates a store for a bucket
+    ///
+    /// # Arguments
+    ///
+    /// * `bucket` - Bucket holding the photos, keyed by user id
+    ///
+    /// # Return
+    ///
+    /// Returns the S3 photo store
+    pub fn new(bucket: Bucket) -> S3PhotoStore {
+        S3PhotoStore { bucket }
+    }
+
+    /// Runs a request on its own thread, as the S3 client drives its own event loop which must
+    /// not be nested in the one of the server
+    fn request<T, E, F>(&self, operation: F) -> PhotoStoreResult<T>
+        where T: Send + 'static, E: Display, F: FnOnce(Bucket) -> Result<T, E> + Send + 'static {
+        let bucket: Bucket = self.bucket.clone();
+        spawn(move || operation(bucket).map_err(|error| PhotoStoreError(error.to_string())))
+            .join()
+            .unwrap_or_else(|_| Err(PhotoStoreError("S3 request panicked".to_string())))
+    }
+}
+
+/// Checks the status code answered by S3
+///
+/// # Arguments
+///
+/// * `status` - Status code of the response
+///
+/// * `expected` - Status codes of a successful request
+///
+/// # Return
+///
+/// Returns an error naming the status when it is not expected
+fn check_status(status: u32, expected: &[u32]) -> PhotoStoreResult<()> {
+    if expected.contains(&status) {
+        Ok(())
+    } else {
+        Err(PhotoStoreError(format!("S3 answered with status {}", status)))
+    }
+}
+
+impl PhotoStore for S3PhotoStore {
+    fn put(&self, key: &str, photo: &[u8], content_type: &str) -> PhotoStoreResult<()> {
+        let (key, photo): (String, Vec<u8>) = (key.to_string(), photo.to_vec());
+        let content_type: String = content_type.to_string();
+        let (_, status) = self.request(move |bucket| {
+            bucket.put(key.as_str(), photo.as_slice(), content_type.as_str())
+        })?;
+        check_status(status, &[OK])
+    }
+
+    fn get(&self, key: &str) -> PhotoStoreResult<Option<Vec<u8>>> {
+        let key: String = key.to_string();
+        match self.request(move |bucket| bucket.get(key.as_str()))? {
+            (_, NOT_FOUND) => Ok(None),
+            (photo, status) => check_status(status, &[OK]).map(|_| Some(photo)),
+        }
+    }
+
+    fn delete(&self, key: &str) -> PhotoStoreResult<()> {
+        let key: String = key.to_string();
+        let (_, status) = self.request(move |bucket| bucket.delete(key.as_str()))?;
+        check_status(status, &[OK, NO_CONTENT, NOT_FOUND])
+    }
+
+    fn list(&self) -> PhotoStoreResult<Vec<String>> {
+        let mut keys: Vec<String> = Vec::new();
+        for (page, status) in self.request(|bucket| bucket.list("", None))? {
+            check_status(status, &[OK])?;
+            keys.extend(page.contents.into_iter().map(|object| object.key));
+        }
+        Ok(keys)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::photo_store::s3::{check_status, NO_CONTENT, NOT_FOUND, OK};
+
+    #[test]
+    fn test_check_status_success() {
+        assert!(check_status(OK, &[OK]).is_ok());
+        assert!(check_status(NOT_FOUND, &[OK, NO_CONTENT, NOT_FOUND]).is_ok());
+    }
+
+    #[test]
+    fn test_check_status_failure() {
+        assert!(check_status(403, &[OK]).is_err());
+    }
+}
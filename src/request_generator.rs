@@ -1,48 +1,120 @@
-use std::{env, fs};
+use rusoto_rekognition::{DeleteFacesRequest, DetectFacesRequest, Image, IndexFacesRequest,
+                         ListFacesRequest, SearchFacesByImageRequest};
 
-use rusoto_rekognition::{CompareFacesRequest, Image, S3Object};
+/// Faces listed by page when looking up the faces of a user
+const LISTED_FACES: i64 = 1000;
 
-use crate::constants::SIMILARITY_THRESHOLD;
+/// Creates a request for detecting the faces of a photo in Amazon Rekognition API, reporting
+/// the default attributes which include the quality of every face
+///
+/// # Arguments
+///
+/// * `photo` - Photo uploaded by the user
+///
+/// # Return
+///
+/// Returns the instance of the DetectFacesRequest
+pub fn create_detect_faces_request(photo: Vec<u8>) -> DetectFacesRequest {
+    DetectFacesRequest {
+        attributes: None,
+        image: Image {
+            bytes: Some(photo),
+            s3_object: None,
+        },
+    }
+}
 
-static BUCKET_NAME: &str = "Clicked_Image_Bucket";
+/// Creates a request for indexing the largest face of a reference photo into a collection,
+/// under the id of its user
+///
+/// # Arguments
+///
+/// * `collection_id` - Id of the collection of the reference faces
+///
+/// * `user_id` - Id of the user the face belongs to
+///
+/// * `photo` - Normalized reference photo of the user
+///
+/// # Return
+///
+/// Returns the instance of the IndexFacesRequest
+pub fn create_index_faces_request(collection_id: &str, user_id: &str, photo: Vec<u8>)
+                                  -> IndexFacesRequest {
+    IndexFacesRequest {
+        collection_id: collection_id.to_string(),
+        external_image_id: Some(user_id.to_string()),
+        image: Image {
+            bytes: Some(photo),
+            s3_object: None,
+        },
+        max_faces: Some(1),
+        ..IndexFacesRequest::default()
+    }
+}
 
-/// Creates a request for comparing two images in Amazon Rekognition API
+/// Creates a request for searching a collection for the face most similar to the face of an
+/// image
 ///
 /// # Arguments
 ///
-/// * `user_id` - Id of the user
+/// * `collection_id` - Id of the collection of the reference faces
+///
+/// * `face` - Image cropped around a single face of a capture
 ///
-/// * `target_image_path` - Path of the target image
+/// * `similarity_threshold` - Minimum similarity of the reported face
 ///
 /// # Return
 ///
-/// Returns the instance of the CompareFacesRequest
-pub fn create_compare_faces_request(
-    user_id: &str,
-    target_image_path: &str,
-) -> CompareFacesRequest {
-    let source_image = Image {
-        bytes: None,
-        s3_object: Some(
-            S3Object {
-                bucket: Some(env::var(BUCKET_NAME)
-                    .expect("Bucket Name not exported").to_string()),
-                name: Some(user_id.to_string()),
-                version: None,
-            }
-        ),
-    };
+/// Returns the instance of the SearchFacesByImageRequest
+pub fn create_search_faces_request(collection_id: &str, face: Vec<u8>, similarity_threshold: f32)
+                                   -> SearchFacesByImageRequest {
+    SearchFacesByImageRequest {
+        collection_id: collection_id.to_string(),
+        face_match_threshold: Some(similarity_threshold),
+        image: Image {
+            bytes: Some(face),
+            s3_object: None,
+        },
+        max_faces: Some(1),
+    }
+}
 
-    let target_image = Image {
-        bytes: Some(fs::read(target_image_path)
-            .expect("Target Image Path not exported")),
-        s3_object: None,
-    };
+/// Creates a request for a page of the faces of a collection
+///
+/// # Arguments
+///
+/// * `collection_id` - Id of the collection of the reference faces
+///
+/// * `next_token` - Token of the page, none for the first one
+///
+/// # Return
+///
+/// Returns the instance of the ListFacesRequest
+pub fn create_list_faces_request(collection_id: &str, next_token: Option<String>)
+                                 -> ListFacesRequest {
+    ListFacesRequest {
+        collection_id: collection_id.to_string(),
+        max_results: Some(LISTED_FACES),
+        next_token,
+    }
+}
 
-    CompareFacesRequest {
-        similarity_threshold: Some(SIMILARITY_THRESHOLD),
-        source_image,
-        target_image,
+/// Creates a request for removing faces from a collection
+///
+/// # Arguments
+///
+/// * `collection_id` - Id of the collection of the reference faces
+///
+/// * `face_ids` - Ids given by Rekognition to the faces
+///
+/// # Return
+///
+/// Returns the instance of the DeleteFacesRequest
+pub fn create_delete_faces_request(collection_id: &str, face_ids: Vec<String>)
+                                   -> DeleteFacesRequest {
+    DeleteFacesRequest {
+        collection_id: collection_id.to_string(),
+        face_ids,
     }
 }
 
@@ -50,68 +122,39 @@ pub fn create_compare_faces_request(
 mod test {
     use std::fs;
 
-    use rusoto_rekognition::{CompareFacesRequest, Image, S3Object};
-
-    use crate::request_generator::create_compare_faces_request;
+    use rusoto_rekognition::{DetectFacesRequest, IndexFacesRequest, SearchFacesByImageRequest};
 
-    use super::SIMILARITY_THRESHOLD;
+    use crate::request_generator::{create_detect_faces_request, create_index_faces_request,
+                                   create_search_faces_request};
 
-    static BUCKET_NAME: &str = "labelsfacedetect";
-    static KEY: &str = "1122334455";
+    const SIMILARITY_THRESHOLD: f32 = 75.0;
+    static SOURCE_IMAGE_PATH: &str = "tests/resources/test2.jpg";
     static TARGET_IMAGE_PATH: &str = "tests/resources/test.jpg";
-    static DIFFERENT_SOURCE_IMAGE_PATH: &str = "tests/resources/test1.jpg";
 
     #[test]
-    fn test_create_compare_faces_request_success() {
-        let source_image = Image {
-            bytes: None,
-            s3_object: Some(
-                S3Object {
-                    bucket: Some(BUCKET_NAME.to_string()),
-                    name: Some(KEY.to_string()),
-                    version: None,
-                }
-            ),
-        };
-        let target_image = Image {
-            bytes: Some(fs::read(TARGET_IMAGE_PATH).unwrap()),
-            s3_object: None,
-        };
-        let compare_faces_request: CompareFacesRequest = CompareFacesRequest {
-            similarity_threshold: Some(SIMILARITY_THRESHOLD),
-            source_image,
-            target_image,
-        };
-        assert_eq!(compare_faces_request,
-                   create_compare_faces_request(
-                       KEY, TARGET_IMAGE_PATH))
+    fn test_create_detect_faces_request_success() {
+        let request: DetectFacesRequest =
+            create_detect_faces_request(fs::read(SOURCE_IMAGE_PATH).unwrap());
+        assert_eq!(request.image.bytes, Some(fs::read(SOURCE_IMAGE_PATH).unwrap()));
+        assert_eq!(request.attributes, None);
     }
 
     #[test]
-    fn test_create_compare_faces_request_failure() {
-        let source_image = Image {
-            bytes: None,
-            s3_object: Some(
-                S3Object {
-                    bucket: Some(BUCKET_NAME.to_string()),
-                    name: Some(KEY.to_string()),
-                    version: None,
-                }
-            ),
-        };
-
-        let target_image = Image {
-            bytes: Some(fs::read(DIFFERENT_SOURCE_IMAGE_PATH).unwrap()),
-            s3_object: None,
-        };
-        let compare_faces_request: CompareFacesRequest = CompareFacesRequest {
-            similarity_threshold: Some(SIMILARITY_THRESHOLD),
-            source_image,
-            target_image,
-        };
+    fn test_create_index_faces_request_success() {
+        let request: IndexFacesRequest = create_index_faces_request(
+            "faces", "1111111111", fs::read(SOURCE_IMAGE_PATH).unwrap());
+        assert_eq!(request.collection_id, "faces");
+        assert_eq!(request.external_image_id, Some("1111111111".to_string()));
+        assert_eq!(request.image.bytes, Some(fs::read(SOURCE_IMAGE_PATH).unwrap()));
+        assert_eq!(request.max_faces, Some(1));
+    }
 
-        assert_ne!(compare_faces_request,
-                   create_compare_faces_request(
-                       KEY, TARGET_IMAGE_PATH))
+    #[test]
+    fn test_create_search_faces_request_success() {
+        let request: SearchFacesByImageRequest = create_search_faces_request(
+            "faces", fs::read(TARGET_IMAGE_PATH).unwrap(), SIMILARITY_THRESHOLD);
+        assert_eq!(request.collection_id, "faces");
+        assert_eq!(request.face_match_threshold, Some(SIMILARITY_THRESHOLD));
+        assert_eq!(request.max_faces, Some(1));
     }
 }
@@ -1,30 +1,40 @@
-use actix_web::{AsyncResponder, Error, Form, http, HttpRequest, HttpResponse};
-use actix_web::http::{header::HeaderValue, HeaderMap};
+use actix_web::{AsyncResponder, Error, Form, http, HttpRequest, HttpResponse, ResponseError,
+                State};
+use actix_web::http::{header, HeaderMap};
 use chrono::{Datelike, NaiveDate};
 use futures::Future;
 use futures::future::result;
-use log::{error, info};
-use mongodb::{bson, doc};
-use mongodb::Document;
-use serde_json::{json, Value};
-
-use crate::constants::{CONFERENCE_NAME, EMAIL, EMPTY_ADDRESS, ID, NAME, RESPONSE, SET, STATUS,
-                       TEXT, USER_ID, WRONG_CONFERENCE_ID_FORMAT, WRONG_DATE, WRONG_DATE_FORMAT,
-                       WRONG_EMAIL_FORMAT, WRONG_NAME_FORMAT, WRONG_USER_ID_FORMAT};
+use log::{info, warn};
+
+use crate::constants::{EMAIL, EMPTY_ADDRESS, NAME, TEXT, USER_ID, WRONG_CONFERENCE_ID_FORMAT,
+                       WRONG_DATE, WRONG_DATE_FORMAT, WRONG_EMAIL_FORMAT, WRONG_NAME_FORMAT,
+                       WRONG_PRESENCE_RULE, WRONG_SIMILARITY_THRESHOLD, WRONG_USER_ID_FORMAT};
+use crate::db_operations::{approve_review, complete_conference, recompute_attendance,
+                           reject_review};
+use crate::error::{ConfCountError, ConfCountResult, ensure, respond_async, Validator};
+use crate::photo_processing::STORED_CONTENT_TYPE;
 use crate::request_handlers::signup_handler::generate_conference_id;
-use crate::utils::{add_conference, check_date_format, check_email_format, check_id_format,
-                   check_name_format, check_non_empty, delete_conference, delete_user,
-                   filter_conference, filter_user_conference, update_conference,
-                   update_user_details};
-
-static CONFERENCE_DATE: &str = "conference_date";
-static CONFERENCE_ID: &str = "_id";
-static CONFERENCE_ADDRESS1: &str = "conference_address1";
-static CONFERENCE_ADDRESS2: &str = "conference_address2";
-static CONFERENCE_ADDRESS3: &str = "conference_address3";
-static GREATER: &str = "$gte";
-static LESSER: &str = "$lte";
+use crate::session::{current_user, unix_time};
+use crate::state::AppState;
+use crate::storage::{Conference as ConferenceRecord, ConferenceUpdate, PresenceRule};
+use crate::utils::{add_conference, attendance_audit, check_date_format, check_email_format,
+                   check_id_format, check_name_format, check_non_empty, delete_conference,
+                   delete_user, filter_conference, filter_user_conference, header_value,
+                   index_conference_faces, parse_presence_rule, parse_similarity_threshold,
+                   pending_reviews, update_conference, update_user_details};
+
 static DATE_FORMAT: &str = "%Y-%m-%d";
+static EMPTY_CONFERENCE_NAME: &str = "Conference name is empty";
+static NONEXISTENT_DATE: &str = "Select a date that exists in the calendar";
+static CONFERENCE_ID_FIELD: &str = "conference_id";
+static REVIEW_ID_FIELD: &str = "review_id";
+static EMPTY_REVIEW_ID: &str = "Review id is empty";
+static PHOTO_KEY_FIELD: &str = "key";
+static WRONG_PHOTO_KEY: &str = "Photo key must be made of letters, digits and dashes";
+static NO_PHOTO: &str = "No photo is stored under this key";
+static PRIVATE_PHOTO: &str = "private, no-store";
+static ACCOUNT_UNLOCKED: &str = "Account unlocked";
+static NO_FAILED_LOGINS: &str = "No failed logins are recorded for this account";
 
 /// This function response for a particular route request
 ///
@@ -36,7 +46,7 @@ static DATE_FORMAT: &str = "%Y-%m-%d";
 ///
 /// This function response for register user button
 pub fn load_admin_dashboard(
-    _http_request: &HttpRequest
+    _http_request: &HttpRequest<AppState>
 ) -> Box<dyn Future<Item=HttpResponse, Error=Error>> {
     result(Ok(HttpResponse::build(http::StatusCode::OK)
         .content_type(TEXT)
@@ -53,24 +63,25 @@ pub struct UserId {
 ///
 /// # Argument
 ///
+/// * `state` - State of the application
+///
 /// * `user_id` - User unique ID
 ///
 /// # Return
 ///
 /// Respond to a delete user button
 pub fn handle_user_deletion(
-    user_id: Form<UserId>
+    (state, user_id): (State<AppState>, Form<UserId>)
 ) -> Box<dyn Future<Item=HttpResponse, Error=Error>> {
-    let response: &str;
-    if check_id_format(user_id.user_id.as_str()) {
-        response = delete_user(user_id.user_id.to_string())
-    } else {
-        error!("{}", WRONG_USER_ID_FORMAT);
-        response = WRONG_USER_ID_FORMAT
-    }
-    result(Ok(HttpResponse::build(http::StatusCode::OK)
-        .json(json!({RESPONSE: response}))))
-        .responder()
+    let app_state: AppState = (*state).clone();
+    let user_id: String = user_id.into_inner().user_id;
+    respond_async(state.executor.run(move || {
+        Validator::new()
+            .check(check_id_format(user_id.as_str()), USER_ID, WRONG_USER_ID_FORMAT)
+            .finish()?;
+        delete_user(&*app_state.storage, &*app_state.photo_store, &*app_state.face_matcher,
+                    user_id)
+    }))
 }
 
 /// This handler function response for a user input submit request
@@ -83,41 +94,25 @@ pub fn handle_user_deletion(
 ///
 /// This function response to a update user button
 pub fn handle_user_updation(
-    request: &HttpRequest
+    request: &HttpRequest<AppState>
 ) -> Box<dyn Future<Item=HttpResponse, Error=Error>> {
-    let header: &HeaderMap<HeaderValue> = request.headers();
-    let response: &str;
-    if check_id_format(header[USER_ID].to_str().unwrap()) {
-        if check_name_format(header[NAME].to_str().unwrap()) {
-            if check_email_format(header[EMAIL].to_str().unwrap()) {
-                let user_id = doc! {ID: header[USER_ID].to_str().unwrap()};
-                let updated_data = doc! {SET: {NAME: header[NAME].to_str().unwrap(),
-                                            EMAIL: header[EMAIL].to_str().unwrap()}};
-                match update_user_details(user_id, updated_data) {
-                    1 => {
-                        info!("User with id {} has been updated", header[USER_ID].to_str().unwrap());
-                        response = "User details updated"
-                    }
-                    _ => {
-                        error!("User ID not found");
-                        response = "User ID not found"
-                    }
-                }
-            } else {
-                error!("{}", WRONG_EMAIL_FORMAT);
-                response = WRONG_EMAIL_FORMAT;
-            }
-        } else {
-            error!("{}", WRONG_NAME_FORMAT);
-            response = WRONG_NAME_FORMAT;
-        }
-    } else {
-        error!("{}", WRONG_USER_ID_FORMAT);
-        response = WRONG_USER_ID_FORMAT;
-    }
-    result(Ok(HttpResponse::build(http::StatusCode::OK)
-        .json(json!({RESPONSE: response}))))
-        .responder()
+    let state: AppState = request.state().clone();
+    let headers: HeaderMap = request.headers().clone();
+    respond_async(request.state().executor.run(move || update_user(&state, &headers)))
+}
+
+/// Validates the user details given in the headers and updates the user
+fn update_user(state: &AppState, headers: &HeaderMap) -> ConfCountResult<&'static str> {
+    let user_id: &str = header_value(headers, USER_ID)?;
+    let name: &str = header_value(headers, NAME)?;
+    let email: &str = header_value(headers, EMAIL)?;
+    Validator::new()
+        .check(check_id_format(user_id), USER_ID, WRONG_USER_ID_FORMAT)
+        .check(check_name_format(name), NAME, WRONG_NAME_FORMAT)
+        .check(check_email_format(email), EMAIL, WRONG_EMAIL_FORMAT)
+        .finish()?;
+    update_user_details(&*state.storage, &*state.mailer, &state.config, user_id, name, email,
+                        unix_time() as i64)
 }
 
 #[derive(Deserialize)]
@@ -129,26 +124,218 @@ pub struct Conference {
 ///
 /// # Argument
 ///
+/// * `state` - State of the application
+///
 /// * `conference` - New Conference name
 ///
 /// # Return
 ///
 /// This function response to a delete user button
 pub fn handle_conference_deletion(
-    conference: Form<Conference>
+    (state, conference): (State<AppState>, Form<Conference>)
 ) -> Box<dyn Future<Item=HttpResponse, Error=Error>> {
-    let response: &str = if check_id_format(conference.conference_id.as_str()) {
-        let filter: Document = doc! {CONFERENCE_ID : conference.conference_id.as_str()};
-        delete_conference(filter)
-    } else {
-        error!("{}", WRONG_CONFERENCE_ID_FORMAT);
-        WRONG_CONFERENCE_ID_FORMAT
-    };
-    result(Ok(HttpResponse::build(http::StatusCode::OK)
-        .json(json!({RESPONSE: response}))))
+    let app_state: AppState = (*state).clone();
+    let conference_id: String = conference.into_inner().conference_id;
+    respond_async(state.executor.run(move || {
+        Validator::new()
+            .check(check_id_format(conference_id.as_str()), CONFERENCE_ID_FIELD,
+                   WRONG_CONFERENCE_ID_FORMAT)
+            .finish()?;
+        delete_conference(&*app_state.storage, conference_id.as_str())
+    }))
+}
+
+/// Responds to a request auditing the attendance of a conference
+///
+/// # Argument
+///
+/// * `state` - State of the application
+///
+/// * `conference` - Id of the conference
+///
+/// # Return
+///
+/// Responds with the attendance of every registered user and the match backing it
+pub fn handle_attendance_audit(
+    (state, conference): (State<AppState>, Form<Conference>)
+) -> Box<dyn Future<Item=HttpResponse, Error=Error>> {
+    let app_state: AppState = (*state).clone();
+    let conference_id: String = conference.into_inner().conference_id;
+    respond_async(state.executor.run(move || {
+        Validator::new()
+            .check(check_id_format(conference_id.as_str()), CONFERENCE_ID_FIELD,
+                   WRONG_CONFERENCE_ID_FORMAT)
+            .finish()?;
+        attendance_audit(&*app_state.storage, conference_id.as_str())
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct ReviewId {
+    review_id: String,
+}
+
+/// Responds to a request listing the matches of a conference pending review
+///
+/// # Argument
+///
+/// * `state` - State of the application
+///
+/// * `conference` - Id of the conference
+///
+/// # Return
+///
+/// Responds with the pending reviews, each with its cropped face and reference photo keys
+pub fn handle_pending_reviews(
+    (state, conference): (State<AppState>, Form<Conference>)
+) -> Box<dyn Future<Item=HttpResponse, Error=Error>> {
+    let app_state: AppState = (*state).clone();
+    let conference_id: String = conference.into_inner().conference_id;
+    respond_async(state.executor.run(move || {
+        Validator::new()
+            .check(check_id_format(conference_id.as_str()), CONFERENCE_ID_FIELD,
+                   WRONG_CONFERENCE_ID_FORMAT)
+            .finish()?;
+        pending_reviews(&*app_state.storage, conference_id.as_str())
+    }))
+}
+
+/// Responds to a request showing a photo of the photo store, such as the cropped face or the
+/// reference photo of a pending review
+///
+/// # Argument
+///
+/// * `request` - An HTTP Request, carrying the key of the photo in its query
+///
+/// # Return
+///
+/// Responds with the stored JPEG photo, kept out of the caches
+pub fn handle_photo_download(
+    request: &HttpRequest<AppState>
+) -> Box<dyn Future<Item=HttpResponse, Error=Error>> {
+    let state: AppState = request.state().clone();
+    let key: String = request.query().get(PHOTO_KEY_FIELD).cloned().unwrap_or_default();
+    request.state().executor
+        .run(move || stored_photo(&state, key.trim()))
+        .then(|photo| Ok::<HttpResponse, Error>(match photo {
+            Ok(photo) => HttpResponse::Ok()
+                .content_type(STORED_CONTENT_TYPE)
+                .header(header::CACHE_CONTROL, PRIVATE_PHOTO)
+                .body(photo),
+            Err(error) => error.error_response(),
+        }))
         .responder()
 }
 
+/// Reads the photo stored under a key of the photo store
+fn stored_photo(state: &AppState, key: &str) -> ConfCountResult<Vec<u8>> {
+    Validator::new()
+        .check(!key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-'),
+               PHOTO_KEY_FIELD, WRONG_PHOTO_KEY)
+        .finish()?;
+    state.photo_store.get(key)?.ok_or_else(|| ConfCountError::NotFound(NO_PHOTO.to_string()))
+}
+
+/// Responds to a request approving a match pending review
+///
+/// # Argument
+///
+/// * `state` - State of the application
+///
+/// * `review` - Id of the review
+///
+/// # Return
+///
+/// Responds with the confirmation once the user is marked present
+pub fn handle_review_approval(
+    (state, review): (State<AppState>, Form<ReviewId>)
+) -> Box<dyn Future<Item=HttpResponse, Error=Error>> {
+    let app_state: AppState = (*state).clone();
+    let review_id: String = review.into_inner().review_id;
+    respond_async(state.executor.run(move || {
+        Validator::new()
+            .check(check_non_empty(&[review_id.as_str()]), REVIEW_ID_FIELD, EMPTY_REVIEW_ID)
+            .finish()?;
+        approve_review(&app_state, review_id.as_str())
+    }))
+}
+
+/// Responds to a request rejecting a match pending review
+///
+/// # Argument
+///
+/// * `state` - State of the application
+///
+/// * `review` - Id of the review
+///
+/// # Return
+///
+/// Responds with the confirmation once the match is dropped. The user keeps the attendance the
+/// other captures give them, and is only notified of an absence when the conference is completed
+pub fn handle_review_rejection(
+    (state, review): (State<AppState>, Form<ReviewId>)
+) -> Box<dyn Future<Item=HttpResponse, Error=Error>> {
+    let app_state: AppState = (*state).clone();
+    let review_id: String = review.into_inner().review_id;
+    respond_async(state.executor.run(move || {
+        Validator::new()
+            .check(check_non_empty(&[review_id.as_str()]), REVIEW_ID_FIELD, EMPTY_REVIEW_ID)
+            .finish()?;
+        reject_review(&app_state, review_id.as_str())
+    }))
+}
+
+/// Responds to a request indexing again the faces of the users registered for a conference
+///
+/// # Argument
+///
+/// * `state` - State of the application
+///
+/// * `conference` - Id of the conference
+///
+/// # Return
+///
+/// Responds with the number of indexed faces
+pub fn handle_face_indexing(
+    (state, conference): (State<AppState>, Form<Conference>)
+) -> Box<dyn Future<Item=HttpResponse, Error=Error>> {
+    let app_state: AppState = (*state).clone();
+    let conference_id: String = conference.into_inner().conference_id;
+    respond_async(state.executor.run(move || {
+        Validator::new()
+            .check(check_id_format(conference_id.as_str()), CONFERENCE_ID_FIELD,
+                   WRONG_CONFERENCE_ID_FORMAT)
+            .finish()?;
+        index_conference_faces(&*app_state.storage, &*app_state.photo_store,
+                               &*app_state.face_matcher, conference_id.as_str())
+    }))
+}
+
+/// Responds to a request completing a conference, which sends the absent users their notice
+///
+/// # Argument
+///
+/// * `state` - State of the application
+///
+/// * `conference` - Id of the conference
+///
+/// # Return
+///
+/// Returns the number of absence notices sent
+pub fn handle_conference_completion(
+    (state, conference): (State<AppState>, Form<Conference>)
+) -> Box<dyn Future<Item=HttpResponse, Error=Error>> {
+    let app_state: AppState = (*state).clone();
+    let conference_id: String = conference.into_inner().conference_id;
+    respond_async(state.executor.run(move || {
+        Validator::new()
+            .check(check_id_format(conference_id.as_str()), CONFERENCE_ID_FIELD,
+                   WRONG_CONFERENCE_ID_FORMAT)
+            .finish()?;
+        complete_conference(&app_state, conference_id.as_str())
+    }))
+}
+
 #[derive(Deserialize)]
 pub struct UpdateConferenceDetails {
     conference_id: String,
@@ -156,54 +343,79 @@ pub struct UpdateConferenceDetails {
     conference_address1: String,
     conference_address2: String,
     conference_address3: String,
+    /// Similarity from 0 to 100 needed to be marked present, empty for the configured one
+    #[serde(default)]
+    similarity_threshold: String,
+    /// Rule deciding from the captures who attended, empty for presence from any capture
+    #[serde(default)]
+    presence_rule: String,
+    #[serde(default)]
+    presence_seen: String,
+    #[serde(default)]
+    presence_captures: String,
+    #[serde(default)]
+    presence_minutes: String,
 }
 
 /// This handler response to a update conference request
 ///
 /// # Argument
 ///
-/// * `update_conference_details` - Conference Id, Conference date, Conference address
+/// * `state` - State of the application
+///
+/// * `update_conference_details` - Conference Id, Conference date, Conference address,
+/// Similarity threshold, Presence rule
 ///
 /// # Return
 ///
 /// This function response to update conStatusference button
 pub fn handle_conference_updation(
-    update_conference_details: Form<UpdateConferenceDetails>
+    (state, update_conference_details): (State<AppState>, Form<UpdateConferenceDetails>)
 ) -> Box<dyn Future<Item=HttpResponse, Error=Error>> {
-    let response: &str;
-    if check_id_format(update_conference_details.conference_id.as_str()) {
-        if check_date_format(update_conference_details.conference_date.as_str()) {
-            if check_non_empty(&[update_conference_details.conference_address1.as_str(),
-                update_conference_details.conference_address2.as_str(),
-                update_conference_details.conference_address3.as_str()]) {
-                let filter_data: Document = doc! {
-                        CONFERENCE_ID : update_conference_details.conference_id.as_str(),
-                };
-
-                let updated_conference_data: Document = doc! {
-                SET : {
-                CONFERENCE_DATE : update_conference_details.conference_date.as_str(),
-                CONFERENCE_ADDRESS1 : update_conference_details.conference_address1.as_str(),
-                CONFERENCE_ADDRESS2 : update_conference_details.conference_address2.as_str(),
-                CONFERENCE_ADDRESS3 : update_conference_details.conference_address3.as_str(),
-                }
-            };
-                response = update_conference(filter_data, updated_conference_data);
-            } else {
-                error!("{}", EMPTY_ADDRESS);
-                response = EMPTY_ADDRESS;
-            }
-        } else {
-            error!("{}", WRONG_DATE_FORMAT);
-            response = WRONG_DATE_FORMAT;
-        }
-    } else {
-        error!("{}", WRONG_CONFERENCE_ID_FORMAT);
-        response = WRONG_CONFERENCE_ID_FORMAT;
-    }
-    result(Ok(HttpResponse::build(http::StatusCode::OK)
-        .json(json!({RESPONSE: response}))))
-        .responder()
+    let app_state: AppState = (*state).clone();
+    let details: UpdateConferenceDetails = update_conference_details.into_inner();
+    respond_async(state.executor.run(move || edit_conference(&app_state, &details)))
+}
+
+/// Validates the updated details and updates the conference, deriving again the attendance of
+/// its users under the new threshold and presence rule
+fn edit_conference(
+    state: &AppState,
+    details: &UpdateConferenceDetails,
+) -> ConfCountResult<&'static str> {
+    let similarity_threshold: Option<Option<f32>> =
+        parse_similarity_threshold(details.similarity_threshold.as_str());
+    let presence_rule: Option<PresenceRule> =
+        parse_presence_rule(details.presence_rule.as_str(), details.presence_seen.as_str(),
+                            details.presence_captures.as_str(),
+                            details.presence_minutes.as_str());
+    Validator::new()
+        .check(check_id_format(details.conference_id.as_str()), CONFERENCE_ID_FIELD,
+               WRONG_CONFERENCE_ID_FORMAT)
+        .check(check_date_format(details.conference_date.as_str()), "conference_date",
+               WRONG_DATE_FORMAT)
+        .check(check_non_empty(&[details.conference_address1.as_str(),
+            details.conference_address2.as_str(),
+            details.conference_address3.as_str()]), "conference_address", EMPTY_ADDRESS)
+        .check(similarity_threshold.is_some(), "similarity_threshold",
+               WRONG_SIMILARITY_THRESHOLD)
+        .check(presence_rule.is_some(), "presence_rule", WRONG_PRESENCE_RULE)
+        .finish()?;
+    let updated_conference_data: ConferenceUpdate = ConferenceUpdate {
+        conference_date: details.conference_date.clone(),
+        conference_address1: details.conference_address1.clone(),
+        conference_address2: details.conference_address2.clone(),
+        conference_address3: details.conference_address3.clone(),
+        similarity_threshold: similarity_threshold.and_then(|threshold| threshold),
+        presence_rule: presence_rule.unwrap_or_default(),
+    };
+    let updated: &'static str = update_conference(&*state.storage,
+                                                  details.conference_id.as_str(),
+                                                  &updated_conference_data)?;
+    let changed: usize = recompute_attendance(state, details.conference_id.as_str())?;
+    info!("Attendance of {} users of conference {} changed with its rules", changed,
+          details.conference_id);
+    Ok(updated)
 }
 
 #[derive(Deserialize)]
@@ -213,6 +425,18 @@ pub struct NewConference {
     add_conference_address1: String,
     add_conference_address2: String,
     add_conference_address3: String,
+    /// Similarity from 0 to 100 needed to be marked present, empty for the configured one
+    #[serde(default)]
+    add_similarity_threshold: String,
+    /// Rule deciding from the captures who attended, empty for presence from any capture
+    #[serde(default)]
+    add_presence_rule: String,
+    #[serde(default)]
+    add_presence_seen: String,
+    #[serde(default)]
+    add_presence_captures: String,
+    #[serde(default)]
+    add_presence_minutes: String,
 }
 
 pub enum ConferenceStatus {
@@ -233,52 +457,62 @@ impl ConferenceStatus {
 ///
 /// # Argument
 ///
-/// * `new_conference` - Conference name, Conference date, Conference address
+/// * `state` - State of the application
+///
+/// * `new_conference` - Conference name, Conference date, Conference address, Similarity
+/// threshold, Presence rule
 ///
 /// # Return
 ///
 /// This function response to a add new conference button
 pub fn handle_conference_addition(
-    new_conference: Form<NewConference>
+    (state, new_conference): (State<AppState>, Form<NewConference>)
 ) -> Box<dyn Future<Item=HttpResponse, Error=Error>> {
-    let response: &str;
-    if check_non_empty(&[new_conference.add_conference.as_str()]) {
-        if check_date_format(new_conference.add_conference_date.as_str()) {
-            if check_non_empty(&[new_conference.add_conference_address1.as_str(),
-                new_conference.add_conference_address2.as_str(),
-                new_conference.add_conference_address3.as_str()]) {
-                let mut conf_data: String = new_conference.add_conference.clone();
-                conf_data.push_str(&new_conference.add_conference_date);
-                let conf_id: String = generate_conference_id(conf_data).to_string();
-                let validation_data: Document = doc! {
-                        CONFERENCE_ID : conf_id.as_str(),
-                };
-
-                let conference_data: Document = doc! {
-                    CONFERENCE_ID : conf_id.as_str(),
-                    CONFERENCE_NAME : new_conference.add_conference.as_str(),
-                    CONFERENCE_DATE : new_conference.add_conference_date.as_str(),
-                    CONFERENCE_ADDRESS1 : new_conference.add_conference_address1.as_str(),
-                    CONFERENCE_ADDRESS2 : new_conference.add_conference_address2.as_str(),
-                    CONFERENCE_ADDRESS3 : new_conference.add_conference_address3.as_str(),
-                    STATUS: ConferenceStatus::NotCompleted.as_str(),
-            };
-                response = add_conference(conference_data, validation_data);
-            } else {
-                error!("{}", EMPTY_ADDRESS);
-                response = EMPTY_ADDRESS;
-            }
-        } else {
-            error!("{}", WRONG_DATE_FORMAT);
-            response = WRONG_DATE_FORMAT;
-        }
-    } else {
-        error!("Conference name is empty");
-        response = "Conference name is empty";
-    }
-    result(Ok(HttpResponse::build(http::StatusCode::OK)
-        .json(json!({RESPONSE: response}))))
-        .responder()
+    let app_state: AppState = (*state).clone();
+    let new_conference: NewConference = new_conference.into_inner();
+    respond_async(state.executor.run(move || create_conference(&app_state, &new_conference)))
+}
+
+/// Validates the details of the new conference and adds it
+fn create_conference(
+    state: &AppState,
+    new_conference: &NewConference,
+) -> ConfCountResult<&'static str> {
+    let similarity_threshold: Option<Option<f32>> =
+        parse_similarity_threshold(new_conference.add_similarity_threshold.as_str());
+    let presence_rule: Option<PresenceRule> =
+        parse_presence_rule(new_conference.add_presence_rule.as_str(),
+                            new_conference.add_presence_seen.as_str(),
+                            new_conference.add_presence_captures.as_str(),
+                            new_conference.add_presence_minutes.as_str());
+    Validator::new()
+        .check(check_non_empty(&[new_conference.add_conference.as_str()]), "add_conference",
+               EMPTY_CONFERENCE_NAME)
+        .check(check_date_format(new_conference.add_conference_date.as_str()),
+               "add_conference_date", WRONG_DATE_FORMAT)
+        .check(check_non_empty(&[new_conference.add_conference_address1.as_str(),
+            new_conference.add_conference_address2.as_str(),
+            new_conference.add_conference_address3.as_str()]), "add_conference_address",
+               EMPTY_ADDRESS)
+        .check(similarity_threshold.is_some(), "add_similarity_threshold",
+               WRONG_SIMILARITY_THRESHOLD)
+        .check(presence_rule.is_some(), "add_presence_rule", WRONG_PRESENCE_RULE)
+        .finish()?;
+    let mut conf_data: String = new_conference.add_conference.clone();
+    conf_data.push_str(&new_conference.add_conference_date);
+    let conf_id: String = generate_conference_id(conf_data).to_string();
+    let conference_data: ConferenceRecord = ConferenceRecord {
+        id: conf_id,
+        name: new_conference.add_conference.clone(),
+        conference_date: new_conference.add_conference_date.clone(),
+        conference_address1: new_conference.add_conference_address1.clone(),
+        conference_address2: new_conference.add_conference_address2.clone(),
+        conference_address3: new_conference.add_conference_address3.clone(),
+        status: ConferenceStatus::NotCompleted.as_str().to_string(),
+        similarity_threshold: similarity_threshold.and_then(|threshold| threshold),
+        presence_rule: presence_rule.unwrap_or_default(),
+    };
+    add_conference(&*state.storage, &conference_data)
 }
 
 #[derive(Deserialize)]
@@ -291,41 +525,41 @@ pub struct FilterConference {
 ///
 /// # Argument
 ///
+/// * `state` - State of the application
+///
 /// * `filter_conferences` - first and last conference date to filter conferences
 ///
 /// # Return
 ///
 /// Responds to a filter conference button
 pub fn handle_conference_filtration(
-    filter_conferences: Form<FilterConference>
+    (state, filter_conferences): (State<AppState>, Form<FilterConference>)
 ) -> Box<dyn Future<Item=HttpResponse, Error=Error>> {
-    let response: Value;
-    let day1: &str = filter_conferences.first_conference_date.as_str();
-    let day2: &str = filter_conferences.last_conference_date.as_str();
-    if check_date_format(day1) && check_date_format(day2) {
-        let first_date: NaiveDate = NaiveDate::parse_from_str(day1, DATE_FORMAT).unwrap();
-        let last_date: NaiveDate = NaiveDate::parse_from_str(day2, DATE_FORMAT).unwrap();
-        let no_of_days_day1: i32 = first_date.num_days_from_ce();
-        let no_of_days_day2: i32 = last_date.num_days_from_ce();
-        let diff_of_dates: i32 = no_of_days_day2 - no_of_days_day1;
-        match diff_of_dates {
-            0..=365_000 => {
-                let filter = doc! {CONFERENCE_DATE: {GREATER: day1,LESSER: day2,},};
-                info!("Admin just filtered out some conferences");
-                response = json!({RESPONSE: filter_conference(filter)});
-            }
-            _ => {
-                error!("{}", WRONG_DATE);
-                response = json!({RESPONSE: WRONG_DATE.to_string()});
-            }
-        }
-    } else {
-        error!("{}", WRONG_DATE_FORMAT);
-        response = json!({RESPONSE: WRONG_DATE_FORMAT});
-    }
-    result(Ok(HttpResponse::build(http::StatusCode::OK)
-        .json(response)))
-        .responder()
+    let app_state: AppState = (*state).clone();
+    let range: FilterConference = filter_conferences.into_inner();
+    respond_async(state.executor.run(move || conferences_between(&app_state, &range)))
+}
+
+/// Parses a date of the expected format, rejecting the field when no such day exists
+fn parse_date(date: &str, field: &str) -> ConfCountResult<NaiveDate> {
+    NaiveDate::parse_from_str(date, DATE_FORMAT)
+        .map_err(|_| ConfCountError::invalid_field(field, NONEXISTENT_DATE))
+}
+
+/// Validates the date range and filters the conferences within it
+fn conferences_between(state: &AppState, range: &FilterConference) -> ConfCountResult<String> {
+    let day1: &str = range.first_conference_date.as_str();
+    let day2: &str = range.last_conference_date.as_str();
+    Validator::new()
+        .check(check_date_format(day1), "first_conference_date", WRONG_DATE_FORMAT)
+        .check(check_date_format(day2), "last_conference_date", WRONG_DATE_FORMAT)
+        .finish()?;
+    let first_date: NaiveDate = parse_date(day1, "first_conference_date")?;
+    let last_date: NaiveDate = parse_date(day2, "last_conference_date")?;
+    let diff_of_dates: i32 = last_date.num_days_from_ce() - first_date.num_days_from_ce();
+    ensure((0..=365_000).contains(&diff_of_dates), WRONG_DATE)?;
+    info!("Admin just filtered out some conferences");
+    filter_conference(&*state.storage, day1, day2)
 }
 
 #[derive(Deserialize)]
@@ -337,23 +571,65 @@ pub struct FilterUser {
 ///
 /// # Argument
 ///
+/// * `state` - State of the application
+///
 /// * `filter_conferences` - conference name
 ///
 /// # Return
 ///
 /// Responds to a view users button for a specific conference
 pub fn handle_user_filtration(
-    filter_conference: Form<FilterUser>
+    (state, filter_conference): (State<AppState>, Form<FilterUser>)
+) -> Box<dyn Future<Item=HttpResponse, Error=Error>> {
+    let app_state: AppState = (*state).clone();
+    let conference_id: String = filter_conference.into_inner().conference_id;
+    respond_async(state.executor.run(move || {
+        Validator::new()
+            .check(check_id_format(conference_id.as_str()), CONFERENCE_ID_FIELD,
+                   WRONG_CONFERENCE_ID_FORMAT)
+            .finish()?;
+        filter_user_conference(&*app_state.storage, conference_id.as_str())
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct LockedAccount {
+    email: String,
+}
+
+/// Lifts the login delay or lockout of an account
+///
+/// # Argument
+///
+/// * `request` - An HTTP request of the signed in admin
+///
+/// * `account` - Email of the account to unlock
+///
+/// # Return
+///
+/// Responds to an unlock account button
+pub fn handle_account_unlock(
+    (request, account): (HttpRequest<AppState>, Form<LockedAccount>)
 ) -> Box<dyn Future<Item=HttpResponse, Error=Error>> {
-    let response: String = if check_id_format(filter_conference.conference_id.as_str()) {
-        filter_user_conference(filter_conference.conference_id.as_str())
+    let state: AppState = request.state().clone();
+    let admin: String = current_user(&request).map(|admin| admin.email).unwrap_or_default();
+    let email: String = account.into_inner().email;
+    respond_async(request.state().executor.run(move || {
+        unlock_account(&state, admin.as_str(), email.trim())
+    }))
+}
+
+/// Validates the email and forgets the failed logins of its account
+fn unlock_account(state: &AppState, admin: &str, email: &str) -> ConfCountResult<&'static str> {
+    Validator::new()
+        .check(check_email_format(email), EMAIL, WRONG_EMAIL_FORMAT)
+        .finish()?;
+    if state.login_throttle.unlock(email) {
+        warn!("Admin {} unlocked the logins of account {}", admin, email);
+        Ok(ACCOUNT_UNLOCKED)
     } else {
-        info!("Admin entered wrong Conference Id format");
-        WRONG_CONFERENCE_ID_FORMAT.to_string()
-    };
-    result(Ok(HttpResponse::build(http::StatusCode::OK)
-        .json(json!({RESPONSE: response}))))
-        .responder()
+        Err(ConfCountError::NotFound(NO_FAILED_LOGINS.to_string()))
+    }
 }
 
 /// Respond to timeout of the form
@@ -366,7 +642,7 @@ pub fn handle_user_filtration(
 ///
 /// Responds to a session timeout
 pub fn handle_admin_timeout(
-    _http_request: &HttpRequest
+    _http_request: &HttpRequest<AppState>
 ) -> Box<dyn Future<Item=HttpResponse, Error=Error>> {
     result(Ok(HttpResponse::build(http::StatusCode::OK)
         .content_type(TEXT)
@@ -377,29 +653,52 @@ pub fn handle_admin_timeout(
 
 #[cfg(test)]
 pub mod tests {
-    use actix_web::{Form, http, HttpResponse, test};
-    use actix_web::http::StatusCode;
+    use actix_web::{Form, http, HttpRequest, HttpResponse, test};
+    use actix_web::http::{header, StatusCode};
     use futures::future::Future;
 
-    use crate::constants::{EMAIL, NAME, USER_ID};
+    use crate::constants::{ADMIN_EMAIL, EMAIL, NAME, TEST_EMAIL_ID, USER_ID, WRONG_PRESENCE_RULE,
+                           WRONG_SIMILARITY_THRESHOLD};
+    use crate::error::ConfCountError;
     use crate::request_handlers::admin_handler::{
         Conference,
+        conferences_between,
+        create_conference,
+        edit_conference,
         FilterConference,
+        handle_account_unlock,
         handle_admin_timeout,
+        handle_attendance_audit,
         handle_conference_addition,
+        handle_conference_completion,
         handle_conference_deletion,
         handle_conference_filtration,
         handle_conference_updation,
+        handle_face_indexing,
+        handle_pending_reviews,
+        handle_photo_download,
+        handle_review_approval,
+        handle_review_rejection,
         handle_user_deletion,
         handle_user_updation,
         load_admin_dashboard,
+        LockedAccount,
         NewConference,
+        NONEXISTENT_DATE,
+        ReviewId,
         UpdateConferenceDetails,
         UserId};
+    use crate::session::test::sign_in;
+    use crate::session::unix_time;
+    use crate::state::AppState;
+    use crate::state::test::{state_extractor, test_request, test_state};
+    use crate::storage::{ConferenceRepository, PresenceRule};
+    use crate::storage::memory::test::{TEST_CONFERENCE_ID, TEST_USER_ID, test_observation};
 
     #[test]
     fn test_load_admin_dashboard_success() {
-        let response: HttpResponse = test::TestRequest::with_header("content-type", "text/html")
+        let response: HttpResponse = test::TestRequest::with_state(test_state())
+            .header("content-type", "text/html")
             .run(&load_admin_dashboard)
             .unwrap();
         assert_eq!(response.status(), http::StatusCode::OK);
@@ -407,7 +706,8 @@ pub mod tests {
 
     #[test]
     fn test_handle_admin_timeout_success() {
-        let response: HttpResponse = test::TestRequest::with_header("content-type", "text/html")
+        let response: HttpResponse = test::TestRequest::with_state(test_state())
+            .header("content-type", "text/html")
             .run(&handle_admin_timeout)
             .unwrap();
         assert_eq!(response.status(), http::StatusCode::OK);
@@ -416,20 +716,20 @@ pub mod tests {
     #[test]
     fn test_handle_user_deletion_success() {
         let user_id = UserId { user_id: "2121212121".to_string() };
-        assert!(handle_user_deletion(Form(user_id)).wait().is_ok())
+        assert!(handle_user_deletion((state_extractor(), Form(user_id))).wait().is_ok())
     }
 
     #[test]
     fn test_handle_user_deletion_failure() {
         let user_id = UserId { user_id: "212".to_string() };
-        assert!(handle_user_deletion(Form(user_id)).wait().is_ok())
+        assert!(handle_user_deletion((state_extractor(), Form(user_id))).wait().is_ok())
     }
 
     #[test]
     fn test_handle_user_update_success() {
-        let response: HttpResponse = test::TestRequest::default()
+        let response: HttpResponse = test::TestRequest::with_state(test_state())
             .header(USER_ID, "1111111111")
-            .header(NAME, "Test")
+            .header(NAME, "Tester")
             .header(EMAIL, "test@knoldus.in")
             .run(&handle_user_updation)
             .unwrap();
@@ -439,38 +739,38 @@ pub mod tests {
 
     #[test]
     fn test_handle_user_update_wrong_name_format() {
-        let response: HttpResponse = test::TestRequest::default()
+        let response: HttpResponse = test::TestRequest::with_state(test_state())
             .header(USER_ID, "111")
             .header(NAME, "a")
             .header(EMAIL, "test@knoldus.in")
             .run(&handle_user_updation)
             .unwrap();
 
-        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
     }
 
     #[test]
     fn test_handle_user_update_wrong_id_format() {
-        let response: HttpResponse = test::TestRequest::default()
+        let response: HttpResponse = test::TestRequest::with_state(test_state())
             .header(USER_ID, "111")
             .header(NAME, "Test")
             .header(EMAIL, "test@knoldus.in")
             .run(&handle_user_updation)
             .unwrap();
 
-        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
     }
 
     #[test]
     fn test_handle_user_update_wrong_email_format() {
-        let response: HttpResponse = test::TestRequest::default()
+        let response: HttpResponse = test::TestRequest::with_state(test_state())
             .header(USER_ID, "1111111111")
             .header(NAME, "Test")
             .header(EMAIL, "test@knoldus")
             .run(&handle_user_updation)
             .unwrap();
 
-        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
     }
 
     #[test]
@@ -478,7 +778,7 @@ pub mod tests {
         let conf_name = Conference {
             conference_id: "123456789".to_string(),
         };
-        assert!(handle_conference_deletion(Form(conf_name)).wait().is_ok())
+        assert!(handle_conference_deletion((state_extractor(), Form(conf_name))).wait().is_ok())
     }
 
     #[test]
@@ -486,7 +786,101 @@ pub mod tests {
         let conf_name = Conference {
             conference_id: "Test_conf_name".to_string(),
         };
-        assert!(handle_conference_deletion(Form(conf_name)).wait().is_ok())
+        assert!(handle_conference_deletion((state_extractor(), Form(conf_name))).wait().is_ok())
+    }
+
+    #[test]
+    fn test_handle_attendance_audit_success() {
+        let conference = Conference { conference_id: TEST_CONFERENCE_ID.to_string() };
+        let response: HttpResponse =
+            handle_attendance_audit((state_extractor(), Form(conference))).wait().unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let conference = Conference { conference_id: "conference".to_string() };
+        let response: HttpResponse =
+            handle_attendance_audit((state_extractor(), Form(conference))).wait().unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_handle_pending_reviews_success() {
+        let conference = Conference { conference_id: TEST_CONFERENCE_ID.to_string() };
+        let response: HttpResponse =
+            handle_pending_reviews((state_extractor(), Form(conference))).wait().unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let conference = Conference { conference_id: "conference".to_string() };
+        let response: HttpResponse =
+            handle_pending_reviews((state_extractor(), Form(conference))).wait().unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_handle_photo_download() {
+        let state: AppState = test_state();
+        state.photo_store.put("review-1234", b"face", "image/jpeg").unwrap();
+        let download = |key: &str| -> HttpResponse {
+            let request: HttpRequest<AppState> = test::TestRequest::with_state(state.clone())
+                .uri(&format!("/admin-photo?key={}", key))
+                .finish();
+            handle_photo_download(&request).wait().unwrap()
+        };
+        let response: HttpResponse = download("review-1234");
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers().get(header::CONTENT_TYPE).unwrap(), "image/jpeg");
+        assert_eq!(download("review-0000").status(), StatusCode::NOT_FOUND);
+        assert_eq!(download("..%2Fsecret").status(), StatusCode::BAD_REQUEST);
+        assert_eq!(download("").status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_handle_review_decision_failure() {
+        let review = ReviewId { review_id: "review".to_string() };
+        let response: HttpResponse =
+            handle_review_approval((state_extractor(), Form(review))).wait().unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let review = ReviewId { review_id: String::new() };
+        let response: HttpResponse =
+            handle_review_rejection((state_extractor(), Form(review))).wait().unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_handle_face_indexing_success() {
+        let conference = Conference { conference_id: TEST_CONFERENCE_ID.to_string() };
+        let response: HttpResponse =
+            handle_face_indexing((state_extractor(), Form(conference))).wait().unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn test_handle_face_indexing_failure() {
+        let conference = Conference { conference_id: "55443311".to_string() };
+        let response: HttpResponse =
+            handle_face_indexing((state_extractor(), Form(conference))).wait().unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let conference = Conference { conference_id: "conference".to_string() };
+        let response: HttpResponse =
+            handle_face_indexing((state_extractor(), Form(conference))).wait().unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_handle_conference_completion_success() {
+        let conference = Conference { conference_id: TEST_CONFERENCE_ID.to_string() };
+        let response: HttpResponse =
+            handle_conference_completion((state_extractor(), Form(conference))).wait().unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn test_handle_conference_completion_failure() {
+        let conference = Conference { conference_id: "55443311".to_string() };
+        let response: HttpResponse =
+            handle_conference_completion((state_extractor(), Form(conference))).wait().unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let conference = Conference { conference_id: "conference".to_string() };
+        let response: HttpResponse =
+            handle_conference_completion((state_extractor(), Form(conference))).wait().unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
     }
 
     #[test]
@@ -497,8 +891,13 @@ pub mod tests {
             conference_address1: "Test add1".to_string(),
             conference_address2: "Test_add2".to_string(),
             conference_address3: "Test add3".to_string(),
+            similarity_threshold: String::new(),
+            presence_rule: String::new(),
+            presence_seen: String::new(),
+            presence_captures: String::new(),
+            presence_minutes: String::new(),
         };
-        assert!(handle_conference_updation(Form(conf_data)).wait().is_ok())
+        assert!(handle_conference_updation((state_extractor(), Form(conf_data))).wait().is_ok())
     }
 
     #[test]
@@ -509,8 +908,13 @@ pub mod tests {
             conference_address1: "Test add1".to_string(),
             conference_address2: "Test_add2".to_string(),
             conference_address3: "Test add3".to_string(),
+            similarity_threshold: String::new(),
+            presence_rule: String::new(),
+            presence_seen: String::new(),
+            presence_captures: String::new(),
+            presence_minutes: String::new(),
         };
-        assert!(handle_conference_updation(Form(conf_data)).wait().is_ok())
+        assert!(handle_conference_updation((state_extractor(), Form(conf_data))).wait().is_ok())
     }
 
     #[test]
@@ -521,8 +925,13 @@ pub mod tests {
             conference_address1: "".to_string(),
             conference_address2: "".to_string(),
             conference_address3: "".to_string(),
+            similarity_threshold: String::new(),
+            presence_rule: String::new(),
+            presence_seen: String::new(),
+            presence_captures: String::new(),
+            presence_minutes: String::new(),
         };
-        assert!(handle_conference_updation(Form(conf_data)).wait().is_ok())
+        assert!(handle_conference_updation((state_extractor(), Form(conf_data))).wait().is_ok())
     }
 
     #[test]
@@ -533,8 +942,92 @@ pub mod tests {
             conference_address1: "test_address_1".to_string(),
             conference_address2: "test_address_2".to_string(),
             conference_address3: "test_address_3".to_string(),
+            similarity_threshold: String::new(),
+            presence_rule: String::new(),
+            presence_seen: String::new(),
+            presence_captures: String::new(),
+            presence_minutes: String::new(),
         };
-        assert!(handle_conference_updation(Form(conf_data)).wait().is_ok())
+        assert!(handle_conference_updation((state_extractor(), Form(conf_data))).wait().is_ok())
+    }
+
+    #[test]
+    fn test_edit_conference_similarity_threshold() {
+        let state: AppState = test_state();
+        let mut conf_data = UpdateConferenceDetails {
+            conference_id: TEST_CONFERENCE_ID.to_string(),
+            conference_date: "2018-06-24".to_string(),
+            conference_address1: "test_address_1".to_string(),
+            conference_address2: "test_address_2".to_string(),
+            conference_address3: "test_address_3".to_string(),
+            similarity_threshold: "60".to_string(),
+            presence_rule: String::new(),
+            presence_seen: String::new(),
+            presence_captures: String::new(),
+            presence_minutes: String::new(),
+        };
+        let threshold = || state.storage.conference(TEST_CONFERENCE_ID).unwrap().unwrap()
+            .similarity_threshold;
+        assert!(edit_conference(&state, &conf_data).is_ok());
+        assert_eq!(threshold(), Some(60.0));
+        conf_data.similarity_threshold = "150".to_string();
+        assert_eq!(edit_conference(&state, &conf_data),
+                   Err(ConfCountError::invalid_field("similarity_threshold",
+                                                     WRONG_SIMILARITY_THRESHOLD)));
+        assert_eq!(threshold(), Some(60.0));
+        conf_data.similarity_threshold = String::new();
+        assert!(edit_conference(&state, &conf_data).is_ok());
+        assert_eq!(threshold(), None);
+    }
+
+    #[test]
+    fn test_edit_conference_presence_rule() {
+        let state: AppState = test_state();
+        state.storage.save_observation(&test_observation("capture", 1000, 98.5)).unwrap();
+        let mut conf_data = UpdateConferenceDetails {
+            conference_id: TEST_CONFERENCE_ID.to_string(),
+            conference_date: "2018-06-24".to_string(),
+            conference_address1: "test_address_1".to_string(),
+            conference_address2: "test_address_2".to_string(),
+            conference_address3: "test_address_3".to_string(),
+            similarity_threshold: String::new(),
+            presence_rule: "seen_in_captures".to_string(),
+            presence_seen: "2".to_string(),
+            presence_captures: "1".to_string(),
+            presence_minutes: String::new(),
+        };
+        assert_eq!(edit_conference(&state, &conf_data),
+                   Err(ConfCountError::invalid_field("presence_rule", WRONG_PRESENCE_RULE)));
+        let status = || state.storage.registration(TEST_USER_ID, TEST_CONFERENCE_ID).unwrap()
+            .unwrap().status;
+        conf_data.presence_rule = "seen_within_minutes".to_string();
+        conf_data.presence_minutes = "30".to_string();
+        assert!(edit_conference(&state, &conf_data).is_ok());
+        assert_eq!(state.storage.conference(TEST_CONFERENCE_ID).unwrap().unwrap().presence_rule,
+                   PresenceRule::SeenWithinMinutes { minutes: 30 });
+        assert_eq!(status(), "present");
+        conf_data.similarity_threshold = "99".to_string();
+        assert!(edit_conference(&state, &conf_data).is_ok());
+        assert_eq!(status(), "absent");
+    }
+
+    #[test]
+    fn test_create_conference_invalid_similarity_threshold() {
+        let new_conf = NewConference {
+            add_conference: "test_conference".to_string(),
+            add_conference_date: "2018-06-24".to_string(),
+            add_conference_address1: "test_address_1".to_string(),
+            add_conference_address2: "test_address_2".to_string(),
+            add_conference_address3: "test_address_3".to_string(),
+            add_similarity_threshold: "dim".to_string(),
+            add_presence_rule: String::new(),
+            add_presence_seen: String::new(),
+            add_presence_captures: String::new(),
+            add_presence_minutes: String::new(),
+        };
+        assert_eq!(create_conference(&test_state(), &new_conf),
+                   Err(ConfCountError::invalid_field("add_similarity_threshold",
+                                                     WRONG_SIMILARITY_THRESHOLD)));
     }
 
     #[test]
@@ -545,8 +1038,13 @@ pub mod tests {
             add_conference_address1: "test_address_1".to_string(),
             add_conference_address2: "test_address_2".to_string(),
             add_conference_address3: "test_address_3".to_string(),
+            add_similarity_threshold: String::new(),
+            add_presence_rule: String::new(),
+            add_presence_seen: String::new(),
+            add_presence_captures: String::new(),
+            add_presence_minutes: String::new(),
         };
-        assert!(handle_conference_addition(Form(new_conf)).wait().is_ok())
+        assert!(handle_conference_addition((state_extractor(), Form(new_conf))).wait().is_ok())
     }
 
     #[test]
@@ -555,7 +1053,20 @@ pub mod tests {
             first_conference_date: "09-02-2019".to_string(),
             last_conference_date: "05-07-2019".to_string(),
         };
-        assert!(handle_conference_filtration(Form(filter_conf)).wait().is_ok())
+        assert!(handle_conference_filtration((state_extractor(), Form(filter_conf))).wait().is_ok())
+    }
+
+    #[test]
+    fn test_handle_conference_filtration_nonexistent_date() {
+        let filter_conf = FilterConference {
+            first_conference_date: "2019-02-31".to_string(),
+            last_conference_date: "2019-07-05".to_string(),
+        };
+        assert_eq!(conferences_between(&test_state(), &filter_conf),
+                   Err(ConfCountError::invalid_field("first_conference_date", NONEXISTENT_DATE)));
+        let response: HttpResponse =
+            handle_conference_filtration((state_extractor(), Form(filter_conf))).wait().unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
     }
 
 
@@ -567,7 +1078,38 @@ pub mod tests {
             add_conference_address1: "Test add1".to_string(),
             add_conference_address2: "Test add2".to_string(),
             add_conference_address3: "Test add3".to_string(),
+            add_similarity_threshold: String::new(),
+            add_presence_rule: String::new(),
+            add_presence_seen: String::new(),
+            add_presence_captures: String::new(),
+            add_presence_minutes: String::new(),
         };
-        assert!(handle_conference_addition(Form(new_conf)).wait().is_ok())
+        assert!(handle_conference_addition((state_extractor(), Form(new_conf))).wait().is_ok())
+    }
+
+    #[test]
+    fn test_handle_account_unlock_success() {
+        let request: HttpRequest<AppState> = sign_in(test_request(), ADMIN_EMAIL);
+        for _ in 0..10 {
+            request.state().login_throttle.record_failure(TEST_EMAIL_ID, None, unix_time());
+        }
+        let account = LockedAccount { email: TEST_EMAIL_ID.to_string() };
+        let response: HttpResponse =
+            handle_account_unlock((request.clone(), Form(account))).wait().unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(request.state().login_throttle.check(TEST_EMAIL_ID, None, unix_time()).is_ok());
+    }
+
+    #[test]
+    fn test_handle_account_unlock_failure() {
+        let request: HttpRequest<AppState> = sign_in(test_request(), ADMIN_EMAIL);
+        let account = LockedAccount { email: TEST_EMAIL_ID.to_string() };
+        let response: HttpResponse =
+            handle_account_unlock((request.clone(), Form(account))).wait().unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let account = LockedAccount { email: "not-an-email".to_string() };
+        let response: HttpResponse =
+            handle_account_unlock((request, Form(account))).wait().unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
     }
 }
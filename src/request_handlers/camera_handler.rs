@@ -1,18 +1,14 @@
-use actix_web::{AsyncResponder, Error, HttpRequest, HttpResponse};
-use actix_web::http::header::HeaderValue;
+use actix_web::{Error, HttpRequest, HttpResponse};
 use actix_web::http::HeaderMap;
 use futures::Future;
-use futures::future::result;
-use log::error;
 use log::info;
-use mongodb::coll::Collection;
-use serde_json::{json, Value};
 
-use crate::connection::{connect_database_collection, create_db_connection};
-use crate::constants::{COLLECTION_EMPTY, CONFERENCE_DETAILS, CONFERENCE_ID, DB_NAME, PROCESS_COMPLETE, RESPONSE, WRONG_CONFERENCE_ID_FORMAT};
+use crate::capture::Capture;
+use crate::constants::{CONFERENCE_ID, WRONG_CONFERENCE_ID_FORMAT};
+use crate::error::{ConfCountResult, respond_async, Validator};
 use crate::response_service::fetch_response;
-use crate::user_data::trigger_camera;
-use crate::utils::check_conference_id_format;
+use crate::state::AppState;
+use crate::utils::{check_conference_id_format, header_value};
 
 /// Returns camera response
 ///
@@ -24,39 +20,25 @@ use crate::utils::check_conference_id_format;
 ///
 /// Returns the Camera status
 pub fn handle_camera(
-    request: &HttpRequest
+    request: &HttpRequest<AppState>
 ) -> Box<dyn Future<Item=HttpResponse, Error=Error>> {
-    let response_body: Value;
-    let header: &HeaderMap<HeaderValue> = request.headers();
-    let conference_id: &str = header[CONFERENCE_ID].to_str().unwrap();
-    if check_conference_id_format(conference_id) {
-        let conference_collection: Collection = connect_database_collection(
-            create_db_connection(), DB_NAME, CONFERENCE_DETAILS);
-        info!("Camera triggered Successfully for conference_id {}", conference_id);
-        let response: &str;
-        match trigger_camera() {
-            Ok(_) => {
-                response = match fetch_response(conference_collection, conference_id) {
-                    PROCESS_COMPLETE => PROCESS_COMPLETE,
-                    _ => COLLECTION_EMPTY
-                };
-                info!("{}", response);
-            }
-            Err(camera_error) => {
-                response = "No Camera Detected";
-                error!("{}", camera_error);
-            }
-        }
-        response_body = json!({
-                      RESPONSE: response
-                      });
-    } else {
-        error!("{}", WRONG_CONFERENCE_ID_FORMAT);
-        response_body = json!({RESPONSE: WRONG_CONFERENCE_ID_FORMAT});
-    }
-    result(Ok(HttpResponse::Ok()
-        .json(response_body)))
-        .responder()
+    let state: AppState = request.state().clone();
+    let headers: HeaderMap = request.headers().clone();
+    respond_async(request.state().executor.run(move || take_attendance(&state, &headers)))
+}
+
+/// Captures the audience of the conference named in the headers and marks the attendance
+fn take_attendance(state: &AppState, headers: &HeaderMap) -> ConfCountResult<&'static str> {
+    let conference_id: &str = header_value(headers, CONFERENCE_ID)?;
+    Validator::new()
+        .check(check_conference_id_format(conference_id), CONFERENCE_ID,
+               WRONG_CONFERENCE_ID_FORMAT)
+        .finish()?;
+    info!("Camera triggered Successfully for conference_id {}", conference_id);
+    let capture: Capture = Capture::from_image(state.capture_device.capture()?)?;
+    let response: &str = fetch_response(state, conference_id, &capture)?;
+    info!("{}", response);
+    Ok(response)
 }
 
 #[cfg(test)]
@@ -66,23 +48,43 @@ mod test {
 
     use crate::constants::{CONFERENCE_ID, TEST_CONFERENCE_ID};
     use crate::request_handlers::camera_handler::handle_camera;
+    use crate::state::AppState;
+    use crate::state::test::test_state;
+    use crate::storage::{AttendanceRepository, Registration};
+    use crate::storage::memory::test::{TEST_CONFERENCE_ID as STORED_CONFERENCE_ID, TEST_USER_ID};
 
     #[test]
     fn test_handle_camera_trigger_failure()
     {
-        let response: HttpResponse = test::TestRequest::with_header(CONFERENCE_ID,
-                                                                    TEST_CONFERENCE_ID)
+        let response: HttpResponse = test::TestRequest::with_state(test_state())
+            .header(CONFERENCE_ID, TEST_CONFERENCE_ID)
             .run(&handle_camera)
             .unwrap();
-        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
     }
 
     #[test]
     fn test_handle_camera_wrong_confrant_format()
     {
-        let response: HttpResponse = test::TestRequest::with_header(CONFERENCE_ID, "1122")
+        let response: HttpResponse = test::TestRequest::with_state(test_state())
+            .header(CONFERENCE_ID, "1122")
+            .run(&handle_camera)
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_handle_camera_marks_attendance_success()
+    {
+        let state: AppState = test_state();
+        let response: HttpResponse = test::TestRequest::with_state(state.clone())
+            .header(CONFERENCE_ID, STORED_CONFERENCE_ID)
             .run(&handle_camera)
             .unwrap();
         assert_eq!(response.status(), StatusCode::OK);
+        let registration: Registration =
+            state.storage.registration(TEST_USER_ID, STORED_CONFERENCE_ID).unwrap().unwrap();
+        assert_eq!(registration.status, "present");
+        assert!(!registration.attendance_match.unwrap().capture_id.is_empty());
     }
-}
\ No newline at end of file
+}
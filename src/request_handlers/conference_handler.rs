@@ -1,35 +1,31 @@
-use actix_web::{AsyncResponder, Error, HttpRequest, HttpResponse};
-use actix_web::http::{header::HeaderValue, HeaderMap};
+use std::sync::Arc;
+
+use actix_web::{Error, HttpRequest, HttpResponse};
 use futures::Future;
-use futures::future::result;
-use log::error;
-use mongodb::{bson, doc};
-use mongodb::coll::Collection;
-use serde_json::{json, Value};
 
-use crate::connection::{connect_database_collection, create_db_connection};
-use crate::constants::{DB_NAME, EMAIL, NON_REGISTERED_USER_MESSAGE, RESPONSE, USERS_COLLECTION,
-                       WRONG_EMAIL_FORMAT};
-use crate::utils::{check_email_format, fetch_conference_for_user, fetch_conferences};
+use crate::error::{ConfCountResult, respond_async};
+use crate::session::current_user;
+use crate::state::AppState;
+use crate::storage::{Storage, User};
+use crate::utils::{fetch_conference_for_user, fetch_conferences};
 
 /// Fetches conference details
 ///
 /// # Argument
 ///
-/// * `_request` - An HTTP Request
+/// * `request` - An HTTP Request
 ///
 /// # Return
 ///
 /// Returns the conference details
 pub fn fetch_conference_details(
-    _request: &HttpRequest
+    request: &HttpRequest<AppState>
 ) -> Box<dyn Future<Item=HttpResponse, Error=Error>> {
-    result(Ok(HttpResponse::Ok()
-        .json(json!({RESPONSE: fetch_conferences()}))))
-        .responder()
+    let storage: Arc<dyn Storage> = request.state().storage.clone();
+    respond_async(request.state().executor.run(move || fetch_conferences(&*storage)))
 }
 
-/// Fetches conference registered by a particular user
+/// Fetches conference registered by the signed in user
 ///
 /// # Argument
 ///
@@ -39,65 +35,48 @@ pub fn fetch_conference_details(
 ///
 /// Returns the list of conferences registered by a user
 pub fn fetch_registered_conferences(
-    request: &HttpRequest
+    request: &HttpRequest<AppState>
 ) -> Box<dyn Future<Item=HttpResponse, Error=Error>> {
-    let header: &HeaderMap<HeaderValue> = request.headers();
-    let response: Value;
-    let user_email: &str = header[EMAIL].to_str().unwrap();
-    if check_email_format(user_email) {
-        let users_collection: Collection = connect_database_collection
-            (create_db_connection(), DB_NAME, USERS_COLLECTION);
-        let user_doc = doc! {EMAIL: user_email};
-        response = match users_collection.find_one(Some(user_doc), None).unwrap() {
-            Some(_) => {
-                json!({RESPONSE: &fetch_conference_for_user(user_email)})
-            }
-            None => {
-                error!("A non registered user tried to access the conference list");
-                json!({RESPONSE: NON_REGISTERED_USER_MESSAGE})
-            }
-        }
-    } else {
-        error!("{}", WRONG_EMAIL_FORMAT);
-        response = json!({RESPONSE: WRONG_EMAIL_FORMAT});
-    }
-    result(Ok(HttpResponse::Ok()
-        .json(response)))
-        .responder()
+    let storage: Arc<dyn Storage> = request.state().storage.clone();
+    let user: ConfCountResult<User> = current_user(request);
+    respond_async(request.state().executor
+        .run(move || user
+            .and_then(|user| fetch_conference_for_user(&*storage, user.email.as_str()))))
 }
 
 #[cfg(test)]
 mod test {
     use actix_web::{HttpResponse, test};
     use actix_web::http::StatusCode;
+    use futures::Future;
 
-    use crate::constants::{EMAIL, TEST_EMAIL_ID};
+    use crate::constants::TEST_EMAIL_ID;
     use crate::request_handlers::conference_handler::{
         fetch_conference_details,
         fetch_registered_conferences};
+    use crate::session::test::sign_in;
+    use crate::state::test::{test_request, test_state};
 
     #[test]
     fn test_fetch_registered_conferences_success() {
-        let response: HttpResponse = test::TestRequest::with_header(
-            EMAIL, TEST_EMAIL_ID)
-            .run(&fetch_registered_conferences)
-            .unwrap();
+        let response: HttpResponse =
+            fetch_registered_conferences(&sign_in(test_request(), TEST_EMAIL_ID)).wait().unwrap();
         assert_eq!(response.status(), StatusCode::OK);
     }
 
     #[test]
     fn test_fetch_conference_details_success() {
-        let response: HttpResponse = test::TestRequest::default()
+        let response: HttpResponse = test::TestRequest::with_state(test_state())
             .run(&fetch_conference_details)
             .unwrap();
         assert_eq!(response.status(), StatusCode::OK);
     }
 
     #[test]
-    #[should_panic]
     fn test_fetch_registered_conferences_failure() {
-        test::TestRequest::default()
+        let response: HttpResponse = test::TestRequest::with_state(test_state())
             .run(&fetch_registered_conferences)
             .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
     }
 }
@@ -1,15 +1,15 @@
-use actix_web::{AsyncResponder, Error, HttpRequest, HttpResponse};
-use actix_web::http::header::HeaderValue;
+use actix_web::{AsyncResponder, Error, HttpRequest, HttpResponse, ResponseError};
 use actix_web::http::HeaderMap;
 use futures::Future;
-use futures::future::result;
-use log::error;
-use serde_json::{json, Value};
+use serde_json::json;
 
-use crate::constants::{EMAIL, RESPONSE, USER_STATUS, WRONG_EMAIL_FORMAT};
-use crate::utils::{check_email_format, validate_user_email};
+use crate::constants::{EMAIL, USER_STATUS, WRONG_EMAIL_FORMAT};
+use crate::error::{ConfCountResult, Validator};
+use crate::session::unix_time;
+use crate::state::AppState;
+use crate::utils::{check_email_format, header_value, send_password_reset};
 
-/// Returns user status
+/// Mails a link resetting the password to the user
 ///
 /// # Argument
 ///
@@ -19,22 +19,29 @@ use crate::utils::{check_email_format, validate_user_email};
 ///
 /// Returns the User Status
 pub fn handle_forgot_password(
-    request: &HttpRequest
+    request: &HttpRequest<AppState>
 ) -> Box<dyn Future<Item=HttpResponse, Error=Error>> {
-    let header: &HeaderMap<HeaderValue> = request.headers();
-    let response_body: Value;
-    let email: &str = header[EMAIL].to_str().unwrap();
-    if check_email_format(email) {
-        response_body = json!({USER_STATUS: validate_user_email(email)});
-    } else {
-        error!("{}", WRONG_EMAIL_FORMAT);
-        response_body = json!({RESPONSE: WRONG_EMAIL_FORMAT});
-    }
-    result(Ok(HttpResponse::Ok()
-        .json(response_body)))
+    let state: AppState = request.state().clone();
+    let headers: HeaderMap = request.headers().clone();
+    request.state().executor
+        .run(move || user_status(&state, &headers))
+        .then(|user_status| Ok::<HttpResponse, Error>(match user_status {
+            Ok(status) => HttpResponse::Ok().json(json!({USER_STATUS: status})),
+            Err(error) => error.error_response(),
+        }))
         .responder()
 }
 
+/// Validates the email given in the headers and mails the reset link to the user
+fn user_status(state: &AppState, headers: &HeaderMap) -> ConfCountResult<&'static str> {
+    let email: &str = header_value(headers, EMAIL)?;
+    Validator::new()
+        .check(check_email_format(email), EMAIL, WRONG_EMAIL_FORMAT)
+        .finish()?;
+    send_password_reset(&*state.storage, &*state.mailer, &state.config, email,
+                        unix_time() as i64)
+}
+
 #[cfg(test)]
 mod test {
     use actix_web::{HttpResponse, test};
@@ -42,20 +49,31 @@ mod test {
 
     use crate::constants::{EMAIL, TEST_EMAIL_ID};
     use crate::request_handlers::forgot_password_handler::handle_forgot_password;
+    use crate::state::test::test_state;
 
     #[test]
     fn test_handle_forgot_password_success() {
-        let response: HttpResponse = test::TestRequest::with_header(EMAIL, TEST_EMAIL_ID)
+        let response: HttpResponse = test::TestRequest::with_state(test_state())
+            .header(EMAIL, TEST_EMAIL_ID)
             .run(&handle_forgot_password)
             .unwrap();
         assert_eq!(response.status(), StatusCode::OK);
     }
 
     #[test]
-    #[should_panic]
+    fn test_handle_forgot_password_unknown_user() {
+        let response: HttpResponse = test::TestRequest::with_state(test_state())
+            .header(EMAIL, "unknown@knoldus.in")
+            .run(&handle_forgot_password)
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
     fn test_handle_forgot_password_failure() {
-        test::TestRequest::default()
+        let response: HttpResponse = test::TestRequest::with_state(test_state())
             .run(&handle_forgot_password)
             .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
     }
 }
\ No newline at end of file
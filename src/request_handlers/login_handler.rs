@@ -1,10 +1,17 @@
-use actix_web::{AsyncResponder, Error, Form, http, HttpRequest, HttpResponse};
+use std::sync::Arc;
+
+use actix_web::{AsyncResponder, Error, Form, http, HttpRequest, HttpResponse, ResponseError};
 use futures::Future;
 use futures::future::result;
 use serde_json::json;
 
-use crate::constants::{RESPONSE, TEXT};
-use crate::utils::validate_user_details;
+use crate::config::AppConfig;
+use crate::constants::{CSRF_TOKEN, RESPONSE, TEXT, TOKEN};
+use crate::error::{ConfCountError, ConfCountResult};
+use crate::session::{csrf_token, issue_token, session_cookie, unix_time};
+use crate::state::AppState;
+use crate::storage::User;
+use crate::utils::authenticate_user;
 
 /// Respond to a particular route
 ///
@@ -16,7 +23,7 @@ use crate::utils::validate_user_details;
 ///
 /// Responds to a particular event trigger
 pub fn load_login_form(
-    _http_request: &HttpRequest
+    _http_request: &HttpRequest<AppState>
 ) -> Box<dyn Future<Item=HttpResponse, Error=Error>> {
     result(Ok(HttpResponse::build(http::StatusCode::OK)
         .content_type(TEXT)
@@ -30,37 +37,76 @@ pub struct UserData {
     password: String,
 }
 
-/// Returns user type
+/// Signs the user in
 ///
 /// # Argument
 ///
+/// * `request` - An HTTP request, whose peer address identifies the client
+///
 /// * `user_data` - User e-mail and password
 ///
 /// # Return
 ///
-/// Returns the User type
+/// Returns the User type with the session token, also set as the session cookie, and the CSRF
+/// token the requests changing state send along with the cookie
 pub fn handle_login(
-    user_data: Form<UserData>
+    (request, user_data): (HttpRequest<AppState>, Form<UserData>)
 ) -> Box<dyn Future<Item=HttpResponse, Error=Error>> {
-    let response = json!({RESPONSE: validate_user_details(
-        user_data.email.as_str(), user_data.password.as_str())
-    });
-    result(Ok(HttpResponse::Ok()
-        .json(response)))
+    let state: AppState = request.state().clone();
+    let config: Arc<AppConfig> = state.config.clone();
+    let client: Option<String> = request.peer_addr().map(|address| address.ip().to_string());
+    let user_data: UserData = user_data.into_inner();
+    request.state().executor
+        .run(move || sign_in(&state, &user_data, client.as_ref().map(String::as_str)))
+        .then(move |user| Ok::<HttpResponse, Error>(match user {
+            Ok(user) => {
+                let token: String = issue_token(&config.session, &user, unix_time());
+                let csrf: String = csrf_token(&config.session, token.as_str());
+                HttpResponse::Ok()
+                    .cookie(session_cookie(&config.session, token.clone()))
+                    .json(json!({RESPONSE: user.user_type, TOKEN: token, CSRF_TOKEN: csrf}))
+            }
+            Err(error) => error.error_response(),
+        }))
         .responder()
 }
 
+/// Authenticates the user unless the account or the client is throttled, counting the wrong
+/// passwords and the unknown emails as failures
+fn sign_in(state: &AppState, user_data: &UserData, client: Option<&str>)
+           -> ConfCountResult<User> {
+    let email: &str = user_data.email.as_str();
+    let now: u64 = unix_time();
+    state.login_throttle.check(email, client, now)?;
+    match authenticate_user(&*state.storage, email, user_data.password.as_str()) {
+        Ok(user) => {
+            state.login_throttle.record_success(email);
+            Ok(user)
+        }
+        Err(error @ ConfCountError::Unauthorized(_)) | Err(error @ ConfCountError::NotFound(_)) => {
+            state.login_throttle.record_failure(email, client, now);
+            Err(error)
+        }
+        Err(error) => Err(error),
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use actix_web::{Form, http, HttpResponse, test};
+    use actix_web::{Form, http, HttpRequest, HttpResponse, test};
+    use actix_web::http::header;
     use futures::future::Future;
 
-    use crate::constants::{PASSWORD, TEST_EMAIL_ID};
+    use crate::constants::TEST_EMAIL_ID;
     use crate::request_handlers::login_handler::{handle_login, load_login_form, UserData};
+    use crate::session::{SESSION_COOKIE, unix_time};
+    use crate::state::AppState;
+    use crate::state::test::{test_request, test_state};
+    use crate::storage::memory::test::TEST_PASSWORD;
 
     #[test]
     fn test_load_login_form_success() {
-        let response: HttpResponse = test::TestRequest::default()
+        let response: HttpResponse = test::TestRequest::with_state(test_state())
             .run(&load_login_form)
             .unwrap();
         assert_eq!(response.status(), http::StatusCode::OK);
@@ -72,8 +118,43 @@ mod test {
         let user_data = UserData
             {
                 email: TEST_EMAIL_ID.to_string(),
-                password: PASSWORD.to_string(),
+                password: TEST_PASSWORD.to_string(),
             };
-        assert!(handle_login(Form(user_data)).wait().is_ok());
+        let response: HttpResponse =
+            handle_login((test_request(), Form(user_data))).wait().unwrap();
+        assert_eq!(response.status(), http::StatusCode::OK);
+        assert!(response.cookies().any(|cookie| cookie.name() == SESSION_COOKIE));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_handle_login_wrong_password()
+    {
+        let user_data = UserData
+            {
+                email: TEST_EMAIL_ID.to_string(),
+                password: "wrong-password".to_string(),
+            };
+        let response: HttpResponse =
+            handle_login((test_request(), Form(user_data))).wait().unwrap();
+        assert_eq!(response.status(), http::StatusCode::UNAUTHORIZED);
+        assert_eq!(response.cookies().count(), 0);
+    }
+
+    #[test]
+    fn test_handle_login_locked_out()
+    {
+        let request: HttpRequest<AppState> = test_request();
+        for _ in 0..10 {
+            request.state().login_throttle.record_failure(TEST_EMAIL_ID, None, unix_time());
+        }
+        let user_data = UserData
+            {
+                email: TEST_EMAIL_ID.to_string(),
+                password: TEST_PASSWORD.to_string(),
+            };
+        let response: HttpResponse = handle_login((request, Form(user_data))).wait().unwrap();
+        assert_eq!(response.status(), http::StatusCode::TOO_MANY_REQUESTS);
+        assert!(response.headers().contains_key(header::RETRY_AFTER));
+        assert_eq!(response.cookies().count(), 0);
+    }
+}
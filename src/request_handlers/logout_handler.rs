@@ -1,38 +1,76 @@
+use std::sync::Arc;
+
 use actix_web::{AsyncResponder, Error, http, HttpRequest, HttpResponse};
 use futures::Future;
-use futures::future::result;
+use log::{error, info};
 
 use crate::constants::TEXT;
+use crate::session::{current_user, expired_session_cookie};
+use crate::state::AppState;
+use crate::storage::{Storage, User};
 
-/// Responds to timeout of the form
+/// Responds to timeout of the form and ends the session
 ///
 /// # Argument
 ///
-/// * '_http_request' - An HTTP request
+/// * 'http_request' - An HTTP request
 ///
 /// # Return
 ///
-/// Displays the timeout page
+/// Displays the timeout page and removes the session cookie, once every session of the signed
+/// in user is revoked
 pub fn handle_logout(
-    _http_request: &HttpRequest
+    http_request: &HttpRequest<AppState>
 ) -> Box<dyn Future<Item=HttpResponse, Error=Error>> {
-    result(Ok(HttpResponse::build(http::StatusCode::OK)
-        .content_type(TEXT)
-        .body(include_str!("../../static/logout.html"))))
+    let storage: Arc<dyn Storage> = http_request.state().storage.clone();
+    let user: Option<User> = current_user(http_request).ok();
+    http_request.state().executor
+        .run(move || match user {
+            Some(user) => {
+                storage.revoke_sessions(user.id.as_str())?;
+                info!("Sessions of user {} revoked at logout", user.id);
+                Ok(())
+            }
+            None => Ok(()),
+        })
+        .then(|revoked| {
+            if let Err(revoke_error) = revoked {
+                error!("Unable to revoke the sessions at logout - {}", revoke_error);
+            }
+            Ok::<HttpResponse, Error>(HttpResponse::build(http::StatusCode::OK)
+                .del_cookie(&expired_session_cookie())
+                .content_type(TEXT)
+                .body(include_str!("../../static/logout.html")))
+        })
         .responder()
 }
 
 #[cfg(test)]
 mod test {
-    use actix_web::{http, HttpResponse, test};
+    use actix_web::{http, HttpRequest, HttpResponse, test};
+    use futures::Future;
 
+    use crate::constants::TEST_EMAIL_ID;
     use crate::request_handlers::logout_handler::handle_logout;
+    use crate::session::test::sign_in;
+    use crate::state::AppState;
+    use crate::state::test::{test_request, test_state};
+    use crate::storage::memory::test::TEST_USER_ID;
 
     #[test]
     fn test_handle_logout_success() {
-        let response: HttpResponse = test::TestRequest::default()
+        let response: HttpResponse = test::TestRequest::with_state(test_state())
             .run(&handle_logout)
             .unwrap();
         assert_eq!(response.status(), http::StatusCode::OK);
     }
+
+    #[test]
+    fn test_handle_logout_revokes_sessions() {
+        let request: HttpRequest<AppState> = sign_in(test_request(), TEST_EMAIL_ID);
+        let response: HttpResponse = handle_logout(&request).wait().unwrap();
+        assert_eq!(response.status(), http::StatusCode::OK);
+        assert_eq!(request.state().storage.user_by_id(TEST_USER_ID).unwrap().unwrap()
+                       .session_version, 1);
+    }
 }
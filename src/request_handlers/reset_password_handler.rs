@@ -1,15 +1,37 @@
-use actix_web::{AsyncResponder, Error, Form, HttpResponse};
+use actix_web::{AsyncResponder, Error, Form, http, HttpRequest, HttpResponse, State};
 use futures::Future;
 use futures::future::result;
-use log::error;
-use serde_json::json;
 
-use crate::constants::{RESPONSE, WRONG_EMAIL_FORMAT, WRONG_PASSWORD_FORMAT};
-use crate::utils::{check_email_format, check_password_format, validate_reset_password};
+use crate::constants::{PASSWORD, TEXT, TOKEN, WRONG_PASSWORD_FORMAT};
+use crate::error::{ConfCountResult, respond_async, Validator};
+use crate::session::unix_time;
+use crate::state::AppState;
+use crate::utils::{check_password_format, validate_reset_password};
+
+static CONFIRM_PASSWORD: &str = "confirm_password";
+static MISSING_TOKEN: &str = "Open the link mailed to you to reset the password";
+
+/// Responds with the form the mailed reset link leads to
+///
+/// # Argument
+///
+/// * `_http_request` - An HTTP request
+///
+/// # Return
+///
+/// Displays the reset password form
+pub fn load_reset_password_form(
+    _http_request: &HttpRequest<AppState>
+) -> Box<dyn Future<Item=HttpResponse, Error=Error>> {
+    result(Ok(HttpResponse::build(http::StatusCode::OK)
+        .content_type(TEXT)
+        .body(include_str!("../../static/reset_password.html"))))
+        .responder()
+}
 
 #[derive(Deserialize)]
 pub struct PasswordData {
-    email: String,
+    token: String,
     password: String,
     confirm_password: String,
 }
@@ -18,52 +40,68 @@ pub struct PasswordData {
 ///
 /// # Argument
 ///
-/// * `password_data` - Email, Password and Confirm Password
+/// * `state` - State of the application
+///
+/// * `password_data` - Reset token, Password and Confirm Password
 ///
 /// # Return
 ///
 /// Returns the password reset message
 pub fn handle_reset_password(
-    password_data: Form<PasswordData>
+    (state, password_data): (State<AppState>, Form<PasswordData>)
 ) -> Box<dyn Future<Item=HttpResponse, Error=Error>> {
-    let response: &str;
-    if check_email_format(password_data.email.as_str()) {
-        if check_password_format(password_data.password.as_str()) &&
-            check_password_format(password_data.confirm_password.as_str()) {
-            response = validate_reset_password(password_data.email.as_str(),
-                                               password_data.password.as_str(),
-                                               password_data.confirm_password.as_str())
-        } else {
-            error!("{}", WRONG_PASSWORD_FORMAT);
-            response = WRONG_PASSWORD_FORMAT;
-        }
-    } else {
-        error!("{}", WRONG_EMAIL_FORMAT);
-        response = WRONG_EMAIL_FORMAT;
-    }
-    result(Ok(HttpResponse::Ok()
-        .json(json!({RESPONSE: response}))))
-        .responder()
+    let app_state: AppState = (*state).clone();
+    let password_data: PasswordData = password_data.into_inner();
+    respond_async(state.executor.run(move || reset_password(&app_state, &password_data)))
+}
+
+/// Validates the form and resets the password, consuming the reset token
+fn reset_password(state: &AppState, password_data: &PasswordData)
+                  -> ConfCountResult<&'static str> {
+    Validator::new()
+        .check(!password_data.token.trim().is_empty(), TOKEN, MISSING_TOKEN)
+        .check(check_password_format(password_data.password.as_str()), PASSWORD,
+               WRONG_PASSWORD_FORMAT)
+        .check(check_password_format(password_data.confirm_password.as_str()), CONFIRM_PASSWORD,
+               WRONG_PASSWORD_FORMAT)
+        .finish()?;
+    validate_reset_password(&*state.storage,
+                            password_data.token.trim(),
+                            password_data.password.as_str(),
+                            password_data.confirm_password.as_str(),
+                            unix_time() as i64)
 }
 
 #[cfg(test)]
 mod test {
-    use actix_web::Form;
+    use actix_web::{Form, http, HttpResponse, test};
     use futures::future::Future;
 
-    use crate::constants::{EMAIL, PASSWORD};
-    use crate::request_handlers::reset_password_handler::{handle_reset_password, PasswordData};
+    use crate::constants::PASSWORD;
+    use crate::request_handlers::reset_password_handler::{handle_reset_password,
+                                                          load_reset_password_form, PasswordData};
+    use crate::state::test::{state_extractor, test_state};
+
+    #[test]
+    fn test_load_reset_password_form_success() {
+        let response: HttpResponse = test::TestRequest::with_state(test_state())
+            .run(&load_reset_password_form)
+            .unwrap();
+        assert_eq!(response.status(), http::StatusCode::OK);
+    }
 
     #[test]
-    fn test_handle_reset_password_wrong_email_format()
+    fn test_handle_reset_password_missing_token()
     {
         let password_data = PasswordData
             {
-                email: EMAIL.to_string(),
+                token: String::new(),
                 password: PASSWORD.to_string(),
                 confirm_password: PASSWORD.to_string(),
             };
-        assert!(handle_reset_password(Form(password_data)).wait().is_ok());
+        let response: HttpResponse =
+            handle_reset_password((state_extractor(), Form(password_data))).wait().unwrap();
+        assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
     }
 
     #[test]
@@ -71,22 +109,26 @@ mod test {
     {
         let password_data = PasswordData
             {
-                email: EMAIL.to_string(),
+                token: "token".to_string(),
                 password: "abcd".to_string(),
                 confirm_password: PASSWORD.to_string(),
             };
-        assert!(handle_reset_password(Form(password_data)).wait().is_ok());
+        let response: HttpResponse =
+            handle_reset_password((state_extractor(), Form(password_data))).wait().unwrap();
+        assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
     }
 
     #[test]
-    fn test_handle_reset_password_success()
+    fn test_handle_reset_password_invalid_token()
     {
         let password_data = PasswordData
             {
-                email: "test@knoldus.in".to_string(),
+                token: "forged".to_string(),
                 password: PASSWORD.to_string(),
                 confirm_password: PASSWORD.to_string(),
             };
-        assert!(handle_reset_password(Form(password_data)).wait().is_ok());
+        let response: HttpResponse =
+            handle_reset_password((state_extractor(), Form(password_data))).wait().unwrap();
+        assert_eq!(response.status(), http::StatusCode::UNAUTHORIZED);
     }
 }
@@ -1,37 +1,25 @@
-use std::fs;
-use std::fs::File;
-use std::fs::OpenOptions;
-use std::io::Write;
-use std::str;
-
-use actix::FinishStream;
-use actix_web::{Either, error, http, HttpMessage, HttpRequest, HttpResponse, multipart};
-use actix_web::AsyncResponder;
-use actix_web::error::MultipartError;
-use actix_web::http::header::ContentDisposition;
-use crypto::digest::Digest;
-use crypto::sha2::Sha256;
-use futures::{future, Future, Stream};
-use futures::future::ok;
+use actix_web::{Error, error, http, HttpRequest, HttpResponse};
+use futures::Future;
 use log::{error, info};
-use serde_json::json;
 use uuid::Uuid;
 
-use crate::constants::{ALREADY_REGISTERED, RESPONSE, S3_UPLOAD_SUCCESS, USER_INFO};
-use crate::user_data_upload_operations::{upload_user_image, write_user_info};
-use crate::utils::{check_password_format, path_exists};
-
-const DB_SUCCESS_MESSAGE: &str = "Successfully Inserted";
-static SUCCESS: &str = "Your data is successfully registered";
-static NOT_SUCCESS: &str = "Registration unsuccessful as some fields are missing";
-static USER_DATA: &str = "user_data";
-static UPLOAD: &str = "upload.jpg";
+use crate::error::{ConfCountError, ConfCountResult, respond_async};
+use crate::executor::Executor;
+use crate::face_matching::quality::check_reference_face;
+use crate::photo_processing::NormalizedPhoto;
+use crate::photo_store::PhotoStore;
+use crate::session::unix_time;
+use crate::signup_form::{read_signup_form, SignupForm};
+use crate::state::AppState;
+use crate::storage::{Storage, User};
+use crate::user_data_upload_operations::{check_email_available, normalize_user_image,
+                                         upload_user_image, write_user_info};
+use crate::utils::{delete_user, expire_unverified_users, send_email_verification};
+
+static SUCCESS: &str =
+    "Your data is successfully registered, please verify your email address through the link \
+     mailed to you";
 static TEXT: &str = "text/html; charset=utf-8";
-static INVALID_FILE: &str = "Unable to read file";
-const USER_NAME: &str = "user_name";
-const EMAIL_ID: &str = "email_id";
-const UPLOAD_IMAGE: &str = "upload_image";
-const PASSWORD: &str = "user_password";
 
 /// Generate_registration_number generates a unique registration number of the user
 ///
@@ -51,156 +39,83 @@ pub fn generate_conference_id(conf_data: String) -> u32 {
     Uuid::new_v5(&Uuid::NAMESPACE_DNS, conf_data.as_bytes()).as_fields().0
 }
 
-/// This function create a file
+/// The handler function response for a user registration event
 ///
 /// # Argument
 ///
-/// * `file_path` - Path of the file
-///
-/// * `append_permission` - Permission to append data on file
+/// * 'http_request' - An HTTP request
 ///
 /// # Return
 ///
-/// This function returns a file
-fn create_file(file_path: &str, append_permission: bool) -> File {
-    OpenOptions::new()
-        .read(true)
-        .write(true)
-        .append(append_permission)
-        .create(true)
-        .open(file_path)
-        .expect(INVALID_FILE)
+/// This function respond to a particular event trigger
+pub fn handle_user_signup(
+    http_request: &HttpRequest<AppState>,
+) -> Box<dyn Future<Item=HttpResponse, Error=Error>> {
+    let uuid: String = generate_user_id().to_string();
+    let state: AppState = http_request.state().clone();
+    let executor: Executor = http_request.state().executor.clone();
+    let registration = read_signup_form(http_request, state.config.photos.max_upload_bytes)
+        .and_then(move |form| executor.run(move || register_user(&state, uuid, form)));
+    respond_async(registration)
 }
 
-/// The handler function response for a user registration event
+/// Registers the user of a signup form and mails the link verifying the email address
+///
+/// The users who never verified theirs in time are deleted first, releasing their email address,
+/// and a taken email address is refused before the photo is processed. The photo is stored once
+/// normalized and checked to show a single clear face, whose index lets the captures recognise
+/// the user.
 ///
 /// # Argument
 ///
-/// * 'http_request' - An HTTP request
+/// * `state` - State of the application
+///
+/// * `uuid` - Id generated for the user
+///
+/// * `form` - Signup form of the request
 ///
 /// # Return
 ///
-/// This function respond to a particular event trigger
-pub fn handle_user_signup(
-    http_request: &HttpRequest,
-) -> Box<dyn Future<Item=HttpResponse, Error=MultipartError>> {
-    let uuid: String = generate_user_id().to_string();
-    http_request
-        .multipart()
-        .from_err()
-        .and_then(move |multipart_item| match multipart_item {
-            multipart::MultipartItem::Field(field) => {
-                let content: ContentDisposition = field.content_disposition().unwrap();
-                let key: &str = content.get_name().unwrap();
-                match key {
-                    USER_NAME | EMAIL_ID => {
-                        let user_data_file_path: &str = USER_DATA;
-                        let mut file: File = create_file(user_data_file_path, true);
-
-                        Either::A(Either::A(
-                            field
-                                .from_err()
-                                .map(move |chunk| {
-                                    writeln!(
-                                        file,
-                                        "{}",
-                                        str::from_utf8(&chunk).expect(
-                                            "Unable to parse bytes")
-                                    )
-                                })
-                                .finish(),
-                        ))
-                    }
-                    PASSWORD => {
-                        let user_data_file_path: &str = USER_DATA;
-                        let mut file: File = create_file(user_data_file_path, true);
-
-                        Either::A(Either::B(
-                            field
-                                .map(move |chunk| {
-                                    let password: &str =
-                                        str::from_utf8(&chunk).expect(
-                                            "Unable to parse bytes");
-                                    if check_password_format(password) {
-                                        let mut hasher: Sha256 = Sha256::new();
-                                        hasher.input_str(password);
-                                        writeln!(file, "{}", hasher.result_str().as_str())
-                                    } else {
-                                        writeln!(file)
-                                    }
-                                })
-                                .finish()
-                        ))
-                    }
-
-                    UPLOAD_IMAGE => {
-                        let user_image: &str = UPLOAD;
-                        let mut file: File = create_file(user_image, false);
-                        Either::B(Either::A(field
-                            .fold(0i64, move |acc, bytes| {
-                                let file_size: Result<i64, MultipartError> = file
-                                    .write(bytes.as_ref())
-                                    .map(|_| acc + bytes.len() as i64)
-                                    .map_err(|error| {
-                                        error!("Writing image in file failed with : {:?}", error);
-                                        error::MultipartError::Payload(error::PayloadError::Io(
-                                            error,
-                                        ))
-                                    });
-                                future::result(file_size)
-                            }).map(|_| ())))
-                    }
-                    _ => Either::B(Either::B(ok(()))),
-                }
-            }
-            multipart::MultipartItem::Nested(_) => Either::B(Either::B(ok(()))),
-        })
-        .finish()
-        .map(move |_| {
-            HttpResponse::Ok().json({
-                if path_exists("upload.jpg") && fs::metadata("upload.jpg").unwrap().len() > 0 {
-                    let db_success: &str =
-                        write_user_info(uuid.clone(), USER_INFO);
-                    fs::remove_file(USER_INFO).expect("User info file not found");
-                    match db_success {
-                        DB_SUCCESS_MESSAGE => {
-                            let s3_success: &str = upload_user_image(
-                                UPLOAD,
-                                uuid.clone(),
-                            );
+/// Returns the success message, or the failure of the registration
+fn register_user(state: &AppState, uuid: String, form: SignupForm)
+                 -> ConfCountResult<&'static str> {
+    let storage: &dyn Storage = &*state.storage;
+    let photos: &dyn PhotoStore = &*state.photo_store;
+    let now: i64 = unix_time() as i64;
+    if let Err(expiry_error) =
+        expire_unverified_users(storage, photos, &*state.face_matcher, &state.config, now) {
+        error!("Unable to delete the unverified users - {}", expiry_error);
+    }
+    check_email_available(storage, form.email.as_str())?;
+    let photo: NormalizedPhoto = normalize_user_image(&state.config.photos, &form.photo)?;
+    check_reference_face(&*state.face_matcher, &state.config.enrollment, photo.bytes())?;
+    let user: User = write_user_info(storage, uuid, &form, now)?;
+    upload_user_image(photos, &photo, user.id.clone())
+        .map_err(|upload_error| discard_registration(state, &user, upload_error))?;
+    state.face_matcher.index_face(user.id.as_str(), photo.bytes())
+        .map_err(|index_error| discard_registration(state, &user, index_error.into()))?;
+    verify_email_address(state, &user)?;
+    info!("{}", "New user registered");
+    Ok(SUCCESS)
+}
 
-                            fs::remove_file(UPLOAD).expect(
-                                "Unable to delete user information");
+/// Mails the verification link to a new user, deleting the account when the mail can not be
+/// delivered so that the user can sign up again
+fn verify_email_address(state: &AppState, user: &User) -> ConfCountResult<()> {
+    send_email_verification(&*state.mailer, &state.config, user).map_err(|mail_error| {
+        error!("Unable to mail the verification link to {} - {}", user.email, mail_error);
+        discard_registration(state, user, mail_error)
+    })
+}
 
-                            match s3_success {
-                                S3_UPLOAD_SUCCESS => {
-                                    info!("{}", "New user registered");
-                                    json!({RESPONSE: SUCCESS})
-                                }
-                                _ => {
-                                    json!({RESPONSE: "Please upload the image"})
-                                }
-                            }
-                        }
-                        ALREADY_REGISTERED => {
-                            fs::remove_file(UPLOAD).expect(
-                                "Unable to delete uer information");
-                            json!({RESPONSE: ALREADY_REGISTERED})
-                        }
-                        _ => {
-                            fs::remove_file(UPLOAD).expect(
-                                "Unable to delete use information");
-                            json!({RESPONSE: NOT_SUCCESS})
-                        }
-                    }
-                } else {
-                    fs::remove_file(USER_INFO).expect("User info file not found");
-                    error!("User is trying to register without image");
-                    json!({RESPONSE: "Please upload the image"})
-                }
-            })
-        })
-        .responder()
+/// Deletes a user whose registration failed midway, passing the failure on
+fn discard_registration(state: &AppState, user: &User, failure: ConfCountError)
+                        -> ConfCountError {
+    if let Err(discard_error) = delete_user(&*state.storage, &*state.photo_store,
+                                            &*state.face_matcher, user.id.clone()) {
+        error!("Unable to discard the registration of {} - {}", user.email, discard_error);
+    }
+    failure
 }
 
 /// The handler function response for a particular route
@@ -212,7 +127,9 @@ pub fn handle_user_signup(
 /// # Return
 ///
 /// This function response to a particular event trigger
-pub fn load_registration_form(_http_request: &HttpRequest) -> Result<HttpResponse, error::Error> {
+pub fn load_registration_form(
+    _http_request: &HttpRequest<AppState>
+) -> Result<HttpResponse, error::Error> {
     Ok(HttpResponse::build(http::StatusCode::OK)
         .content_type(TEXT)
         .body(include_str!("../../static/signup.html")))
@@ -220,37 +137,36 @@ pub fn load_registration_form(_http_request: &HttpRequest) -> Result<HttpRespons
 
 #[cfg(test)]
 pub mod tests {
-    use std::fs::File;
+    use std::fs;
 
     use actix_web::{http, HttpResponse, test};
 
-    use crate::request_handlers::signup_handler::{create_file, generate_conference_id,
-                                                  generate_user_id, load_registration_form};
+    use crate::constants::ALREADY_REGISTERED;
+    use crate::error::ConfCountError;
+    use crate::request_handlers::signup_handler::{generate_conference_id, generate_user_id,
+                                                  load_registration_form, register_user, SUCCESS};
+    use crate::signup_form::{INVALID_IMAGE_FORMAT, UPLOAD_IMAGE};
+    use crate::signup_form::test::test_form;
+    use crate::state::AppState;
+    use crate::state::test::test_state;
+    use crate::storage::{User, UserRepository};
 
     static TRUE: &str = "True";
     static FALSE: &str = "False";
 
+    fn test_image() -> Vec<u8> {
+        fs::read("tests/resources/test.jpg").unwrap()
+    }
+
     #[test]
     fn test_form_response_success() {
-        let response: HttpResponse = test::TestRequest::with_header("content-type",
-                                                                    "text/html")
+        let response: HttpResponse = test::TestRequest::with_state(test_state())
+            .header("content-type", "text/html")
             .run(&load_registration_form)
             .unwrap();
         assert_eq!(response.status(), http::StatusCode::OK);
     }
 
-    #[test]
-    #[should_panic]
-    fn test_create_file_failure() {
-        create_file("", true);
-    }
-
-    #[test]
-    fn test_create_file_success() {
-        let _ = create_file("tests/resources/test_file", true);
-        assert!(File::open("tests/resources/test_file").is_ok());
-    }
-
     fn test_range() -> &'static str {
         match generate_user_id() {
             1..=4294967295 => TRUE,
@@ -269,4 +185,33 @@ pub mod tests {
         assert!(generate_conference_id(
             "1234567".to_string()) > 0)
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_register_user_success() {
+        let state: AppState = test_state();
+        assert_eq!(register_user(&state, "2233445566".to_string(),
+                                 test_form("new@knoldus.in", test_image())), Ok(SUCCESS));
+        let user: User = state.storage.user_by_email("new@knoldus.in").unwrap().unwrap();
+        assert!(user.pending_since.is_some());
+        assert!(state.photo_store.list().unwrap().contains(&"2233445566".to_string()));
+    }
+
+    #[test]
+    fn test_register_user_already_registered() {
+        assert_eq!(register_user(&test_state(), "2233445567".to_string(),
+                                 test_form("test@knoldus.in", test_image())),
+                   Err(ConfCountError::Conflict(ALREADY_REGISTERED.to_string())));
+        assert_eq!(register_user(&test_state(), "2233445567".to_string(),
+                                 test_form("test@knoldus.in", b"not a photo".to_vec())),
+                   Err(ConfCountError::Conflict(ALREADY_REGISTERED.to_string())));
+    }
+
+    #[test]
+    fn test_register_user_invalid_photo() {
+        let state: AppState = test_state();
+        assert_eq!(register_user(&state, "2233445568".to_string(),
+                                 test_form("new@knoldus.in", b"not a photo".to_vec())),
+                   Err(ConfCountError::invalid_field(UPLOAD_IMAGE, INVALID_IMAGE_FORMAT)));
+        assert_eq!(state.storage.user_by_email("new@knoldus.in").unwrap(), None);
+    }
+}
@@ -1,18 +1,17 @@
-use actix_web::{AsyncResponder, Error, HttpRequest, HttpResponse};
-use actix_web::http::{header::HeaderValue, HeaderMap};
+use actix_web::{AsyncResponder, Error, HttpRequest, HttpResponse, ResponseError};
+use actix_web::http::HeaderMap;
 use futures::Future;
-use futures::future::result;
-use log::{error, info};
-use mongodb::{bson, doc};
-use serde_json::json;
-
-use crate::constants::{CONFERENCE_ID, EMAIL, ID, JSON, NAME, RESPONSE, SET, USER_ID,
-                       WRONG_CONFERENCE_ID_FORMAT, WRONG_EMAIL_FORMAT, WRONG_NAME_FORMAT,
-                       WRONG_USER_ID_FORMAT};
+
+use crate::constants::{CONFERENCE_ID, EMAIL, JSON, NAME, WRONG_CONFERENCE_ID_FORMAT,
+                       WRONG_EMAIL_FORMAT, WRONG_NAME_FORMAT};
+use crate::error::{ConfCountResult, respond_async, Validator};
+use crate::session::{current_user, unix_time};
+use crate::state::AppState;
+use crate::storage::User;
 use crate::utils::{check_email_format, check_id_format, check_name_format, fetch_user_details,
-                   update_user_details, validate_user_for_conference};
+                   header_value, update_user_details, validate_user_for_conference};
 
-/// Returns user details
+/// Returns the details of the signed in user
 ///
 /// # Argument
 ///
@@ -22,13 +21,19 @@ use crate::utils::{check_email_format, check_id_format, check_name_format, fetch
 ///
 /// Returns the user details
 pub fn handle_user_details(
-    request: &HttpRequest
+    request: &HttpRequest<AppState>
 ) -> Box<dyn Future<Item=HttpResponse, Error=Error>> {
-    let header: &HeaderMap<HeaderValue> = request.headers();
-    let user_key = doc! {EMAIL: header[EMAIL].to_str().unwrap()};
-    result(Ok(HttpResponse::Ok()
-        .content_type(JSON)
-        .json(fetch_user_details(user_key))))
+    let state: AppState = request.state().clone();
+    let user: ConfCountResult<User> = current_user(request);
+    request.state().executor
+        .run(move || user
+            .and_then(|user| fetch_user_details(&*state.storage, user.email.as_str())))
+        .then(|user_details| Ok::<HttpResponse, Error>(match user_details {
+            Ok(user_details) => HttpResponse::Ok()
+                .content_type(JSON)
+                .json(user_details),
+            Err(error) => error.error_response(),
+        }))
         .responder()
 }
 
@@ -40,42 +45,28 @@ pub fn handle_user_details(
 ///
 /// # Return
 ///
-/// Returns the User Updation status
+/// Returns the status of the updation of the signed in user
 pub fn handle_user_details_updation(
-    request: &HttpRequest
+    request: &HttpRequest<AppState>
 ) -> Box<dyn Future<Item=HttpResponse, Error=Error>> {
-    let header: &HeaderMap<HeaderValue> = request.headers();
-    let response: &str;
-    if check_id_format(header[USER_ID].to_str().unwrap()) {
-        if check_name_format(header[NAME].to_str().unwrap()) {
-            if check_email_format(header[EMAIL].to_str().unwrap()) {
-                let user_id = doc! {ID: header[USER_ID].to_str().unwrap()};
-                let updated_data = doc! {SET: {NAME: header[NAME].to_str().unwrap(), EMAIL: header[EMAIL].to_str().unwrap()}};
-                match update_user_details(user_id, updated_data) {
-                    1 => {
-                        info!("User details updated");
-                        response = "User details updated"
-                    }
-                    _ => {
-                        error!("User ID not found");
-                        response = "User ID not found"
-                    }
-                }
-            } else {
-                error!("{}", WRONG_EMAIL_FORMAT);
-                response = WRONG_EMAIL_FORMAT;
-            }
-        } else {
-            error!("{}", WRONG_NAME_FORMAT);
-            response = WRONG_NAME_FORMAT;
-        }
-    } else {
-        error!("{}", WRONG_USER_ID_FORMAT);
-        response = WRONG_USER_ID_FORMAT;
-    }
-    result(Ok(HttpResponse::Ok()
-        .json(json!({RESPONSE: response}))))
-        .responder()
+    let state: AppState = request.state().clone();
+    let headers: HeaderMap = request.headers().clone();
+    let user: ConfCountResult<User> = current_user(request);
+    respond_async(request.state().executor
+        .run(move || user.and_then(|user| update_details(&state, &user, &headers))))
+}
+
+/// Validates the headers and updates the details of the user
+fn update_details(state: &AppState, user: &User, headers: &HeaderMap)
+                  -> ConfCountResult<&'static str> {
+    let name: &str = header_value(headers, NAME)?;
+    let email: &str = header_value(headers, EMAIL)?;
+    Validator::new()
+        .check(check_name_format(name), NAME, WRONG_NAME_FORMAT)
+        .check(check_email_format(email), EMAIL, WRONG_EMAIL_FORMAT)
+        .finish()?;
+    update_user_details(&*state.storage, &*state.mailer, &state.config, user.id.as_str(), name,
+                        email, unix_time() as i64)
 }
 
 /// Returns register conference response
@@ -86,152 +77,142 @@ pub fn handle_user_details_updation(
 ///
 /// # Return
 ///
-/// Returns the Conference registration status
+/// Returns the status of the registration of the signed in user for the Conference
 pub fn handle_conference_registration(
-    request: &HttpRequest
+    request: &HttpRequest<AppState>
 ) -> Box<dyn Future<Item=HttpResponse, Error=Error>> {
-    let header: &HeaderMap<HeaderValue> = request.headers();
-    let response: &str;
-    let user_email: &str = header[EMAIL].to_str().unwrap();
-    if check_email_format(user_email) {
-        let user_id: &str = header[USER_ID].to_str().unwrap();
-        if check_id_format(user_id) {
-            let conference_id: &str = header[CONFERENCE_ID].to_str().unwrap();
-            if check_id_format(conference_id) {
-                response = validate_user_for_conference(user_email, user_id, conference_id);
-            } else {
-                error!("{}", WRONG_CONFERENCE_ID_FORMAT);
-                response = WRONG_CONFERENCE_ID_FORMAT;
-            }
-        } else {
-            error!("{}", WRONG_USER_ID_FORMAT);
-            response = WRONG_USER_ID_FORMAT;
-        }
-    } else {
-        error!("{}", WRONG_EMAIL_FORMAT);
-        response = WRONG_EMAIL_FORMAT;
-    }
-    result(Ok(HttpResponse::Ok()
-        .content_type(JSON)
-        .json(json!({RESPONSE: response}))))
-        .responder()
+    let state: AppState = request.state().clone();
+    let headers: HeaderMap = request.headers().clone();
+    let user: ConfCountResult<User> = current_user(request);
+    respond_async(request.state().executor
+        .run(move || user.and_then(|user| register_for_conference(&state, &user, &headers))))
+}
+
+/// Validates the headers and registers the user for the conference
+fn register_for_conference(state: &AppState, user: &User, headers: &HeaderMap)
+                           -> ConfCountResult<&'static str> {
+    let conference_id: &str = header_value(headers, CONFERENCE_ID)?;
+    Validator::new()
+        .check(check_id_format(conference_id), CONFERENCE_ID, WRONG_CONFERENCE_ID_FORMAT)
+        .finish()?;
+    validate_user_for_conference(&*state.storage, user.email.as_str(), user.id.as_str(),
+                                 conference_id)
 }
 
 #[cfg(test)]
 mod test {
-    use actix_web::{HttpResponse, test};
+    use actix_web::{Body, HttpResponse, test};
     use actix_web::http::StatusCode;
+    use futures::Future;
 
-    use crate::constants::{ CONFERENCE_ID, EMAIL, NAME, TEST_CONFERENCE_ID, TEST_EMAIL_ID,
-                            TEST_NAME, USER_ID};
-
+    use crate::constants::{ADMIN_EMAIL, CONFERENCE_ID, EMAIL, NAME, TEST_EMAIL_ID, TEST_NAME};
     use crate::request_handlers::user_details_handler::{
         handle_conference_registration, handle_user_details, handle_user_details_updation};
+    use crate::session::test::sign_in;
+    use crate::state::test::{test_request, test_state};
 
     #[test]
     fn test_handle_user_details_success() {
-        let response: HttpResponse = test::TestRequest::with_header(EMAIL, TEST_EMAIL_ID)
-            .run(&handle_user_details)
-            .unwrap();
+        let response: HttpResponse =
+            handle_user_details(&sign_in(test_request(), TEST_EMAIL_ID)).wait().unwrap();
         assert_eq!(response.status(), StatusCode::OK);
+        let body: String = match response.body() {
+            Body::Binary(binary) => String::from_utf8_lossy(binary.as_ref()).into_owned(),
+            _ => panic!("User details are not a binary body"),
+        };
+        assert!(body.contains(TEST_EMAIL_ID));
+        assert!(!body.contains("password"));
     }
 
     #[test]
-    fn test_handle_conference_registration_success() {
-        let response: HttpResponse = test::TestRequest::default()
+    fn test_handle_user_details_signed_out() {
+        let response: HttpResponse = test::TestRequest::with_state(test_state())
             .header(EMAIL, TEST_EMAIL_ID)
-            .header(USER_ID, "1111111111")
-            .header(CONFERENCE_ID, "5544332211")
-            .run(&handle_conference_registration)
+            .run(&handle_user_details)
             .unwrap();
-        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
     }
 
     #[test]
-    fn test_handle_conference_registration_wrong_user_id_format() {
-        let response: HttpResponse = test::TestRequest::default()
-            .header(EMAIL, TEST_EMAIL_ID)
-            .header(USER_ID, "1111")
+    fn test_handle_conference_registration_already_registered() {
+        let request = test::TestRequest::with_state(test_state())
             .header(CONFERENCE_ID, "5544332211")
-            .run(&handle_conference_registration)
-            .unwrap();
-        assert_eq!(response.status(), StatusCode::OK);
+            .finish();
+        let response: HttpResponse =
+            handle_conference_registration(&sign_in(request, TEST_EMAIL_ID)).wait().unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
     }
 
     #[test]
     fn test_handle_conference_registration_wrong_conference_id_format() {
-        let response: HttpResponse = test::TestRequest::default()
-            .header(EMAIL, TEST_EMAIL_ID)
-            .header(USER_ID, "1111")
+        let request = test::TestRequest::with_state(test_state())
             .header(CONFERENCE_ID, "5544")
-            .run(&handle_conference_registration)
-            .unwrap();
-        assert_eq!(response.status(), StatusCode::OK);
+            .finish();
+        let response: HttpResponse =
+            handle_conference_registration(&sign_in(request, TEST_EMAIL_ID)).wait().unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
     }
 
     #[test]
-    fn test_handle_conference_registration_wrong_email_format() {
-        let response: HttpResponse = test::TestRequest::default()
-            .header(EMAIL, "test@knoldus")
-            .header(USER_ID, "1111")
+    fn test_handle_conference_registration_signed_out() {
+        let response: HttpResponse = test::TestRequest::with_state(test_state())
             .header(CONFERENCE_ID, "5544332211")
             .run(&handle_conference_registration)
             .unwrap();
-        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
     }
 
     #[test]
     fn test_handle_user_details_updation_success() {
-        let response: HttpResponse = test::TestRequest::default()
-            .header(USER_ID, "1111111111")
+        let request = test::TestRequest::with_state(test_state())
             .header(EMAIL, TEST_EMAIL_ID)
-            .header(NAME, TEST_NAME)
-            .run(&handle_user_details_updation)
-            .unwrap();
+            .header(NAME, "Tester")
+            .finish();
+        let response: HttpResponse =
+            handle_user_details_updation(&sign_in(request, TEST_EMAIL_ID)).wait().unwrap();
         assert_eq!(response.status(), StatusCode::OK);
     }
 
     #[test]
-    fn test_handle_user_details_updation_failure() {
-        let response: HttpResponse = test::TestRequest::default()
-            .header(USER_ID, TEST_CONFERENCE_ID)
+    fn test_handle_user_details_updation_email_taken() {
+        let request = test::TestRequest::with_state(test_state())
+            .header(EMAIL, ADMIN_EMAIL)
+            .header(NAME, TEST_NAME)
+            .finish();
+        let response: HttpResponse =
+            handle_user_details_updation(&sign_in(request, TEST_EMAIL_ID)).wait().unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+    }
+
+    #[test]
+    fn test_handle_user_details_updation_signed_out() {
+        let response: HttpResponse = test::TestRequest::with_state(test_state())
             .header(EMAIL, TEST_EMAIL_ID)
             .header(NAME, TEST_NAME)
             .run(&handle_user_details_updation)
             .unwrap();
-        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
     }
 
     #[test]
     fn test_handle_user_details_update_wrong_email_format() {
-        let response: HttpResponse = test::TestRequest::default()
-            .header(USER_ID, "1111111111")
+        let request = test::TestRequest::with_state(test_state())
             .header(EMAIL, "test@knoldus")
             .header(NAME, TEST_NAME)
-            .run(&handle_user_details_updation)
-            .unwrap();
-        assert_eq!(response.status(), StatusCode::OK);
+            .finish();
+        let response: HttpResponse =
+            handle_user_details_updation(&sign_in(request, TEST_EMAIL_ID)).wait().unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
     }
 
     #[test]
     fn test_handle_user_details_update_wrong_name_format() {
-        let response: HttpResponse = test::TestRequest::default()
-            .header(USER_ID, "1111111111")
+        let request = test::TestRequest::with_state(test_state())
             .header(EMAIL, "test@knoldus.in")
             .header(NAME, "T")
-            .run(&handle_user_details_updation)
-            .unwrap();
-        assert_eq!(response.status(), StatusCode::OK);
-    }
-
-    #[test]
-    fn test_handle_user_details_update_wrong_id_format() {
-        let response: HttpResponse = test::TestRequest::default()
-            .header(USER_ID, "111")
-            .header(EMAIL, "test@knoldus")
-            .header(NAME, TEST_NAME)
-            .run(&handle_user_details_updation)
-            .unwrap();
-        assert_eq!(response.status(), StatusCode::OK);
+            .finish();
+        let response: HttpResponse =
+            handle_user_details_updation(&sign_in(request, TEST_EMAIL_ID)).wait().unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
     }
-}
\ No newline at end of file
+}
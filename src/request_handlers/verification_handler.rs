@@ -0,0 +1,78 @@
+use actix_web::{Error, HttpRequest, HttpResponse};
+use futures::Future;
+
+use crate::constants::TOKEN;
+use crate::error::{ConfCountResult, respond_async, Validator};
+use crate::session::unix_time;
+use crate::state::AppState;
+use crate::utils::verify_email;
+
+static MISSING_TOKEN: &str = "Open the link mailed to you at signup to verify your email address";
+
+/// Verifies the email address of a new user from the link mailed at signup
+///
+/// # Argument
+///
+/// * `request` - An HTTP Request, carrying the token of the link in its query
+///
+/// # Return
+///
+/// Returns the verification message
+pub fn handle_email_verification(
+    request: &HttpRequest<AppState>
+) -> Box<dyn Future<Item=HttpResponse, Error=Error>> {
+    let state: AppState = request.state().clone();
+    let token: String = request.query().get(TOKEN).cloned().unwrap_or_default();
+    respond_async(request.state().executor.run(move || verify_link(&state, token.trim())))
+}
+
+/// Validates the token of the link and verifies the email address of its user
+fn verify_link(state: &AppState, token: &str) -> ConfCountResult<&'static str> {
+    Validator::new()
+        .check(!token.is_empty(), TOKEN, MISSING_TOKEN)
+        .finish()?;
+    verify_email(&*state.storage, &state.config, token, unix_time())
+}
+
+#[cfg(test)]
+mod test {
+    use actix_web::{http, HttpResponse, test};
+
+    use crate::constants::TEST_EMAIL_ID;
+    use crate::request_handlers::verification_handler::handle_email_verification;
+    use crate::session::{issue_verification_token, unix_time};
+    use crate::state::AppState;
+    use crate::state::test::test_state;
+    use crate::storage::memory::test::TEST_USER_ID;
+
+    #[test]
+    fn test_handle_email_verification_success() {
+        let state: AppState = test_state();
+        let token: String = issue_verification_token(&state.config.session, TEST_USER_ID,
+                                                     TEST_EMAIL_ID, unix_time() + 60);
+        let response: HttpResponse =
+            test::TestRequest::with_state(state)
+                .uri(&format!("/verify-email?token={}", token))
+                .run(&handle_email_verification)
+                .unwrap();
+        assert_eq!(response.status(), http::StatusCode::OK);
+    }
+
+    #[test]
+    fn test_handle_email_verification_invalid_token() {
+        let response: HttpResponse = test::TestRequest::with_state(test_state())
+            .uri("/verify-email?token=forged")
+            .run(&handle_email_verification)
+            .unwrap();
+        assert_eq!(response.status(), http::StatusCode::UNAUTHORIZED);
+    }
+
+    #[test]
+    fn test_handle_email_verification_missing_token() {
+        let response: HttpResponse = test::TestRequest::with_state(test_state())
+            .uri("/verify-email")
+            .run(&handle_email_verification)
+            .unwrap();
+        assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+    }
+}
@@ -1,62 +1,157 @@
-use log::info;
-use mongodb::coll::Collection;
+use std::collections::HashMap;
 
-use crate::connection::create_rekognition_connection;
+use log::{error, info};
+
+use crate::capture::Capture;
 use crate::constants::{COLLECTION_EMPTY, PROCESS_COMPLETE};
-use crate::db_operations::{fetch_user_ids, update_db};
+use crate::db_operations::{attendance_rules, AttendanceRules, fetch_user_ids, update_db};
+use crate::error::{ConfCountError, ConfCountResult};
+use crate::face_matching::FaceMatch;
+use crate::state::AppState;
+use crate::storage::AttendanceMatch;
 
 /// Returns the response based on the bucket values
 ///
 /// # Arguments
 ///
-/// * `conference_collection` - Instance of the Database collection
+/// * `state` - State of the application, holding the storage, face matcher and mailer
+///
+/// * `conference_id` - Id of the conference
+///
+/// * `capture` - Image of the audience
 ///
 /// # Return
 ///
-/// Returns response message for the db update process
-pub fn fetch_response(conference_collection: Collection, conference_id: &str) -> &'static str
+/// Returns response message for the db update process, or the failure of the face search in
+/// which case no participant is updated. The failure for one participant is logged without
+/// stopping the others.
+pub fn fetch_response(
+    state: &AppState,
+    conference_id: &str,
+    capture: &Capture,
+) -> ConfCountResult<&'static str>
 {
-    let res: Vec<String> = fetch_user_ids(&conference_collection, conference_id);
+    let res: Vec<String> = fetch_user_ids(&*state.storage, conference_id)?;
     match res.len() {
-        0 => COLLECTION_EMPTY,
+        0 => Err(ConfCountError::NotFound(COLLECTION_EMPTY.to_string())),
         _ => {
+            let mut rules: AttendanceRules = attendance_rules(state, conference_id)?;
+            rules.add_capture(capture);
+            let matches: HashMap<String, FaceMatch> =
+                state.face_matcher.search_faces(capture.image_path.as_str())?;
+            info!("Recognised {} users in capture {} of {}, seen from a similarity of {}",
+                  matches.len(), capture.id, capture.image_path, rules.similarity_threshold);
             for user_id in res {
-                let response: &str =
-                    update_db(&create_rekognition_connection(),
-                              &conference_collection,
-                              user_id.clone());
-                info!("{} - {}", response, user_id.clone());
+                let face_match: Option<&FaceMatch> = matches.get(&user_id);
+                let attendance_match: AttendanceMatch = AttendanceMatch {
+                    similarity: face_match.map_or(0.0, |face_match| face_match.similarity),
+                    face_box: face_match.and_then(|face_match| face_match.face_box),
+                    capture_id: capture.id.clone(),
+                    captured_at: capture.captured_at,
+                };
+                match update_db(state, conference_id, user_id.clone(), &attendance_match,
+                                &rules, capture) {
+                    Ok(response) => info!("{} - {}", response, user_id),
+                    Err(update_error) => error!("{} - {}", update_error, user_id),
+                }
             }
-            PROCESS_COMPLETE
+            Ok(PROCESS_COMPLETE)
         }
     }
 }
 
 #[cfg(test)]
 mod test {
-    use mongodb::coll::Collection;
-
-    use crate::connection::{connect_database_collection, create_db_connection};
-    use crate::constants::{COLLECTION_EMPTY, PROCESS_COMPLETE};
+    use crate::capture::Capture;
+    use crate::constants::{ABSENT, COLLECTION_EMPTY, PROCESS_COMPLETE};
+    use crate::error::ConfCountError;
     use crate::response_service::fetch_response;
+    use crate::state::AppState;
+    use crate::state::test::test_state;
+    use crate::storage::{AttendanceMatch, AttendanceRepository, ConferenceRepository,
+                         ConferenceUpdate, Observation, PresenceRule, Registration, Review,
+                         ReviewRepository};
+    use crate::storage::memory::test::{TEST_CONFERENCE_ID, TEST_USER_ID};
+
+    static CAPTURED_IMAGE: &str = "tests/resources/test.jpg";
 
-    static TEST_DB_NAME: &str = "Conf_Count";
-    static TEST_EMPTY_CONFERENCE_COLLECTION: &str = "conf";
-    static TEST_CONFERENCE_COLLECTION: &str = "conference_details";
+    fn capture(image_path: &str) -> Capture {
+        Capture::new(image_path.to_string())
+    }
 
     #[test]
     fn test_fetch_response_empty()
     {
-        let conference_collection: Collection = connect_database_collection
-            (create_db_connection(), TEST_DB_NAME, TEST_EMPTY_CONFERENCE_COLLECTION);
-        assert_eq!(COLLECTION_EMPTY, fetch_response(conference_collection, "test_conference"))
+        assert_eq!(Err(ConfCountError::NotFound(COLLECTION_EMPTY.to_string())),
+                   fetch_response(&test_state(), "test_conference", &capture(CAPTURED_IMAGE)))
     }
 
     #[test]
     fn test_fetch_response_success()
     {
-        let conference_collection: Collection = connect_database_collection
-            (create_db_connection(), TEST_DB_NAME, TEST_CONFERENCE_COLLECTION);
-        assert_eq!(PROCESS_COMPLETE, fetch_response(conference_collection, "5544332211"))
+        let state: AppState = test_state();
+        let capture: Capture = capture(CAPTURED_IMAGE);
+        assert_eq!(Ok(PROCESS_COMPLETE), fetch_response(&state, TEST_CONFERENCE_ID, &capture));
+        let registration: Registration =
+            state.storage.registration(TEST_USER_ID, TEST_CONFERENCE_ID).unwrap().unwrap();
+        assert_ne!(registration.status, ABSENT);
+        assert_eq!(registration.attendance_match, Some(AttendanceMatch {
+            similarity: 98.5,
+            face_box: None,
+            capture_id: capture.id,
+            captured_at: capture.captured_at,
+        }));
+    }
+
+    #[test]
+    fn test_fetch_response_conference_threshold()
+    {
+        let state: AppState = test_state();
+        state.storage.update_conference(TEST_CONFERENCE_ID, &ConferenceUpdate {
+            conference_date: "2019-06-24".to_string(),
+            conference_address1: "test_address_1".to_string(),
+            conference_address2: "test_address_2".to_string(),
+            conference_address3: "test_address_3".to_string(),
+            similarity_threshold: Some(99.0),
+            presence_rule: PresenceRule::AnyCapture,
+        }).unwrap();
+        assert_eq!(Ok(PROCESS_COMPLETE),
+                   fetch_response(&state, TEST_CONFERENCE_ID, &capture(CAPTURED_IMAGE)));
+        let registration: Registration =
+            state.storage.registration(TEST_USER_ID, TEST_CONFERENCE_ID).unwrap().unwrap();
+        assert_eq!(registration.status, ABSENT);
+        assert_eq!(registration.attendance_match.unwrap().similarity, 98.5);
+        let reviews: Vec<Review> = state.storage.reviews_for_conference(TEST_CONFERENCE_ID)
+            .unwrap();
+        assert_eq!(reviews.len(), 1);
+        assert_eq!(reviews[0].face_key, None);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_fetch_response_rerun_capture()
+    {
+        let state: AppState = test_state();
+        let first: Capture = capture(CAPTURED_IMAGE);
+        let rerun: Capture = Capture { captured_at: first.captured_at + 60, ..first.clone() };
+        fetch_response(&state, TEST_CONFERENCE_ID, &first).unwrap();
+        fetch_response(&state, TEST_CONFERENCE_ID, &rerun).unwrap();
+        fetch_response(&state, TEST_CONFERENCE_ID, &capture(CAPTURED_IMAGE)).unwrap();
+        let observations: Vec<Observation> =
+            state.storage.observations(TEST_USER_ID, TEST_CONFERENCE_ID).unwrap();
+        assert_eq!(observations.len(), 2);
+        assert!(observations.iter()
+            .any(|observation| observation.attendance_match.capture_id == first.id
+                && observation.attendance_match.captured_at == first.captured_at));
+    }
+
+    #[test]
+    fn test_fetch_response_search_failure()
+    {
+        let state: AppState = test_state();
+        assert!(matches!(fetch_response(&state, TEST_CONFERENCE_ID,
+                                        &capture("tests/resources/missing.jpg")),
+                         Err(ConfCountError::FaceMatching(_))));
+        assert_eq!(state.storage.registration(TEST_USER_ID, TEST_CONFERENCE_ID).unwrap().unwrap()
+                       .status, ABSENT)
+    }
+}
@@ -0,0 +1,441 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use actix_web::{HttpRequest, Result};
+use actix_web::http::{Cookie, header};
+use actix_web::middleware::{Middleware, Started};
+use cookie::SameSite;
+use crypto::hmac::Hmac;
+use crypto::mac::Mac;
+use crypto::sha2::Sha256;
+use crypto::util::fixed_time_eq;
+use futures::Future;
+use log::warn;
+
+use crate::config::SessionConfig;
+use crate::error::{ConfCountError, ConfCountResult};
+use crate::state::AppState;
+use crate::storage::User;
+
+/// Cookie carrying the session token to the browsers
+pub static SESSION_COOKIE: &str = "conf_count_session";
+/// Header echoing the CSRF token of a session cookie on the requests changing state
+pub static CSRF_HEADER: &str = "X-CSRF-Token";
+static BEARER_PREFIX: &str = "Bearer ";
+static NOT_SIGNED_IN: &str = "Sign in to access this resource";
+
+/// User signed in through the session of the request, set by `Authentication`
+#[derive(Clone, Debug, PartialEq)]
+pub struct CurrentUser(pub User);
+
+/// Seconds elapsed since the UNIX epoch
+pub fn unix_time() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_secs()).unwrap_or(0)
+}
+
+/// Purposes of the signed tokens, so that a token issued for one is refused for the others
+static SESSION_PURPOSE: &str = "session";
+static VERIFICATION_PURPOSE: &str = "verify-email";
+static CSRF_PURPOSE: &str = "csrf";
+
+/// Signs a payload for a purpose with the secret of the sessions
+fn sign(config: &SessionConfig, purpose: &str, payload: &str) -> String {
+    let mut mac: Hmac<Sha256> = Hmac::new(Sha256::new(), config.secret.as_bytes());
+    mac.input(purpose.as_bytes());
+    mac.input(b":");
+    mac.input(payload.as_bytes());
+    mac.result().code().iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Issues a token of the form `<user id>.<expiry>.<signature>` for a purpose
+fn issue_signed(config: &SessionConfig, purpose: &str, user_id: &str, expiry: u64) -> String {
+    let payload: String = format!("{}.{}", user_id, expiry);
+    let signature: String = sign(config, purpose, payload.as_str());
+    format!("{}.{}", payload, signature)
+}
+
+/// Verifies the signature of a token for a purpose and its expiry, returning the user id
+fn verify_signed(config: &SessionConfig, purpose: &str, token: &str, now: u64)
+                 -> Option<String> {
+    let mut token_parts = token.rsplitn(2, '.');
+    let signature: &str = token_parts.next()?;
+    let payload: &str = token_parts.next()?;
+    if !fixed_time_eq(sign(config, purpose, payload).as_bytes(), signature.as_bytes()) {
+        return None;
+    }
+    let mut payload_parts = payload.rsplitn(2, '.');
+    let expiry: u64 = payload_parts.next()?.parse().ok()?;
+    let user_id: &str = payload_parts.next()?;
+    if expiry > now {
+        Some(user_id.to_string())
+    } else {
+        None
+    }
+}
+
+/// Issues a session token for a user
+///
+/// # Arguments
+///
+/// * `config` - Session settings
+///
+/// * `user` - Signed in user, whose session version the token carries
+///
+/// * `now` - Current UNIX time in seconds
+///
+/// # Return
+///
+/// Returns the token of the form `<user id>:<session version>.<expiry>.<signature>`
+pub fn issue_token(config: &SessionConfig, user: &User, now: u64) -> String {
+    let session: String = format!("{}:{}", user.id, user.session_version);
+    issue_signed(config, SESSION_PURPOSE, session.as_str(), now + config.ttl_minutes * 60)
+}
+
+/// Verifies the signature and the expiry of a session token
+///
+/// # Arguments
+///
+/// * `config` - Session settings
+///
+/// * `token` - Token presented by the client
+///
+/// * `now` - Current UNIX time in seconds
+///
+/// # Return
+///
+/// Returns the id of the signed in user and the session version the token was issued for, if
+/// the token is valid
+pub fn verify_token(config: &SessionConfig, token: &str, now: u64) -> Option<(String, i64)> {
+    let session: String = verify_signed(config, SESSION_PURPOSE, token, now)?;
+    let mut session_parts = session.rsplitn(2, ':');
+    let session_version: i64 = session_parts.next()?.parse().ok()?;
+    let user_id: &str = session_parts.next()?;
+    Some((user_id.to_string(), session_version))
+}
+
+/// Purpose of the token verifying an email address, so that a link mailed to one address is
+/// refused once the user moved on to another
+fn verification_purpose(email: &str) -> String {
+    format!("{}:{}", VERIFICATION_PURPOSE, email)
+}
+
+/// Issues the token of the link verifying the email address of a user
+///
+/// # Arguments
+///
+/// * `config` - Session settings, whose secret signs the token
+///
+/// * `user_id` - Id of the user
+///
+/// * `email` - Email address the link verifies
+///
+/// * `expiry` - UNIX time in seconds from which the token is refused
+///
+/// # Return
+///
+/// Returns the token of the form `<user id>.<expiry>.<signature>`
+pub fn issue_verification_token(config: &SessionConfig, user_id: &str, email: &str, expiry: u64)
+                                -> String {
+    issue_signed(config, verification_purpose(email).as_str(), user_id, expiry)
+}
+
+/// Reads the user id of the token of a verification link, without verifying the token
+///
+/// # Arguments
+///
+/// * `token` - Token of the opened link
+///
+/// # Return
+///
+/// Returns the id of the user the token claims to verify
+pub fn verification_token_user(token: &str) -> Option<&str> {
+    token.rsplitn(3, '.').nth(2)
+}
+
+/// Verifies the signature and the expiry of the token of a verification link
+///
+/// # Arguments
+///
+/// * `config` - Session settings
+///
+/// * `token` - Token of the opened link
+///
+/// * `email` - Email address the token should verify
+///
+/// * `now` - Current UNIX time in seconds
+///
+/// # Return
+///
+/// Returns the id of the user to verify, if the token is valid for the email address
+pub fn verify_verification_token(config: &SessionConfig, token: &str, email: &str, now: u64)
+                                 -> Option<String> {
+    verify_signed(config, verification_purpose(email).as_str(), token, now)
+}
+
+/// Derives the CSRF token of a session, which the requests changing state send in the
+/// `X-CSRF-Token` header along with the session cookie
+///
+/// # Arguments
+///
+/// * `config` - Session settings
+///
+/// * `session_token` - Token issued at login
+///
+/// # Return
+///
+/// Returns the CSRF token of the session
+pub fn csrf_token(config: &SessionConfig, session_token: &str) -> String {
+    sign(config, CSRF_PURPOSE, session_token)
+}
+
+/// Builds the cookie handing a session token to a browser
+///
+/// # Arguments
+///
+/// * `config` - Session settings
+///
+/// * `token` - Token issued at login
+///
+/// # Return
+///
+/// Returns the session cookie, hidden from the scripts of the pages and from other sites
+pub fn session_cookie(config: &SessionConfig, token: String) -> Cookie<'static> {
+    Cookie::build(SESSION_COOKIE, token)
+        .path("/")
+        .http_only(true)
+        .secure(config.secure_cookie)
+        .same_site(SameSite::Strict)
+        .finish()
+}
+
+/// Builds the cookie ending the session of a browser
+pub fn expired_session_cookie() -> Cookie<'static> {
+    Cookie::build(SESSION_COOKIE, "").path("/").finish()
+}
+
+/// Reads the session token from the `Authorization: Bearer` header, or else from the cookie,
+/// which the requests changing state only carry along with the CSRF token of the session
+fn session_token(request: &HttpRequest<AppState>) -> Option<String> {
+    let bearer: Option<String> = request.headers().get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| value.starts_with(BEARER_PREFIX))
+        .map(|value| value[BEARER_PREFIX.len()..].trim().to_string());
+    if bearer.is_some() {
+        return bearer;
+    }
+    let token: String = request.cookie(SESSION_COOKIE)?.value().to_string();
+    let expected: String = csrf_token(&request.state().config.session, token.as_str());
+    let csrf_matches: bool = request.headers().get(CSRF_HEADER)
+        .map_or(false, |value| fixed_time_eq(value.as_bytes(), expected.as_bytes()));
+    if request.method().is_safe() || csrf_matches {
+        Some(token)
+    } else {
+        warn!("Rejected a session cookie without its CSRF token for {}", request.path());
+        None
+    }
+}
+
+/// Resolves the user signed in through the session token of every request
+pub struct Authentication;
+
+impl Middleware<AppState> for Authentication {
+    fn start(&self, request: &HttpRequest<AppState>) -> Result<Started> {
+        let (user_id, session_version): (String, i64) = match session_token(request) {
+            Some(token) => match verify_token(&request.state().config.session, &token,
+                                              unix_time()) {
+                Some(session) => session,
+                None => {
+                    warn!("Rejected an invalid or expired session token for {}", request.path());
+                    return Ok(Started::Done);
+                }
+            },
+            None => return Ok(Started::Done),
+        };
+        let storage = request.state().storage.clone();
+        let lookup = request.state().executor
+            .run(move || storage.user_by_id(user_id.as_str()).map_err(ConfCountError::from));
+        let request: HttpRequest<AppState> = request.clone();
+        Ok(Started::Future(Box::new(lookup
+            .map(move |user| {
+                match user {
+                    Some(user) if user.session_version == session_version => {
+                        request.extensions_mut().insert(CurrentUser(user));
+                    }
+                    Some(_) => warn!("Rejected a revoked session token for {}", request.path()),
+                    None => warn!("Rejected the session of a user who no longer exists"),
+                }
+                None
+            })
+            .from_err())))
+    }
+}
+
+/// Returns the user signed in through the session of the request
+///
+/// # Argument
+///
+/// * `request` - An HTTP Request
+///
+/// # Return
+///
+/// Returns the signed in user, or an unauthorized error without a valid session
+pub fn current_user(request: &HttpRequest<AppState>) -> ConfCountResult<User> {
+    request.extensions().get::<CurrentUser>()
+        .map(|current_user| current_user.0.clone())
+        .ok_or_else(|| ConfCountError::Unauthorized(NOT_SIGNED_IN.to_string()))
+}
+
+#[cfg(test)]
+pub mod test {
+    use actix_web::HttpRequest;
+    use actix_web::http::{header, Method};
+    use actix_web::middleware::{Middleware, Started};
+    use actix_web::test::TestRequest;
+    use futures::Future;
+
+    use crate::config::test::test_config;
+    use crate::config::SessionConfig;
+    use crate::constants::TEST_EMAIL_ID;
+    use crate::session::{Authentication, CSRF_HEADER, csrf_token, current_user, CurrentUser,
+                         issue_token,
+                         issue_verification_token, SESSION_COOKIE, session_cookie, unix_time,
+                         verification_token_user, verify_token, verify_verification_token};
+    use crate::state::AppState;
+    use crate::state::test::test_state;
+    use crate::storage::User;
+
+    /// Signs a request of the tests in as the user with the given email
+    pub fn sign_in(request: HttpRequest<AppState>, email: &str) -> HttpRequest<AppState> {
+        let user: User = request.state().storage.user_by_email(email).unwrap().unwrap();
+        request.extensions_mut().insert(CurrentUser(user));
+        request
+    }
+
+    fn session_config() -> SessionConfig {
+        test_config().session
+    }
+
+    fn test_user() -> User {
+        test_state().storage.user_by_email(TEST_EMAIL_ID).unwrap().unwrap()
+    }
+
+    #[test]
+    fn test_verify_token_success() {
+        let token: String = issue_token(&session_config(), &test_user(), 1000);
+        assert_eq!(verify_token(&session_config(), &token, 1001),
+                   Some(("1111111111".to_string(), 0)));
+    }
+
+    #[test]
+    fn test_verify_token_expired() {
+        let token: String = issue_token(&session_config(), &test_user(), 1000);
+        assert_eq!(verify_token(&session_config(), &token, 1000 + 720 * 60), None);
+    }
+
+    #[test]
+    fn test_verify_token_tampered() {
+        let token: String = issue_token(&session_config(), &test_user(), 1000);
+        let forged: String = token.replacen("1111111111", "2222222222", 1);
+        assert_eq!(verify_token(&session_config(), &forged, 1001), None);
+        let upgraded: String = token.replacen(":0.", ":1.", 1);
+        assert_eq!(verify_token(&session_config(), &upgraded, 1001), None);
+        let mut other_secret: SessionConfig = session_config();
+        other_secret.secret = "another-secret-of-at-least-32-chars".to_string();
+        assert_eq!(verify_token(&other_secret, &token, 1001), None);
+        assert_eq!(verify_token(&session_config(), "garbage", 1001), None);
+    }
+
+    #[test]
+    fn test_verification_token_purpose() {
+        let verification: String =
+            issue_verification_token(&session_config(), "1111111111", TEST_EMAIL_ID, 2000);
+        assert_eq!(verification_token_user(&verification), Some("1111111111"));
+        assert_eq!(verify_verification_token(&session_config(), &verification, TEST_EMAIL_ID, 1999),
+                   Some("1111111111".to_string()));
+        assert_eq!(verify_verification_token(&session_config(), &verification, TEST_EMAIL_ID, 2000),
+                   None);
+        assert_eq!(verify_token(&session_config(), &verification, 1999), None);
+        let session: String = issue_token(&session_config(), &test_user(), 1000);
+        assert_eq!(verify_verification_token(&session_config(), &session, TEST_EMAIL_ID, 1001),
+                   None);
+    }
+
+    #[test]
+    fn test_verification_token_email() {
+        let verification: String =
+            issue_verification_token(&session_config(), "1111111111", TEST_EMAIL_ID, 2000);
+        assert_eq!(verify_verification_token(&session_config(), &verification,
+                                             "moved@knoldus.in", 1999), None);
+    }
+
+    #[test]
+    fn test_authentication_bearer_success() {
+        let token: String = issue_token(&session_config(), &test_user(), unix_time());
+        let request: HttpRequest<AppState> = TestRequest::with_state(test_state())
+            .header(header::AUTHORIZATION, format!("Bearer {}", token))
+            .finish();
+        match Authentication.start(&request) {
+            Ok(Started::Future(future)) => assert!(future.wait().is_ok()),
+            _ => panic!("the session should be resolved"),
+        }
+        assert_eq!(current_user(&request).unwrap().email, TEST_EMAIL_ID);
+    }
+
+    #[test]
+    fn test_authentication_cookie_success() {
+        let token: String = issue_token(&session_config(), &test_user(), unix_time());
+        let request: HttpRequest<AppState> = TestRequest::with_state(test_state())
+            .cookie(session_cookie(&session_config(), token))
+            .finish();
+        match Authentication.start(&request) {
+            Ok(Started::Future(future)) => assert!(future.wait().is_ok()),
+            _ => panic!("the session should be resolved"),
+        }
+        assert_eq!(current_user(&request).unwrap().id, "1111111111");
+    }
+
+    #[test]
+    fn test_authentication_cookie_csrf() {
+        let token: String = issue_token(&session_config(), &test_user(), unix_time());
+        let forged: HttpRequest<AppState> = TestRequest::with_state(test_state())
+            .method(Method::POST)
+            .cookie(session_cookie(&session_config(), token.clone()))
+            .header(CSRF_HEADER, "forged")
+            .finish();
+        assert!(matches!(Authentication.start(&forged), Ok(Started::Done)));
+        assert!(current_user(&forged).is_err());
+        let request: HttpRequest<AppState> = TestRequest::with_state(test_state())
+            .method(Method::POST)
+            .cookie(session_cookie(&session_config(), token.clone()))
+            .header(CSRF_HEADER, csrf_token(&session_config(), token.as_str()))
+            .finish();
+        match Authentication.start(&request) {
+            Ok(Started::Future(future)) => assert!(future.wait().is_ok()),
+            _ => panic!("the session should be resolved"),
+        }
+        assert_eq!(current_user(&request).unwrap().id, "1111111111");
+    }
+
+    #[test]
+    fn test_authentication_revoked_session() {
+        let state: AppState = test_state();
+        let token: String = issue_token(&session_config(), &test_user(), unix_time());
+        state.storage.revoke_sessions("1111111111").unwrap();
+        let request: HttpRequest<AppState> = TestRequest::with_state(state)
+            .header(header::AUTHORIZATION, format!("Bearer {}", token))
+            .finish();
+        match Authentication.start(&request) {
+            Ok(Started::Future(future)) => assert!(future.wait().is_ok()),
+            _ => panic!("the session should be looked up"),
+        }
+        assert!(current_user(&request).is_err());
+    }
+
+    #[test]
+    fn test_authentication_without_session() {
+        let request: HttpRequest<AppState> = TestRequest::with_state(test_state())
+            .header(header::COOKIE, format!("{}=forged", SESSION_COOKIE))
+            .finish();
+        assert!(matches!(Authentication.start(&request), Ok(Started::Done)));
+        assert!(current_user(&request).is_err());
+    }
+}
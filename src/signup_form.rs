@@ -0,0 +1,363 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use actix_web::{HttpMessage, HttpRequest};
+use actix_web::dev::Payload;
+use actix_web::http::header::ContentDisposition;
+use actix_web::multipart::MultipartItem;
+use futures::{future, Future, Stream};
+use log::error;
+use uuid::Uuid;
+
+use crate::constants::{WRONG_EMAIL_FORMAT, WRONG_NAME_FORMAT, WRONG_PASSWORD_FORMAT};
+use crate::error::{ConfCountError, ConfCountResult, Validator};
+use crate::utils::{check_email_format, check_name_format, check_password_format};
+
+pub const USER_NAME: &str = "user_name";
+pub const EMAIL_ID: &str = "email_id";
+pub const PASSWORD: &str = "user_password";
+pub const UPLOAD_IMAGE: &str = "upload_image";
+pub static IMAGE_MISSING: &str = "Please upload the image";
+pub static INVALID_IMAGE_FORMAT: &str = "Only JPEG and PNG photos are accepted";
+pub static PHOTO_TOO_LARGE: &str = "The photo is too large, please upload a smaller one";
+static SPOOL_PREFIX: &str = "conf_count-signup-";
+/// Photos up to this size stay in memory while the form is read, larger ones are spooled to a
+/// temporary file
+const IN_MEMORY_PHOTO_BYTES: usize = 1024 * 1024;
+/// Longest accepted text field, the longer ones are rejected as invalid
+const MAX_TEXT_FIELD_BYTES: usize = 1024;
+
+/// Form submitted by a user signing up, read from a single request
+#[derive(Debug)]
+pub struct SignupForm {
+    pub name: String,
+    pub email: String,
+    /// Password as typed by the user, hashed only when the user is stored
+    pub password: String,
+    /// Photo as uploaded, validated only once normalized
+    pub photo: Photo,
+}
+
+/// Photo uploaded at signup
+#[derive(Debug)]
+pub enum Photo {
+    Memory(Vec<u8>),
+    /// Photo too large to be buffered while the form is read, loaded whole once normalized
+    Spooled(SpooledFile),
+}
+
+/// Temporary file owned by a single request, removed once dropped
+#[derive(Debug)]
+pub struct SpooledFile {
+    path: PathBuf,
+    file: File,
+    length: u64,
+}
+
+impl SpooledFile {
+    /// Creates an empty file with a unique name in the temporary directory
+    fn create() -> io::Result<Self> {
+        let path: PathBuf =
+            env::temp_dir().join(format!("{}{}", SPOOL_PREFIX, Uuid::new_v4().to_simple()));
+        let file: File = OpenOptions::new().write(true).create_new(true).open(&path)?;
+        Ok(SpooledFile { path, file, length: 0 })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for SpooledFile {
+    fn drop(&mut self) {
+        if let Err(remove_error) = fs::remove_file(&self.path) {
+            error!("Unable to remove the spooled photo {:?} - {}", self.path, remove_error);
+        }
+    }
+}
+
+impl Photo {
+    /// Appends a chunk of the upload, moving the photo to a temporary file once it outgrows
+    /// the memory
+    fn append(&mut self, chunk: &[u8]) -> io::Result<()> {
+        let spilled: Option<SpooledFile> = match self {
+            Photo::Memory(bytes) if bytes.len() + chunk.len() > IN_MEMORY_PHOTO_BYTES => {
+                let mut spooled: SpooledFile = SpooledFile::create()?;
+                spooled.file.write_all(bytes)?;
+                spooled.length = bytes.len() as u64;
+                Some(spooled)
+            }
+            _ => None,
+        };
+        if let Some(spooled) = spilled {
+            *self = Photo::Spooled(spooled);
+        }
+        match self {
+            Photo::Memory(bytes) => {
+                bytes.extend_from_slice(chunk);
+                Ok(())
+            }
+            Photo::Spooled(spooled) => {
+                spooled.file.write_all(chunk)?;
+                spooled.length += chunk.len() as u64;
+                Ok(())
+            }
+        }
+    }
+
+    /// Size of the photo in bytes
+    pub fn len(&self) -> u64 {
+        match self {
+            Photo::Memory(bytes) => bytes.len() as u64,
+            Photo::Spooled(spooled) => spooled.length,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Reads the whole photo, loading a spooled one in memory
+    ///
+    /// # Return
+    ///
+    /// Returns the bytes of the photo, borrowed when it is kept in memory
+    pub fn bytes(&self) -> io::Result<Cow<[u8]>> {
+        match self {
+            Photo::Memory(bytes) => Ok(Cow::Borrowed(bytes.as_slice())),
+            Photo::Spooled(spooled) => fs::read(&spooled.path).map(Cow::Owned),
+        }
+    }
+}
+
+/// Destination of the chunks of a multipart field
+enum FieldSink {
+    Text(&'static str, Vec<u8>),
+    /// Photo along with the most bytes accepted
+    Photo(Photo, u64),
+    Ignored,
+}
+
+impl FieldSink {
+    fn for_field(name: Option<&str>, max_photo_bytes: u64) -> Self {
+        match name {
+            Some(USER_NAME) => FieldSink::Text(USER_NAME, Vec::new()),
+            Some(EMAIL_ID) => FieldSink::Text(EMAIL_ID, Vec::new()),
+            Some(PASSWORD) => FieldSink::Text(PASSWORD, Vec::new()),
+            Some(UPLOAD_IMAGE) => FieldSink::Photo(Photo::Memory(Vec::new()), max_photo_bytes),
+            _ => FieldSink::Ignored,
+        }
+    }
+
+    /// Keeps a chunk of the field, text beyond the longest accepted being dropped
+    ///
+    /// # Arguments
+    ///
+    /// * `chunk` - Next bytes of the field
+    ///
+    /// # Return
+    ///
+    /// Returns an error once the photo exceeds the most bytes accepted, or can not be spooled
+    fn write(&mut self, chunk: &[u8]) -> ConfCountResult<()> {
+        match self {
+            FieldSink::Text(_, text) => {
+                let room: usize = (MAX_TEXT_FIELD_BYTES + 1).saturating_sub(text.len());
+                text.extend_from_slice(&chunk[..room.min(chunk.len())]);
+                Ok(())
+            }
+            FieldSink::Photo(photo, max_photo_bytes) => {
+                if photo.len() + chunk.len() as u64 > *max_photo_bytes {
+                    return Err(ConfCountError::invalid_field(UPLOAD_IMAGE, PHOTO_TOO_LARGE));
+                }
+                photo.append(chunk).map_err(|spool_error| {
+                    ConfCountError::Internal(format!("Unable to spool a photo: {}", spool_error))
+                })
+            }
+            FieldSink::Ignored => Ok(()),
+        }
+    }
+}
+
+/// Fields of a signup form as they are read from the request
+#[derive(Debug, Default)]
+struct SignupParts {
+    texts: HashMap<&'static str, Vec<u8>>,
+    photo: Option<Photo>,
+}
+
+impl SignupParts {
+    fn add(&mut self, sink: FieldSink) {
+        match sink {
+            FieldSink::Text(name, text) => {
+                self.texts.insert(name, text);
+            }
+            FieldSink::Photo(photo, _) => self.photo = Some(photo),
+            FieldSink::Ignored => {}
+        }
+    }
+
+    /// Text of a field, empty when missing, too long or not UTF-8
+    fn text(&self, name: &str) -> String {
+        self.texts.get(name)
+            .filter(|text| text.len() <= MAX_TEXT_FIELD_BYTES)
+            .and_then(|text| String::from_utf8(text.clone()).ok())
+            .unwrap_or_default()
+    }
+
+    /// Validates the fields
+    ///
+    /// # Return
+    ///
+    /// Returns the form, or every invalid field
+    fn into_form(self) -> ConfCountResult<SignupForm> {
+        let name: String = self.text(USER_NAME).trim().to_string();
+        let email: String = self.text(EMAIL_ID).trim().to_string();
+        let password: String = self.text(PASSWORD);
+        Validator::new()
+            .check(check_name_format(&name), USER_NAME, WRONG_NAME_FORMAT)
+            .check(check_email_format(&email), EMAIL_ID, WRONG_EMAIL_FORMAT)
+            .check(check_password_format(&password), PASSWORD, WRONG_PASSWORD_FORMAT)
+            .check(self.photo.as_ref().map_or(false, |photo| !photo.is_empty()), UPLOAD_IMAGE,
+                   IMAGE_MISSING)
+            .finish()?;
+        match self.photo {
+            Some(photo) => Ok(SignupForm { name, email, password, photo }),
+            None => Err(ConfCountError::invalid_field(UPLOAD_IMAGE, IMAGE_MISSING)),
+        }
+    }
+}
+
+fn invalid_multipart<E: ToString>(multipart_error: E) -> ConfCountError {
+    ConfCountError::Validation(multipart_error.to_string())
+}
+
+/// Reads a field of the multipart form into the parts read so far
+fn read_item(
+    mut parts: SignupParts,
+    item: MultipartItem<Payload>,
+    max_photo_bytes: u64,
+) -> Box<dyn Future<Item=SignupParts, Error=ConfCountError>> {
+    match item {
+        MultipartItem::Field(field) => {
+            let disposition: Option<ContentDisposition> = field.content_disposition();
+            let sink: FieldSink = FieldSink::for_field(
+                disposition.as_ref().and_then(ContentDisposition::get_name), max_photo_bytes);
+            Box::new(field
+                .map_err(invalid_multipart)
+                .fold(sink, |mut sink, chunk| sink.write(chunk.as_ref()).map(|_| sink))
+                .map(move |sink| {
+                    parts.add(sink);
+                    parts
+                }))
+        }
+        MultipartItem::Nested(_) => Box::new(future::ok(parts)),
+    }
+}
+
+/// Reads the multipart signup form of a request
+///
+/// # Argument
+///
+/// * `request` - An HTTP request carrying the form
+///
+/// * `max_photo_bytes` - Largest photo accepted, the reading stops past it
+///
+/// # Return
+///
+/// Returns the future form, or every invalid field of it
+pub fn read_signup_form<S: 'static>(
+    request: &HttpRequest<S>,
+    max_photo_bytes: u64,
+) -> Box<dyn Future<Item=SignupForm, Error=ConfCountError>> {
+    Box::new(request
+        .multipart()
+        .map_err(invalid_multipart)
+        .fold(SignupParts::default(), move |parts, item| read_item(parts, item, max_photo_bytes))
+        .and_then(SignupParts::into_form))
+}
+
+#[cfg(test)]
+pub mod test {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use crate::constants::{WRONG_EMAIL_FORMAT, WRONG_PASSWORD_FORMAT};
+    use crate::error::{ConfCountError, FieldError};
+    use crate::signup_form::{EMAIL_ID, FieldSink, IMAGE_MISSING, IN_MEMORY_PHOTO_BYTES,
+                             MAX_TEXT_FIELD_BYTES, PASSWORD, Photo, PHOTO_TOO_LARGE, SignupForm,
+                             SignupParts, UPLOAD_IMAGE, USER_NAME};
+
+    pub fn test_form(email: &str, photo: Vec<u8>) -> SignupForm {
+        SignupForm {
+            name: "testName".to_string(),
+            email: email.to_string(),
+            password: "test_password".to_string(),
+            photo: Photo::Memory(photo),
+        }
+    }
+
+    fn parts(fields: &[(&str, &str)]) -> SignupParts {
+        let mut parts: SignupParts = SignupParts::default();
+        for (name, value) in fields {
+            let mut sink: FieldSink = FieldSink::for_field(Some(*name), 1024);
+            sink.write(value.as_bytes()).unwrap();
+            parts.add(sink);
+        }
+        parts
+    }
+
+    #[test]
+    fn test_into_form_success() {
+        let form: SignupForm = parts(&[(USER_NAME, " testName "), (EMAIL_ID, "test@knoldus.in"),
+                                       (PASSWORD, "test_password"), (UPLOAD_IMAGE, "photo")])
+            .into_form()
+            .unwrap();
+        assert_eq!(form.name, "testName");
+        assert_eq!(form.email, "test@knoldus.in");
+        assert_eq!(form.password, "test_password");
+        assert_eq!(form.photo.bytes().unwrap().as_ref(), b"photo");
+    }
+
+    #[test]
+    fn test_into_form_reports_every_invalid_field() {
+        let long_password: String = "a".repeat(MAX_TEXT_FIELD_BYTES + 10);
+        assert_eq!(
+            parts(&[(USER_NAME, "testName"), (EMAIL_ID, "not an email"),
+                    (PASSWORD, &long_password)]).into_form().unwrap_err(),
+            ConfCountError::InvalidFields(vec![
+                FieldError::new(EMAIL_ID, WRONG_EMAIL_FORMAT),
+                FieldError::new(PASSWORD, WRONG_PASSWORD_FORMAT),
+                FieldError::new(UPLOAD_IMAGE, IMAGE_MISSING),
+            ]));
+    }
+
+    #[test]
+    fn test_photo_over_limit_rejected() {
+        let mut sink: FieldSink = FieldSink::for_field(Some(UPLOAD_IMAGE), 4);
+        assert_eq!(sink.write(b"1234"), Ok(()));
+        assert_eq!(sink.write(b"5"),
+                   Err(ConfCountError::invalid_field(UPLOAD_IMAGE, PHOTO_TOO_LARGE)));
+    }
+
+    #[test]
+    fn test_large_photo_spooled_and_removed() {
+        let mut photo: Photo = Photo::Memory(Vec::new());
+        photo.append(&vec![1u8; IN_MEMORY_PHOTO_BYTES]).unwrap();
+        if let Photo::Spooled(_) = photo {
+            panic!("photo spooled before outgrowing the memory");
+        }
+        photo.append(&[2u8, 3u8]).unwrap();
+        let path: PathBuf = match &photo {
+            Photo::Spooled(spooled) => spooled.path().to_path_buf(),
+            Photo::Memory(_) => panic!("photo kept in memory"),
+        };
+        assert_eq!(photo.len(), IN_MEMORY_PHOTO_BYTES as u64 + 2);
+        assert_eq!(&photo.bytes().unwrap()[IN_MEMORY_PHOTO_BYTES..], &[2u8, 3u8]);
+        drop(photo);
+        assert!(fs::metadata(path).is_err());
+    }
+}
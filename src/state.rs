@@ -0,0 +1,114 @@
+use std::sync::Arc;
+
+use crate::capture::CaptureDevice;
+use crate::config::AppConfig;
+use crate::executor::Executor;
+use crate::face_matching::FaceMatcher;
+use crate::login_throttle::LoginThrottle;
+use crate::mail::Mailer;
+use crate::photo_store::PhotoStore;
+use crate::storage::Storage;
+
+/// State shared by every handler of the application
+#[derive(Clone)]
+pub struct AppState {
+    pub config: Arc<AppConfig>,
+    pub storage: Arc<dyn Storage>,
+    pub face_matcher: Arc<dyn FaceMatcher>,
+    pub capture_device: Arc<dyn CaptureDevice>,
+    pub mailer: Arc<dyn Mailer>,
+    pub photo_store: Arc<dyn PhotoStore>,
+    pub executor: Executor,
+    pub login_throttle: Arc<LoginThrottle>,
+}
+
+impl AppState {
+    /// Creates the application state
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - Validated configuration of the application
+    ///
+    /// * `storage` - Storage backend selected in the configuration
+    ///
+    /// * `face_matcher` - Face matcher selected in the configuration
+    ///
+    /// * `capture_device` - Camera selected in the configuration
+    ///
+    /// * `mailer` - Mail transport selected in the configuration
+    ///
+    /// * `photo_store` - Photo store selected in the configuration
+    ///
+    /// * `executor` - Executor of the blocking work of the handlers
+    ///
+    /// # Return
+    ///
+    /// Returns the state handed to every worker, with the login counters they share
+    pub fn new(
+        config: AppConfig,
+        storage: Arc<dyn Storage>,
+        face_matcher: Arc<dyn FaceMatcher>,
+        capture_device: Arc<dyn CaptureDevice>,
+        mailer: Arc<dyn Mailer>,
+        photo_store: Arc<dyn PhotoStore>,
+        executor: Executor,
+    ) -> AppState {
+        let login_throttle: LoginThrottle = LoginThrottle::new(config.login_throttle.clone());
+        AppState {
+            config: Arc::new(config),
+            storage,
+            face_matcher,
+            capture_device,
+            mailer,
+            photo_store,
+            executor,
+            login_throttle: Arc::new(login_throttle),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use std::sync::Arc;
+
+    use actix_web::{FromRequest, HttpRequest, State, test};
+
+    use crate::capture::replay::ReplayDevice;
+    use crate::config::test::test_config;
+    use crate::executor::Executor;
+    use crate::face_matching::fixtures::FixtureMatcher;
+    use crate::mail::memory::MemoryOutbox;
+    use crate::photo_store::local::LocalPhotoStore;
+    use crate::state::AppState;
+    use crate::storage::memory::test::test_storage;
+
+    /// State shared by the tests of the crate, backed by the in-memory storage, the
+    /// fixtures face matcher, the replay camera, the in-memory outbox and a local photo store,
+    /// running the blocking work inline
+    pub fn test_state() -> AppState {
+        let matcher: FixtureMatcher =
+            FixtureMatcher::from_file(&test_config().matching.fixtures_path).unwrap();
+        let camera: ReplayDevice =
+            ReplayDevice::from_directory(&test_config().camera.replay_directory).unwrap();
+        let photos: LocalPhotoStore =
+            LocalPhotoStore::new(&test_config().photos.directory).unwrap();
+        AppState::new(test_config(), Arc::new(test_storage()), Arc::new(matcher),
+                      Arc::new(camera), Arc::new(MemoryOutbox::default()), Arc::new(photos),
+                      Executor::Inline)
+    }
+
+    /// Request carrying the test state
+    pub fn test_request() -> HttpRequest<AppState> {
+        test::TestRequest::with_state(test_state()).finish()
+    }
+
+    /// State extractor as handed to the handlers
+    pub fn state_extractor() -> State<AppState> {
+        State::from_request(&test_request(), &())
+    }
+
+    #[test]
+    fn test_new_state_success() {
+        assert_eq!(*test_state().config, test_config());
+    }
+}
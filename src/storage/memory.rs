@@ -0,0 +1,632 @@
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use crate::constants::USER;
+use crate::storage::{AttendanceMatch, AttendanceRepository, Conference, ConferenceRepository,
+                     ConferenceUpdate, Observation, ObservationRepository, Registration,
+                     ResetToken, ResetTokenRepository, Review, ReviewRepository, StorageError,
+                     StorageResult, User, UserRepository};
+
+static POISONED: &str = "In-memory storage lock poisoned";
+
+/// Storage keeping everything in the memory of the process, lost on restart
+#[derive(Default)]
+pub struct MemoryStorage {
+    users: RwLock<Vec<User>>,
+    conferences: RwLock<Vec<Conference>>,
+    registrations: RwLock<Vec<Registration>>,
+    observations: RwLock<Vec<Observation>>,
+    reviews: RwLock<Vec<Review>>,
+    reset_tokens: RwLock<Vec<ResetToken>>,
+}
+
+/// Acquires a lock for reading
+fn read<T>(lock: &RwLock<T>) -> StorageResult<RwLockReadGuard<T>> {
+    lock.read().map_err(|_| StorageError(POISONED.to_string()))
+}
+
+/// Acquires a lock for writing
+fn write<T>(lock: &RwLock<T>) -> StorageResult<RwLockWriteGuard<T>> {
+    lock.write().map_err(|_| StorageError(POISONED.to_string()))
+}
+
+impl UserRepository for MemoryStorage {
+    fn user_by_email(&self, email: &str) -> StorageResult<Option<User>> {
+        Ok(read(&self.users)?.iter().find(|user| user.email == email).cloned())
+    }
+
+    fn user_by_id(&self, user_id: &str) -> StorageResult<Option<User>> {
+        Ok(read(&self.users)?.iter().find(|user| user.id == user_id).cloned())
+    }
+
+    fn insert_user(&self, user: &User) -> StorageResult<()> {
+        write(&self.users)?.push(user.clone());
+        Ok(())
+    }
+
+    fn update_user(&self, user_id: &str, name: &str, email: &str) -> StorageResult<bool> {
+        Ok(match write(&self.users)?.iter_mut().find(|user| user.id == user_id) {
+            Some(user) if user.name != name || user.email != email => {
+                user.name = name.to_string();
+                user.email = email.to_string();
+                true
+            }
+            _ => false,
+        })
+    }
+
+    fn update_password(&self, email: &str, password: &str) -> StorageResult<bool> {
+        Ok(match write(&self.users)?.iter_mut().find(|user| user.email == email) {
+            Some(user) => {
+                user.password = password.to_string();
+                true
+            }
+            None => false,
+        })
+    }
+
+    fn revoke_sessions(&self, user_id: &str) -> StorageResult<bool> {
+        Ok(match write(&self.users)?.iter_mut().find(|user| user.id == user_id) {
+            Some(user) => {
+                user.session_version += 1;
+                true
+            }
+            None => false,
+        })
+    }
+
+    fn delete_user(&self, user_id: &str) -> StorageResult<bool> {
+        let mut users = write(&self.users)?;
+        let count: usize = users.len();
+        users.retain(|user| user.id != user_id || user.user_type != USER);
+        Ok(users.len() != count)
+    }
+
+    fn verify_user(&self, user_id: &str) -> StorageResult<bool> {
+        Ok(match write(&self.users)?.iter_mut().find(|user| user.id == user_id) {
+            Some(user) if user.pending_since.is_some() => {
+                user.pending_since = None;
+                true
+            }
+            _ => false,
+        })
+    }
+
+    fn set_pending_email(&self, user_id: &str, email: &str) -> StorageResult<bool> {
+        Ok(match write(&self.users)?.iter_mut().find(|user| user.id == user_id) {
+            Some(user) => {
+                user.pending_email = Some(email.to_string());
+                true
+            }
+            None => false,
+        })
+    }
+
+    fn confirm_pending_email(&self, user_id: &str, email: &str) -> StorageResult<bool> {
+        Ok(match write(&self.users)?.iter_mut().find(|user| user.id == user_id) {
+            Some(user) if user.pending_email.as_ref().map(String::as_str) == Some(email) => {
+                user.email = email.to_string();
+                user.pending_email = None;
+                true
+            }
+            _ => false,
+        })
+    }
+
+    fn delete_unverified_users(&self, signed_up_before: i64) -> StorageResult<Vec<String>> {
+        let mut users = write(&self.users)?;
+        let stale = |user: &User| user.user_type == USER
+            && user.pending_since.map_or(false, |since| since < signed_up_before);
+        let stale_ids: Vec<String> = users.iter()
+            .filter(|user| stale(user))
+            .map(|user| user.id.clone())
+            .collect();
+        users.retain(|user| !stale(user));
+        Ok(stale_ids)
+    }
+}
+
+impl ConferenceRepository for MemoryStorage {
+    fn conference(&self, conference_id: &str) -> StorageResult<Option<Conference>> {
+        Ok(read(&self.conferences)?.iter()
+            .find(|conference| conference.id == conference_id)
+            .cloned())
+    }
+
+    fn conferences_by_status(&self, status: &str) -> StorageResult<Vec<Conference>> {
+        Ok(read(&self.conferences)?.iter()
+            .filter(|conference| conference.status == status)
+            .cloned()
+            .collect())
+    }
+
+    fn conferences_between(&self, first_date: &str, last_date: &str)
+                           -> StorageResult<Vec<Conference>> {
+        Ok(read(&self.conferences)?.iter()
+            .filter(|conference| conference.conference_date.as_str() >= first_date
+                && conference.conference_date.as_str() <= last_date)
+            .cloned()
+            .collect())
+    }
+
+    fn insert_conference(&self, conference: &Conference) -> StorageResult<()> {
+        write(&self.conferences)?.push(conference.clone());
+        Ok(())
+    }
+
+    fn update_conference(&self, conference_id: &str, update: &ConferenceUpdate)
+                         -> StorageResult<bool> {
+        Ok(match write(&self.conferences)?.iter_mut()
+            .find(|conference| conference.id == conference_id) {
+            Some(conference) => {
+                conference.conference_date = update.conference_date.clone();
+                conference.conference_address1 = update.conference_address1.clone();
+                conference.conference_address2 = update.conference_address2.clone();
+                conference.conference_address3 = update.conference_address3.clone();
+                conference.similarity_threshold = update.similarity_threshold;
+                conference.presence_rule = update.presence_rule;
+                true
+            }
+            None => false,
+        })
+    }
+
+    fn set_conference_status(&self, conference_id: &str, status: &str) -> StorageResult<bool> {
+        Ok(match write(&self.conferences)?.iter_mut()
+            .find(|conference| conference.id == conference_id) {
+            Some(conference) => {
+                conference.status = status.to_string();
+                true
+            }
+            None => false,
+        })
+    }
+
+    fn delete_conference(&self, conference_id: &str) -> StorageResult<bool> {
+        let mut conferences = write(&self.conferences)?;
+        let count: usize = conferences.len();
+        conferences.retain(|conference| conference.id != conference_id);
+        Ok(conferences.len() != count)
+    }
+}
+
+impl AttendanceRepository for MemoryStorage {
+    fn registration(&self, user_id: &str, conference_id: &str)
+                    -> StorageResult<Option<Registration>> {
+        Ok(read(&self.registrations)?.iter()
+            .find(|registration| registration.user_id == user_id
+                && registration.conference_id == conference_id)
+            .cloned())
+    }
+
+    fn insert_registration(&self, registration: &Registration) -> StorageResult<()> {
+        write(&self.registrations)?.push(registration.clone());
+        Ok(())
+    }
+
+    fn registrations_for_conference(&self, conference_id: &str)
+                                    -> StorageResult<Vec<Registration>> {
+        Ok(read(&self.registrations)?.iter()
+            .filter(|registration| registration.conference_id == conference_id)
+            .cloned()
+            .collect())
+    }
+
+    fn registrations_for_email(&self, email: &str) -> StorageResult<Vec<Registration>> {
+        Ok(read(&self.registrations)?.iter()
+            .filter(|registration| registration.email == email)
+            .cloned()
+            .collect())
+    }
+
+    fn set_attendance_status(&self, user_id: &str, conference_id: &str, status: &str)
+                             -> StorageResult<bool> {
+        Ok(match write(&self.registrations)?.iter_mut()
+            .find(|registration| registration.user_id == user_id
+                && registration.conference_id == conference_id) {
+            Some(registration) if registration.status != status => {
+                registration.status = status.to_string();
+                true
+            }
+            _ => false,
+        })
+    }
+
+    fn set_attendance_match(&self, user_id: &str, conference_id: &str,
+                            attendance_match: &AttendanceMatch) -> StorageResult<bool> {
+        Ok(match write(&self.registrations)?.iter_mut()
+            .find(|registration| registration.user_id == user_id
+                && registration.conference_id == conference_id) {
+            Some(registration) => {
+                registration.attendance_match = Some(attendance_match.clone());
+                true
+            }
+            None => false,
+        })
+    }
+
+    fn set_notified(&self, user_id: &str, conference_id: &str) -> StorageResult<bool> {
+        Ok(match write(&self.registrations)?.iter_mut()
+            .find(|registration| registration.user_id == user_id
+                && registration.conference_id == conference_id) {
+            Some(registration) if !registration.notified => {
+                registration.notified = true;
+                true
+            }
+            _ => false,
+        })
+    }
+
+    fn delete_registrations(&self, user_id: &str) -> StorageResult<()> {
+        write(&self.registrations)?.retain(|registration| registration.user_id != user_id);
+        Ok(())
+    }
+}
+
+impl ObservationRepository for MemoryStorage {
+    fn save_observation(&self, observation: &Observation) -> StorageResult<()> {
+        let mut observations = write(&self.observations)?;
+        observations.retain(|saved| saved.user_id != observation.user_id
+            || saved.conference_id != observation.conference_id
+            || saved.attendance_match.capture_id != observation.attendance_match.capture_id);
+        observations.push(observation.clone());
+        Ok(())
+    }
+
+    fn observations(&self, user_id: &str, conference_id: &str)
+                    -> StorageResult<Vec<Observation>> {
+        Ok(read(&self.observations)?.iter()
+            .filter(|observation| observation.user_id == user_id
+                && observation.conference_id == conference_id)
+            .cloned()
+            .collect())
+    }
+
+    fn observations_for_conference(&self, conference_id: &str)
+                                   -> StorageResult<Vec<Observation>> {
+        Ok(read(&self.observations)?.iter()
+            .filter(|observation| observation.conference_id == conference_id)
+            .cloned()
+            .collect())
+    }
+
+    fn delete_observations(&self, user_id: &str) -> StorageResult<()> {
+        write(&self.observations)?.retain(|observation| observation.user_id != user_id);
+        Ok(())
+    }
+}
+
+impl ReviewRepository for MemoryStorage {
+    fn review(&self, review_id: &str) -> StorageResult<Option<Review>> {
+        Ok(read(&self.reviews)?.iter().find(|review| review.id == review_id).cloned())
+    }
+
+    fn reviews_for_conference(&self, conference_id: &str) -> StorageResult<Vec<Review>> {
+        Ok(read(&self.reviews)?.iter()
+            .filter(|review| review.conference_id == conference_id)
+            .cloned()
+            .collect())
+    }
+
+    fn reviews_for_user(&self, user_id: &str) -> StorageResult<Vec<Review>> {
+        Ok(read(&self.reviews)?.iter()
+            .filter(|review| review.user_id == user_id)
+            .cloned()
+            .collect())
+    }
+
+    fn insert_review(&self, review: &Review) -> StorageResult<()> {
+        write(&self.reviews)?.push(review.clone());
+        Ok(())
+    }
+
+    fn delete_review(&self, review_id: &str) -> StorageResult<bool> {
+        let mut reviews = write(&self.reviews)?;
+        let count: usize = reviews.len();
+        reviews.retain(|review| review.id != review_id);
+        Ok(reviews.len() != count)
+    }
+}
+
+impl ResetTokenRepository for MemoryStorage {
+    fn insert_reset_token(&self, token: &ResetToken) -> StorageResult<()> {
+        write(&self.reset_tokens)?.push(token.clone());
+        Ok(())
+    }
+
+    fn take_reset_token(&self, token_hash: &str) -> StorageResult<Option<ResetToken>> {
+        let mut tokens = write(&self.reset_tokens)?;
+        Ok(tokens.iter()
+            .position(|token| token.token_hash == token_hash)
+            .map(|position| tokens.remove(position)))
+    }
+
+    fn delete_reset_tokens(&self, user_id: &str) -> StorageResult<()> {
+        write(&self.reset_tokens)?.retain(|token| token.user_id != user_id);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use crypto::digest::Digest;
+    use crypto::sha2::Sha256;
+
+    use crate::constants::{ABSENT, ADMIN, ADMIN_EMAIL, COMPLETED, NOT_COMPLETED, TEST_EMAIL_ID,
+                           TEST_NAME, USER};
+    use crate::face_matching::FaceBox;
+    use crate::storage::{AttendanceMatch, AttendanceRepository, Conference, ConferenceRepository,
+                         ConferenceUpdate, Observation, ObservationRepository, PresenceRule,
+                         Registration, ResetToken, ResetTokenRepository, Review, ReviewRepository,
+                         User, UserRepository};
+    use crate::storage::memory::MemoryStorage;
+
+    pub static TEST_USER_ID: &str = "1111111111";
+    pub static TEST_ADMIN_ID: &str = "2222222222";
+    pub static TEST_CONFERENCE_ID: &str = "5544332211";
+    pub static TEST_PASSWORD: &str = "test_password";
+    pub static AMBIGUOUS_EMAIL: &str = "test.testing@knoldus.in";
+
+    fn hash(password: &str) -> String {
+        let mut hasher: Sha256 = Sha256::new();
+        hasher.input_str(password);
+        hasher.result_str()
+    }
+
+    /// Storage holding the records the tests of the crate rely on
+    pub fn test_storage() -> MemoryStorage {
+        let storage: MemoryStorage = MemoryStorage::default();
+        let users: [(&str, &str, &str, &str); 3] = [
+            (TEST_USER_ID, TEST_EMAIL_ID, TEST_PASSWORD, USER),
+            (TEST_ADMIN_ID, ADMIN_EMAIL, ADMIN, ADMIN),
+            ("3333333333", AMBIGUOUS_EMAIL, TEST_PASSWORD, "visitor"),
+        ];
+        for (id, email, password, user_type) in users.iter() {
+            storage.insert_user(&User {
+                id: id.to_string(),
+                name: TEST_NAME.to_string(),
+                email: email.to_string(),
+                password: hash(password),
+                user_type: user_type.to_string(),
+                pending_since: None,
+                pending_email: None,
+                session_version: 0,
+            }).unwrap();
+        }
+        storage.insert_conference(&Conference {
+            id: TEST_CONFERENCE_ID.to_string(),
+            name: "test_conference".to_string(),
+            conference_date: "2019-06-24".to_string(),
+            conference_address1: "test_address_1".to_string(),
+            conference_address2: "test_address_2".to_string(),
+            conference_address3: "test_address_3".to_string(),
+            status: NOT_COMPLETED.to_string(),
+            similarity_threshold: None,
+            presence_rule: PresenceRule::AnyCapture,
+        }).unwrap();
+        storage.insert_registration(&Registration {
+            user_id: TEST_USER_ID.to_string(),
+            email: TEST_EMAIL_ID.to_string(),
+            status: ABSENT.to_string(),
+            conference_id: TEST_CONFERENCE_ID.to_string(),
+            attendance_match: None,
+            notified: false,
+        }).unwrap();
+        storage
+    }
+
+    /// Match of a face found in the middle of a capture
+    pub fn test_attendance_match(similarity: f32) -> AttendanceMatch {
+        AttendanceMatch {
+            similarity,
+            face_box: Some(FaceBox { left: 0.25, top: 0.25, width: 0.5, height: 0.5 }),
+            capture_id: "capture".to_string(),
+            captured_at: 1000,
+        }
+    }
+
+    /// Observation of the test user at the test conference in a capture taken at a given time
+    pub fn test_observation(capture_id: &str, captured_at: i64, similarity: f32) -> Observation {
+        Observation {
+            user_id: TEST_USER_ID.to_string(),
+            conference_id: TEST_CONFERENCE_ID.to_string(),
+            attendance_match: AttendanceMatch {
+                capture_id: capture_id.to_string(),
+                captured_at,
+                ..test_attendance_match(similarity)
+            },
+            approved: false,
+        }
+    }
+
+    /// Review of a match of the test user at the test conference
+    pub fn test_review(review_id: &str, similarity: f32) -> Review {
+        Review {
+            id: review_id.to_string(),
+            user_id: TEST_USER_ID.to_string(),
+            conference_id: TEST_CONFERENCE_ID.to_string(),
+            attendance_match: test_attendance_match(similarity),
+            face_key: Some(format!("review-{}", review_id)),
+            reference_key: TEST_USER_ID.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_user_by_email_success() {
+        let user: User = test_storage().user_by_email(TEST_EMAIL_ID).unwrap().unwrap();
+        assert_eq!(user.id, TEST_USER_ID);
+    }
+
+    #[test]
+    fn test_update_user_success() {
+        let storage: MemoryStorage = test_storage();
+        assert!(storage.update_user(TEST_USER_ID, "Tester", TEST_EMAIL_ID).unwrap());
+        assert!(!storage.update_user(TEST_USER_ID, "Tester", TEST_EMAIL_ID).unwrap());
+        assert!(!storage.update_user("999999", "Tester", TEST_EMAIL_ID).unwrap());
+    }
+
+    #[test]
+    fn test_revoke_sessions_success() {
+        let storage: MemoryStorage = test_storage();
+        assert!(storage.revoke_sessions(TEST_USER_ID).unwrap());
+        assert!(storage.revoke_sessions(TEST_USER_ID).unwrap());
+        assert!(!storage.revoke_sessions("6666666666").unwrap());
+        assert_eq!(storage.user_by_id(TEST_USER_ID).unwrap().unwrap().session_version, 2);
+    }
+
+    #[test]
+    fn test_delete_user_keeps_admins() {
+        let storage: MemoryStorage = test_storage();
+        assert!(!storage.delete_user(TEST_ADMIN_ID).unwrap());
+        assert!(storage.delete_user(TEST_USER_ID).unwrap());
+        assert_eq!(storage.user_by_id(TEST_USER_ID).unwrap(), None);
+    }
+
+    #[test]
+    fn test_delete_unverified_users_success() {
+        let storage: MemoryStorage = test_storage();
+        let mut pending: User = storage.user_by_id(TEST_USER_ID).unwrap().unwrap();
+        pending.id = "4444444444".to_string();
+        pending.email = "pending@knoldus.in".to_string();
+        pending.pending_since = Some(1000);
+        storage.insert_user(&pending).unwrap();
+        assert!(storage.delete_unverified_users(1000).unwrap().is_empty());
+        assert!(storage.verify_user("4444444444").unwrap());
+        assert!(!storage.verify_user("4444444444").unwrap());
+        assert!(!storage.verify_user(TEST_USER_ID).unwrap());
+        pending.id = "5555555555".to_string();
+        storage.insert_user(&pending).unwrap();
+        assert_eq!(storage.delete_unverified_users(1001).unwrap(), vec!["5555555555".to_string()]);
+        assert!(storage.user_by_id("4444444444").unwrap().is_some());
+        assert!(storage.user_by_id(TEST_USER_ID).unwrap().is_some());
+        assert!(storage.delete_unverified_users(3001).unwrap().is_empty());
+        let mut pending_admin: User = storage.user_by_id(TEST_ADMIN_ID).unwrap().unwrap();
+        pending_admin.id = "6666666666".to_string();
+        pending_admin.email = "pending-admin@knoldus.in".to_string();
+        pending_admin.pending_since = Some(1000);
+        storage.insert_user(&pending_admin).unwrap();
+        assert!(storage.delete_unverified_users(3001).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_confirm_pending_email_success() {
+        let storage: MemoryStorage = test_storage();
+        assert!(!storage.confirm_pending_email(TEST_USER_ID, "moved@knoldus.in").unwrap());
+        assert!(storage.set_pending_email(TEST_USER_ID, "typo@knoldus.in").unwrap());
+        assert!(storage.set_pending_email(TEST_USER_ID, "moved@knoldus.in").unwrap());
+        assert!(!storage.set_pending_email("6666666666", "moved@knoldus.in").unwrap());
+        assert!(!storage.confirm_pending_email(TEST_USER_ID, "typo@knoldus.in").unwrap());
+        assert_eq!(storage.user_by_id(TEST_USER_ID).unwrap().unwrap().email, TEST_EMAIL_ID);
+        assert!(storage.confirm_pending_email(TEST_USER_ID, "moved@knoldus.in").unwrap());
+        let user: User = storage.user_by_id(TEST_USER_ID).unwrap().unwrap();
+        assert_eq!(user.email, "moved@knoldus.in");
+        assert_eq!(user.pending_email, None);
+    }
+
+    #[test]
+    fn test_conferences_between_success() {
+        let storage: MemoryStorage = test_storage();
+        assert_eq!(storage.conferences_between("2019-06-01", "2019-06-30").unwrap().len(), 1);
+        assert!(storage.conferences_between("2019-07-01", "2019-07-30").unwrap().is_empty());
+        assert!(storage.conferences_by_status(COMPLETED).unwrap().is_empty());
+        assert!(storage.set_conference_status(TEST_CONFERENCE_ID, COMPLETED).unwrap());
+        assert!(!storage.set_conference_status("55443311", COMPLETED).unwrap());
+        assert_eq!(storage.conferences_by_status(COMPLETED).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_update_conference_success() {
+        let storage: MemoryStorage = test_storage();
+        let update: ConferenceUpdate = ConferenceUpdate {
+            conference_date: "2019-07-01".to_string(),
+            conference_address1: "a".to_string(),
+            conference_address2: "b".to_string(),
+            conference_address3: "c".to_string(),
+            similarity_threshold: Some(60.0),
+            presence_rule: PresenceRule::SeenWithinMinutes { minutes: 30 },
+        };
+        assert!(storage.update_conference(TEST_CONFERENCE_ID, &update).unwrap());
+        let conference: Conference = storage.conference(TEST_CONFERENCE_ID).unwrap().unwrap();
+        assert_eq!(conference.conference_date, "2019-07-01");
+        assert_eq!(conference.similarity_threshold, Some(60.0));
+        assert_eq!(conference.presence_rule, PresenceRule::SeenWithinMinutes { minutes: 30 });
+        assert!(storage.delete_conference(TEST_CONFERENCE_ID).unwrap());
+        assert!(!storage.update_conference(TEST_CONFERENCE_ID, &update).unwrap());
+    }
+
+    #[test]
+    fn test_observations_success() {
+        let storage: MemoryStorage = test_storage();
+        storage.save_observation(&test_observation("first", 1000, 60.0)).unwrap();
+        storage.save_observation(&test_observation("second", 2000, 0.0)).unwrap();
+        storage.save_observation(&test_observation("first", 1000, 90.0)).unwrap();
+        let observations: Vec<Observation> =
+            storage.observations(TEST_USER_ID, TEST_CONFERENCE_ID).unwrap();
+        assert_eq!(observations.len(), 2);
+        assert!(observations.contains(&test_observation("first", 1000, 90.0)));
+        assert_eq!(storage.observations_for_conference(TEST_CONFERENCE_ID).unwrap().len(), 2);
+        assert!(storage.observations(TEST_ADMIN_ID, TEST_CONFERENCE_ID).unwrap().is_empty());
+        storage.delete_observations(TEST_USER_ID).unwrap();
+        assert!(storage.observations_for_conference(TEST_CONFERENCE_ID).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_reviews_success() {
+        let storage: MemoryStorage = test_storage();
+        storage.insert_review(&test_review("review", 70.0)).unwrap();
+        assert_eq!(storage.review("review").unwrap(), Some(test_review("review", 70.0)));
+        assert_eq!(storage.reviews_for_conference(TEST_CONFERENCE_ID).unwrap().len(), 1);
+        assert_eq!(storage.reviews_for_user(TEST_USER_ID).unwrap().len(), 1);
+        assert!(storage.reviews_for_user(TEST_ADMIN_ID).unwrap().is_empty());
+        assert!(storage.delete_review("review").unwrap());
+        assert!(!storage.delete_review("review").unwrap());
+        assert_eq!(storage.review("review").unwrap(), None);
+    }
+
+    #[test]
+    fn test_set_attendance_status_success() {
+        let storage: MemoryStorage = test_storage();
+        assert!(storage.set_attendance_status(TEST_USER_ID, TEST_CONFERENCE_ID, "present")
+            .unwrap());
+        assert!(!storage.set_attendance_status(TEST_USER_ID, TEST_CONFERENCE_ID, "present")
+            .unwrap());
+        assert_eq!(storage.registrations_for_email(TEST_EMAIL_ID).unwrap()[0].status, "present");
+        storage.delete_registrations(TEST_USER_ID).unwrap();
+        assert!(storage.registrations_for_email(TEST_EMAIL_ID).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_set_notified_once() {
+        let storage: MemoryStorage = test_storage();
+        assert!(storage.set_notified(TEST_USER_ID, TEST_CONFERENCE_ID).unwrap());
+        assert!(!storage.set_notified(TEST_USER_ID, TEST_CONFERENCE_ID).unwrap());
+        assert!(!storage.set_notified(TEST_USER_ID, "55443311").unwrap());
+        assert!(storage.registration(TEST_USER_ID, TEST_CONFERENCE_ID).unwrap().unwrap().notified);
+    }
+
+    #[test]
+    fn test_set_attendance_match_success() {
+        let storage: MemoryStorage = test_storage();
+        assert!(storage.set_attendance_match(TEST_USER_ID, TEST_CONFERENCE_ID,
+                                             &test_attendance_match(91.5)).unwrap());
+        assert!(!storage.set_attendance_match(TEST_USER_ID, "55443311",
+                                              &test_attendance_match(91.5)).unwrap());
+        assert_eq!(storage.registration(TEST_USER_ID, TEST_CONFERENCE_ID).unwrap().unwrap()
+                       .attendance_match, Some(test_attendance_match(91.5)));
+    }
+
+    #[test]
+    fn test_take_reset_token_once() {
+        let storage: MemoryStorage = test_storage();
+        let token: ResetToken = ResetToken {
+            token_hash: "hash".to_string(),
+            user_id: TEST_USER_ID.to_string(),
+            expires_at: 1000,
+        };
+        storage.insert_reset_token(&token).unwrap();
+        assert_eq!(storage.take_reset_token("hash").unwrap(), Some(token.clone()));
+        assert_eq!(storage.take_reset_token("hash").unwrap(), None);
+        storage.insert_reset_token(&token).unwrap();
+        storage.delete_reset_tokens(TEST_USER_ID).unwrap();
+        assert_eq!(storage.take_reset_token("hash").unwrap(), None);
+    }
+}
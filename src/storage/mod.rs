@@ -0,0 +1,319 @@
+use std::fmt;
+use std::sync::Arc;
+
+use crate::config::{AppConfig, StorageBackend};
+use crate::face_matching::FaceBox;
+use crate::storage::memory::MemoryStorage;
+use crate::storage::mongo::MongoStorage;
+use crate::storage::sqlite::SqliteStorage;
+
+pub mod memory;
+
+pub mod mongo;
+
+pub mod sqlite;
+
+/// A registered user, as stored in the users collection
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct User {
+    #[serde(rename = "_id")]
+    pub id: String,
+    pub name: String,
+    pub email: String,
+    pub password: String,
+    pub user_type: String,
+    /// UNIX time in seconds of the signup, while the email address is not verified
+    #[serde(default)]
+    pub pending_since: Option<i64>,
+    /// Email address the user asked to move to, until it is verified
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pending_email: Option<String>,
+    /// Version signed into the sessions of the user, raised to end all of them
+    #[serde(default)]
+    pub session_version: i64,
+}
+
+/// A conference, as stored in the conferences collection
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Conference {
+    #[serde(rename = "_id")]
+    pub id: String,
+    #[serde(rename = "conf_name")]
+    pub name: String,
+    pub conference_date: String,
+    pub conference_address1: String,
+    pub conference_address2: String,
+    pub conference_address3: String,
+    pub status: String,
+    /// Similarity from 0 to 100 a user needs to be marked present, none to apply
+    /// `matching.similarity_threshold`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub similarity_threshold: Option<f32>,
+    #[serde(default)]
+    pub presence_rule: PresenceRule,
+}
+
+/// Rule deciding from the captures of a conference whether a registered user attended it
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(tag = "rule", rename_all = "snake_case")]
+pub enum PresenceRule {
+    /// Present once seen in any capture
+    AnyCapture,
+    /// Present once seen in at least `seen` of the first `captures` captures of the conference
+    SeenInCaptures { seen: u32, captures: u32 },
+    /// Present once seen in a capture taken within `minutes` of the first capture of the
+    /// conference
+    SeenWithinMinutes { minutes: u32 },
+}
+
+impl Default for PresenceRule {
+    fn default() -> Self {
+        PresenceRule::AnyCapture
+    }
+}
+
+/// Editable details of a conference
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConferenceUpdate {
+    pub conference_date: String,
+    pub conference_address1: String,
+    pub conference_address2: String,
+    pub conference_address3: String,
+    /// Threshold replacing that of the conference, none to restore the configured one
+    pub similarity_threshold: Option<f32>,
+    pub presence_rule: PresenceRule,
+}
+
+/// Registration of a user for a conference, along with the attendance status
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Registration {
+    pub user_id: String,
+    pub email: String,
+    pub status: String,
+    #[serde(rename = "conf_id")]
+    pub conference_id: String,
+    /// Best match of the user among the captures of the conference, none before the first one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attendance_match: Option<AttendanceMatch>,
+    /// Whether the user was sent the notice of their absence
+    #[serde(default)]
+    pub notified: bool,
+}
+
+/// Evidence of the attendance of a user, kept to audit why they were marked present
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct AttendanceMatch {
+    /// Similarity from 0 to 100 of the user with their most similar face of the capture
+    pub similarity: f32,
+    /// Position of that face in the capture, none when no face matched
+    pub face_box: Option<FaceBox>,
+    pub capture_id: String,
+    /// UNIX time in seconds of the capture
+    pub captured_at: i64,
+}
+
+/// What a capture showed of a registered user, the attendance being derived from all of them
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Observation {
+    pub user_id: String,
+    #[serde(rename = "conf_id")]
+    pub conference_id: String,
+    /// Match of the user in the capture, of similarity 0 when they were not recognised
+    pub attendance_match: AttendanceMatch,
+    /// Whether an admin approved the match after reviewing it
+    #[serde(default)]
+    pub approved: bool,
+}
+
+/// Match of a user falling just under the similarity threshold, pending the decision of an admin
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Review {
+    #[serde(rename = "_id")]
+    pub id: String,
+    pub user_id: String,
+    #[serde(rename = "conf_id")]
+    pub conference_id: String,
+    pub attendance_match: AttendanceMatch,
+    /// Key of the face cropped from the capture in the photo store, none when it was not cropped
+    pub face_key: Option<String>,
+    /// Key of the reference photo of the user in the photo store
+    pub reference_key: String,
+}
+
+/// Pending password reset, stored by the hash of the token mailed to the user
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ResetToken {
+    #[serde(rename = "_id")]
+    pub token_hash: String,
+    pub user_id: String,
+    /// UNIX time in seconds from which the token is refused
+    pub expires_at: i64,
+}
+
+/// Failure of the underlying storage
+#[derive(Debug, PartialEq)]
+pub struct StorageError(pub String);
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "Storage error: {}", self.0)
+    }
+}
+
+impl std::error::Error for StorageError {}
+
+pub type StorageResult<T> = Result<T, StorageError>;
+
+/// Access to the registered users
+pub trait UserRepository {
+    fn user_by_email(&self, email: &str) -> StorageResult<Option<User>>;
+
+    fn user_by_id(&self, user_id: &str) -> StorageResult<Option<User>>;
+
+    fn insert_user(&self, user: &User) -> StorageResult<()>;
+
+    /// Returns whether the user was modified
+    fn update_user(&self, user_id: &str, name: &str, email: &str) -> StorageResult<bool>;
+
+    /// Returns whether a user with the email exists
+    fn update_password(&self, email: &str, password: &str) -> StorageResult<bool>;
+
+    /// Ends every session issued to the user so far by raising its session version.
+    /// Returns whether the user exists
+    fn revoke_sessions(&self, user_id: &str) -> StorageResult<bool>;
+
+    /// Deletes a user of type `user`, admins can not be deleted.
+    /// Returns whether a user was deleted
+    fn delete_user(&self, user_id: &str) -> StorageResult<bool>;
+
+    /// Marks the email address of a pending user as verified.
+    /// Returns whether a pending user was verified
+    fn verify_user(&self, user_id: &str) -> StorageResult<bool>;
+
+    /// Records the email address a user asked to move to, replacing any earlier one.
+    /// Returns whether the user exists
+    fn set_pending_email(&self, user_id: &str, email: &str) -> StorageResult<bool>;
+
+    /// Moves a user to its pending email address, when it is the given one.
+    /// Returns whether the email address was changed
+    fn confirm_pending_email(&self, user_id: &str, email: &str) -> StorageResult<bool>;
+
+    /// Deletes the users of type `user` who signed up before the given UNIX time and never
+    /// verified their email address. Returns the ids of the deleted users
+    fn delete_unverified_users(&self, signed_up_before: i64) -> StorageResult<Vec<String>>;
+}
+
+/// Access to the conferences
+pub trait ConferenceRepository {
+    fn conference(&self, conference_id: &str) -> StorageResult<Option<Conference>>;
+
+    fn conferences_by_status(&self, status: &str) -> StorageResult<Vec<Conference>>;
+
+    /// Conferences held between the two dates (YYYY-MM-DD), both included
+    fn conferences_between(&self, first_date: &str, last_date: &str)
+                           -> StorageResult<Vec<Conference>>;
+
+    fn insert_conference(&self, conference: &Conference) -> StorageResult<()>;
+
+    /// Returns whether the conference exists
+    fn update_conference(&self, conference_id: &str, update: &ConferenceUpdate)
+                         -> StorageResult<bool>;
+
+    /// Returns whether the conference exists
+    fn set_conference_status(&self, conference_id: &str, status: &str) -> StorageResult<bool>;
+
+    /// Returns whether a conference was deleted
+    fn delete_conference(&self, conference_id: &str) -> StorageResult<bool>;
+}
+
+/// Access to the registrations of users for conferences and their attendance
+pub trait AttendanceRepository {
+    fn registration(&self, user_id: &str, conference_id: &str)
+                    -> StorageResult<Option<Registration>>;
+
+    fn insert_registration(&self, registration: &Registration) -> StorageResult<()>;
+
+    fn registrations_for_conference(&self, conference_id: &str)
+                                    -> StorageResult<Vec<Registration>>;
+
+    fn registrations_for_email(&self, email: &str) -> StorageResult<Vec<Registration>>;
+
+    /// Returns whether the status of the registration was changed
+    fn set_attendance_status(&self, user_id: &str, conference_id: &str, status: &str)
+                             -> StorageResult<bool>;
+
+    /// Replaces the match recorded on the registration, returns whether it exists
+    fn set_attendance_match(&self, user_id: &str, conference_id: &str,
+                            attendance_match: &AttendanceMatch) -> StorageResult<bool>;
+
+    /// Marks the user as sent the notice of their absence, returns whether they were not yet
+    fn set_notified(&self, user_id: &str, conference_id: &str) -> StorageResult<bool>;
+
+    /// Removes every registration of the user
+    fn delete_registrations(&self, user_id: &str) -> StorageResult<()>;
+}
+
+/// Access to the observations of the registered users in the captures of the conferences
+pub trait ObservationRepository {
+    /// Records an observation, replacing the one of the same user in the same capture
+    fn save_observation(&self, observation: &Observation) -> StorageResult<()>;
+
+    fn observations(&self, user_id: &str, conference_id: &str)
+                    -> StorageResult<Vec<Observation>>;
+
+    fn observations_for_conference(&self, conference_id: &str)
+                                   -> StorageResult<Vec<Observation>>;
+
+    /// Removes every observation of the user
+    fn delete_observations(&self, user_id: &str) -> StorageResult<()>;
+}
+
+/// Access to the matches pending review
+pub trait ReviewRepository {
+    fn review(&self, review_id: &str) -> StorageResult<Option<Review>>;
+
+    fn reviews_for_conference(&self, conference_id: &str) -> StorageResult<Vec<Review>>;
+
+    fn reviews_for_user(&self, user_id: &str) -> StorageResult<Vec<Review>>;
+
+    fn insert_review(&self, review: &Review) -> StorageResult<()>;
+
+    /// Removes a decided review, returns whether it was still pending
+    fn delete_review(&self, review_id: &str) -> StorageResult<bool>;
+}
+
+/// Access to the pending password resets
+pub trait ResetTokenRepository {
+    fn insert_reset_token(&self, token: &ResetToken) -> StorageResult<()>;
+
+    /// Removes the token and returns it, so that it can only be used once
+    fn take_reset_token(&self, token_hash: &str) -> StorageResult<Option<ResetToken>>;
+
+    /// Removes every pending reset of the user
+    fn delete_reset_tokens(&self, user_id: &str) -> StorageResult<()>;
+}
+
+/// Everything the application persists
+pub trait Storage: UserRepository + ConferenceRepository + AttendanceRepository
++ ObservationRepository + ReviewRepository + ResetTokenRepository + Send + Sync {}
+
+impl<T> Storage for T
+    where T: UserRepository + ConferenceRepository + AttendanceRepository
+    + ObservationRepository + ReviewRepository + ResetTokenRepository + Send + Sync {}
+
+/// Creates the storage backend selected in the configuration
+///
+/// # Arguments
+///
+/// * `config` - Configuration of the application
+///
+/// # Return
+///
+/// Returns the storage shared by all the handlers, or the error of opening it
+pub fn create_storage(config: &AppConfig) -> StorageResult<Arc<dyn Storage>> {
+    Ok(match config.storage.backend {
+        StorageBackend::Mongo => Arc::new(MongoStorage::connect(&config.mongo)?),
+        StorageBackend::Memory => Arc::new(MemoryStorage::default()),
+        StorageBackend::Sqlite => Arc::new(SqliteStorage::open(&config.storage.sqlite_path)?),
+    })
+}
@@ -0,0 +1,603 @@
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use mongodb::{bson, doc, ThreadedClient};
+use mongodb::Bson;
+use mongodb::coll::Collection;
+use mongodb::db::{Database, ThreadedDatabase};
+use mongodb::Document;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::config::MongoConfig;
+use crate::connection::create_db_connection;
+use crate::constants::{CONFERENCE_COLLECTION, CONFERENCE_DETAILS, CONFERENCE_ID, EMAIL, ID, NAME,
+                       PASSWORD, SET, STATUS, USER, USER_ID, USER_TYPE, USERS_COLLECTION};
+use crate::storage::{AttendanceMatch, AttendanceRepository, Conference, ConferenceRepository,
+                     ConferenceUpdate, Observation, ObservationRepository, Registration,
+                     ResetToken, ResetTokenRepository, Review, ReviewRepository, StorageError,
+                     StorageResult, User, UserRepository};
+
+static CONFERENCE_DATE: &str = "conference_date";
+static CONFERENCE_ADDRESS1: &str = "conference_address1";
+static CONFERENCE_ADDRESS2: &str = "conference_address2";
+static CONFERENCE_ADDRESS3: &str = "conference_address3";
+static GREATER: &str = "$gte";
+static LESSER: &str = "$lte";
+static LESS_THAN: &str = "$lt";
+static NOT_EQUAL: &str = "$ne";
+static IN: &str = "$in";
+static UNSET: &str = "$unset";
+static INCREMENT: &str = "$inc";
+static PENDING_SINCE: &str = "pending_since";
+static PENDING_EMAIL: &str = "pending_email";
+static SESSION_VERSION: &str = "session_version";
+static ATTENDANCE_MATCH: &str = "attendance_match";
+static SIMILARITY_THRESHOLD: &str = "similarity_threshold";
+static PRESENCE_RULE: &str = "presence_rule";
+static NOTIFIED: &str = "notified";
+static OBSERVED_CAPTURE_ID: &str = "attendance_match.capture_id";
+static OBSERVATIONS_COLLECTION: &str = "observations";
+static RESET_TOKENS_COLLECTION: &str = "reset_tokens";
+static REVIEWS_COLLECTION: &str = "reviews";
+static CHECKOUTS_EXHAUSTED: &str = "Timed out waiting to run an operation against MongoDB";
+
+impl From<mongodb::Error> for StorageError {
+    fn from(error: mongodb::Error) -> Self {
+        StorageError(error.to_string())
+    }
+}
+
+/// Counting semaphore limiting the operations checked out against MongoDB at the same time,
+/// the connections themselves being managed by the driver
+struct CheckoutLimit {
+    in_use: Mutex<usize>,
+    released: Condvar,
+    size: usize,
+    timeout: Duration,
+}
+
+/// Slot of the limit held while an operation runs, given back when dropped
+struct Checkout<'a> {
+    limit: &'a CheckoutLimit,
+}
+
+impl CheckoutLimit {
+    fn new(size: usize, timeout: Duration) -> CheckoutLimit {
+        CheckoutLimit {
+            in_use: Mutex::new(0),
+            released: Condvar::new(),
+            size,
+            timeout,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<usize> {
+        self.in_use.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Waits for a free slot, failing once the checkout timeout elapsed
+    fn acquire(&self) -> StorageResult<Checkout> {
+        let deadline: Instant = Instant::now() + self.timeout;
+        let mut in_use: MutexGuard<usize> = self.lock();
+        while *in_use >= self.size {
+            let now: Instant = Instant::now();
+            if now >= deadline {
+                return Err(StorageError(CHECKOUTS_EXHAUSTED.to_string()));
+            }
+            in_use = self.released.wait_timeout(in_use, deadline - now)
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .0;
+        }
+        *in_use += 1;
+        Ok(Checkout { limit: self })
+    }
+}
+
+impl<'a> Drop for Checkout<'a> {
+    fn drop(&mut self) {
+        *self.limit.lock() -= 1;
+        self.limit.released.notify_one();
+    }
+}
+
+/// Storage backed by the collections of a MongoDB database, sharing one client
+pub struct MongoStorage {
+    database: Database,
+    checkout_limit: CheckoutLimit,
+}
+
+impl MongoStorage {
+    /// Connects the storage to the configured database
+    ///
+    /// # Arguments
+    ///
+    /// * `mongo_config` - Connection and checkout settings of MongoDB
+    ///
+    /// # Return
+    ///
+    /// Returns the Mongo storage, or the failure of the connection
+    pub fn connect(mongo_config: &MongoConfig) -> StorageResult<MongoStorage> {
+        let database: Database =
+            create_db_connection(mongo_config)?.db(mongo_config.database.as_str());
+        Ok(MongoStorage {
+            database,
+            checkout_limit: CheckoutLimit::new(
+                mongo_config.pool_size, Duration::from_millis(mongo_config.checkout_timeout_ms)),
+        })
+    }
+
+    /// Runs an operation on a collection while holding a slot of the checkout limit
+    fn with_collection<T, F>(&self, name: &str, operation: F) -> StorageResult<T>
+        where F: FnOnce(Collection) -> StorageResult<T> {
+        let _checkout: Checkout = self.checkout_limit.acquire()?;
+        operation(self.database.collection(name))
+    }
+
+    fn find_one<T: DeserializeOwned>(&self, collection: &str, filter: Document)
+                                     -> StorageResult<Option<T>> {
+        self.with_collection(collection, |collection| {
+            match collection.find_one(Some(filter), None)? {
+                Some(document) => from_document(document).map(Some),
+                None => Ok(None),
+            }
+        })
+    }
+
+    fn find<T: DeserializeOwned>(&self, collection: &str, filter: Document)
+                                 -> StorageResult<Vec<T>> {
+        self.with_collection(collection, |collection| {
+            let mut records: Vec<T> = Vec::new();
+            for document in collection.find(Some(filter), None)? {
+                records.push(from_document(document?)?);
+            }
+            Ok(records)
+        })
+    }
+
+    fn insert<T: Serialize>(&self, collection: &str, record: &T) -> StorageResult<()> {
+        let document: Document = to_document(record)?;
+        self.with_collection(collection, |collection| {
+            collection.insert_one(document, None)?;
+            Ok(())
+        })
+    }
+}
+
+/// Converts a record into a BSON document
+///
+/// # Arguments
+///
+/// * `record` - Record to convert
+///
+/// # Return
+///
+/// Returns the BSON document
+pub fn to_document<T: Serialize>(record: &T) -> StorageResult<Document> {
+    match bson::to_bson(record) {
+        Ok(Bson::Document(document)) => Ok(document),
+        Ok(_) => Err(StorageError("Record is not a document".to_string())),
+        Err(error) => Err(StorageError(error.to_string())),
+    }
+}
+
+/// Converts a BSON document into a record
+///
+/// # Arguments
+///
+/// * `document` - Document read from MongoDB
+///
+/// # Return
+///
+/// Returns the record
+pub fn from_document<T: DeserializeOwned>(document: Document) -> StorageResult<T> {
+    bson::from_bson(Bson::Document(document)).map_err(|error| StorageError(error.to_string()))
+}
+
+impl UserRepository for MongoStorage {
+    fn user_by_email(&self, email: &str) -> StorageResult<Option<User>> {
+        self.find_one(USERS_COLLECTION, doc! {EMAIL: email})
+    }
+
+    fn user_by_id(&self, user_id: &str) -> StorageResult<Option<User>> {
+        self.find_one(USERS_COLLECTION, doc! {ID: user_id})
+    }
+
+    fn insert_user(&self, user: &User) -> StorageResult<()> {
+        self.insert(USERS_COLLECTION, user)
+    }
+
+    fn update_user(&self, user_id: &str, name: &str, email: &str) -> StorageResult<bool> {
+        let updated_data = doc! {SET: {NAME: name, EMAIL: email}};
+        self.with_collection(USERS_COLLECTION, |users| {
+            Ok(users.update_one(doc! {ID: user_id}, updated_data, None)?.modified_count > 0)
+        })
+    }
+
+    fn update_password(&self, email: &str, password: &str) -> StorageResult<bool> {
+        let new_password = doc! {SET: {PASSWORD: password}};
+        self.with_collection(USERS_COLLECTION, |users| {
+            Ok(users.update_one(doc! {EMAIL: email}, new_password, None)?.matched_count > 0)
+        })
+    }
+
+    fn revoke_sessions(&self, user_id: &str) -> StorageResult<bool> {
+        let next_version = doc! {INCREMENT: {SESSION_VERSION: 1i64}};
+        self.with_collection(USERS_COLLECTION, |users| {
+            Ok(users.update_one(doc! {ID: user_id}, next_version, None)?.matched_count > 0)
+        })
+    }
+
+    fn delete_user(&self, user_id: &str) -> StorageResult<bool> {
+        self.with_collection(USERS_COLLECTION, |users| {
+            Ok(users.find_one_and_delete(doc! {ID: user_id, USER_TYPE: USER}, None)?.is_some())
+        })
+    }
+
+    fn verify_user(&self, user_id: &str) -> StorageResult<bool> {
+        let pending_user = doc! {ID: user_id, PENDING_SINCE: {NOT_EQUAL: Bson::Null}};
+        self.with_collection(USERS_COLLECTION, |users| {
+            Ok(users.update_one(pending_user, doc! {UNSET: {PENDING_SINCE: ""}}, None)?
+                .modified_count > 0)
+        })
+    }
+
+    fn set_pending_email(&self, user_id: &str, email: &str) -> StorageResult<bool> {
+        self.with_collection(USERS_COLLECTION, |users| {
+            Ok(users.update_one(doc! {ID: user_id}, doc! {SET: {PENDING_EMAIL: email}}, None)?
+                .matched_count > 0)
+        })
+    }
+
+    fn confirm_pending_email(&self, user_id: &str, email: &str) -> StorageResult<bool> {
+        let moving_user = doc! {ID: user_id, PENDING_EMAIL: email};
+        let confirmed = doc! {SET: {EMAIL: email}, UNSET: {PENDING_EMAIL: ""}};
+        self.with_collection(USERS_COLLECTION, |users| {
+            Ok(users.update_one(moving_user, confirmed, None)?.modified_count > 0)
+        })
+    }
+
+    fn delete_unverified_users(&self, signed_up_before: i64) -> StorageResult<Vec<String>> {
+        let stale_users: Vec<User> =
+            self.find(USERS_COLLECTION, doc! {
+                USER_TYPE: USER,
+                PENDING_SINCE: {LESS_THAN: signed_up_before},
+            })?;
+        let stale_ids: Vec<String> = stale_users.into_iter().map(|user| user.id).collect();
+        if !stale_ids.is_empty() {
+            let ids: Vec<Bson> = stale_ids.iter().cloned().map(Bson::String).collect();
+            let stale_filter = doc! {
+                ID: {IN: ids},
+                USER_TYPE: USER,
+                PENDING_SINCE: {LESS_THAN: signed_up_before},
+            };
+            self.with_collection(USERS_COLLECTION, |users| {
+                users.delete_many(stale_filter, None)?;
+                Ok(())
+            })?;
+        }
+        Ok(stale_ids)
+    }
+}
+
+impl ConferenceRepository for MongoStorage {
+    fn conference(&self, conference_id: &str) -> StorageResult<Option<Conference>> {
+        self.find_one(CONFERENCE_COLLECTION, doc! {ID: conference_id})
+    }
+
+    fn conferences_by_status(&self, status: &str) -> StorageResult<Vec<Conference>> {
+        self.find(CONFERENCE_COLLECTION, doc! {STATUS: status})
+    }
+
+    fn conferences_between(&self, first_date: &str, last_date: &str)
+                           -> StorageResult<Vec<Conference>> {
+        self.find(CONFERENCE_COLLECTION,
+                  doc! {CONFERENCE_DATE: {GREATER: first_date, LESSER: last_date}})
+    }
+
+    fn insert_conference(&self, conference: &Conference) -> StorageResult<()> {
+        self.insert(CONFERENCE_COLLECTION, conference)
+    }
+
+    fn update_conference(&self, conference_id: &str, update: &ConferenceUpdate)
+                         -> StorageResult<bool> {
+        let presence_rule: Document = to_document(&update.presence_rule)?;
+        let updated_conference_data = doc! {
+            SET: {
+                CONFERENCE_DATE: update.conference_date.as_str(),
+                CONFERENCE_ADDRESS1: update.conference_address1.as_str(),
+                CONFERENCE_ADDRESS2: update.conference_address2.as_str(),
+                CONFERENCE_ADDRESS3: update.conference_address3.as_str(),
+                SIMILARITY_THRESHOLD: match update.similarity_threshold {
+                    Some(threshold) => Bson::FloatingPoint(f64::from(threshold)),
+                    None => Bson::Null,
+                },
+                PRESENCE_RULE: presence_rule,
+            }
+        };
+        self.with_collection(CONFERENCE_COLLECTION, |conferences| {
+            Ok(conferences
+                .find_one_and_update(doc! {ID: conference_id}, updated_conference_data, None)?
+                .is_some())
+        })
+    }
+
+    fn set_conference_status(&self, conference_id: &str, status: &str) -> StorageResult<bool> {
+        self.with_collection(CONFERENCE_COLLECTION, |conferences| {
+            Ok(conferences.update_one(doc! {ID: conference_id}, doc! {SET: {STATUS: status}}, None)?
+                .matched_count > 0)
+        })
+    }
+
+    fn delete_conference(&self, conference_id: &str) -> StorageResult<bool> {
+        self.with_collection(CONFERENCE_COLLECTION, |conferences| {
+            Ok(conferences.find_one_and_delete(doc! {ID: conference_id}, None)?.is_some())
+        })
+    }
+}
+
+impl AttendanceRepository for MongoStorage {
+    fn registration(&self, user_id: &str, conference_id: &str)
+                    -> StorageResult<Option<Registration>> {
+        self.find_one(CONFERENCE_DETAILS, doc! {USER_ID: user_id, CONFERENCE_ID: conference_id})
+    }
+
+    fn insert_registration(&self, registration: &Registration) -> StorageResult<()> {
+        self.insert(CONFERENCE_DETAILS, registration)
+    }
+
+    fn registrations_for_conference(&self, conference_id: &str)
+                                    -> StorageResult<Vec<Registration>> {
+        self.find(CONFERENCE_DETAILS, doc! {CONFERENCE_ID: conference_id})
+    }
+
+    fn registrations_for_email(&self, email: &str) -> StorageResult<Vec<Registration>> {
+        self.find(CONFERENCE_DETAILS, doc! {EMAIL: email})
+    }
+
+    fn set_attendance_status(&self, user_id: &str, conference_id: &str, status: &str)
+                             -> StorageResult<bool> {
+        let record = doc! {USER_ID: user_id, CONFERENCE_ID: conference_id};
+        self.with_collection(CONFERENCE_DETAILS, |registrations| {
+            Ok(registrations.update_one(record, doc! {SET: {STATUS: status}}, None)?
+                .modified_count > 0)
+        })
+    }
+
+    fn set_attendance_match(&self, user_id: &str, conference_id: &str,
+                            attendance_match: &AttendanceMatch) -> StorageResult<bool> {
+        let record = doc! {USER_ID: user_id, CONFERENCE_ID: conference_id};
+        let evidence: Document = to_document(attendance_match)?;
+        self.with_collection(CONFERENCE_DETAILS, |registrations| {
+            Ok(registrations.update_one(record, doc! {SET: {ATTENDANCE_MATCH: evidence}}, None)?
+                .matched_count > 0)
+        })
+    }
+
+    fn set_notified(&self, user_id: &str, conference_id: &str) -> StorageResult<bool> {
+        let record = doc! {
+            USER_ID: user_id,
+            CONFERENCE_ID: conference_id,
+            NOTIFIED: {NOT_EQUAL: true},
+        };
+        self.with_collection(CONFERENCE_DETAILS, |registrations| {
+            Ok(registrations.update_one(record, doc! {SET: {NOTIFIED: true}}, None)?
+                .modified_count > 0)
+        })
+    }
+
+    fn delete_registrations(&self, user_id: &str) -> StorageResult<()> {
+        self.with_collection(CONFERENCE_DETAILS, |registrations| {
+            registrations.delete_many(doc! {USER_ID: user_id}, None)?;
+            Ok(())
+        })
+    }
+}
+
+impl ObservationRepository for MongoStorage {
+    fn save_observation(&self, observation: &Observation) -> StorageResult<()> {
+        let document: Document = to_document(observation)?;
+        let same_capture = doc! {
+            USER_ID: observation.user_id.as_str(),
+            CONFERENCE_ID: observation.conference_id.as_str(),
+            OBSERVED_CAPTURE_ID: observation.attendance_match.capture_id.as_str(),
+        };
+        self.with_collection(OBSERVATIONS_COLLECTION, |observations| {
+            observations.delete_many(same_capture, None)?;
+            observations.insert_one(document, None)?;
+            Ok(())
+        })
+    }
+
+    fn observations(&self, user_id: &str, conference_id: &str)
+                    -> StorageResult<Vec<Observation>> {
+        self.find(OBSERVATIONS_COLLECTION, doc! {USER_ID: user_id, CONFERENCE_ID: conference_id})
+    }
+
+    fn observations_for_conference(&self, conference_id: &str)
+                                   -> StorageResult<Vec<Observation>> {
+        self.find(OBSERVATIONS_COLLECTION, doc! {CONFERENCE_ID: conference_id})
+    }
+
+    fn delete_observations(&self, user_id: &str) -> StorageResult<()> {
+        self.with_collection(OBSERVATIONS_COLLECTION, |observations| {
+            observations.delete_many(doc! {USER_ID: user_id}, None)?;
+            Ok(())
+        })
+    }
+}
+
+impl ReviewRepository for MongoStorage {
+    fn review(&self, review_id: &str) -> StorageResult<Option<Review>> {
+        self.find_one(REVIEWS_COLLECTION, doc! {ID: review_id})
+    }
+
+    fn reviews_for_conference(&self, conference_id: &str) -> StorageResult<Vec<Review>> {
+        self.find(REVIEWS_COLLECTION, doc! {CONFERENCE_ID: conference_id})
+    }
+
+    fn reviews_for_user(&self, user_id: &str) -> StorageResult<Vec<Review>> {
+        self.find(REVIEWS_COLLECTION, doc! {USER_ID: user_id})
+    }
+
+    fn insert_review(&self, review: &Review) -> StorageResult<()> {
+        self.insert(REVIEWS_COLLECTION, review)
+    }
+
+    fn delete_review(&self, review_id: &str) -> StorageResult<bool> {
+        self.with_collection(REVIEWS_COLLECTION, |reviews| {
+            Ok(reviews.find_one_and_delete(doc! {ID: review_id}, None)?.is_some())
+        })
+    }
+}
+
+impl ResetTokenRepository for MongoStorage {
+    fn insert_reset_token(&self, token: &ResetToken) -> StorageResult<()> {
+        self.insert(RESET_TOKENS_COLLECTION, token)
+    }
+
+    fn take_reset_token(&self, token_hash: &str) -> StorageResult<Option<ResetToken>> {
+        self.with_collection(RESET_TOKENS_COLLECTION, |tokens| {
+            match tokens.find_one_and_delete(doc! {ID: token_hash}, None)? {
+                Some(document) => from_document(document).map(Some),
+                None => Ok(None),
+            }
+        })
+    }
+
+    fn delete_reset_tokens(&self, user_id: &str) -> StorageResult<()> {
+        self.with_collection(RESET_TOKENS_COLLECTION, |tokens| {
+            tokens.delete_many(doc! {USER_ID: user_id}, None)?;
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    use mongodb::{bson, doc};
+
+    use crate::constants::NOT_COMPLETED;
+    use crate::storage::{Conference, Observation, PresenceRule, Registration, Review};
+    use crate::storage::memory::test::{test_attendance_match, test_observation, test_review};
+    use crate::storage::mongo::{CheckoutLimit, from_document, to_document};
+
+    #[test]
+    fn test_checkout_limit_timeout() {
+        let limit: CheckoutLimit = CheckoutLimit::new(1, Duration::from_millis(10));
+        let checkout = limit.acquire().unwrap();
+        assert!(limit.acquire().is_err());
+        drop(checkout);
+        assert!(limit.acquire().is_ok());
+    }
+
+    #[test]
+    fn test_checkout_limit_wait_for_release() {
+        let limit: Arc<CheckoutLimit> = Arc::new(CheckoutLimit::new(1, Duration::from_secs(5)));
+        let holder: Arc<CheckoutLimit> = limit.clone();
+        let (acquired, release) = std::sync::mpsc::channel();
+        let worker = thread::spawn(move || {
+            let _checkout = holder.acquire().unwrap();
+            acquired.send(()).unwrap();
+            thread::sleep(Duration::from_millis(20));
+        });
+        release.recv().unwrap();
+        assert!(limit.acquire().is_ok());
+        worker.join().unwrap();
+    }
+
+    #[test]
+    fn test_to_document_success() {
+        let registration: Registration = Registration {
+            user_id: "1111111111".to_string(),
+            email: "test@knoldus.in".to_string(),
+            status: "absent".to_string(),
+            conference_id: "5544332211".to_string(),
+            attendance_match: None,
+            notified: false,
+        };
+        assert_eq!(to_document(&registration).unwrap(), doc! {
+            "user_id": "1111111111",
+            "email": "test@knoldus.in",
+            "status": "absent",
+            "conf_id": "5544332211",
+            "notified": false,
+        });
+    }
+
+    #[test]
+    fn test_from_document_success() {
+        let conference: Conference = from_document(doc! {
+            "_id": "5544332211",
+            "conf_name": "test_conference",
+            "conference_date": "2019-06-24",
+            "conference_address1": "test_address_1",
+            "conference_address2": "test_address_2",
+            "conference_address3": "test_address_3",
+            "status": NOT_COMPLETED,
+        }).unwrap();
+        assert_eq!(conference.name, "test_conference");
+        assert_eq!(conference.similarity_threshold, None);
+        assert_eq!(conference.presence_rule, PresenceRule::AnyCapture);
+    }
+
+    #[test]
+    fn test_similarity_threshold_document_success() {
+        let conference: Conference = from_document(doc! {
+            "_id": "5544332211",
+            "conf_name": "test_conference",
+            "conference_date": "2019-06-24",
+            "conference_address1": "test_address_1",
+            "conference_address2": "test_address_2",
+            "conference_address3": "test_address_3",
+            "status": NOT_COMPLETED,
+            "similarity_threshold": 60.0,
+            "presence_rule": {"rule": "seen_in_captures", "seen": 2, "captures": 3},
+        }).unwrap();
+        assert_eq!(conference.similarity_threshold, Some(60.0));
+        assert_eq!(conference.presence_rule, PresenceRule::SeenInCaptures { seen: 2, captures: 3 });
+        assert_eq!(from_document::<Conference>(to_document(&conference).unwrap()).unwrap(),
+                   conference);
+    }
+
+    #[test]
+    fn test_attendance_match_document_success() {
+        let mut registration: Registration = from_document(doc! {
+            "user_id": "1111111111",
+            "email": "test@knoldus.in",
+            "status": "absent",
+            "conf_id": "5544332211",
+        }).unwrap();
+        assert_eq!(registration.attendance_match, None);
+        registration.attendance_match = Some(test_attendance_match(91.5));
+        assert_eq!(from_document::<Registration>(to_document(&registration).unwrap()).unwrap(),
+                   registration);
+    }
+
+    #[test]
+    fn test_observation_document_success() {
+        let observation: Observation = test_observation("capture", 1000, 91.5);
+        let document = to_document(&observation).unwrap();
+        assert_eq!(document.get_document("attendance_match").unwrap().get_str("capture_id")
+                       .unwrap(), "capture");
+        assert_eq!(from_document::<Observation>(document).unwrap(), observation);
+    }
+
+    #[test]
+    fn test_review_document_success() {
+        let review: Review = test_review("review", 70.0);
+        let document = to_document(&review).unwrap();
+        assert_eq!(document.get_str("_id").unwrap(), "review");
+        assert_eq!(document.get_str("conf_id").unwrap(), "5544332211");
+        assert_eq!(from_document::<Review>(document).unwrap(), review);
+    }
+
+    #[test]
+    fn test_from_document_failure() {
+        assert!(from_document::<Conference>(doc! {"_id": "5544332211"}).is_err());
+    }
+}
@@ -0,0 +1,872 @@
+use std::sync::{Mutex, MutexGuard};
+
+use rusqlite::{Connection, OptionalExtension, params, Row};
+use rusqlite::types::{ToSql, Type};
+
+use crate::constants::USER;
+use crate::face_matching::FaceBox;
+use crate::storage::{AttendanceMatch, AttendanceRepository, Conference, ConferenceRepository,
+                     ConferenceUpdate, Observation, ObservationRepository, PresenceRule,
+                     Registration, ResetToken, ResetTokenRepository, Review, ReviewRepository,
+                     StorageError, StorageResult, User, UserRepository};
+
+static POISONED: &str = "SQLite connection lock poisoned";
+
+/// Tables of the database, created on startup when missing
+static SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS users (
+        id TEXT PRIMARY KEY NOT NULL,
+        name TEXT NOT NULL,
+        email TEXT NOT NULL UNIQUE,
+        password TEXT NOT NULL,
+        user_type TEXT NOT NULL,
+        pending_since INTEGER,
+        pending_email TEXT,
+        session_version INTEGER NOT NULL DEFAULT 0
+    );
+    CREATE TABLE IF NOT EXISTS conferences (
+        id TEXT PRIMARY KEY NOT NULL,
+        name TEXT NOT NULL,
+        conference_date TEXT NOT NULL,
+        conference_address1 TEXT NOT NULL,
+        conference_address2 TEXT NOT NULL,
+        conference_address3 TEXT NOT NULL,
+        status TEXT NOT NULL,
+        similarity_threshold REAL,
+        presence_rule TEXT
+    );
+    CREATE INDEX IF NOT EXISTS conferences_by_date ON conferences (conference_date);
+    CREATE TABLE IF NOT EXISTS registrations (
+        user_id TEXT NOT NULL,
+        email TEXT NOT NULL,
+        status TEXT NOT NULL,
+        conference_id TEXT NOT NULL,
+        match_similarity REAL,
+        match_left REAL,
+        match_top REAL,
+        match_width REAL,
+        match_height REAL,
+        capture_id TEXT,
+        captured_at INTEGER,
+        notified INTEGER NOT NULL DEFAULT 0,
+        PRIMARY KEY (user_id, conference_id)
+    );
+    CREATE INDEX IF NOT EXISTS registrations_by_email ON registrations (email);
+    CREATE TABLE IF NOT EXISTS observations (
+        user_id TEXT NOT NULL,
+        conference_id TEXT NOT NULL,
+        capture_id TEXT NOT NULL,
+        captured_at INTEGER NOT NULL,
+        similarity REAL NOT NULL,
+        match_left REAL,
+        match_top REAL,
+        match_width REAL,
+        match_height REAL,
+        approved INTEGER NOT NULL,
+        PRIMARY KEY (user_id, conference_id, capture_id)
+    );
+    CREATE INDEX IF NOT EXISTS observations_by_conference ON observations (conference_id);
+    CREATE TABLE IF NOT EXISTS reviews (
+        id TEXT PRIMARY KEY NOT NULL,
+        user_id TEXT NOT NULL,
+        conference_id TEXT NOT NULL,
+        similarity REAL NOT NULL,
+        match_left REAL,
+        match_top REAL,
+        match_width REAL,
+        match_height REAL,
+        capture_id TEXT NOT NULL,
+        captured_at INTEGER NOT NULL,
+        face_key TEXT,
+        reference_key TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS reviews_by_conference ON reviews (conference_id);
+    CREATE TABLE IF NOT EXISTS reset_tokens (
+        token_hash TEXT PRIMARY KEY NOT NULL,
+        user_id TEXT NOT NULL,
+        expires_at INTEGER NOT NULL
+    );
+";
+
+/// Columns added to the tables after their first release, created in older databases on startup
+static ADDED_COLUMNS: [(&str, &str, &str); 13] = [
+    ("users", "pending_since", "INTEGER"),
+    ("users", "pending_email", "TEXT"),
+    ("users", "session_version", "INTEGER NOT NULL DEFAULT 0"),
+    ("conferences", "similarity_threshold", "REAL"),
+    ("conferences", "presence_rule", "TEXT"),
+    ("registrations", "match_similarity", "REAL"),
+    ("registrations", "match_left", "REAL"),
+    ("registrations", "match_top", "REAL"),
+    ("registrations", "match_width", "REAL"),
+    ("registrations", "match_height", "REAL"),
+    ("registrations", "capture_id", "TEXT"),
+    ("registrations", "captured_at", "INTEGER"),
+    ("registrations", "notified", "INTEGER NOT NULL DEFAULT 0"),
+];
+
+static USER_COLUMNS: &str = "id, name, email, password, user_type, pending_since, pending_email, \
+                             session_version";
+static CONFERENCE_COLUMNS: &str = "id, name, conference_date, conference_address1, \
+                                   conference_address2, conference_address3, status, \
+                                   similarity_threshold, presence_rule";
+static REGISTRATION_COLUMNS: &str = "user_id, email, status, conference_id, match_similarity, \
+                                     match_left, match_top, match_width, match_height, \
+                                     capture_id, captured_at, notified";
+static OBSERVATION_COLUMNS: &str = "user_id, conference_id, capture_id, captured_at, similarity, \
+                                    match_left, match_top, match_width, match_height, approved";
+static REVIEW_COLUMNS: &str = "id, user_id, conference_id, similarity, match_left, match_top, \
+                               match_width, match_height, capture_id, captured_at, face_key, \
+                               reference_key";
+static RESET_TOKEN_COLUMNS: &str = "token_hash, user_id, expires_at";
+
+impl From<rusqlite::Error> for StorageError {
+    fn from(error: rusqlite::Error) -> Self {
+        StorageError(error.to_string())
+    }
+}
+
+/// Storage backed by a single SQLite file, for deployments without a database server
+pub struct SqliteStorage {
+    connection: Mutex<Connection>,
+}
+
+impl SqliteStorage {
+    /// Opens the database, creating the file and its tables when missing
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the database file, `:memory:` for a throwaway database
+    ///
+    /// # Return
+    ///
+    /// Returns the SQLite storage
+    pub fn open(path: &str) -> StorageResult<SqliteStorage> {
+        let connection: Connection = Connection::open(path)?;
+        connection.execute_batch(SCHEMA)?;
+        add_missing_columns(&connection)?;
+        Ok(SqliteStorage { connection: Mutex::new(connection) })
+    }
+
+    fn connection(&self) -> StorageResult<MutexGuard<Connection>> {
+        self.connection.lock().map_err(|_| StorageError(POISONED.to_string()))
+    }
+
+    fn execute(&self, statement: &str, values: &[&dyn ToSql]) -> StorageResult<usize> {
+        Ok(self.connection()?.execute(statement, values)?)
+    }
+
+    fn find_one<T, F>(&self, statement: &str, values: &[&dyn ToSql], map: F)
+                      -> StorageResult<Option<T>>
+        where F: FnOnce(&Row) -> rusqlite::Result<T> {
+        Ok(self.connection()?.query_row(statement, values, map).optional()?)
+    }
+
+    fn find<T, F>(&self, statement: &str, values: &[&dyn ToSql], map: F) -> StorageResult<Vec<T>>
+        where F: FnMut(&Row) -> rusqlite::Result<T> {
+        let connection = self.connection()?;
+        let mut prepared = connection.prepare(statement)?;
+        let records = prepared.query_map(values, map)?;
+        Ok(records.collect::<rusqlite::Result<Vec<T>>>()?)
+    }
+}
+
+/// Adds the columns a database created by an older release lacks
+fn add_missing_columns(connection: &Connection) -> rusqlite::Result<()> {
+    for (table, column, definition) in ADDED_COLUMNS.iter() {
+        let mut table_info = connection.prepare(&format!("PRAGMA table_info({})", table))?;
+        let columns: Vec<String> = table_info.query_map(params![], |row| row.get(1))?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+        if !columns.iter().any(|name| name == column) {
+            connection.execute_batch(&format!("ALTER TABLE {} ADD COLUMN {} {}",
+                                              table, column, definition))?;
+        }
+    }
+    Ok(())
+}
+
+fn user_from_row(row: &Row) -> rusqlite::Result<User> {
+    Ok(User {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        email: row.get(2)?,
+        password: row.get(3)?,
+        user_type: row.get(4)?,
+        pending_since: row.get(5)?,
+        pending_email: row.get(6)?,
+        session_version: row.get(7)?,
+    })
+}
+
+fn conference_from_row(row: &Row) -> rusqlite::Result<Conference> {
+    Ok(Conference {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        conference_date: row.get(2)?,
+        conference_address1: row.get(3)?,
+        conference_address2: row.get(4)?,
+        conference_address3: row.get(5)?,
+        status: row.get(6)?,
+        similarity_threshold: row.get::<_, Option<f64>>(7)?.map(|threshold| threshold as f32),
+        presence_rule: presence_rule_from_row(row, 8)?,
+    })
+}
+
+/// Reads a presence rule from its JSON column, the default one when empty
+fn presence_rule_from_row(row: &Row, index: usize) -> rusqlite::Result<PresenceRule> {
+    match row.get::<_, Option<String>>(index)? {
+        Some(rule) => serde_json::from_str(&rule).map_err(|json_error| {
+            rusqlite::Error::FromSqlConversionFailure(index, Type::Text, Box::new(json_error))
+        }),
+        None => Ok(PresenceRule::default()),
+    }
+}
+
+/// Presence rule as stored in its JSON column
+fn presence_rule_column(rule: &PresenceRule) -> StorageResult<String> {
+    serde_json::to_string(rule).map_err(|json_error| StorageError(json_error.to_string()))
+}
+
+/// Reads a face box from the four columns starting at `first`, none when they are empty
+fn face_box_from_row(row: &Row, first: usize) -> rusqlite::Result<Option<FaceBox>> {
+    let coordinates: [Option<f64>; 4] =
+        [row.get(first)?, row.get(first + 1)?, row.get(first + 2)?, row.get(first + 3)?];
+    Ok(match coordinates {
+        [Some(left), Some(top), Some(width), Some(height)] => Some(FaceBox {
+            left: left as f32,
+            top: top as f32,
+            width: width as f32,
+            height: height as f32,
+        }),
+        _ => None,
+    })
+}
+
+/// Left, top, width and height of a face box, as stored in four columns
+fn face_box_columns(face_box: Option<&FaceBox>) -> [Option<f64>; 4] {
+    match face_box {
+        Some(face) => [Some(f64::from(face.left)), Some(f64::from(face.top)),
+            Some(f64::from(face.width)), Some(f64::from(face.height))],
+        None => [None; 4],
+    }
+}
+
+fn registration_from_row(row: &Row) -> rusqlite::Result<Registration> {
+    let similarity: Option<f64> = row.get(4)?;
+    let face_box: Option<FaceBox> = face_box_from_row(row, 5)?;
+    let capture_id: Option<String> = row.get(9)?;
+    let captured_at: Option<i64> = row.get(10)?;
+    Ok(Registration {
+        user_id: row.get(0)?,
+        email: row.get(1)?,
+        status: row.get(2)?,
+        conference_id: row.get(3)?,
+        attendance_match: match (similarity, capture_id, captured_at) {
+            (Some(similarity), Some(capture_id), Some(captured_at)) => Some(AttendanceMatch {
+                similarity: similarity as f32,
+                face_box,
+                capture_id,
+                captured_at,
+            }),
+            _ => None,
+        },
+        notified: row.get(11)?,
+    })
+}
+
+fn observation_from_row(row: &Row) -> rusqlite::Result<Observation> {
+    Ok(Observation {
+        user_id: row.get(0)?,
+        conference_id: row.get(1)?,
+        attendance_match: AttendanceMatch {
+            similarity: row.get::<_, f64>(4)? as f32,
+            face_box: face_box_from_row(row, 5)?,
+            capture_id: row.get(2)?,
+            captured_at: row.get(3)?,
+        },
+        approved: row.get(9)?,
+    })
+}
+
+fn review_from_row(row: &Row) -> rusqlite::Result<Review> {
+    Ok(Review {
+        id: row.get(0)?,
+        user_id: row.get(1)?,
+        conference_id: row.get(2)?,
+        attendance_match: AttendanceMatch {
+            similarity: row.get::<_, f64>(3)? as f32,
+            face_box: face_box_from_row(row, 4)?,
+            capture_id: row.get(8)?,
+            captured_at: row.get(9)?,
+        },
+        face_key: row.get(10)?,
+        reference_key: row.get(11)?,
+    })
+}
+
+fn reset_token_from_row(row: &Row) -> rusqlite::Result<ResetToken> {
+    Ok(ResetToken {
+        token_hash: row.get(0)?,
+        user_id: row.get(1)?,
+        expires_at: row.get(2)?,
+    })
+}
+
+impl UserRepository for SqliteStorage {
+    fn user_by_email(&self, email: &str) -> StorageResult<Option<User>> {
+        self.find_one(&format!("SELECT {} FROM users WHERE email = ?1", USER_COLUMNS),
+                      params![email], user_from_row)
+    }
+
+    fn user_by_id(&self, user_id: &str) -> StorageResult<Option<User>> {
+        self.find_one(&format!("SELECT {} FROM users WHERE id = ?1", USER_COLUMNS),
+                      params![user_id], user_from_row)
+    }
+
+    fn insert_user(&self, user: &User) -> StorageResult<()> {
+        self.execute(&format!("INSERT INTO users ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                              USER_COLUMNS),
+                     params![user.id, user.name, user.email, user.password, user.user_type,
+                             user.pending_since, user.pending_email, user.session_version])?;
+        Ok(())
+    }
+
+    fn update_user(&self, user_id: &str, name: &str, email: &str) -> StorageResult<bool> {
+        Ok(self.execute("UPDATE users SET name = ?2, email = ?3 \
+                         WHERE id = ?1 AND (name <> ?2 OR email <> ?3)",
+                        params![user_id, name, email])? > 0)
+    }
+
+    fn update_password(&self, email: &str, password: &str) -> StorageResult<bool> {
+        Ok(self.execute("UPDATE users SET password = ?2 WHERE email = ?1",
+                        params![email, password])? > 0)
+    }
+
+    fn revoke_sessions(&self, user_id: &str) -> StorageResult<bool> {
+        Ok(self.execute("UPDATE users SET session_version = session_version + 1 WHERE id = ?1",
+                        params![user_id])? > 0)
+    }
+
+    fn delete_user(&self, user_id: &str) -> StorageResult<bool> {
+        Ok(self.execute("DELETE FROM users WHERE id = ?1 AND user_type = ?2",
+                        params![user_id, USER])? > 0)
+    }
+
+    fn verify_user(&self, user_id: &str) -> StorageResult<bool> {
+        Ok(self.execute("UPDATE users SET pending_since = NULL \
+                         WHERE id = ?1 AND pending_since IS NOT NULL",
+                        params![user_id])? > 0)
+    }
+
+    fn set_pending_email(&self, user_id: &str, email: &str) -> StorageResult<bool> {
+        Ok(self.execute("UPDATE users SET pending_email = ?2 WHERE id = ?1",
+                        params![user_id, email])? > 0)
+    }
+
+    fn confirm_pending_email(&self, user_id: &str, email: &str) -> StorageResult<bool> {
+        Ok(self.execute("UPDATE users SET email = ?2, pending_email = NULL \
+                         WHERE id = ?1 AND pending_email = ?2",
+                        params![user_id, email])? > 0)
+    }
+
+    fn delete_unverified_users(&self, signed_up_before: i64) -> StorageResult<Vec<String>> {
+        let connection = self.connection()?;
+        let stale_ids: Vec<String> = connection
+            .prepare("SELECT id FROM users WHERE pending_since < ?1 AND user_type = ?2")?
+            .query_map(params![signed_up_before, USER], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+        connection.execute("DELETE FROM users WHERE pending_since < ?1 AND user_type = ?2",
+                           params![signed_up_before, USER])?;
+        Ok(stale_ids)
+    }
+}
+
+impl ConferenceRepository for SqliteStorage {
+    fn conference(&self, conference_id: &str) -> StorageResult<Option<Conference>> {
+        self.find_one(&format!("SELECT {} FROM conferences WHERE id = ?1", CONFERENCE_COLUMNS),
+                      params![conference_id], conference_from_row)
+    }
+
+    fn conferences_by_status(&self, status: &str) -> StorageResult<Vec<Conference>> {
+        self.find(&format!("SELECT {} FROM conferences WHERE status = ?1", CONFERENCE_COLUMNS),
+                  params![status], conference_from_row)
+    }
+
+    fn conferences_between(&self, first_date: &str, last_date: &str)
+                           -> StorageResult<Vec<Conference>> {
+        self.find(&format!("SELECT {} FROM conferences \
+                            WHERE conference_date >= ?1 AND conference_date <= ?2",
+                           CONFERENCE_COLUMNS),
+                  params![first_date, last_date], conference_from_row)
+    }
+
+    fn insert_conference(&self, conference: &Conference) -> StorageResult<()> {
+        self.execute(&format!("INSERT INTO conferences ({}) \
+                               VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                              CONFERENCE_COLUMNS),
+                     params![conference.id, conference.name, conference.conference_date,
+                             conference.conference_address1, conference.conference_address2,
+                             conference.conference_address3, conference.status,
+                             conference.similarity_threshold.map(f64::from),
+                             presence_rule_column(&conference.presence_rule)?])?;
+        Ok(())
+    }
+
+    fn update_conference(&self, conference_id: &str, update: &ConferenceUpdate)
+                         -> StorageResult<bool> {
+        Ok(self.execute("UPDATE conferences SET conference_date = ?2, conference_address1 = ?3, \
+                         conference_address2 = ?4, conference_address3 = ?5, \
+                         similarity_threshold = ?6, presence_rule = ?7 WHERE id = ?1",
+                        params![conference_id, update.conference_date,
+                                update.conference_address1, update.conference_address2,
+                                update.conference_address3,
+                                update.similarity_threshold.map(f64::from),
+                                presence_rule_column(&update.presence_rule)?])? > 0)
+    }
+
+    fn set_conference_status(&self, conference_id: &str, status: &str) -> StorageResult<bool> {
+        Ok(self.execute("UPDATE conferences SET status = ?2 WHERE id = ?1",
+                        params![conference_id, status])? > 0)
+    }
+
+    fn delete_conference(&self, conference_id: &str) -> StorageResult<bool> {
+        Ok(self.execute("DELETE FROM conferences WHERE id = ?1", params![conference_id])? > 0)
+    }
+}
+
+impl AttendanceRepository for SqliteStorage {
+    fn registration(&self, user_id: &str, conference_id: &str)
+                    -> StorageResult<Option<Registration>> {
+        self.find_one(&format!("SELECT {} FROM registrations \
+                                WHERE user_id = ?1 AND conference_id = ?2",
+                               REGISTRATION_COLUMNS),
+                      params![user_id, conference_id], registration_from_row)
+    }
+
+    fn insert_registration(&self, registration: &Registration) -> StorageResult<()> {
+        self.execute("INSERT INTO registrations (user_id, email, status, conference_id, notified) \
+                      VALUES (?1, ?2, ?3, ?4, ?5)",
+                     params![registration.user_id, registration.email, registration.status,
+                             registration.conference_id, registration.notified])?;
+        if let Some(ref attendance_match) = registration.attendance_match {
+            self.set_attendance_match(registration.user_id.as_str(),
+                                      registration.conference_id.as_str(), attendance_match)?;
+        }
+        Ok(())
+    }
+
+    fn registrations_for_conference(&self, conference_id: &str)
+                                    -> StorageResult<Vec<Registration>> {
+        self.find(&format!("SELECT {} FROM registrations WHERE conference_id = ?1",
+                           REGISTRATION_COLUMNS),
+                  params![conference_id], registration_from_row)
+    }
+
+    fn registrations_for_email(&self, email: &str) -> StorageResult<Vec<Registration>> {
+        self.find(&format!("SELECT {} FROM registrations WHERE email = ?1", REGISTRATION_COLUMNS),
+                  params![email], registration_from_row)
+    }
+
+    fn set_attendance_status(&self, user_id: &str, conference_id: &str, status: &str)
+                             -> StorageResult<bool> {
+        Ok(self.execute("UPDATE registrations SET status = ?3 \
+                         WHERE user_id = ?1 AND conference_id = ?2 AND status <> ?3",
+                        params![user_id, conference_id, status])? > 0)
+    }
+
+    fn set_attendance_match(&self, user_id: &str, conference_id: &str,
+                            attendance_match: &AttendanceMatch) -> StorageResult<bool> {
+        let [left, top, width, height] = face_box_columns(attendance_match.face_box.as_ref());
+        Ok(self.execute("UPDATE registrations SET match_similarity = ?3, match_left = ?4, \
+                         match_top = ?5, match_width = ?6, match_height = ?7, capture_id = ?8, \
+                         captured_at = ?9 WHERE user_id = ?1 AND conference_id = ?2",
+                        params![user_id, conference_id,
+                                f64::from(attendance_match.similarity),
+                                left, top, width, height,
+                                attendance_match.capture_id, attendance_match.captured_at])? > 0)
+    }
+
+    fn set_notified(&self, user_id: &str, conference_id: &str) -> StorageResult<bool> {
+        Ok(self.execute("UPDATE registrations SET notified = 1 \
+                         WHERE user_id = ?1 AND conference_id = ?2 AND notified = 0",
+                        params![user_id, conference_id])? > 0)
+    }
+
+    fn delete_registrations(&self, user_id: &str) -> StorageResult<()> {
+        self.execute("DELETE FROM registrations WHERE user_id = ?1", params![user_id])?;
+        Ok(())
+    }
+}
+
+impl ObservationRepository for SqliteStorage {
+    fn save_observation(&self, observation: &Observation) -> StorageResult<()> {
+        let attendance_match: &AttendanceMatch = &observation.attendance_match;
+        let [left, top, width, height] = face_box_columns(attendance_match.face_box.as_ref());
+        self.execute(&format!("INSERT OR REPLACE INTO observations ({}) \
+                               VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                              OBSERVATION_COLUMNS),
+                     params![observation.user_id, observation.conference_id,
+                             attendance_match.capture_id, attendance_match.captured_at,
+                             f64::from(attendance_match.similarity), left, top, width, height,
+                             observation.approved])?;
+        Ok(())
+    }
+
+    fn observations(&self, user_id: &str, conference_id: &str)
+                    -> StorageResult<Vec<Observation>> {
+        self.find(&format!("SELECT {} FROM observations \
+                            WHERE user_id = ?1 AND conference_id = ?2",
+                           OBSERVATION_COLUMNS),
+                  params![user_id, conference_id], observation_from_row)
+    }
+
+    fn observations_for_conference(&self, conference_id: &str)
+                                   -> StorageResult<Vec<Observation>> {
+        self.find(&format!("SELECT {} FROM observations WHERE conference_id = ?1",
+                           OBSERVATION_COLUMNS),
+                  params![conference_id], observation_from_row)
+    }
+
+    fn delete_observations(&self, user_id: &str) -> StorageResult<()> {
+        self.execute("DELETE FROM observations WHERE user_id = ?1", params![user_id])?;
+        Ok(())
+    }
+}
+
+impl ReviewRepository for SqliteStorage {
+    fn review(&self, review_id: &str) -> StorageResult<Option<Review>> {
+        self.find_one(&format!("SELECT {} FROM reviews WHERE id = ?1", REVIEW_COLUMNS),
+                      params![review_id], review_from_row)
+    }
+
+    fn reviews_for_conference(&self, conference_id: &str) -> StorageResult<Vec<Review>> {
+        self.find(&format!("SELECT {} FROM reviews WHERE conference_id = ?1", REVIEW_COLUMNS),
+                  params![conference_id], review_from_row)
+    }
+
+    fn reviews_for_user(&self, user_id: &str) -> StorageResult<Vec<Review>> {
+        self.find(&format!("SELECT {} FROM reviews WHERE user_id = ?1", REVIEW_COLUMNS),
+                  params![user_id], review_from_row)
+    }
+
+    fn insert_review(&self, review: &Review) -> StorageResult<()> {
+        let attendance_match: &AttendanceMatch = &review.attendance_match;
+        let [left, top, width, height] = face_box_columns(attendance_match.face_box.as_ref());
+        self.execute(&format!("INSERT INTO reviews ({}) \
+                               VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+                              REVIEW_COLUMNS),
+                     params![review.id, review.user_id, review.conference_id,
+                             f64::from(attendance_match.similarity), left, top, width, height,
+                             attendance_match.capture_id, attendance_match.captured_at,
+                             review.face_key, review.reference_key])?;
+        Ok(())
+    }
+
+    fn delete_review(&self, review_id: &str) -> StorageResult<bool> {
+        Ok(self.execute("DELETE FROM reviews WHERE id = ?1", params![review_id])? > 0)
+    }
+}
+
+impl ResetTokenRepository for SqliteStorage {
+    fn insert_reset_token(&self, token: &ResetToken) -> StorageResult<()> {
+        self.execute(&format!("INSERT INTO reset_tokens ({}) VALUES (?1, ?2, ?3)",
+                              RESET_TOKEN_COLUMNS),
+                     params![token.token_hash, token.user_id, token.expires_at])?;
+        Ok(())
+    }
+
+    fn take_reset_token(&self, token_hash: &str) -> StorageResult<Option<ResetToken>> {
+        let connection = self.connection()?;
+        let token: Option<ResetToken> = connection
+            .query_row(&format!("SELECT {} FROM reset_tokens WHERE token_hash = ?1",
+                                RESET_TOKEN_COLUMNS),
+                       params![token_hash], reset_token_from_row)
+            .optional()?;
+        connection.execute("DELETE FROM reset_tokens WHERE token_hash = ?1", params![token_hash])?;
+        Ok(token)
+    }
+
+    fn delete_reset_tokens(&self, user_id: &str) -> StorageResult<()> {
+        self.execute("DELETE FROM reset_tokens WHERE user_id = ?1", params![user_id])?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rusqlite::{Connection, params};
+
+    use crate::constants::{ABSENT, ADMIN, NOT_COMPLETED, USER};
+    use crate::storage::{AttendanceMatch, AttendanceRepository, Conference, ConferenceRepository,
+                         ConferenceUpdate, Observation, ObservationRepository, PresenceRule,
+                         Registration, ResetToken, ResetTokenRepository, Review, ReviewRepository,
+                         User, UserRepository};
+    use crate::storage::memory::test::{test_attendance_match, test_observation, test_review};
+    use crate::storage::sqlite::{add_missing_columns, SCHEMA, SqliteStorage};
+
+    static IN_MEMORY: &str = ":memory:";
+
+    fn user(id: &str, email: &str, user_type: &str) -> User {
+        User {
+            id: id.to_string(),
+            name: "Test".to_string(),
+            email: email.to_string(),
+            password: "hash".to_string(),
+            user_type: user_type.to_string(),
+            pending_since: None,
+            pending_email: None,
+            session_version: 0,
+        }
+    }
+
+    fn seeded_storage() -> SqliteStorage {
+        let storage: SqliteStorage = SqliteStorage::open(IN_MEMORY).unwrap();
+        storage.insert_user(&user("1111111111", "test@knoldus.in", USER)).unwrap();
+        storage.insert_user(&user("2222222222", "admin@knoldus.in", ADMIN)).unwrap();
+        storage.insert_conference(&Conference {
+            id: "5544332211".to_string(),
+            name: "test_conference".to_string(),
+            conference_date: "2019-06-24".to_string(),
+            conference_address1: "test_address_1".to_string(),
+            conference_address2: "test_address_2".to_string(),
+            conference_address3: "test_address_3".to_string(),
+            status: NOT_COMPLETED.to_string(),
+            similarity_threshold: None,
+            presence_rule: PresenceRule::AnyCapture,
+        }).unwrap();
+        storage.insert_registration(&Registration {
+            user_id: "1111111111".to_string(),
+            email: "test@knoldus.in".to_string(),
+            status: ABSENT.to_string(),
+            conference_id: "5544332211".to_string(),
+            attendance_match: None,
+            notified: false,
+        }).unwrap();
+        storage
+    }
+
+    #[test]
+    fn test_open_creates_schema_success() {
+        let storage: SqliteStorage = seeded_storage();
+        storage.connection().unwrap().execute_batch(SCHEMA).unwrap();
+        assert_eq!(storage.user_by_id("1111111111").unwrap(),
+                   Some(user("1111111111", "test@knoldus.in", USER)));
+    }
+
+    #[test]
+    fn test_open_failure() {
+        assert!(SqliteStorage::open("tests/missing_directory/conf_count.db").is_err());
+    }
+
+    #[test]
+    fn test_insert_user_duplicate_email_failure() {
+        assert!(seeded_storage().insert_user(&user("3333333333", "test@knoldus.in", USER))
+            .is_err());
+    }
+
+    #[test]
+    fn test_update_user_success() {
+        let storage: SqliteStorage = seeded_storage();
+        assert!(storage.update_user("1111111111", "Tester", "test@knoldus.in").unwrap());
+        assert!(!storage.update_user("1111111111", "Tester", "test@knoldus.in").unwrap());
+        assert!(storage.update_password("test@knoldus.in", "hash").unwrap());
+        assert!(!storage.update_password("unknown@knoldus.in", "hash").unwrap());
+        assert!(storage.revoke_sessions("1111111111").unwrap());
+        assert!(!storage.revoke_sessions("5555555555").unwrap());
+        assert_eq!(storage.user_by_id("1111111111").unwrap().unwrap().session_version, 1);
+    }
+
+    #[test]
+    fn test_delete_user_keeps_admins() {
+        let storage: SqliteStorage = seeded_storage();
+        assert!(!storage.delete_user("2222222222").unwrap());
+        assert!(storage.delete_user("1111111111").unwrap());
+        assert_eq!(storage.user_by_email("test@knoldus.in").unwrap(), None);
+    }
+
+    #[test]
+    fn test_open_adds_missing_columns_success() {
+        let connection: Connection = Connection::open_in_memory().unwrap();
+        connection.execute_batch("CREATE TABLE users (id TEXT PRIMARY KEY NOT NULL, \
+                                  name TEXT NOT NULL, email TEXT NOT NULL UNIQUE, \
+                                  password TEXT NOT NULL, user_type TEXT NOT NULL); \
+                                  CREATE TABLE registrations (user_id TEXT NOT NULL, \
+                                  email TEXT NOT NULL, status TEXT NOT NULL, \
+                                  conference_id TEXT NOT NULL); \
+                                  CREATE TABLE conferences (id TEXT PRIMARY KEY NOT NULL)")
+            .unwrap();
+        add_missing_columns(&connection).unwrap();
+        add_missing_columns(&connection).unwrap();
+        connection.execute("UPDATE users SET pending_since = 1", params![]).unwrap();
+        connection.execute("UPDATE users SET pending_email = NULL", params![]).unwrap();
+        connection.execute("UPDATE users SET session_version = 1", params![]).unwrap();
+        connection.execute("UPDATE registrations SET captured_at = 1", params![]).unwrap();
+        connection.execute("UPDATE registrations SET notified = 1", params![]).unwrap();
+        connection.execute("UPDATE conferences SET similarity_threshold = 60", params![]).unwrap();
+        connection.execute("UPDATE conferences SET presence_rule = NULL", params![]).unwrap();
+    }
+
+    #[test]
+    fn test_delete_unverified_users_success() {
+        let storage: SqliteStorage = seeded_storage();
+        let mut pending: User = user("3333333333", "pending@knoldus.in", USER);
+        pending.pending_since = Some(1000);
+        storage.insert_user(&pending).unwrap();
+        assert_eq!(storage.user_by_id("3333333333").unwrap(), Some(pending));
+        assert!(storage.delete_unverified_users(1000).unwrap().is_empty());
+        assert_eq!(storage.delete_unverified_users(1001).unwrap(), vec!["3333333333".to_string()]);
+        assert_eq!(storage.user_by_id("3333333333").unwrap(), None);
+        assert!(storage.user_by_id("1111111111").unwrap().is_some());
+        let mut verified: User = user("4444444444", "verified@knoldus.in", USER);
+        verified.pending_since = Some(1000);
+        storage.insert_user(&verified).unwrap();
+        assert!(storage.verify_user("4444444444").unwrap());
+        assert!(!storage.verify_user("4444444444").unwrap());
+        assert!(storage.delete_unverified_users(2000).unwrap().is_empty());
+        let mut pending_admin: User = user("5555555555", "pending-admin@knoldus.in", ADMIN);
+        pending_admin.pending_since = Some(1000);
+        storage.insert_user(&pending_admin).unwrap();
+        assert!(storage.delete_unverified_users(2000).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_confirm_pending_email_success() {
+        let storage: SqliteStorage = seeded_storage();
+        assert!(storage.set_pending_email("1111111111", "moved@knoldus.in").unwrap());
+        assert!(!storage.set_pending_email("5555555555", "moved@knoldus.in").unwrap());
+        assert_eq!(storage.user_by_id("1111111111").unwrap().unwrap().pending_email,
+                   Some("moved@knoldus.in".to_string()));
+        assert!(!storage.confirm_pending_email("1111111111", "other@knoldus.in").unwrap());
+        assert!(storage.confirm_pending_email("1111111111", "moved@knoldus.in").unwrap());
+        let user: User = storage.user_by_id("1111111111").unwrap().unwrap();
+        assert_eq!(user.email, "moved@knoldus.in");
+        assert_eq!(user.pending_email, None);
+        assert!(storage.set_pending_email("1111111111", "admin@knoldus.in").unwrap());
+        assert!(storage.confirm_pending_email("1111111111", "admin@knoldus.in").is_err());
+    }
+
+    #[test]
+    fn test_conferences_between_success() {
+        let storage: SqliteStorage = seeded_storage();
+        assert_eq!(storage.conferences_between("2019-06-01", "2019-06-30").unwrap().len(), 1);
+        assert!(storage.conferences_between("2019-07-01", "2019-07-30").unwrap().is_empty());
+        assert!(storage.delete_conference("5544332211").unwrap());
+        assert_eq!(storage.conference("5544332211").unwrap(), None);
+    }
+
+    #[test]
+    fn test_update_conference_success() {
+        let storage: SqliteStorage = seeded_storage();
+        let mut update: ConferenceUpdate = ConferenceUpdate {
+            conference_date: "2019-07-01".to_string(),
+            conference_address1: "a".to_string(),
+            conference_address2: "b".to_string(),
+            conference_address3: "c".to_string(),
+            similarity_threshold: Some(60.0),
+            presence_rule: PresenceRule::SeenInCaptures { seen: 2, captures: 3 },
+        };
+        assert!(storage.update_conference("5544332211", &update).unwrap());
+        assert!(!storage.update_conference("55443311", &update).unwrap());
+        let conference: Conference = storage.conference("5544332211").unwrap().unwrap();
+        assert_eq!(conference.conference_date, "2019-07-01");
+        assert_eq!(conference.similarity_threshold, Some(60.0));
+        assert_eq!(conference.presence_rule, PresenceRule::SeenInCaptures { seen: 2, captures: 3 });
+        update.similarity_threshold = None;
+        storage.update_conference("5544332211", &update).unwrap();
+        assert_eq!(storage.conference("5544332211").unwrap().unwrap().similarity_threshold, None);
+    }
+
+    #[test]
+    fn test_observations_success() {
+        let storage: SqliteStorage = seeded_storage();
+        storage.save_observation(&test_observation("first", 1000, 60.0)).unwrap();
+        let mut observation: Observation = test_observation("second", 2000, 0.0);
+        observation.attendance_match.face_box = None;
+        observation.approved = true;
+        storage.save_observation(&observation).unwrap();
+        storage.save_observation(&test_observation("first", 1000, 90.0)).unwrap();
+        let observations: Vec<Observation> =
+            storage.observations("1111111111", "5544332211").unwrap();
+        assert_eq!(observations.len(), 2);
+        assert!(observations.contains(&test_observation("first", 1000, 90.0)));
+        assert!(observations.contains(&observation));
+        assert_eq!(storage.observations_for_conference("5544332211").unwrap().len(), 2);
+        assert!(storage.observations("2222222222", "5544332211").unwrap().is_empty());
+        storage.delete_observations("1111111111").unwrap();
+        assert!(storage.observations_for_conference("5544332211").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_reviews_success() {
+        let storage: SqliteStorage = seeded_storage();
+        let mut review: Review = test_review("review", 70.0);
+        storage.insert_review(&review).unwrap();
+        review.id = "unboxed".to_string();
+        review.attendance_match.face_box = None;
+        review.face_key = None;
+        storage.insert_review(&review).unwrap();
+        assert_eq!(storage.review("review").unwrap(), Some(test_review("review", 70.0)));
+        assert_eq!(storage.review("unboxed").unwrap(), Some(review));
+        assert_eq!(storage.reviews_for_conference("5544332211").unwrap().len(), 2);
+        assert_eq!(storage.reviews_for_user("1111111111").unwrap().len(), 2);
+        assert!(storage.reviews_for_user("2222222222").unwrap().is_empty());
+        assert!(storage.delete_review("review").unwrap());
+        assert!(!storage.delete_review("review").unwrap());
+        assert_eq!(storage.reviews_for_conference("5544332211").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_set_attendance_status_success() {
+        let storage: SqliteStorage = seeded_storage();
+        assert!(storage.set_attendance_status("1111111111", "5544332211", "present").unwrap());
+        assert!(!storage.set_attendance_status("1111111111", "5544332211", "present").unwrap());
+        assert_eq!(storage.registrations_for_conference("5544332211").unwrap()[0].status,
+                   "present");
+        storage.delete_registrations("1111111111").unwrap();
+        assert!(storage.registrations_for_conference("5544332211").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_set_notified_once() {
+        let storage: SqliteStorage = seeded_storage();
+        assert!(!storage.registration("1111111111", "5544332211").unwrap().unwrap().notified);
+        assert!(storage.set_notified("1111111111", "5544332211").unwrap());
+        assert!(!storage.set_notified("1111111111", "5544332211").unwrap());
+        assert!(storage.registration("1111111111", "5544332211").unwrap().unwrap().notified);
+        assert!(storage.set_conference_status("5544332211", "completed").unwrap());
+        assert!(!storage.set_conference_status("55443311", "completed").unwrap());
+        assert_eq!(storage.conferences_by_status("completed").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_set_attendance_match_success() {
+        let storage: SqliteStorage = seeded_storage();
+        let mut attendance_match: AttendanceMatch = test_attendance_match(91.5);
+        assert!(storage.set_attendance_match("1111111111", "5544332211", &attendance_match)
+            .unwrap());
+        assert!(!storage.set_attendance_match("1111111111", "55443311", &attendance_match)
+            .unwrap());
+        assert_eq!(storage.registration("1111111111", "5544332211").unwrap().unwrap()
+                       .attendance_match, Some(attendance_match.clone()));
+        attendance_match.face_box = None;
+        storage.set_attendance_match("1111111111", "5544332211", &attendance_match).unwrap();
+        assert_eq!(storage.registrations_for_email("test@knoldus.in").unwrap()[0]
+                       .attendance_match, Some(attendance_match));
+    }
+
+    #[test]
+    fn test_take_reset_token_once() {
+        let storage: SqliteStorage = seeded_storage();
+        let token: ResetToken = ResetToken {
+            token_hash: "hash".to_string(),
+            user_id: "1111111111".to_string(),
+            expires_at: 1000,
+        };
+        storage.insert_reset_token(&token).unwrap();
+        assert_eq!(storage.take_reset_token("hash").unwrap(), Some(token.clone()));
+        assert_eq!(storage.take_reset_token("hash").unwrap(), None);
+        storage.insert_reset_token(&token).unwrap();
+        storage.delete_reset_tokens("1111111111").unwrap();
+        assert_eq!(storage.take_reset_token("hash").unwrap(), None);
+    }
+}
@@ -1,219 +1,185 @@
-use std::{ffi::OsStr, fs, path::Path};
-use std::fs::File;
-use std::io::{BufRead, BufReader};
-use std::thread::spawn;
+use std::borrow::Cow;
 
 use log::error;
-use log::info;
-use mongodb::{bson, doc};
-use mongodb::coll::Collection;
-use s3::bucket::Bucket;
-
-use crate::connection::{connect_database_collection, create_bucket_connection, create_db_connection,
-                        insert_data};
-use crate::constants::{ALREADY_REGISTERED, DB_NAME, EMAIL, ID, NAME, PASSWORD, S3_UPLOAD_SUCCESS,
-                       USER, USER_TYPE, USERS_COLLECTION};
-use crate::utils::{check_email_format, check_name_format};
-
-const IMAGE_FORMATS: [&str; 2] = ["jpg", "png"];
-static INVALID_IMAGE_FORMAT: &str = "Invalid Image Format";
-static IO_ERROR: &str = "No such file or directory found";
-static S3_UPLOAD_FAILURE: &str = "Unable to upload image on S3 bucket";
-static INVALID_EXTENSION: &str = "Invalid extension";
-static REGISTRATION_UNSUCCESSFUL: &str = "Registration unsuccessful as some fields are missing";
-const SUCCESS_STATUS_CODE: u32 = 200;
-const S3ERROR: u32 = 400;
-
-/// Get the mime type for a clicked image
+
+use crate::config::PhotoConfig;
+use crate::constants::{ALREADY_REGISTERED, USER};
+use crate::error::{ConfCountError, ConfCountResult};
+use crate::password::hash_password;
+use crate::photo_processing::{normalize_photo, NormalizedPhoto, STORED_CONTENT_TYPE};
+use crate::photo_store::PhotoStore;
+use crate::signup_form::{Photo, SignupForm};
+use crate::storage::{Storage, User};
+
+/// Reads the photo of a signup form and validates it by its content
 ///
 /// # Arguments
 ///
-/// * `file` - This is the path of user's image
+/// * `limits` - Limits of the accepted and of the stored photos
+///
+/// * `photo` - Photo uploaded by the user
 ///
 /// # Return
 ///
-/// This function returns mime type of user's image
-fn extract_image_extension(file: &str) -> Result<&str, &'static str> {
-    let clicked_image_format: &str = Path::new(file)
-        .extension()
-        .and_then(OsStr::to_str)
-        .unwrap_or(INVALID_EXTENSION);
-    for image_format in IMAGE_FORMATS.iter() {
-        if *image_format == clicked_image_format {
-            return Ok(clicked_image_format);
-        }
-    }
-    Err(INVALID_IMAGE_FORMAT)
+/// Returns the photo normalized for the storage, or the reason it was rejected
+pub fn normalize_user_image(limits: &PhotoConfig, photo: &Photo)
+                            -> ConfCountResult<NormalizedPhoto> {
+    let clicked_image: Cow<[u8]> = photo.bytes().map_err(|read_error| {
+        ConfCountError::Internal(format!("Unable to read a spooled photo: {}", read_error))
+    })?;
+    normalize_photo(limits, &clicked_image)
 }
 
 
-/// This function upload the user image on s3 bucket returning a string response message
+/// This function stores the user image in the photo store
 ///
 /// # Arguments
 ///
-/// * `upload_credentials` - This is the instance of UploadCredentials
+/// * `photos` - Store holding the photos of the users
+///
+/// * `photo` - Normalized photo of the user
 ///
 /// * `user_id` - This is the unique id of the user
 ///
 /// # Return
 ///
-/// This function returns image upload response message from the s3 bucket.
-pub fn upload_user_image(user_image: &'static str, user_id: String) -> &'static str {
-    let bucket: Bucket = create_bucket_connection();
-    match extract_image_extension(user_image.trim()) {
-        Err(file_extension_error) => file_extension_error,
-        Ok(content_type) => match fs::read(user_image.trim()) {
-            Ok(clicked_image) => {
-                match
-                    spawn(move || match bucket.put(user_id.as_str(),
-                                                   clicked_image.as_slice(), content_type) {
-                        Ok(s3_result) => s3_result.1,
-                        Err(s3_error) => {
-                            error!("{:?}", s3_error);
-                            S3ERROR
-                        }
-                    }).join().unwrap()
-                    {
-                        SUCCESS_STATUS_CODE => S3_UPLOAD_SUCCESS,
-                        _ => S3_UPLOAD_FAILURE,
-                    }
-            }
-            Err(_) => IO_ERROR,
-        },
-    }
+/// This function returns the failure of the photo store, if any.
+pub fn upload_user_image(
+    photos: &dyn PhotoStore,
+    photo: &NormalizedPhoto,
+    user_id: String,
+) -> ConfCountResult<()> {
+    photos.put(user_id.as_str(), photo.bytes(), STORED_CONTENT_TYPE)?;
+    Ok(())
 }
 
 
-/// Write user form data in mongodb
+/// Checks that no account is registered with an email address yet
 ///
 /// # Arguments
 ///
-/// * `user_id` - This is the unique user identity number
+/// * `storage` - Storage holding the users
+///
+/// * `email` - Email address of the signup form
 ///
-/// * `user_data_path` - File path of user data
+/// # Return
+///
+/// Returns the conflict of an already registered address, or the failure of the storage
+pub fn check_email_available(storage: &dyn Storage, email: &str) -> ConfCountResult<()> {
+    if storage.user_by_email(email)?.is_some() {
+        error!("User with email {} already registered", email);
+        return Err(ConfCountError::Conflict(ALREADY_REGISTERED.to_string()));
+    }
+    Ok(())
+}
+
+/// Write the user of a signup form in the storage
 ///
-/// * `db_name` - Database Name
+/// # Arguments
+///
+/// * `storage` - Storage holding the users
+///
+/// * `user_id` - This is the unique user identity number
 ///
-/// * `collection_name` - Collection Name
+/// * `form` - Validated signup form of the user
+///
+/// * `signed_up_at` - Current UNIX time in seconds, from which the user is pending verification
 ///
 /// # Return
 ///
-/// Returns success or failure message of write method
+/// Returns the registered user, or the failure of write method
 pub fn write_user_info(
+    storage: &dyn Storage,
     user_id: String,
-    user_data_path: &'static str,
-) -> &'static str {
-    let mut registration_id: String = String::new();
-    let mut name: String = String::new();
-    let mut email: String = String::new();
-    let mut password: String = String::new();
-    registration_id.push_str(user_id.as_str());
-    let file: File = File::open(user_data_path).unwrap();
-    for (counter, line) in BufReader::new(file).lines().enumerate() {
-        match counter + 1 {
-            1 => name.push_str(line.unwrap().as_str()),
-            2 => email.push_str(line.unwrap().as_str()),
-            3 => password.push_str(line.unwrap().as_str()),
-            _ => info!("No data found"),
-        }
-    }
-
-    if (check_name_format(name.as_str()) && check_email_format(email.as_str()))
-        && !password.is_empty() {
-        let collection: Collection =
-            connect_database_collection(
-                create_db_connection(), DB_NAME, USERS_COLLECTION);
-        let filter_doc = doc! {EMAIL: email.clone()};
-        match collection.count(Some(filter_doc), None).expect("Unable to connect to Mongo DB") {
-            0 => {
-                let data = doc! {
-                                    ID: registration_id.as_str(),
-                                    NAME: name.as_str(),
-                                    EMAIL: email.as_str(),
-                                    PASSWORD: password.as_str(),
-                                    USER_TYPE: USER,
-                                };
-                insert_data(collection, data)
-            }
-            _ => {
-                error!("User with email {} already registered", email);
-                ALREADY_REGISTERED
-            }
-        }
-    } else {
-        error!("{}", REGISTRATION_UNSUCCESSFUL);
-        REGISTRATION_UNSUCCESSFUL
-    }
+    form: &SignupForm,
+    signed_up_at: i64,
+) -> ConfCountResult<User> {
+    check_email_available(storage, form.email.as_str())?;
+    let user: User = User {
+        id: user_id,
+        name: form.name.clone(),
+        email: form.email.clone(),
+        password: hash_password(&form.password)?,
+        user_type: USER.to_string(),
+        pending_since: Some(signed_up_at),
+        pending_email: None,
+        session_version: 0,
+    };
+    storage.insert_user(&user)?;
+    Ok(user)
 }
 
 
 #[cfg(test)]
 pub mod tests {
-
-    use crate::constants::{ALREADY_REGISTERED, S3_UPLOAD_SUCCESS};
-    use crate::user_data_upload_operations::{extract_image_extension, INVALID_IMAGE_FORMAT, IO_ERROR,
-                                             REGISTRATION_UNSUCCESSFUL, upload_user_image,
+    use std::fs;
+    use std::sync::Arc;
+
+    use crate::config::PhotoConfig;
+    use crate::constants::ALREADY_REGISTERED;
+    use crate::error::ConfCountError;
+    use crate::password::verify_password;
+    use crate::photo_processing::NormalizedPhoto;
+    use crate::photo_store::PhotoStore;
+    use crate::signup_form::{INVALID_IMAGE_FORMAT, Photo, UPLOAD_IMAGE};
+    use crate::signup_form::test::test_form;
+    use crate::state::test::test_state;
+    use crate::storage::{User, UserRepository};
+    use crate::storage::memory::MemoryStorage;
+    use crate::storage::memory::test::test_storage;
+    use crate::user_data_upload_operations::{normalize_user_image, upload_user_image,
                                              write_user_info};
 
     pub static TEST_IMAGE_FILE: &str = "tests/resources/test.jpg";
     pub static TEST_UUID: &str = "1122334455";
-    static TEST_TEXT_FILE: &str = "tests/resources/test.txt";
 
-    #[test]
-    fn test_extract_image_extension_success() {
-        assert_eq!(
-            extract_image_extension(TEST_IMAGE_FILE).unwrap_or_default(),
-            "jpg"
-        )
+    fn test_image() -> NormalizedPhoto {
+        let photo: Photo = Photo::Memory(fs::read(TEST_IMAGE_FILE).unwrap());
+        normalize_user_image(&PhotoConfig::default(), &photo).unwrap()
     }
 
     #[test]
-    fn test_extract_image_extension_failure() {
-        assert_eq!(
-            extract_image_extension(TEST_TEXT_FILE).unwrap_err(),
-            INVALID_IMAGE_FORMAT
-        );
+    fn test_normalize_user_image_failure() {
+        let photo: Photo = Photo::Memory(fs::read("tests/resources/test.txt").unwrap());
+        assert_eq!(normalize_user_image(&PhotoConfig::default(), &photo).unwrap_err(),
+                   ConfCountError::invalid_field(UPLOAD_IMAGE, INVALID_IMAGE_FORMAT));
     }
 
     #[test]
     fn test_upload_user_image_success() {
-        let upload_image: &str = TEST_IMAGE_FILE;
         assert_eq!(
-            upload_user_image(upload_image, TEST_UUID.to_string()),
-            S3_UPLOAD_SUCCESS
+            upload_user_image(&*test_state().photo_store, &test_image(), TEST_UUID.to_string()),
+            Ok(())
         );
     }
 
     #[test]
-    fn test_upload_user_image_failure() {
-        let upload_image: &str = "tests/wrong_path.jpg";
-        assert_eq!(
-            upload_user_image(upload_image, TEST_UUID.to_string()),
-            IO_ERROR
-        );
-    }
-
-
-    #[test]
-    fn test_upload_user_image_file_format_failure() {
-        let upload_image: &str = TEST_TEXT_FILE;
-        assert_eq!(
-            upload_user_image(upload_image, TEST_UUID.to_string()),
-            INVALID_IMAGE_FORMAT
-        );
+    fn test_upload_user_image_stored() {
+        let photos: Arc<dyn PhotoStore> = test_state().photo_store;
+        upload_user_image(&*photos, &test_image(), "3344556677".to_string()).unwrap();
+        assert!(photos.list().unwrap().contains(&"3344556677".to_string()));
     }
 
     #[test]
-    fn test_put_data_mongo_db_already_register_failure() {
+    fn test_put_data_success() {
+        let storage: MemoryStorage = MemoryStorage::default();
+        let user: User = write_user_info(&storage, TEST_UUID.to_owned(),
+                                         &test_form("new@knoldus.in", vec![1]), 1000)
+            .unwrap();
+        assert_eq!(user.pending_since, Some(1000));
+        assert!(verify_password("test_password", &user.password));
+        assert_eq!(storage.user_by_id(TEST_UUID).unwrap(), Some(user));
         assert_eq!(
-            write_user_info(TEST_UUID.to_owned(), TEST_TEXT_FILE),
-            ALREADY_REGISTERED)
+            write_user_info(&storage, TEST_UUID.to_owned(), &test_form("new@knoldus.in", vec![1]),
+                            1000),
+            Err(ConfCountError::Conflict(ALREADY_REGISTERED.to_string())))
     }
 
     #[test]
-    fn test_put_data_mongo_db_invalid_data_failure() {
+    fn test_put_data_already_register_failure() {
         assert_eq!(
-            write_user_info(TEST_UUID.to_owned(), "tests/resources/test_file"),
-            REGISTRATION_UNSUCCESSFUL)
+            write_user_info(&test_storage(), TEST_UUID.to_owned(),
+                            &test_form("test@knoldus.in", vec![1]), 1000),
+            Err(ConfCountError::Conflict(ALREADY_REGISTERED.to_string())))
     }
 }
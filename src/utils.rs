@@ -1,31 +1,66 @@
+use std::collections::HashSet;
 use std::path::Path;
 
-use crypto::digest::Digest;
-use crypto::sha2::Sha256;
+use actix_web::http::HeaderMap;
 use log::{error, info};
-use mongodb::{bson, doc};
-use mongodb::coll::Collection;
-use mongodb::cursor::Cursor;
-use mongodb::Document;
 use regex;
 use regex::RegexSet;
 
-use crate::connection::{connect_database_collection, create_db_connection};
-use crate::constants::{ABSENT, ADMIN, ALREADY_REGISTERED, CONFERENCE_COLLECTION, CONFERENCE_DETAILS,
-                       CONFERENCE_ID, CONFERENCE_NAME, DB_NAME, DELETION_FAIL,
-                       DELETION_SUCCESS, EMAIL, ID, NAME, NO_CONFERENCE, NON_REGISTERED_USER_MESSAGE,
-                       NOT_COMPLETED, PASSWORD, REGISTERED_USER_MESSAGE, SET, STATUS, UPDATION_FAIL,
-                       UPDATION_SUCCESS, USER, USER_ID, USER_TYPE, USERS_COLLECTION,
-                       WRONG_EMAIL_FORMAT, WRONG_PASSWORD_FORMAT};
+use crate::constants::{ABSENT, ADMIN, ALREADY_REGISTERED, COLLECTION_EMPTY, DELETION_SUCCESS,
+                       EMAIL, NO_CONFERENCE, NON_REGISTERED_USER_MESSAGE, NOT_COMPLETED, PASSWORD,
+                       REGISTERED_USER_MESSAGE, UPDATION_SUCCESS, USER, WRONG_EMAIL_FORMAT,
+                       WRONG_PASSWORD_FORMAT};
+use crate::config::AppConfig;
+use crate::db_operations::discard_review;
+use crate::email_service::{send_reset_link, send_verification_link};
+use crate::error::{ConfCountError, ConfCountResult, ensure, Validator};
+use crate::face_matching::FaceMatcher;
+use crate::mail::Mailer;
+use crate::password::{generate_token, hash_password, hash_token, needs_rehash, verify_password};
+use crate::photo_store::PhotoStore;
+use crate::session::{issue_verification_token, verification_token_user,
+                     verify_verification_token};
+use crate::storage::{Conference, ConferenceUpdate, PresenceRule, Registration, ResetToken, Review,
+                     Storage, User};
 
 static WRONG_PASSWORD: &str = "Wrong Password";
 static WRONG_USER_TYPE: &str = "User Type is ambiguous, Please contact Admin";
-static NO_USER_TYPE: &str = "User_type field not present";
 static RESET_SUCCESSFUL: &str = "Password has been reset successfully";
 static RESET_UNSUCCESSFUL: &str = "Password and confirm password field does not match";
+static RESET_LINK_INVALID: &str = "The reset link is invalid, already used or expired";
+static RESET_LINK_PATH: &str = "/reset-password?token=";
+static VERIFICATION_LINK_PATH: &str = "/verify-email?token=";
+static VERIFICATION_LINK_INVALID: &str = "The verification link is invalid or expired";
+static EMAIL_VERIFIED: &str = "Your email address is verified, you can now log in";
+static EMAIL_ALREADY_VERIFIED: &str = "Your email address is already verified";
+static EMAIL_CHANGED: &str = "Your new email address is verified, log in with it from now on";
+pub static EMAIL_NOT_VERIFIED: &str =
+    "Please verify your email address through the link mailed to you at signup";
 static EMPTY_DATA: &str = "No such value exists";
-static ADDITION_FAIL: &str = "Error occurred in adding conference";
 static ADDITION_SUCCESS: &str = "Successfully added conference";
+static CONFERENCE_REGISTERED: &str = "Conference Registered";
+static CONFERENCE_ALREADY_REGISTERED: &str = "Conference Already Registered";
+pub static USER_DETAILS_UPDATED: &str = "User details updated";
+pub static USER_ID_NOT_FOUND: &str = "User ID not found";
+static EMAIL_TAKEN: &str = "Email is already registered to another account";
+pub static MISSING_HEADER: &str = "Header is missing";
+
+/// Reads a header of a request
+///
+/// # Argument
+///
+/// * `headers` - Headers of an HTTP request
+///
+/// * `name` - Name of the header
+///
+/// # Return
+///
+/// Returns the value of the header, or the field error of a missing header
+pub fn header_value<'a>(headers: &'a HeaderMap, name: &str) -> ConfCountResult<&'a str> {
+    headers.get(name)
+        .and_then(|value| value.to_str().ok())
+        .ok_or_else(|| ConfCountError::invalid_field(name, MISSING_HEADER))
+}
 
 /// Validate weather function's arguments are empty or not
 ///
@@ -125,80 +160,321 @@ pub fn check_name_format(name: &str) -> bool {
     RegexSet::new(&[r"\b[a-zA-Z]{2,35}\b"]).unwrap().is_match(name)
 }
 
-/// Validate user email-id, password and returns the user type or error,
+/// Parses the similarity threshold of a conference, as entered by an admin
+///
+/// # Argument
+///
+/// * `threshold` - Similarity from 0 to 100, empty for the configured threshold
+///
+/// # Return
+///
+/// Returns the threshold of the conference, none when it is not a valid one
+pub fn parse_similarity_threshold(threshold: &str) -> Option<Option<f32>> {
+    match threshold.trim() {
+        "" => Some(None),
+        threshold => threshold.parse::<f32>().ok()
+            .filter(|threshold| (0.0..=100.0).contains(threshold))
+            .map(Some),
+    }
+}
+
+/// Parses the presence rule of a conference, as entered by an admin
+///
+/// # Arguments
+///
+/// * `rule` - Name of the rule, empty for presence from any capture
+///
+/// * `seen` - Captures the user must be seen in, for `seen_in_captures`
+///
+/// * `captures` - First captures of the conference counted, for `seen_in_captures`
+///
+/// * `minutes` - Minutes after the first capture the user must be seen within, for
+/// `seen_within_minutes`
+///
+/// # Return
+///
+/// Returns the presence rule, none when it is not a valid one
+pub fn parse_presence_rule(rule: &str, seen: &str, captures: &str, minutes: &str)
+                           -> Option<PresenceRule> {
+    let count = |value: &str| value.trim().parse::<u32>().ok().filter(|count| *count > 0);
+    match rule.trim() {
+        "" | "any_capture" => Some(PresenceRule::AnyCapture),
+        "seen_in_captures" => match (count(seen), count(captures)) {
+            (Some(seen), Some(captures)) if seen <= captures =>
+                Some(PresenceRule::SeenInCaptures { seen, captures }),
+            _ => None,
+        },
+        "seen_within_minutes" =>
+            count(minutes).map(|minutes| PresenceRule::SeenWithinMinutes { minutes }),
+        _ => None,
+    }
+}
+
+/// Validate user email-id, password and returns the signed in user or error,
 /// based on the input in the Login form
 ///
 /// # Arguments
 ///
+/// * `storage` - Storage holding the users
+///
 /// * `email_id` - Email-id entered by the user
 ///
 /// * `password` - Password entered by the user
 ///
 /// # Return
 ///
-/// Returns type of user
-pub fn validate_user_details(
+/// Returns the user, whose type is checked
+pub fn authenticate_user(
+    storage: &dyn Storage,
     email_id: &str,
     password: &str,
-) -> &'static str {
-    if check_email_format(email_id) {
-        if check_password_format(password) {
-            let email = doc! {EMAIL: email_id};
-            let users_collection: Collection = connect_database_collection
-                (create_db_connection(), DB_NAME, USERS_COLLECTION);
-            match users_collection.find_one(Some(email), None).unwrap() {
-                Some(email_doc) => {
-                    validate_password(email_doc, password)
-                }
-                None => {
-                    error!("A non registered user tried to login with email id as {}", email_id);
-                    NON_REGISTERED_USER_MESSAGE
-                }
+) -> ConfCountResult<User> {
+    Validator::new()
+        .check(check_email_format(email_id), EMAIL, WRONG_EMAIL_FORMAT)
+        .check(check_password_format(password), PASSWORD, WRONG_PASSWORD_FORMAT)
+        .finish()?;
+    match storage.user_by_email(email_id)? {
+        Some(user) => {
+            validate_password(&user, password)?;
+            if user.pending_since.is_some() {
+                error!("User with unverified email {} tried to login", email_id);
+                return Err(ConfCountError::Forbidden(EMAIL_NOT_VERIFIED.to_string()));
             }
-        } else {
-            error!("{}", WRONG_PASSWORD_FORMAT);
-            WRONG_PASSWORD_FORMAT
+            if needs_rehash(user.password.as_str()) {
+                rehash_password(storage, &user, password);
+            }
+            Ok(user)
+        }
+        None => {
+            error!("A non registered user tried to login with email id as {}", email_id);
+            Err(ConfCountError::NotFound(NON_REGISTERED_USER_MESSAGE.to_string()))
         }
-    } else {
-        error!("{}", WRONG_EMAIL_FORMAT);
-        WRONG_EMAIL_FORMAT
     }
 }
 
-/// Validate user email-id
+/// Upgrades the legacy hash of a user who just logged in to the current format. The login
+/// goes on with the legacy hash if the upgrade fails, to be retried on the next one.
 ///
 /// # Arguments
 ///
-/// * `users_collection` - Instance of the Database collection
+/// * `storage` - Storage holding the users
+///
+/// * `user` - User who just logged in
+///
+/// * `password` - Password the user logged in with
+fn rehash_password(storage: &dyn Storage, user: &User, password: &str) {
+    let upgrade: ConfCountResult<bool> = hash_password(password)
+        .and_then(|hash| storage.update_password(user.email.as_str(), hash.as_str())
+            .map_err(ConfCountError::from));
+    match upgrade {
+        Ok(_) => info!("Password hash of user {} upgraded to the current format", user.id),
+        Err(error) => error!("Unable to upgrade the password hash of user {}: {}", user.id, error),
+    }
+}
+
+/// Mails a link resetting the password to a registered user. The link carries a random token
+/// which is stored hashed and can be used once, until it expires.
+///
+/// # Arguments
+///
+/// * `storage` - Storage holding the users and the pending resets
+///
+/// * `mailer` - Mailer delivering the link
+///
+/// * `config` - Configuration of the application
 ///
 /// * `email_id` - Email-id entered by the user
 ///
+/// * `now` - Current UNIX time in seconds
+///
 /// # Return
 ///
 /// Returns user message
-pub fn validate_user_email(
-    email_id: &str
-) -> &'static str {
-    let users_collection: Collection = connect_database_collection
-        (create_db_connection(), DB_NAME, USERS_COLLECTION);
-    let email = doc! {EMAIL: email_id};
-    match users_collection.find_one(Some(email), None).unwrap() {
-        Some(_) => {
-            info!("Registered user with email {} accessed forgot password module", email_id);
-            REGISTERED_USER_MESSAGE
-        }
+pub fn send_password_reset(
+    storage: &dyn Storage,
+    mailer: &dyn Mailer,
+    config: &AppConfig,
+    email_id: &str,
+    now: i64,
+) -> ConfCountResult<&'static str> {
+    let user: User = match storage.user_by_email(email_id)? {
+        Some(user) => user,
         None => {
-            error!("Non registered user with email {} tried to access forget password module", email_id);
-            NON_REGISTERED_USER_MESSAGE
+            error!("Non registered user with email {} tried to access forget password module",
+                   email_id);
+            return Err(ConfCountError::NotFound(NON_REGISTERED_USER_MESSAGE.to_string()));
+        }
+    };
+    let token: String = generate_token();
+    let ttl_minutes: i64 = config.password_reset.token_ttl_minutes;
+    storage.insert_reset_token(&ResetToken {
+        token_hash: hash_token(token.as_str()),
+        user_id: user.id.clone(),
+        expires_at: now + ttl_minutes * 60,
+    })?;
+    let link: String = format!("{}{}{}", config.server.public_url.trim_end_matches('/'),
+                               RESET_LINK_PATH, token);
+    send_reset_link(mailer, config.mail.sender.as_str(), user.email.as_str(), link.as_str(),
+                    ttl_minutes)?;
+    info!("Password reset link mailed to the user with email {}", email_id);
+    Ok(REGISTERED_USER_MESSAGE)
+}
+
+/// Mails the link verifying the email address of a user who just signed up
+///
+/// # Arguments
+///
+/// * `mailer` - Mailer delivering the link
+///
+/// * `config` - Configuration of the application
+///
+/// * `user` - User pending verification
+///
+/// # Return
+///
+/// Returns the failure of delivering the mail, if any
+pub fn send_email_verification(
+    mailer: &dyn Mailer,
+    config: &AppConfig,
+    user: &User,
+) -> ConfCountResult<()> {
+    let signed_up_at: i64 = user.pending_since.unwrap_or_default();
+    mail_verification_link(mailer, config, user.id.as_str(), user.email.as_str(), signed_up_at)
+}
+
+/// Mails a link verifying an email address of a user, valid for
+/// `verification.pending_ttl_hours` from the given UNIX time
+fn mail_verification_link(
+    mailer: &dyn Mailer,
+    config: &AppConfig,
+    user_id: &str,
+    email: &str,
+    issued_at: i64,
+) -> ConfCountResult<()> {
+    let ttl_hours: i64 = config.verification.pending_ttl_hours;
+    let expiry: i64 = issued_at + ttl_hours * 3600;
+    let token: String =
+        issue_verification_token(&config.session, user_id, email, expiry.max(0) as u64);
+    let link: String = format!("{}{}{}", config.server.public_url.trim_end_matches('/'),
+                               VERIFICATION_LINK_PATH, token);
+    send_verification_link(mailer, config.mail.sender.as_str(), email, link.as_str(), ttl_hours)?;
+    info!("Verification link mailed to the user with email {}", email);
+    Ok(())
+}
+
+/// Verifies the email address of a pending user, or the new address of a user changing it, from
+/// the token of the mailed link
+///
+/// # Arguments
+///
+/// * `storage` - Storage holding the users
+///
+/// * `config` - Configuration of the application
+///
+/// * `token` - Token of the verification link
+///
+/// * `now` - Current UNIX time in seconds
+///
+/// # Return
+///
+/// Returns the verification message
+pub fn verify_email(
+    storage: &dyn Storage,
+    config: &AppConfig,
+    token: &str,
+    now: u64,
+) -> ConfCountResult<&'static str> {
+    let invalid_link = || {
+        error!("An email verification was attempted with an invalid or expired token");
+        ConfCountError::Unauthorized(VERIFICATION_LINK_INVALID.to_string())
+    };
+    let user_id: &str = verification_token_user(token).ok_or_else(invalid_link)?;
+    let user: User = storage.user_by_id(user_id)?.ok_or_else(|| {
+        error!("Verification link opened for user {} who no longer exists", user_id);
+        ConfCountError::NotFound(NON_REGISTERED_USER_MESSAGE.to_string())
+    })?;
+    if let Some(pending_email) = user.pending_email.as_ref() {
+        if verify_verification_token(&config.session, token, pending_email, now).is_some() {
+            return confirm_email_change(storage, user_id, pending_email);
         }
     }
+    verify_verification_token(&config.session, token, user.email.as_str(), now)
+        .ok_or_else(invalid_link)?;
+    if storage.verify_user(user_id)? {
+        info!("Email address of user {} verified", user_id);
+        Ok(EMAIL_VERIFIED)
+    } else {
+        Ok(EMAIL_ALREADY_VERIFIED)
+    }
+}
+
+/// Moves a user to the new email address it just verified, unless another account took the
+/// address in the meantime
+fn confirm_email_change(storage: &dyn Storage, user_id: &str, email: &str)
+                        -> ConfCountResult<&'static str> {
+    if storage.user_by_email(email)?.is_some() {
+        error!("User with id {} verified the email {} taken by another account", user_id, email);
+        return Err(ConfCountError::Conflict(EMAIL_TAKEN.to_string()));
+    }
+    storage.confirm_pending_email(user_id, email)?;
+    info!("User with id {} moved to the email {}", user_id, email);
+    Ok(EMAIL_CHANGED)
+}
+
+/// Removes the photo and the indexed face of a deleted user, logging the failures since the
+/// user is already gone
+fn forget_user_face(photos: &dyn PhotoStore, matcher: &dyn FaceMatcher, user_id: &str) {
+    if let Err(store_error) = photos.delete(user_id) {
+        error!("Unable to delete the photo of user {:?} - {}", user_id, store_error);
+    }
+    if let Err(match_error) = matcher.remove_faces(&[user_id]) {
+        error!("Unable to remove the indexed face of user {:?} - {}", user_id, match_error);
+    }
+}
+
+/// Deletes the users who never verified the email address they signed up with in time, along
+/// with their photo, indexed face, registrations, observations and pending reviews
+///
+/// # Arguments
+///
+/// * `storage` - Storage holding the users
+///
+/// * `photos` - Store holding the photos of the users
+///
+/// * `matcher` - Face matcher indexing the faces of the users
+///
+/// * `config` - Configuration of the application
+///
+/// * `now` - Current UNIX time in seconds
+///
+/// # Return
+///
+/// Returns the failure of the storage, if any
+pub fn expire_unverified_users(
+    storage: &dyn Storage,
+    photos: &dyn PhotoStore,
+    matcher: &dyn FaceMatcher,
+    config: &AppConfig,
+    now: i64,
+) -> ConfCountResult<()> {
+    let signed_up_before: i64 = now - config.verification.pending_ttl_hours * 3600;
+    for user_id in storage.delete_unverified_users(signed_up_before)? {
+        info!("Deleted user {} whose email address was never verified", user_id);
+        forget_user_face(photos, matcher, user_id.as_str());
+        storage.delete_registrations(user_id.as_str())?;
+        storage.delete_observations(user_id.as_str())?;
+        for review in storage.reviews_for_user(user_id.as_str())? {
+            discard_review(storage, photos, &review)?;
+        }
+    }
+    Ok(())
 }
 
 /// Validates the password against the entry in the users collection
 ///
 /// # Arguments
 ///
-/// * `email_doc` - Document containing matching E-mail
+/// * `user` - User with the matching E-mail
 ///
 /// * `password` - Password entered by the user
 ///
@@ -206,17 +482,15 @@ pub fn validate_user_email(
 ///
 /// Returns type of user
 pub fn validate_password(
-    email_doc: bson::ordered::OrderedDocument,
+    user: &User,
     password: &str,
-) -> &'static str {
-    let mut encrypted_password: Sha256 = Sha256::new();
-    encrypted_password.input_str(password);
-    if encrypted_password.result_str().eq(email_doc.get_str(PASSWORD).unwrap())
+) -> ConfCountResult<&'static str> {
+    if verify_password(password, user.password.as_str())
     {
-        check_user_type(email_doc)
+        check_user_type(user)
     } else {
-        error!("{:?} has entered a wrong password", email_doc.get_str(NAME).unwrap());
-        WRONG_PASSWORD
+        error!("{:?} has entered a wrong password", user.name);
+        Err(ConfCountError::Unauthorized(WRONG_PASSWORD.to_string()))
     }
 }
 
@@ -224,26 +498,24 @@ pub fn validate_password(
 ///
 /// # Arguments
 ///
-/// * `email_doc` - Document containing matching E-mail
+/// * `user` - User with the matching E-mail
 ///
 /// # Return
 ///
 /// Returns type of user
 pub fn check_user_type(
-    email_doc: bson::ordered::OrderedDocument
-) -> &'static str {
-    match email_doc.get_str(USER_TYPE).expect(NO_USER_TYPE) {
+    user: &User
+) -> ConfCountResult<&'static str> {
+    match user.user_type.as_str() {
         USER => {
-            info!("User {:?} logged in with email id {:?}", email_doc.get_str(NAME).unwrap(),
-                  email_doc.get_str(EMAIL).unwrap());
-            USER
+            info!("User {:?} logged in with email id {:?}", user.name, user.email);
+            Ok(USER)
         }
         ADMIN => {
-            info!("Admin {:?} logged in with email id {:?}", email_doc.get_str(NAME).unwrap(),
-                  email_doc.get_str(EMAIL).unwrap());
-            ADMIN
+            info!("Admin {:?} logged in with email id {:?}", user.name, user.email);
+            Ok(ADMIN)
         }
-        _ => WRONG_USER_TYPE
+        _ => Err(ConfCountError::Unauthorized(WRONG_USER_TYPE.to_string()))
     }
 }
 
@@ -251,210 +523,165 @@ pub fn check_user_type(
 ///
 /// # Arguments
 ///
+/// * `storage` - Storage holding the users and the pending resets
+///
+/// * `token` - Reset token of the mailed link
+///
 /// * `password` - Desired password
 ///
 /// * `confirm_password` - Desired password
 ///
+/// * `now` - Current UNIX time in seconds
+///
 /// # Return
 ///
-/// Returns password reset response
+/// Returns password reset response, the sessions of the user being ended once the password is
+/// reset
 pub fn validate_reset_password(
-    email: &str,
+    storage: &dyn Storage,
+    token: &str,
     password: &str,
     confirm_password: &str,
-) -> &'static str {
-    let email_doc = doc! {EMAIL: email};
-    let users_collection: Collection = connect_database_collection
-        (create_db_connection(), DB_NAME, USERS_COLLECTION);
-    match users_collection.find_one(Some(email_doc), None).unwrap() {
-        Some(doc) => {
-            if password == confirm_password {
-                let mut encrypted_password: Sha256 = Sha256::new();
-                encrypted_password.input_str(password);
-                let new_password = doc! {PASSWORD: encrypted_password.result_str()};
-                let data = doc! {SET => new_password};
-                match users_collection.update_one(doc, data, None) {
-                    Ok(_) => info!("Password has been reset for email id {}", email),
-                    Err(_) => error!("Password has not been reset for email id {} due to MongoDB Error", email)
-                }
-                RESET_SUCCESSFUL
-            } else {
-                error!("Password and confirm password field does not match for {}", email);
-                RESET_UNSUCCESSFUL
-            }
-        }
-        None => {
-            error!("An unregistered user with Email Id {} has tried to reset the password", email);
-            NON_REGISTERED_USER_MESSAGE
+    now: i64,
+) -> ConfCountResult<&'static str> {
+    ensure(password == confirm_password, RESET_UNSUCCESSFUL)?;
+    let reset: ResetToken = match storage.take_reset_token(hash_token(token).as_str())? {
+        Some(reset) if reset.expires_at > now => reset,
+        _ => {
+            error!("A password reset was attempted with an unknown, used or expired token");
+            return Err(ConfCountError::Unauthorized(RESET_LINK_INVALID.to_string()));
         }
-    }
+    };
+    let user: User = storage.user_by_id(reset.user_id.as_str())?
+        .ok_or_else(|| ConfCountError::NotFound(NON_REGISTERED_USER_MESSAGE.to_string()))?;
+    storage.update_password(user.email.as_str(), hash_password(password)?.as_str())?;
+    storage.delete_reset_tokens(user.id.as_str())?;
+    storage.revoke_sessions(user.id.as_str())?;
+    info!("Password has been reset for email id {}", user.email);
+    Ok(RESET_SUCCESSFUL)
 }
 
-/// Fetches the list of registered conferences for a user
+/// Registers a user for a conference
 ///
 /// # Argument
 ///
-/// * `user_id` - User Id
+/// * `storage` - Storage holding users, conferences and registrations
 ///
 /// * `user_email` - User email Id
 ///
+/// * `user_id` - User Id
+///
 /// * `conference_id` - Conference name to register
 ///
 /// # Return
 ///
 /// Returns Conference registration status
 pub fn validate_user_for_conference(
+    storage: &dyn Storage,
     user_email: &str,
     user_id: &str,
     conference_id: &str,
-) -> &'static str {
-    let conference_collection: Collection = connect_database_collection(
-        create_db_connection(), DB_NAME, CONFERENCE_COLLECTION);
-    let conference_filter = doc! {ID: conference_id};
-
-    match conference_collection.find_one(Some(conference_filter), None).unwrap() {
-        Some(_) => {
-            let users_collection: Collection = connect_database_collection(
-                create_db_connection(), DB_NAME, USERS_COLLECTION);
-            let user_filter = doc! {ID: user_id, EMAIL: user_email};
-
-            match users_collection.find_one(Some(user_filter), None).unwrap() {
-                Some(_) => {
-                    let db_collection: Collection = connect_database_collection(
-                        create_db_connection(), DB_NAME, CONFERENCE_DETAILS);
-
-                    let user_detail = doc! {
-                        USER_ID: user_id,
-                        EMAIL: user_email,
-                        STATUS: ABSENT,
-                        CONFERENCE_ID: conference_id};
-                    let check_user = doc! {USER_ID: user_id, CONFERENCE_ID: conference_id};
-                    match db_collection.find_one(Some(check_user), None).unwrap()
-                        {
-                            Some(_) => {
-                                info!("{:?} tried to register for already registered conference {}",
-                                      user_email, conference_id);
-                                "Conference Already Registered"
-                            }
-                            None => {
-                                match db_collection.insert_one(user_detail, None) {
-                                    Ok(_) => {
-                                        info!("{:?} registered for {:?} conference", user_email, conference_id);
-                                        "Conference Registered"
-                                    }
-                                    Err(_) => {
-                                        info!("{:?} tried to register for {:?} but an unexpected error happened",
-                                              user_email, conference_id);
-                                        "Failed to Register Conference"
-                                    }
-                                }
-                            }
-                        }
-                }
-                None => {
-                    info!("{}", NON_REGISTERED_USER_MESSAGE);
-                    NON_REGISTERED_USER_MESSAGE
-                }
-            }
-        }
-        None => {
-            info!("{}", NO_CONFERENCE);
-            NO_CONFERENCE
+) -> ConfCountResult<&'static str> {
+    if storage.conference(conference_id)?.is_none() {
+        return Err(ConfCountError::NotFound(NO_CONFERENCE.to_string()));
+    }
+    match storage.user_by_id(user_id)? {
+        Some(ref user) if user.email == user_email && user.pending_since.is_some() => {
+            error!("User with unverified email {} tried to register for a conference",
+                   user_email);
+            return Err(ConfCountError::Forbidden(EMAIL_NOT_VERIFIED.to_string()));
         }
+        Some(ref user) if user.email == user_email => {}
+        _ => return Err(ConfCountError::NotFound(NON_REGISTERED_USER_MESSAGE.to_string())),
+    }
+    if storage.registration(user_id, conference_id)?.is_some() {
+        info!("{:?} tried to register for already registered conference {}",
+              user_email, conference_id);
+        return Err(ConfCountError::Conflict(CONFERENCE_ALREADY_REGISTERED.to_string()));
     }
+    storage.insert_registration(&Registration {
+        user_id: user_id.to_string(),
+        email: user_email.to_string(),
+        status: ABSENT.to_string(),
+        conference_id: conference_id.to_string(),
+        attendance_match: None,
+        notified: false,
+    })?;
+    info!("{:?} registered for {:?} conference", user_email, conference_id);
+    Ok(CONFERENCE_REGISTERED)
 }
 
 /// Fetches the list of registered conferences for a user
 ///
 /// # Argument
 ///
+/// * `storage` - Storage holding conferences and registrations
+///
 /// * `user_email` - User email Id
 ///
 /// # Return
 ///
 /// Returns list of registered conferences for a user
 pub fn fetch_conference_for_user(
-    user_email: &str
-) -> Vec<String> {
-    let conference_key = doc! {STATUS: NOT_COMPLETED};
-    let db_collection: Collection = connect_database_collection
-        (create_db_connection(), DB_NAME, CONFERENCE_COLLECTION);
-    let conference_collection: Collection = connect_database_collection
-        (create_db_connection(), DB_NAME, CONFERENCE_DETAILS);
+    storage: &dyn Storage,
+    user_email: &str,
+) -> ConfCountResult<Vec<String>> {
     info!("List of registered conferences fetched for user details module");
-    let conference_data: Cursor =
-        db_collection.find(Some(conference_key), None).unwrap();
-    let mut user_conference_list: Vec<String> = Vec::new();
-    for conference_detail in conference_data {
-        let conference_document = conference_detail.unwrap();
-        let conference_name: String = conference_document.get_str(ID).expect(
-            "There is no such field like _id").to_string();
-        let user_conference = doc! {EMAIL: user_email,CONFERENCE_ID: conference_name};
-        if let Ok(user_conference) =
-        conference_collection.find_one(Some(user_conference.clone()), None) {
-            if let Some(user_conference) = user_conference {
-                let conference = user_conference.get_str(CONFERENCE_ID).expect(
-                    "No such field like conf_id exists").to_string();
-                user_conference_list.push(conference)
-            }
-        }
-    }
-    user_conference_list
+    let upcoming_conferences: Vec<Conference> = storage.conferences_by_status(NOT_COMPLETED)?;
+    let registrations: Vec<Registration> = storage.registrations_for_email(user_email)?;
+    Ok(upcoming_conferences.into_iter()
+        .filter(|conference| registrations.iter()
+            .any(|registration| registration.conference_id == conference.id))
+        .map(|conference| conference.id)
+        .collect())
 }
 
 /// Fetches the list of upcoming conferences
 ///
+/// # Argument
+///
+/// * `storage` - Storage holding the conferences
+///
 /// # Return
 ///
 /// Returns list of upcoming conferences
-pub fn fetch_conferences() -> Vec<bson::Document> {
-    let conference_key = doc! {STATUS: NOT_COMPLETED};
-    let conference_collection: Collection = connect_database_collection
-        (create_db_connection(), DB_NAME, CONFERENCE_COLLECTION);
+pub fn fetch_conferences(storage: &dyn Storage) -> ConfCountResult<Vec<Conference>> {
     info!("List of upcoming conferences fetched for user details module");
-    let conference_data: Cursor = conference_collection.find(Some(conference_key),
-                                                             None).unwrap();
-
-    let mut conf_data: Vec<bson::Document> = Vec::new();
-
-    for conference_detail in conference_data {
-        let conference_document = conference_detail.unwrap();
-        conf_data.push(conference_document)
-    }
-    conf_data
+    Ok(storage.conferences_by_status(NOT_COMPLETED)?)
 }
 
-/// Deletes the user data from the database
+/// Deletes the user data from the database along with the photo, the indexed face and the
+/// pending reviews of the user
 ///
 /// # Argument
 ///
+/// * `storage` - Storage holding the users
+///
+/// * `photos` - Store holding the photos of the users
+///
+/// * `matcher` - Face matcher indexing the faces of the users
+///
 /// * `user_id` - Unique User id
 ///
 /// # Return
 ///
 /// Returns confirmation message for the deleted data
 pub fn delete_user(
-    user_id: String
-) -> &'static str {
-    let users_collection: Collection =
-        connect_database_collection(
-            create_db_connection(), DB_NAME, USERS_COLLECTION);
-    let data = doc! {ID: user_id.as_str(), USER_TYPE: USER};
-    match users_collection.find_one_and_delete(data, None) {
-        Ok(document) => match document {
-            Some(_) => {
-                info!("User with Id {:?} has been deleted by admin", user_id);
-                DELETION_SUCCESS
-            }
-            None => {
-                error!("Admin tried to delete a non existing user with user Id {:?}", user_id);
-                EMPTY_DATA
-            }
-        },
-        Err(error) => {
-            error!("Unable to delete error - {:?} ", error);
-            DELETION_FAIL
+    storage: &dyn Storage,
+    photos: &dyn PhotoStore,
+    matcher: &dyn FaceMatcher,
+    user_id: String,
+) -> ConfCountResult<&'static str> {
+    if storage.delete_user(user_id.as_str())? {
+        info!("User with Id {:?} has been deleted by admin", user_id);
+        forget_user_face(photos, matcher, user_id.as_str());
+        for review in storage.reviews_for_user(user_id.as_str())? {
+            discard_review(storage, photos, &review)?;
         }
+        Ok(DELETION_SUCCESS)
+    } else {
+        error!("Admin tried to delete a non existing user with user Id {:?}", user_id);
+        Err(ConfCountError::NotFound(EMPTY_DATA.to_string()))
     }
 }
 
@@ -463,73 +690,50 @@ pub fn delete_user(
 ///
 /// # Argument
 ///
-/// * `conference_data` - Record to be added in mongodb collection
+/// * `storage` - Storage holding the conferences
+///
+/// * `conference` - Conference to be added
 ///
-/// * `validation_data` - Validation
 /// # Return
 ///
 /// Returns the confirmation message for the addition of the record
 pub fn add_conference(
-    conference_data: Document,
-    validation_data: Document,
-) -> &'static str {
-    let collection: Collection =
-        connect_database_collection(
-            create_db_connection(), DB_NAME, CONFERENCE_COLLECTION);
-    let count: i64 = collection.count(Some(validation_data), None).unwrap();
-    match count {
-        0 => {
-            match collection.insert_one(conference_data, None) {
-                Ok(_) => {
-                    info!("New conference added");
-                    ADDITION_SUCCESS
-                }
-                Err(err) => {
-                    error!("Mongodb error : {} ", err);
-                    ADDITION_FAIL
-                }
-            }
-        }
-        _ => {
-            error!("Admin is trying to create a duplicate conference");
-            ALREADY_REGISTERED
-        }
+    storage: &dyn Storage,
+    conference: &Conference,
+) -> ConfCountResult<&'static str> {
+    if storage.conference(conference.id.as_str())?.is_some() {
+        error!("Admin is trying to create a duplicate conference");
+        return Err(ConfCountError::Conflict(ALREADY_REGISTERED.to_string()));
     }
+    storage.insert_conference(conference)?;
+    info!("New conference added");
+    Ok(ADDITION_SUCCESS)
 }
 
 /// Updates the conference data in the database
 ///
 /// # Argument
 ///
-/// * `filter_data` - Filter record in mongodb collection
+/// * `storage` - Storage holding the conferences
 ///
-/// * `updated_conf_data` - Record to be updated in mongodb collection
+/// * `conference_id` - Id of the conference to update
+///
+/// * `update` - Updated details of the conference
 ///
 /// # Return
 ///
 /// Returns confirmation message for the updation of the record
 pub fn update_conference(
-    filter_data: Document,
-    updated_conf_data: Document,
-) -> &'static str {
-    let collection: Collection =
-        connect_database_collection(
-            create_db_connection(), DB_NAME, CONFERENCE_COLLECTION);
-    match collection.find_one_and_update(filter_data, updated_conf_data, None) {
-        Ok(document) => match document {
-            Some(_) => {
-                info!("A conference was updated");
-                UPDATION_SUCCESS
-            }
-            None => {
-                error!("Admin tried to update a non existing conference");
-                EMPTY_DATA
-            }
-        },
-        Err(error) => {
-            error!("Unable to update error {:?} ", error);
-            UPDATION_FAIL
-        }
+    storage: &dyn Storage,
+    conference_id: &str,
+    update: &ConferenceUpdate,
+) -> ConfCountResult<&'static str> {
+    if storage.update_conference(conference_id, update)? {
+        info!("A conference was updated");
+        Ok(UPDATION_SUCCESS)
+    } else {
+        error!("Admin tried to update a non existing conference");
+        Err(ConfCountError::NotFound(EMPTY_DATA.to_string()))
     }
 }
 
@@ -537,131 +741,254 @@ pub fn update_conference(
 ///
 /// # Argument
 ///
-/// * `conf_name` - Record to be deleted from mongodb collection
+/// * `storage` - Storage holding the conferences
+///
+/// * `conference_id` - Id of the conference to delete
 ///
 /// # Return
 ///
 /// Returns confirmation message for the deleted data
 pub fn delete_conference(
-    conf_id: Document
-) -> &'static str {
-    let collection: Collection =
-        connect_database_collection(
-            create_db_connection(), DB_NAME, CONFERENCE_COLLECTION);
-    match collection.find_one_and_delete(conf_id, None) {
-        Ok(document) => match document {
-            Some(_) => {
-                info!("Conference was deleted successfully");
-                DELETION_SUCCESS
-            }
-            None => {
-                error!("Admin tried to delete a non existing conference");
-                EMPTY_DATA
-            }
-        },
-        Err(error) => {
-            error!("Unable to update error {:?} ", error);
-            DELETION_FAIL
+    storage: &dyn Storage,
+    conference_id: &str,
+) -> ConfCountResult<&'static str> {
+    if storage.delete_conference(conference_id)? {
+        info!("Conference was deleted successfully");
+        Ok(DELETION_SUCCESS)
+    } else {
+        error!("Admin tried to delete a non existing conference");
+        Err(ConfCountError::NotFound(EMPTY_DATA.to_string()))
+    }
+}
+
+/// Lists the attendance of the users registered for a conference with the match backing it
+///
+/// # Argument
+///
+/// * `storage` - Storage holding the registrations
+///
+/// * `conference_id` - Id of the conference
+///
+/// # Return
+///
+/// Returns the registrations as JSON, each with the similarity, face position, capture id and
+/// capture time of the best match of the user
+pub fn attendance_audit(
+    storage: &dyn Storage,
+    conference_id: &str,
+) -> ConfCountResult<String> {
+    info!("Admin audited the attendance of conference {}", conference_id);
+    let registrations: Vec<Registration> = storage.registrations_for_conference(conference_id)?;
+    Ok(serde_json::to_string(&registrations)
+        .expect("Unable to serialize into JSON"))
+}
+
+/// Lists the matches of a conference pending review
+///
+/// # Argument
+///
+/// * `storage` - Storage holding the reviews
+///
+/// * `conference_id` - Id of the conference
+///
+/// # Return
+///
+/// Returns the reviews as JSON, each with the match, the key of the face cropped from the capture
+/// and the key of the reference photo of the user
+pub fn pending_reviews(
+    storage: &dyn Storage,
+    conference_id: &str,
+) -> ConfCountResult<String> {
+    let reviews: Vec<Review> = storage.reviews_for_conference(conference_id)?;
+    info!("Admin listed the {} reviews pending for conference {}", reviews.len(), conference_id);
+    Ok(serde_json::to_string(&reviews)
+        .expect("Unable to serialize into JSON"))
+}
+
+/// Indexes again the faces of the users registered for a conference from their stored photos,
+/// so that the users who signed up before their faces were indexed can be recognised
+///
+/// # Argument
+///
+/// * `storage` - Storage holding the registrations
+///
+/// * `photos` - Store holding the photos of the users
+///
+/// * `matcher` - Face matcher indexing the faces of the users
+///
+/// * `conference_id` - Id of the conference
+///
+/// # Return
+///
+/// Returns the number of indexed faces, or the failure of one of the services
+pub fn index_conference_faces(
+    storage: &dyn Storage,
+    photos: &dyn PhotoStore,
+    matcher: &dyn FaceMatcher,
+    conference_id: &str,
+) -> ConfCountResult<String> {
+    let registrations: Vec<Registration> = storage.registrations_for_conference(conference_id)?;
+    if registrations.is_empty() {
+        return Err(ConfCountError::NotFound(COLLECTION_EMPTY.to_string()));
+    }
+    let stored: HashSet<String> = photos.list()?.into_iter().collect();
+    let mut user_ids: Vec<&str> = Vec::new();
+    for registration in &registrations {
+        let user_id: &str = registration.user_id.as_str();
+        if stored.contains(user_id) {
+            user_ids.push(user_id);
+        } else {
+            error!("No photo is stored for user {:?}, their face is not indexed", user_id);
         }
     }
+    matcher.remove_faces(&user_ids)?;
+    let mut indexed: usize = 0;
+    for user_id in user_ids {
+        if let Some(photo) = photos.get(user_id)? {
+            matcher.index_face(user_id, &photo)?;
+            indexed += 1;
+        }
+    }
+    info!("Indexed {} faces for conference {}", indexed, conference_id);
+    Ok(format!("Indexed the faces of {} of the {} registered users", indexed,
+               registrations.len()))
 }
 
 /// Filters conferences within a date range
 ///
 /// # Argument
 ///
-/// * `conf_filter_date` - Document containing filter data
+/// * `storage` - Storage holding the conferences
+///
+/// * `first_date` - First date of the range
+///
+/// * `last_date` - Last date of the range
 ///
 /// # Return
 ///
 /// Returns the conferences within the date range
 pub fn filter_conference(
-    conf_filter_date: Document,
-) -> String {
-    let collection: Collection =
-        connect_database_collection(
-            create_db_connection(), DB_NAME, CONFERENCE_COLLECTION);
-    let cursor: Cursor = collection.find(Some(conf_filter_date), None).unwrap();
-    let docs: Vec<_> = cursor
-        .map(|doc| doc
-            .expect("Error occurred in iterating over documents")
-            .get(CONFERENCE_NAME)
-            .expect("No such value exists")
-            .clone())
+    storage: &dyn Storage,
+    first_date: &str,
+    last_date: &str,
+) -> ConfCountResult<String> {
+    let names: Vec<String> = storage.conferences_between(first_date, last_date)?
+        .into_iter()
+        .map(|conference| conference.name)
         .collect();
-
-
-    serde_json::to_string(&docs).expect("Unable to serialize into JSON")
+    Ok(serde_json::to_string(&names).expect("Unable to serialize into JSON"))
 }
 
 /// Filters users for a specific conference
 ///
 /// # Argument
 ///
-/// * `collection_name` - Conference Collection
+/// * `storage` - Storage holding the registrations
 ///
-/// * `collection_id` - Conference Id
+/// * `conference_id` - Conference Id
 ///
 /// # Return
 ///
 /// Returns the list of users for a specific conference
 pub fn filter_user_conference(
-    collection_id: &str
-) -> String {
-    let conference_collection: Collection =
-        connect_database_collection(
-            create_db_connection(), DB_NAME, collection_id);
-    let cursor: Cursor = conference_collection.find(None, None).unwrap();
-    info!("Admin just filtered out list of users for conference {}", collection_id);
-    let docs: Vec<_> = cursor
-        .map(|doc| doc
-            .expect("Error occurred in iterating over documents")
-            .get(EMAIL)
-            .unwrap()
-            .clone())
+    storage: &dyn Storage,
+    conference_id: &str,
+) -> ConfCountResult<String> {
+    info!("Admin just filtered out list of users for conference {}", conference_id);
+    let emails: Vec<String> = storage.registrations_for_conference(conference_id)?
+        .into_iter()
+        .map(|registration| registration.email)
         .collect();
-    serde_json::to_string(&docs)
-        .expect("Unable to serialize into JSON")
+    Ok(serde_json::to_string(&emails)
+        .expect("Unable to serialize into JSON"))
+}
+
+/// Details of a user shown back to them, leaving out their password hash and verification state
+#[derive(Serialize)]
+struct UserDetails<'a> {
+    #[serde(rename = "_id")]
+    id: &'a str,
+    name: &'a str,
+    email: &'a str,
+    user_type: &'a str,
 }
 
 /// Fetches user details for a specific user
 ///
 /// # Argument
 ///
-/// * `user_key` - Document for filtration
+/// * `storage` - Storage holding the users
+///
+/// * `email` - Email of the user
 ///
 /// # Return
 ///
 /// Returns all the details about a user
 pub fn fetch_user_details(
-    user_key: Document
-) -> String {
-    let users_collection: Collection = connect_database_collection
-        (create_db_connection(), DB_NAME, USERS_COLLECTION);
-    let user_data: Option<bson::Document> =
-        users_collection.find_one(Some(user_key), None).unwrap();
-    serde_json::to_string(&user_data).unwrap()
+    storage: &dyn Storage,
+    email: &str,
+) -> ConfCountResult<String> {
+    match storage.user_by_email(email)? {
+        Some(user_data) => Ok(serde_json::to_string(&UserDetails {
+            id: user_data.id.as_str(),
+            name: user_data.name.as_str(),
+            email: user_data.email.as_str(),
+            user_type: user_data.user_type.as_str(),
+        }).expect("Unable to serialize into JSON")),
+        None => Err(ConfCountError::NotFound(NON_REGISTERED_USER_MESSAGE.to_string())),
+    }
 }
 
-/// Updates user details for a specific user
+/// Updates user details for a specific user. A new email address must not belong to another
+/// account, and replaces the current one once the link mailed to it is opened
 ///
 /// # Argument
 ///
-/// * `filter_data` - Document for filtration
+/// * `storage` - Storage holding the users
+///
+/// * `mailer` - Mailer delivering the verification link
 ///
-/// * `updated_user_data` - Document with updated data
+/// * `config` - Configuration of the application
+///
+/// * `user_id` - Id of the user
+///
+/// * `name` - New name of the user
+///
+/// * `email` - New email of the user
+///
+/// * `now` - Current UNIX time
 ///
 /// # Return
 ///
-/// Returns update status
+/// Returns the confirmation message for the updation of the user
 pub fn update_user_details(
-    filter_data: Document,
-    updated_user_data: Document,
-) -> i32 {
-    let users_collection: Collection = connect_database_collection
-        (create_db_connection(), DB_NAME, USERS_COLLECTION);
-    users_collection.update_one(
-        filter_data, updated_user_data, None).unwrap().modified_count
+    storage: &dyn Storage,
+    mailer: &dyn Mailer,
+    config: &AppConfig,
+    user_id: &str,
+    name: &str,
+    email: &str,
+    now: i64,
+) -> ConfCountResult<&'static str> {
+    let user: User = match storage.user_by_id(user_id)? {
+        Some(user) => user,
+        None => {
+            error!("User ID not found");
+            return Err(ConfCountError::NotFound(USER_ID_NOT_FOUND.to_string()));
+        }
+    };
+    let email_changed: bool = user.email != email;
+    if email_changed && storage.user_by_email(email)?.is_some() {
+        error!("User with id {} tried to take the email {} of another account", user_id, email);
+        return Err(ConfCountError::Conflict(EMAIL_TAKEN.to_string()));
+    }
+    storage.update_user(user_id, name, user.email.as_str())?;
+    if email_changed {
+        storage.set_pending_email(user_id, email)?;
+        mail_verification_link(mailer, config, user_id, email, now)?;
+    }
+    info!("User with id {} has been updated", user_id);
+    Ok(USER_DETAILS_UPDATED)
 }
 
 /// Checks weather a file path exists on not
@@ -679,26 +1006,87 @@ pub fn path_exists(path: &'static str) -> bool {
 
 #[cfg(test)]
 mod test {
-    use mongodb::{bson, doc};
-    use mongodb::coll::Collection;
-    use mongodb::Document;
-
-    use crate::connection::{connect_database_collection, create_db_connection};
-    use crate::constants::{ADMIN, ADMIN_EMAIL, ALREADY_REGISTERED, EMAIL, ID, NAME, NO_CONFERENCE,
-                           NON_REGISTERED_USER_MESSAGE, PASSWORD, REGISTERED_USER_MESSAGE, SET,
-                           STATUS, TEST_DB_NAME, TEST_EMAIL_ID, TEST_USERS_COLLECTION, UPDATION_SUCCESS,
-                           USER, WRONG_EMAIL_FORMAT, WRONG_PASSWORD_FORMAT};
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    use crate::config::test::test_config;
+    use crate::constants::{ABSENT, ADMIN, ADMIN_EMAIL, ALREADY_REGISTERED, COLLECTION_EMPTY,
+                           DELETION_SUCCESS, EMAIL, NO_CONFERENCE, NON_REGISTERED_USER_MESSAGE,
+                           PASSWORD, REGISTERED_USER_MESSAGE, TEST_EMAIL_ID, UPDATION_SUCCESS, USER,
+                           WRONG_EMAIL_FORMAT, WRONG_PASSWORD_FORMAT};
+    use crate::error::ConfCountError;
+    use crate::face_matching::{DetectedFace, FaceMatch, FaceMatcher, FaceMatchResult};
+    use crate::face_matching::fixtures::FixtureMatcher;
+    use crate::mail::memory::MemoryOutbox;
+    use crate::password::needs_rehash;
+    use crate::photo_store::local::LocalPhotoStore;
+    use crate::photo_store::PhotoStore;
     use crate::request_handlers::admin_handler::ConferenceStatus;
-    use crate::utils::{add_conference, check_conference_id_format, check_date_format,
-                       check_email_format, check_id_format, check_name_format, check_non_empty,
-                       check_password_format, check_user_type, delete_conference, delete_user,
-                       EMPTY_DATA, fetch_user_details, filter_conference, filter_user_conference,
-                       path_exists, RESET_SUCCESSFUL, RESET_UNSUCCESSFUL, update_conference,
-                       update_user_details, validate_password, validate_reset_password,
-                       validate_user_details, validate_user_email, validate_user_for_conference, };
+    use crate::state::AppState;
+    use crate::state::test::test_state;
+    use crate::storage::{AttendanceRepository, Conference, ConferenceUpdate, Observation,
+                         ObservationRepository, PresenceRule, Registration, Review,
+                         ReviewRepository, User, UserRepository};
+    use crate::storage::memory::MemoryStorage;
+    use crate::storage::memory::test::{AMBIGUOUS_EMAIL, TEST_CONFERENCE_ID, TEST_PASSWORD,
+                                       TEST_USER_ID, test_attendance_match, test_observation,
+                                       test_review, test_storage};
+    use crate::utils::{add_conference, attendance_audit, authenticate_user,
+                       check_conference_id_format, check_date_format, check_email_format,
+                       check_id_format, check_name_format,
+                       check_non_empty, check_password_format, check_user_type,
+                       CONFERENCE_ALREADY_REGISTERED, CONFERENCE_REGISTERED, delete_conference,
+                       delete_user, EMAIL_ALREADY_VERIFIED, EMAIL_CHANGED, EMAIL_NOT_VERIFIED,
+                       EMAIL_TAKEN, EMAIL_VERIFIED,
+                       EMPTY_DATA, expire_unverified_users, fetch_conference_for_user,
+                       fetch_conferences, fetch_user_details, filter_conference,
+                       filter_user_conference, index_conference_faces,
+                       parse_presence_rule, parse_similarity_threshold, path_exists,
+                       pending_reviews,
+                       RESET_LINK_INVALID, RESET_SUCCESSFUL, RESET_UNSUCCESSFUL,
+                       send_email_verification, send_password_reset,
+                       update_conference, update_user_details, USER_DETAILS_UPDATED,
+                       USER_ID_NOT_FOUND,
+                       validate_password, validate_reset_password, validate_user_for_conference,
+                       VERIFICATION_LINK_INVALID, verify_email, WRONG_PASSWORD, WRONG_USER_TYPE};
+
+    static PENDING_USER_ID: &str = "4444444444";
+    static PENDING_EMAIL: &str = "pending@knoldus.in";
+
+    fn test_user(email: &str) -> User {
+        test_storage().user_by_email(email).unwrap().unwrap()
+    }
+
+    /// Signs up a user, still pending verification, with the password of the test user
+    fn insert_pending_user(storage: &MemoryStorage, signed_up_at: i64) -> User {
+        let mut user: User = test_user(TEST_EMAIL_ID);
+        user.id = PENDING_USER_ID.to_string();
+        user.email = PENDING_EMAIL.to_string();
+        user.pending_since = Some(signed_up_at);
+        storage.insert_user(&user).unwrap();
+        user
+    }
+
+    /// Mails the verification link of the user and returns the token it carries
+    fn mailed_verification_token(user: &User) -> String {
+        let outbox: MemoryOutbox = MemoryOutbox::default();
+        send_email_verification(&outbox, &test_config(), user).unwrap();
+        assert_eq!(outbox.mails()[0].to, user.email);
+        let body: String = outbox.mails()[0].body.clone();
+        assert!(body.contains("http://127.0.0.1:8088/verify-email?token="));
+        body.split("token=").nth(1).unwrap().split_whitespace().next().unwrap().to_string()
+    }
 
-    static WRONG_PASSWORD: &str = "Wrong Password";
-    static WRONG_USER_TYPE: &str = "User Type is ambiguous, Please contact Admin";
+    fn test_conference_update() -> ConferenceUpdate {
+        ConferenceUpdate {
+            conference_date: "2018-06-24".to_string(),
+            conference_address1: "test_address_1".to_string(),
+            conference_address2: "test_address_2".to_string(),
+            conference_address3: "test_address_3".to_string(),
+            similarity_threshold: None,
+            presence_rule: PresenceRule::AnyCapture,
+        }
+    }
 
     #[test]
     fn test_check_non_empty_success()
@@ -736,6 +1124,41 @@ mod test {
         assert!(!check_date_format("06-03-2012"))
     }
 
+    #[test]
+    fn test_parse_similarity_threshold_success()
+    {
+        assert_eq!(parse_similarity_threshold("62.5"), Some(Some(62.5)));
+        assert_eq!(parse_similarity_threshold(" 100 "), Some(Some(100.0)));
+        assert_eq!(parse_similarity_threshold(""), Some(None))
+    }
+
+    #[test]
+    fn test_parse_presence_rule_success()
+    {
+        assert_eq!(parse_presence_rule("", "", "", ""), Some(PresenceRule::AnyCapture));
+        assert_eq!(parse_presence_rule("seen_in_captures", "2", " 3 ", ""),
+                   Some(PresenceRule::SeenInCaptures { seen: 2, captures: 3 }));
+        assert_eq!(parse_presence_rule("seen_within_minutes", "", "", "30"),
+                   Some(PresenceRule::SeenWithinMinutes { minutes: 30 }))
+    }
+
+    #[test]
+    fn test_parse_presence_rule_failure()
+    {
+        assert_eq!(parse_presence_rule("seen_in_captures", "4", "3", ""), None);
+        assert_eq!(parse_presence_rule("seen_in_captures", "0", "3", ""), None);
+        assert_eq!(parse_presence_rule("seen_within_minutes", "", "", "soon"), None);
+        assert_eq!(parse_presence_rule("most_captures", "", "", ""), None)
+    }
+
+    #[test]
+    fn test_parse_similarity_threshold_failure()
+    {
+        assert_eq!(parse_similarity_threshold("high"), None);
+        assert_eq!(parse_similarity_threshold("120"), None);
+        assert_eq!(parse_similarity_threshold("NaN"), None)
+    }
+
     #[test]
     fn test_check_email_format_success()
     {
@@ -787,236 +1210,573 @@ mod test {
     #[test]
     fn test_check_user_type_success_admin()
     {
-        let users_collection: Collection = connect_database_collection
-            (create_db_connection(), TEST_DB_NAME, TEST_USERS_COLLECTION);
-        let email = doc! {EMAIL: ADMIN_EMAIL};
-        let email_doc = users_collection.find_one(Some(email), None).unwrap().unwrap();
-        assert_eq!(check_user_type(email_doc), ADMIN);
+        assert_eq!(check_user_type(&test_user(ADMIN_EMAIL)), Ok(ADMIN));
     }
 
     #[test]
     fn test_check_user_type_success_user()
     {
-        let users_collection: Collection = connect_database_collection
-            (create_db_connection(), TEST_DB_NAME, TEST_USERS_COLLECTION);
-        let email = doc! {EMAIL: TEST_EMAIL_ID};
-        let email_doc = users_collection.find_one(Some(email), None).unwrap().unwrap();
-        assert_eq!(check_user_type(email_doc), USER);
+        assert_eq!(check_user_type(&test_user(TEST_EMAIL_ID)), Ok(USER));
     }
 
     #[test]
     fn test_check_user_type_failure()
     {
-        let users_collection: Collection = connect_database_collection
-            (create_db_connection(), TEST_DB_NAME, TEST_USERS_COLLECTION);
-        let email = doc! {EMAIL: "test.testing@knoldus.in"};
-        let email_doc = users_collection.find_one(Some(email), None).unwrap().unwrap();
-        assert_eq!(check_user_type(email_doc), WRONG_USER_TYPE);
+        assert_eq!(check_user_type(&test_user(AMBIGUOUS_EMAIL)),
+                   Err(ConfCountError::Unauthorized(WRONG_USER_TYPE.to_string())));
     }
 
     #[test]
     fn test_validate_password_success()
     {
-        let users_collection: Collection = connect_database_collection
-            (create_db_connection(), TEST_DB_NAME, TEST_USERS_COLLECTION);
-        let email = doc! {EMAIL: ADMIN_EMAIL};
-        let email_doc = users_collection.find_one(Some(email), None).unwrap().unwrap();
-        assert_eq!(validate_password(email_doc, ADMIN), ADMIN);
+        assert_eq!(validate_password(&test_user(ADMIN_EMAIL), ADMIN), Ok(ADMIN));
     }
 
     #[test]
     fn test_validate_password_failure()
     {
-        let users_collection: Collection = connect_database_collection
-            (create_db_connection(), TEST_DB_NAME, TEST_USERS_COLLECTION);
-        let email = doc! {EMAIL: ADMIN_EMAIL};
-        let email_doc = users_collection.find_one(Some(email), None).unwrap().unwrap();
-        assert_eq!(validate_password(email_doc, PASSWORD), WRONG_PASSWORD);
+        assert_eq!(validate_password(&test_user(ADMIN_EMAIL), PASSWORD),
+                   Err(ConfCountError::Unauthorized(WRONG_PASSWORD.to_string())));
+    }
+
+    #[test]
+    fn test_authenticate_user_success()
+    {
+        assert_eq!(authenticate_user(&test_storage(), ADMIN_EMAIL, ADMIN),
+                   Ok(test_user(ADMIN_EMAIL)));
     }
 
     #[test]
-    fn test_validate_user_details_success()
+    fn test_authenticate_user_failure()
     {
-        assert_eq!(validate_user_details(ADMIN_EMAIL, ADMIN), ADMIN);
+        assert_eq!(authenticate_user(&test_storage(), "wrong@email.com", "pword"),
+                   Err(ConfCountError::NotFound(NON_REGISTERED_USER_MESSAGE.to_string())));
     }
 
     #[test]
-    fn test_validate_user_details_failure()
+    fn test_authenticate_user_email_failure()
     {
-        assert_eq!(validate_user_details("wrong@email.com", "pword"),
-                   NON_REGISTERED_USER_MESSAGE);
+        assert_eq!(authenticate_user(&test_storage(), "wrong@email", "pword"),
+                   Err(ConfCountError::invalid_field(EMAIL, WRONG_EMAIL_FORMAT)));
     }
 
     #[test]
-    fn test_validate_user_details_email_failure()
+    fn test_authenticate_user_password_failure()
     {
-        assert_eq!(validate_user_details(
-            "wrong@email", "pword"), WRONG_EMAIL_FORMAT);
+        assert_eq!(authenticate_user(&test_storage(), "wrong@email.com", "pwo"),
+                   Err(ConfCountError::invalid_field(PASSWORD, WRONG_PASSWORD_FORMAT)));
     }
 
     #[test]
-    fn test_validate_user_details_password_failure()
+    fn test_authenticate_user_rehashes_legacy_password()
     {
-        assert_eq!(validate_user_details("wrong@email.com", "pwo"),
-                   WRONG_PASSWORD_FORMAT);
+        let storage: MemoryStorage = test_storage();
+        assert!(needs_rehash(test_user(ADMIN_EMAIL).password.as_str()));
+        assert!(authenticate_user(&storage, ADMIN_EMAIL, ADMIN).is_ok());
+        let upgraded: User = storage.user_by_email(ADMIN_EMAIL).unwrap().unwrap();
+        assert!(!needs_rehash(upgraded.password.as_str()));
+        assert!(authenticate_user(&storage, ADMIN_EMAIL, ADMIN).is_ok());
+        assert!(authenticate_user(&storage, ADMIN_EMAIL, "wrong-password").is_err());
+    }
+
+    #[test]
+    fn test_authenticate_user_unverified_failure()
+    {
+        let storage: MemoryStorage = test_storage();
+        insert_pending_user(&storage, 1000);
+        assert_eq!(authenticate_user(&storage, PENDING_EMAIL, TEST_PASSWORD),
+                   Err(ConfCountError::Forbidden(EMAIL_NOT_VERIFIED.to_string())));
+        assert!(authenticate_user(&storage, PENDING_EMAIL, "wrong-password").is_err());
+    }
+
+    #[test]
+    fn test_verify_email_success()
+    {
+        let storage: MemoryStorage = test_storage();
+        let token: String = mailed_verification_token(&insert_pending_user(&storage, 1000));
+        assert_eq!(verify_email(&storage, &test_config(), token.as_str(), 1001),
+                   Ok(EMAIL_VERIFIED));
+        assert_eq!(verify_email(&storage, &test_config(), token.as_str(), 1002),
+                   Ok(EMAIL_ALREADY_VERIFIED));
+        assert!(authenticate_user(&storage, PENDING_EMAIL, TEST_PASSWORD).is_ok());
+    }
+
+    #[test]
+    fn test_verify_email_failure()
+    {
+        let storage: MemoryStorage = test_storage();
+        let token: String = mailed_verification_token(&insert_pending_user(&storage, 1000));
+        assert_eq!(verify_email(&storage, &test_config(), "forged", 1001),
+                   Err(ConfCountError::Unauthorized(VERIFICATION_LINK_INVALID.to_string())));
+        assert_eq!(verify_email(&storage, &test_config(), token.as_str(), 1000 + 48 * 3600),
+                   Err(ConfCountError::Unauthorized(VERIFICATION_LINK_INVALID.to_string())));
+        storage.update_user(PENDING_USER_ID, "Pending", "moved@knoldus.in").unwrap();
+        assert_eq!(verify_email(&storage, &test_config(), token.as_str(), 1001),
+                   Err(ConfCountError::Unauthorized(VERIFICATION_LINK_INVALID.to_string())));
+        storage.delete_user(PENDING_USER_ID).unwrap();
+        assert_eq!(verify_email(&storage, &test_config(), token.as_str(), 1001),
+                   Err(ConfCountError::NotFound(NON_REGISTERED_USER_MESSAGE.to_string())));
+    }
+
+    #[test]
+    fn test_expire_unverified_users_success()
+    {
+        let storage: MemoryStorage = test_storage();
+        let state: AppState = test_state();
+        let photos: Arc<dyn PhotoStore> = state.photo_store.clone();
+        photos.put(PENDING_USER_ID, b"photo", "jpg").unwrap();
+        insert_pending_user(&storage, 1000);
+        expire_unverified_users(&storage, &*photos, &*state.face_matcher, &test_config(),
+                                1000 + 48 * 3600).unwrap();
+        assert!(storage.user_by_id(PENDING_USER_ID).unwrap().is_some());
+        expire_unverified_users(&storage, &*photos, &*state.face_matcher, &test_config(),
+                                1001 + 48 * 3600).unwrap();
+        assert_eq!(storage.user_by_id(PENDING_USER_ID).unwrap(), None);
+        assert!(!photos.list().unwrap().contains(&PENDING_USER_ID.to_string()));
+        assert!(storage.user_by_id(TEST_USER_ID).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_expire_unverified_users_cascade()
+    {
+        let storage: MemoryStorage = test_storage();
+        let state: AppState = test_state();
+        insert_pending_user(&storage, 1000);
+        let mut pending_admin: User = test_user(ADMIN_EMAIL);
+        pending_admin.id = "5555555555".to_string();
+        pending_admin.email = "pending-admin@knoldus.in".to_string();
+        pending_admin.pending_since = Some(1000);
+        storage.insert_user(&pending_admin).unwrap();
+        storage.insert_registration(&Registration {
+            user_id: PENDING_USER_ID.to_string(),
+            email: PENDING_EMAIL.to_string(),
+            status: ABSENT.to_string(),
+            conference_id: TEST_CONFERENCE_ID.to_string(),
+            attendance_match: None,
+            notified: false,
+        }).unwrap();
+        let mut observation: Observation = test_observation("capture", 1000, 90.0);
+        observation.user_id = PENDING_USER_ID.to_string();
+        storage.save_observation(&observation).unwrap();
+        let mut review: Review = test_review("review", 70.0);
+        review.user_id = PENDING_USER_ID.to_string();
+        storage.insert_review(&review).unwrap();
+        expire_unverified_users(&storage, &*state.photo_store, &*state.face_matcher,
+                                &test_config(), 1001 + 48 * 3600).unwrap();
+        assert_eq!(storage.user_by_id(PENDING_USER_ID).unwrap(), None);
+        assert!(storage.registrations_for_email(PENDING_EMAIL).unwrap().is_empty());
+        assert!(storage.observations(PENDING_USER_ID, TEST_CONFERENCE_ID).unwrap().is_empty());
+        assert!(storage.reviews_for_user(PENDING_USER_ID).unwrap().is_empty());
+        assert!(storage.user_by_id("5555555555").unwrap().is_some());
+    }
+
+    /// Mails a reset link for the email and returns the token it carries
+    fn mailed_reset_token(storage: &MemoryStorage, outbox: &MemoryOutbox, now: i64) -> String {
+        assert_eq!(send_password_reset(storage, outbox, &test_config(), TEST_EMAIL_ID, now),
+                   Ok(REGISTERED_USER_MESSAGE));
+        let body: String = outbox.mails().last().unwrap().body.clone();
+        body.split("token=").nth(1).unwrap().split_whitespace().next().unwrap().to_string()
     }
 
     #[test]
     fn test_validate_reset_password_success()
     {
-        assert_eq!(RESET_SUCCESSFUL, validate_reset_password("test@knoldus.in",
-                                                             "test123",
-                                                             "test123"))
+        let storage: MemoryStorage = test_storage();
+        let token: String = mailed_reset_token(&storage, &MemoryOutbox::default(), 1000);
+        assert_eq!(Ok(RESET_SUCCESSFUL), validate_reset_password(&storage, token.as_str(),
+                                                                 "test123",
+                                                                 "test123",
+                                                                 1001));
+        assert_eq!(Ok(USER.to_string()), authenticate_user(&storage, "test@knoldus.in", "test123")
+            .map(|user| user.user_type));
+        assert_eq!(storage.user_by_id(TEST_USER_ID).unwrap().unwrap().session_version, 1);
+    }
+
+    #[test]
+    fn test_validate_reset_password_single_use()
+    {
+        let storage: MemoryStorage = test_storage();
+        let outbox: MemoryOutbox = MemoryOutbox::default();
+        let token: String = mailed_reset_token(&storage, &outbox, 1000);
+        let other_token: String = mailed_reset_token(&storage, &outbox, 1000);
+        assert!(validate_reset_password(&storage, token.as_str(), "test123", "test123", 1001)
+            .is_ok());
+        assert_eq!(Err(ConfCountError::Unauthorized(RESET_LINK_INVALID.to_string())),
+                   validate_reset_password(&storage, token.as_str(), "test456", "test456", 1002));
+        assert_eq!(Err(ConfCountError::Unauthorized(RESET_LINK_INVALID.to_string())),
+                   validate_reset_password(&storage, other_token.as_str(), "test456", "test456",
+                                           1002));
+    }
+
+    #[test]
+    fn test_validate_reset_password_expired()
+    {
+        let storage: MemoryStorage = test_storage();
+        let token: String = mailed_reset_token(&storage, &MemoryOutbox::default(), 1000);
+        assert_eq!(Err(ConfCountError::Unauthorized(RESET_LINK_INVALID.to_string())),
+                   validate_reset_password(&storage, token.as_str(), "test123", "test123",
+                                           1000 + 30 * 60));
     }
 
     #[test]
     fn test_validate_reset_password_match_failure()
     {
-        assert_eq!(RESET_UNSUCCESSFUL, validate_reset_password("test@knoldus.in",
-                                                               "test123",
-                                                               "test12345"))
+        let storage: MemoryStorage = test_storage();
+        let token: String = mailed_reset_token(&storage, &MemoryOutbox::default(), 1000);
+        assert_eq!(Err(ConfCountError::Validation(RESET_UNSUCCESSFUL.to_string())),
+                   validate_reset_password(&storage, token.as_str(), "test123", "test12345",
+                                           1001));
+        assert!(validate_reset_password(&storage, token.as_str(), "test123", "test123", 1001)
+            .is_ok());
     }
 
     #[test]
     fn test_validate_reset_password_failure()
     {
-        assert_eq!(NON_REGISTERED_USER_MESSAGE, validate_reset_password("test1234@knoldus.in",
-                                                                        "test123",
-                                                                        "test123"))
+        assert_eq!(Err(ConfCountError::Unauthorized(RESET_LINK_INVALID.to_string())),
+                   validate_reset_password(&test_storage(), "forged", "test123", "test123",
+                                           1001))
     }
 
     #[test]
-    fn test_validate_user_email_success()
+    fn test_send_password_reset_success()
     {
-        assert_eq!(REGISTERED_USER_MESSAGE,
-                   validate_user_email("test@knoldus.in"))
+        let outbox: MemoryOutbox = MemoryOutbox::default();
+        mailed_reset_token(&test_storage(), &outbox, 1000);
+        assert_eq!(outbox.mails()[0].to, TEST_EMAIL_ID);
+        assert!(outbox.mails()[0].body.contains("http://127.0.0.1:8088/reset-password?token="));
     }
 
     #[test]
-    fn test_validate_user_email_failure()
+    fn test_send_password_reset_failure()
     {
-        assert_eq!(NON_REGISTERED_USER_MESSAGE,
-                   validate_user_email("test1234@knoldus.in"))
+        let outbox: MemoryOutbox = MemoryOutbox::default();
+        assert_eq!(Err(ConfCountError::NotFound(NON_REGISTERED_USER_MESSAGE.to_string())),
+                   send_password_reset(&test_storage(), &outbox, &test_config(),
+                                       "test1234@knoldus.in", 1000));
+        assert!(outbox.mails().is_empty());
     }
 
     #[test]
     fn test_validate_user_for_conference_success()
     {
-        assert_eq!("Conference Already Registered", validate_user_for_conference(
-            "test@knoldus.in", "1111111111", "5544332211"))
+        assert_eq!(Err(ConfCountError::Conflict(CONFERENCE_ALREADY_REGISTERED.to_string())),
+                   validate_user_for_conference(&test_storage(), "test@knoldus.in", "1111111111",
+                                                "5544332211"))
+    }
+
+    #[test]
+    fn test_validate_user_for_conference_registration_success()
+    {
+        let storage: MemoryStorage = test_storage();
+        add_conference(&storage, &Conference {
+            id: "6655443322".to_string(),
+            name: "second_conference".to_string(),
+            conference_date: "2019-07-24".to_string(),
+            conference_address1: "test_address_1".to_string(),
+            conference_address2: "test_address_2".to_string(),
+            conference_address3: "test_address_3".to_string(),
+            status: ConferenceStatus::NotCompleted.as_str().to_string(),
+            similarity_threshold: None,
+            presence_rule: PresenceRule::AnyCapture,
+        }).unwrap();
+        assert_eq!(Ok(CONFERENCE_REGISTERED), validate_user_for_conference(
+            &storage, "test@knoldus.in", "1111111111", "6655443322"));
+        assert_eq!(fetch_conference_for_user(&storage, TEST_EMAIL_ID).unwrap().len(), 2)
     }
 
     #[test]
     fn test_validate_user_for_conference_user_failure()
     {
-        assert_eq!(NON_REGISTERED_USER_MESSAGE, validate_user_for_conference(
-            "test@knoldus.in", "11111111", "5544332211"))
+        assert_eq!(Err(ConfCountError::NotFound(NON_REGISTERED_USER_MESSAGE.to_string())),
+                   validate_user_for_conference(&test_storage(), "test@knoldus.in", "11111111",
+                                                "5544332211"))
     }
 
 
+    #[test]
+    fn test_validate_user_for_conference_unverified_failure()
+    {
+        let storage: MemoryStorage = test_storage();
+        insert_pending_user(&storage, 1000);
+        assert_eq!(Err(ConfCountError::Forbidden(EMAIL_NOT_VERIFIED.to_string())),
+                   validate_user_for_conference(&storage, PENDING_EMAIL, PENDING_USER_ID,
+                                                TEST_CONFERENCE_ID))
+    }
+
     #[test]
     fn test_validate_user_for_conference_failure()
     {
-        assert_eq!(NO_CONFERENCE, validate_user_for_conference(
-            "test@knoldus.in", "1111111111", "55432211"))
+        assert_eq!(Err(ConfCountError::NotFound(NO_CONFERENCE.to_string())),
+                   validate_user_for_conference(&test_storage(), "test@knoldus.in", "1111111111",
+                                                "55432211"))
+    }
+
+    #[test]
+    fn test_fetch_conferences_success()
+    {
+        assert_eq!(fetch_conferences(&test_storage()).unwrap()[0].id, TEST_CONFERENCE_ID)
     }
 
     #[test]
     fn test_delete_user_failure()
     {
-        assert_eq!(delete_user("abcdefg".to_string()), EMPTY_DATA)
+        let state: AppState = test_state();
+        assert_eq!(delete_user(&test_storage(), &*state.photo_store, &*state.face_matcher,
+                               "abcdefg".to_string()),
+                   Err(ConfCountError::NotFound(EMPTY_DATA.to_string())))
     }
 
     #[test]
-    fn test_add_conference_failure()
+    fn test_delete_user_removes_photo()
     {
-        let validation_data: Document = doc! {
-                        "_id" :"5544332211",
-                };
+        let state: AppState = test_state();
+        let photos: Arc<dyn PhotoStore> = state.photo_store.clone();
+        let storage: MemoryStorage = test_storage();
+        let review: Review = test_review("deleted-user", 70.0);
+        storage.insert_review(&review).unwrap();
+        photos.put(TEST_USER_ID, b"photo", "jpg").unwrap();
+        photos.put(review.face_key.as_ref().unwrap(), b"face", "jpg").unwrap();
+        assert_eq!(delete_user(&storage, &*photos, &*state.face_matcher,
+                               TEST_USER_ID.to_string()),
+                   Ok(DELETION_SUCCESS));
+        assert_eq!(photos.get(TEST_USER_ID).unwrap(), None);
+        assert_eq!(photos.get(review.face_key.as_ref().unwrap()).unwrap(), None);
+        assert!(storage.reviews_for_user(TEST_USER_ID).unwrap().is_empty())
+    }
 
-        let conference_data: Document = doc! {
-                    "_id" : "5544332211",
-                    "conf_name" : "test_conference",
-                    "conference_data" : "2019-02-02",
-                    "conference_address1" : "test_address_1",
-                    "conference_address2" : "test_address_2",
-                    "conference_address3" : "test_address_3",
-                    STATUS: ConferenceStatus::NotCompleted.as_str(),
-            };
-        assert_eq!(ALREADY_REGISTERED, add_conference(conference_data, validation_data))
+    #[test]
+    fn test_add_conference_failure()
+    {
+        let conference_data: Conference = Conference {
+            id: "5544332211".to_string(),
+            name: "test_conference".to_string(),
+            conference_date: "2019-02-02".to_string(),
+            conference_address1: "test_address_1".to_string(),
+            conference_address2: "test_address_2".to_string(),
+            conference_address3: "test_address_3".to_string(),
+            status: ConferenceStatus::NotCompleted.as_str().to_string(),
+            similarity_threshold: None,
+            presence_rule: PresenceRule::AnyCapture,
+        };
+        assert_eq!(Err(ConfCountError::Conflict(ALREADY_REGISTERED.to_string())),
+                   add_conference(&test_storage(), &conference_data))
     }
 
     #[test]
     fn test_update_conference_success()
     {
-        let filter_data: Document = doc! {
-                        "_id" : "5544332211",
-                };
-
-        let updated_conference_data: Document = doc! {
-                SET : {
-                "conference_date" : "2018-06-24",
-                "conference_address1" : "test_address_1",
-                "conference_address2" : "test_address_2",
-                "conference_address3" : "test_address_3",
-                }
-            };
-        assert_eq!(UPDATION_SUCCESS, update_conference(filter_data, updated_conference_data))
+        assert_eq!(Ok(UPDATION_SUCCESS), update_conference(&test_storage(), "5544332211",
+                                                           &test_conference_update()))
     }
 
     #[test]
     fn test_update_conference_failure()
     {
-        let filter_data: Document = doc! {
-                        "_id" : "55443311",
-                };
-
-        let updated_conference_data: Document = doc! {
-                SET : {
-                "conference_date" : "2018-06-24",
-                "conference_address1" : "test_address_1",
-                "conference_address2" : "test_address_2",
-                "conference_address3" : "test_address_3",
-                }
-            };
-        assert_eq!(EMPTY_DATA, update_conference(filter_data, updated_conference_data))
+        assert_eq!(Err(ConfCountError::NotFound(EMPTY_DATA.to_string())),
+                   update_conference(&test_storage(), "55443311", &test_conference_update()))
     }
 
     #[test]
     fn test_delete_conference_failure()
     {
-        let filter_data: Document = doc! {
-                        "_id" : "55443311", };
-        assert_eq!(EMPTY_DATA, delete_conference(filter_data))
+        assert_eq!(Err(ConfCountError::NotFound(EMPTY_DATA.to_string())),
+                   delete_conference(&test_storage(), "55443311"))
+    }
+
+    #[test]
+    fn test_attendance_audit_success()
+    {
+        let storage: MemoryStorage = test_storage();
+        storage.set_attendance_match(TEST_USER_ID, TEST_CONFERENCE_ID,
+                                     &test_attendance_match(91.5)).unwrap();
+        let audit: serde_json::Value =
+            serde_json::from_str(&attendance_audit(&storage, TEST_CONFERENCE_ID).unwrap())
+                .unwrap();
+        assert_eq!(audit[0]["user_id"], TEST_USER_ID);
+        assert_eq!(audit[0]["attendance_match"]["similarity"], 91.5);
+        assert_eq!(audit[0]["attendance_match"]["face_box"]["left"], 0.25);
+        assert_eq!(audit[0]["attendance_match"]["capture_id"], "capture");
+        assert_eq!(attendance_audit(&storage, "55443311"), Ok("[]".to_string()));
+    }
+
+    #[test]
+    fn test_pending_reviews_success()
+    {
+        let storage: MemoryStorage = test_storage();
+        storage.insert_review(&test_review("review", 70.0)).unwrap();
+        let reviews: serde_json::Value =
+            serde_json::from_str(&pending_reviews(&storage, TEST_CONFERENCE_ID).unwrap())
+                .unwrap();
+        assert_eq!(reviews[0]["_id"], "review");
+        assert_eq!(reviews[0]["attendance_match"]["similarity"], 70.0);
+        assert_eq!(reviews[0]["face_key"], "review-review");
+        assert_eq!(reviews[0]["reference_key"], TEST_USER_ID);
+        assert_eq!(pending_reviews(&storage, "55443311"), Ok("[]".to_string()));
+    }
+
+    #[test]
+    fn test_index_conference_faces_success()
+    {
+        let photos: LocalPhotoStore = LocalPhotoStore::new("target/test_index_photos").unwrap();
+        let matcher: FixtureMatcher = FixtureMatcher::new(Vec::new());
+        photos.delete(TEST_USER_ID).unwrap();
+        assert_eq!(index_conference_faces(&test_storage(), &photos, &matcher, TEST_CONFERENCE_ID),
+                   Ok("Indexed the faces of 0 of the 1 registered users".to_string()));
+        photos.put(TEST_USER_ID, b"photo", "jpg").unwrap();
+        assert_eq!(index_conference_faces(&test_storage(), &photos, &matcher, TEST_CONFERENCE_ID),
+                   Ok("Indexed the faces of 1 of the 1 registered users".to_string()));
+        assert_eq!(index_conference_faces(&test_storage(), &photos, &matcher, "55443311"),
+                   Err(ConfCountError::NotFound(COLLECTION_EMPTY.to_string())));
+    }
+
+    /// Matcher recording the users whose faces are indexed or removed
+    #[derive(Default)]
+    struct RecordingMatcher {
+        indexed: Mutex<Vec<String>>,
+        removed: Mutex<Vec<Vec<String>>>,
+    }
+
+    impl FaceMatcher for RecordingMatcher {
+        fn index_face(&self, user_id: &str, _photo: &[u8]) -> FaceMatchResult<()> {
+            self.indexed.lock().unwrap().push(user_id.to_string());
+            Ok(())
+        }
+
+        fn remove_faces(&self, user_ids: &[&str]) -> FaceMatchResult<()> {
+            self.removed.lock().unwrap()
+                .push(user_ids.iter().map(|user_id| user_id.to_string()).collect());
+            Ok(())
+        }
+
+        fn search_faces(&self, _captured_image_path: &str)
+                        -> FaceMatchResult<HashMap<String, FaceMatch>> {
+            Ok(HashMap::new())
+        }
+
+        fn detect_faces(&self, _photo: &[u8]) -> FaceMatchResult<Vec<DetectedFace>> {
+            Ok(Vec::new())
+        }
+    }
+
+    #[test]
+    fn test_index_conference_faces_removes_once()
+    {
+        let storage: MemoryStorage = test_storage();
+        let mut registration: Registration =
+            storage.registration(TEST_USER_ID, TEST_CONFERENCE_ID).unwrap().unwrap();
+        registration.user_id = "3333333333".to_string();
+        storage.insert_registration(&registration).unwrap();
+        registration.user_id = "4444444444".to_string();
+        storage.insert_registration(&registration).unwrap();
+        let photos: LocalPhotoStore =
+            LocalPhotoStore::new("target/test_index_once_photos").unwrap();
+        photos.put(TEST_USER_ID, b"photo", "jpg").unwrap();
+        photos.put("3333333333", b"photo", "jpg").unwrap();
+        photos.delete("4444444444").unwrap();
+        let matcher: RecordingMatcher = RecordingMatcher::default();
+        assert_eq!(index_conference_faces(&storage, &photos, &matcher, TEST_CONFERENCE_ID),
+                   Ok("Indexed the faces of 2 of the 3 registered users".to_string()));
+        assert_eq!(*matcher.removed.lock().unwrap(),
+                   vec![vec![TEST_USER_ID.to_string(), "3333333333".to_string()]]);
+        assert_eq!(*matcher.indexed.lock().unwrap(),
+                   vec![TEST_USER_ID.to_string(), "3333333333".to_string()]);
     }
 
     #[test]
     fn test_filter_conference_success()
     {
-        let filter = doc! {"conference_date": {"$gte": "2017-06-24","$lte": "2017-06-26",},};
-        assert_eq!(filter_conference(filter), "[]".to_string())
+        assert_eq!(filter_conference(&test_storage(), "2017-06-24", "2017-06-26"),
+                   Ok("[]".to_string()))
     }
 
     #[test]
     fn test_filter_user_conference_success()
     {
-        assert_eq!(filter_user_conference("5544332211"), "[]")
+        assert_eq!(filter_user_conference(&test_storage(), "5544332211"),
+                   Ok("[\"test@knoldus.in\"]".to_string()))
     }
 
     #[test]
     fn test_fetch_user_details_success()
     {
-        let filter_data = doc! {"email" : "test@tester.in"};
-        assert_eq!(fetch_user_details(filter_data), "null")
+        let details: serde_json::Value =
+            serde_json::from_str(&fetch_user_details(&test_storage(), TEST_EMAIL_ID).unwrap())
+                .unwrap();
+        assert_eq!(details["email"], TEST_EMAIL_ID);
+        assert_eq!(details["_id"], TEST_USER_ID);
+        assert!(details.get("password").is_none());
+        assert!(details.get("pending_since").is_none());
+        assert_eq!(fetch_user_details(&test_storage(), "test@tester.in"),
+                   Err(ConfCountError::NotFound(NON_REGISTERED_USER_MESSAGE.to_string())))
     }
 
     #[test]
     fn test_update_user_details_success()
     {
-        let user_id = doc! {ID: "2839448279"};
-        let updated_data = doc! {SET: {NAME: "Test",EMAIL: "test@knoldus.in"}};
+        let storage: MemoryStorage = test_storage();
+        let outbox: MemoryOutbox = MemoryOutbox::default();
+        let update = |user_id: &str, name: &str, email: &str|
+            update_user_details(&storage, &outbox, &test_config(), user_id, name, email, 1000);
+        assert_eq!(update("2839448279", "Test", TEST_EMAIL_ID),
+                   Err(ConfCountError::NotFound(USER_ID_NOT_FOUND.to_string())));
+        assert_eq!(update(TEST_USER_ID, "Tester", TEST_EMAIL_ID), Ok(USER_DETAILS_UPDATED));
+        assert_eq!(update(TEST_USER_ID, "Tester", TEST_EMAIL_ID), Ok(USER_DETAILS_UPDATED));
+        let user: User = storage.user_by_id(TEST_USER_ID).unwrap().unwrap();
+        assert_eq!(user.name, "Tester");
+        assert_eq!(user.pending_since, None);
+        assert!(outbox.mails().is_empty());
+    }
+
+    #[test]
+    fn test_update_user_details_email_change()
+    {
+        let storage: MemoryStorage = test_storage();
+        let outbox: MemoryOutbox = MemoryOutbox::default();
+        let update = |email: &str|
+            update_user_details(&storage, &outbox, &test_config(), TEST_USER_ID, "Tester", email,
+                                1000);
+        assert_eq!(update(ADMIN_EMAIL), Err(ConfCountError::Conflict(EMAIL_TAKEN.to_string())));
+        assert_eq!(storage.user_by_id(TEST_USER_ID).unwrap().unwrap().email, TEST_EMAIL_ID);
+        assert!(outbox.mails().is_empty());
+        assert_eq!(update("typo@knoldus.in"), Ok(USER_DETAILS_UPDATED));
+        assert_eq!(update("changed@knoldus.in"), Ok(USER_DETAILS_UPDATED));
+        let user: User = storage.user_by_id(TEST_USER_ID).unwrap().unwrap();
+        assert_eq!(user.email, TEST_EMAIL_ID);
+        assert_eq!(user.pending_since, None);
+        assert_eq!(user.pending_email, Some("changed@knoldus.in".to_string()));
+        assert!(authenticate_user(&storage, TEST_EMAIL_ID, TEST_PASSWORD).is_ok());
+        assert_eq!(outbox.mails().len(), 2);
+        assert_eq!(outbox.mails()[1].to, "changed@knoldus.in");
+        let mailed_token = |index: usize| -> String {
+            let body: String = outbox.mails()[index].body.clone();
+            body.split("token=").nth(1).unwrap().split_whitespace().next().unwrap().to_string()
+        };
+        assert_eq!(verify_email(&storage, &test_config(), mailed_token(0).as_str(), 1001),
+                   Err(ConfCountError::Unauthorized(VERIFICATION_LINK_INVALID.to_string())));
+        assert_eq!(verify_email(&storage, &test_config(), mailed_token(1).as_str(), 1001),
+                   Ok(EMAIL_CHANGED));
+        let user: User = storage.user_by_id(TEST_USER_ID).unwrap().unwrap();
+        assert_eq!(user.email, "changed@knoldus.in");
+        assert_eq!(user.pending_email, None);
+        assert!(authenticate_user(&storage, "changed@knoldus.in", TEST_PASSWORD).is_ok());
+        assert_eq!(verify_email(&storage, &test_config(), mailed_token(1).as_str(), 1002),
+                   Ok(EMAIL_ALREADY_VERIFIED));
+    }
 
-        assert_eq!(update_user_details(user_id, updated_data), 0)
+    #[test]
+    fn test_verify_email_change_taken()
+    {
+        let storage: MemoryStorage = test_storage();
+        let outbox: MemoryOutbox = MemoryOutbox::default();
+        update_user_details(&storage, &outbox, &test_config(), TEST_USER_ID, "Tester",
+                            PENDING_EMAIL, 1000).unwrap();
+        insert_pending_user(&storage, 1000);
+        let body: String = outbox.mails()[0].body.clone();
+        let token: &str = body.split("token=").nth(1).unwrap().split_whitespace().next().unwrap();
+        assert_eq!(verify_email(&storage, &test_config(), token, 1001),
+                   Err(ConfCountError::Conflict(EMAIL_TAKEN.to_string())));
+        assert_eq!(storage.user_by_id(TEST_USER_ID).unwrap().unwrap().email, TEST_EMAIL_ID);
     }
 
     #[test]